@@ -10,6 +10,8 @@ pub struct ServerConfig {
     pub database: DatabaseConfig,
     #[serde(rename = "banners")]
     pub banners: Vec<Banner>,
+    #[serde(default)]
+    pub gameplay: GameplayConfig,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -39,6 +41,120 @@ pub struct DatabaseConfig {
     pub path: PathBuf,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameplayConfig {
+    /// `unlocked`, `fresh` or the path of a custom `.json` profile
+    #[serde(default = "default_starter_profile")]
+    pub starter_profile: String,
+    #[serde(default)]
+    pub battle_pass: BattlePassConfig,
+    #[serde(default)]
+    pub power: PowerConfig,
+    /// Player ids allowed to run GM commands that reach every account
+    #[serde(default)]
+    pub admins: Vec<i64>,
+}
+
+impl Default for GameplayConfig {
+    fn default() -> Self {
+        Self {
+            starter_profile: default_starter_profile(),
+            battle_pass: BattlePassConfig::default(),
+            power: PowerConfig::default(),
+            admins: Vec::new(),
+        }
+    }
+}
+
+fn default_starter_profile() -> String {
+    "unlocked".to_string()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BattlePassConfig {
+    /// `bpId` of the pass to run, 0 picks the newest in the `bp` table
+    #[serde(default)]
+    pub id: i32,
+    /// Start of the first season, `YYYY-MM-DD HH:MM:SS` UTC
+    #[serde(default = "default_bp_start_time")]
+    pub start_time: String,
+    /// Length of a season, a new one starts right after the previous ends
+    #[serde(default = "default_bp_season_days")]
+    pub season_days: i64,
+    /// Currency bought levels are paid with. The `bp` tables carry no level price,
+    /// so it is set here.
+    #[serde(default = "default_bp_buy_level_currency")]
+    pub buy_level_currency: i32,
+    /// Price of one bought level
+    #[serde(default = "default_bp_buy_level_cost")]
+    pub buy_level_cost: i32,
+}
+
+impl Default for BattlePassConfig {
+    fn default() -> Self {
+        Self {
+            id: 0,
+            start_time: default_bp_start_time(),
+            season_days: default_bp_season_days(),
+            buy_level_currency: default_bp_buy_level_currency(),
+            buy_level_cost: default_bp_buy_level_cost(),
+        }
+    }
+}
+
+fn default_bp_start_time() -> String {
+    "2026-01-05 05:00:00".to_string()
+}
+
+fn default_bp_season_days() -> i64 {
+    42
+}
+
+fn default_bp_buy_level_currency() -> i32 {
+    2
+}
+
+fn default_bp_buy_level_cost() -> i32 {
+    100
+}
+
+/// Stamina purchases. The excel tables carry no purchase price or amount, so they are
+/// set here.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PowerConfig {
+    /// Currency purchases are paid with
+    #[serde(default = "default_power_buy_currency")]
+    pub buy_currency: i32,
+    /// Stamina granted by one purchase
+    #[serde(default = "default_power_buy_amount")]
+    pub buy_amount: i32,
+    /// Price of each purchase of the day, its length is the daily purchase limit
+    #[serde(default = "default_power_buy_costs")]
+    pub buy_costs: Vec<i32>,
+}
+
+impl Default for PowerConfig {
+    fn default() -> Self {
+        Self {
+            buy_currency: default_power_buy_currency(),
+            buy_amount: default_power_buy_amount(),
+            buy_costs: default_power_buy_costs(),
+        }
+    }
+}
+
+fn default_power_buy_currency() -> i32 {
+    2
+}
+
+fn default_power_buy_amount() -> i32 {
+    60
+}
+
+fn default_power_buy_costs() -> Vec<i32> {
+    vec![50, 50, 100, 100, 150, 150, 200, 200]
+}
+
 impl ServerConfig {
     pub fn ensure_exists(path: &PathBuf) -> anyhow::Result<()> {
         if path.exists() {
@@ -108,6 +224,11 @@ impl ServerConfig {
         if self.paths.static_data.is_relative() {
             self.paths.static_data = config_dir.join(&self.paths.static_data);
         }
+
+        let profile = Path::new(&self.gameplay.starter_profile);
+        if profile.extension().is_some_and(|e| e == "json") && profile.is_relative() {
+            self.gameplay.starter_profile = config_dir.join(profile).to_string_lossy().into_owned();
+        }
         Ok(())
     }
 
@@ -40,6 +40,37 @@ pub fn excel_data_directory() -> &'static PathBuf {
     &config().paths.excel_data
 }
 
+/// Falls back to `unlocked` when no config was loaded (e.g. the simulator)
+pub fn starter_profile() -> &'static str {
+    CONFIG
+        .get()
+        .map(|c| c.gameplay.starter_profile.as_str())
+        .unwrap_or("unlocked")
+}
+
+/// Falls back to the defaults when no config was loaded (e.g. the simulator)
+pub fn battle_pass() -> config::BattlePassConfig {
+    CONFIG
+        .get()
+        .map(|c| c.gameplay.battle_pass.clone())
+        .unwrap_or_default()
+}
+
+/// Falls back to the defaults when no config was loaded (e.g. the simulator)
+pub fn power() -> config::PowerConfig {
+    CONFIG
+        .get()
+        .map(|c| c.gameplay.power.clone())
+        .unwrap_or_default()
+}
+
+/// Nobody is an admin when no config was loaded
+pub fn is_admin(player_id: i64) -> bool {
+    CONFIG
+        .get()
+        .is_some_and(|c| c.gameplay.admins.contains(&player_id))
+}
+
 pub fn init_tracing() {
     #[cfg(target_os = "windows")]
     let _ = ansi_term::enable_ansi_support();
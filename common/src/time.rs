@@ -54,6 +54,19 @@ impl ServerTime {
         Self::server_week(t1) == Self::server_week(t2)
     }
 
+    /// Unix seconds of the next daily reset
+    #[inline]
+    pub fn next_day_reset_sec(timestamp_ms: i64) -> i64 {
+        ((Self::server_day(timestamp_ms) + 1) * DAY_MS + RESET_OFFSET_MS) / 1000
+    }
+
+    /// Unix seconds of the next weekly reset (Monday)
+    #[inline]
+    pub fn next_week_reset_sec(timestamp_ms: i64) -> i64 {
+        let next_week_day = (Self::server_week(timestamp_ms) as i64 + 1) * 7 - 3;
+        (next_week_day * DAY_MS + RESET_OFFSET_MS) / 1000
+    }
+
     #[inline]
     pub fn server_weekday(timestamp_ms: i64) -> i32 {
         Self::adjusted_datetime(timestamp_ms)
@@ -0,0 +1,16 @@
+-- In-progress fight per user, rewritten after every round so it survives a disconnect.
+-- Round rng is seeded from fight_id and round_number, so no rng state is stored.
+CREATE TABLE IF NOT EXISTS user_active_battles (
+    user_id INTEGER PRIMARY KEY,
+    episode_id INTEGER NOT NULL,
+    fight_id INTEGER NOT NULL,
+    round_number INTEGER NOT NULL,
+    fight TEXT NOT NULL,          -- JSON Fight
+    last_round TEXT NOT NULL,     -- JSON FightRound
+    current_deck TEXT NOT NULL,   -- JSON array of CardInfo
+    ai_deck TEXT NOT NULL,        -- JSON array of CardInfo
+    manager_state TEXT NOT NULL,  -- JSON FightDataMgr state (buffs, mechanics)
+    battle_info TEXT NOT NULL,    -- JSON battle metadata (tower, fight group, ap...)
+    updated_at INTEGER NOT NULL,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS user_power_buy (
+    user_id         INTEGER NOT NULL PRIMARY KEY,
+
+    buy_count       INTEGER NOT NULL DEFAULT 0,  -- stamina purchases made today
+
+    FOREIGN KEY (user_id)
+        REFERENCES users(id)
+        ON DELETE CASCADE
+);
@@ -0,0 +1,10 @@
+CREATE TABLE IF NOT EXISTS user_content_version (
+    user_id         INTEGER NOT NULL PRIMARY KEY,
+
+    data_version    TEXT NOT NULL,     -- content fingerprint last synced into the account
+    updated_at      INTEGER NOT NULL,  -- unix ms
+
+    FOREIGN KEY (user_id)
+        REFERENCES users(id)
+        ON DELETE CASCADE
+);
@@ -0,0 +1,32 @@
+CREATE TABLE IF NOT EXISTS user_tasks (
+    user_id         INTEGER NOT NULL,
+    task_id         INTEGER NOT NULL,
+
+    task_type       INTEGER NOT NULL,            -- 1 daily, 2 weekly, 4 novice
+    progress        INTEGER NOT NULL DEFAULT 0,
+    has_finished    BOOLEAN NOT NULL DEFAULT 0,  -- reward claimed this cycle
+    finish_count    INTEGER NOT NULL DEFAULT 0,
+    expiry_time     INTEGER NOT NULL DEFAULT 0,  -- unix sec of the next reset, 0 never resets
+
+    PRIMARY KEY (user_id, task_id),
+    FOREIGN KEY (user_id)
+        REFERENCES users(id)
+        ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_user_tasks_type ON user_tasks(user_id, task_type);
+
+CREATE TABLE IF NOT EXISTS user_task_activity (
+    user_id         INTEGER NOT NULL,
+    type_id         INTEGER NOT NULL,
+
+    define_id       INTEGER NOT NULL DEFAULT 0,  -- last claimed activity bonus stage
+    value           INTEGER NOT NULL DEFAULT 0,  -- activity points this cycle
+    gain_value      INTEGER NOT NULL DEFAULT 0,  -- activity required by the last claimed stage
+    expiry_time     INTEGER NOT NULL DEFAULT 0,
+
+    PRIMARY KEY (user_id, type_id),
+    FOREIGN KEY (user_id)
+        REFERENCES users(id)
+        ON DELETE CASCADE
+);
@@ -0,0 +1,32 @@
+CREATE TABLE IF NOT EXISTS user_battle_pass (
+    user_id         INTEGER PRIMARY KEY,
+
+    bp_id           INTEGER NOT NULL,
+    season_start    INTEGER NOT NULL,            -- unix sec, a new season starts from scratch
+    score           INTEGER NOT NULL DEFAULT 0,
+    weekly_score    INTEGER NOT NULL DEFAULT 0,  -- task score this week, capped by weekLimitTimes
+    score_week      INTEGER NOT NULL DEFAULT 0,  -- server week weekly_score belongs to
+    pay_status      INTEGER NOT NULL DEFAULT 0,  -- 0 free, 1 paid, 2 paid deluxe
+    first_show      BOOLEAN NOT NULL DEFAULT 1,
+    sp_first_show   BOOLEAN NOT NULL DEFAULT 1,
+
+    FOREIGN KEY (user_id)
+        REFERENCES users(id)
+        ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS user_bp_bonus (
+    user_id         INTEGER NOT NULL,
+    level           INTEGER NOT NULL,
+
+    free_bonus      BOOLEAN NOT NULL DEFAULT 0,
+    pay_bonus       BOOLEAN NOT NULL DEFAULT 0,
+    sp_free_bonus   BOOLEAN NOT NULL DEFAULT 0,
+    sp_pay_bonus    BOOLEAN NOT NULL DEFAULT 0,
+    select_index    INTEGER,                     -- chosen selfSelectPayBonus, NULL until picked
+
+    PRIMARY KEY (user_id, level),
+    FOREIGN KEY (user_id)
+        REFERENCES users(id)
+        ON DELETE CASCADE
+);
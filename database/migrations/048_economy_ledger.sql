@@ -0,0 +1,30 @@
+-- Append-only audit trail of currency, item and equipment changes.
+-- No foreign key to users so the trail outlives deleted accounts.
+CREATE TABLE IF NOT EXISTS economy_ledger (
+    id              INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id         INTEGER NOT NULL,
+
+    resource_type   INTEGER NOT NULL,            -- materilType: 1 item, 2 currency, 9 equip, 10 power item, 24 insight item
+    resource_id     INTEGER NOT NULL,
+    delta           INTEGER NOT NULL,
+    balance_after   INTEGER NOT NULL,            -- total the user holds after the change
+    source          TEXT NOT NULL,               -- gacha, store, dungeon, gm, mail, charge, ...
+    reference_id    INTEGER,                     -- pool, goods, episode, mail, ... id of the source
+    created_at      INTEGER NOT NULL             -- unix ms
+);
+
+CREATE INDEX IF NOT EXISTS idx_economy_ledger_user ON economy_ledger(user_id, created_at);
+CREATE INDEX IF NOT EXISTS idx_economy_ledger_resource ON economy_ledger(resource_type, resource_id, created_at);
+CREATE INDEX IF NOT EXISTS idx_economy_ledger_source ON economy_ledger(source, created_at);
+
+CREATE TRIGGER IF NOT EXISTS economy_ledger_no_update
+BEFORE UPDATE ON economy_ledger
+BEGIN
+    SELECT RAISE(ABORT, 'economy_ledger is append-only');
+END;
+
+CREATE TRIGGER IF NOT EXISTS economy_ledger_no_delete
+BEFORE DELETE ON economy_ledger
+BEGIN
+    SELECT RAISE(ABORT, 'economy_ledger is append-only');
+END;
@@ -0,0 +1,61 @@
+CREATE TABLE IF NOT EXISTS mail_templates (
+    id              INTEGER PRIMARY KEY,
+
+    sender          TEXT NOT NULL DEFAULT '',    -- localized JSON like user_mails.sender
+    sender_type     INTEGER NOT NULL DEFAULT 2,
+    title           TEXT NOT NULL DEFAULT '',
+    content         TEXT NOT NULL DEFAULT '',    -- {0}, {1}, ... take the params of the send
+    attachment      TEXT NOT NULL DEFAULT '',    -- type#id#quantity|...
+    expire_hours    INTEGER NOT NULL DEFAULT 0,  -- 0 never expires
+    jump_title      TEXT NOT NULL DEFAULT '',
+    jump            TEXT NOT NULL DEFAULT ''
+);
+
+-- Mail sent to every account, also delivered to accounts that log in before end_time
+CREATE TABLE IF NOT EXISTS mail_broadcasts (
+    id              INTEGER PRIMARY KEY AUTOINCREMENT,
+
+    mail_id         INTEGER NOT NULL DEFAULT 0,
+    params          TEXT NOT NULL DEFAULT '',
+    attachment      TEXT NOT NULL DEFAULT '',
+    sender          TEXT NOT NULL DEFAULT '',
+    title           TEXT NOT NULL DEFAULT '',
+    content         TEXT NOT NULL DEFAULT '',
+    copy            TEXT NOT NULL DEFAULT '',
+    expire_time     INTEGER NOT NULL DEFAULT 0,  -- unix ms, 0 never expires
+    sender_type     INTEGER NOT NULL DEFAULT 0,
+    jump_title      TEXT NOT NULL DEFAULT '',
+    jump            TEXT NOT NULL DEFAULT '',
+
+    start_time      INTEGER NOT NULL,            -- unix ms
+    end_time        INTEGER NOT NULL             -- unix ms
+);
+
+CREATE INDEX IF NOT EXISTS idx_mail_broadcasts_end ON mail_broadcasts(end_time);
+
+CREATE TABLE IF NOT EXISTS user_mail_broadcasts (
+    user_id         INTEGER NOT NULL,
+    broadcast_id    INTEGER NOT NULL,
+
+    mail_incr_id    INTEGER NOT NULL,
+    delivered_at    INTEGER NOT NULL,            -- unix ms
+
+    PRIMARY KEY (user_id, broadcast_id),
+    FOREIGN KEY (user_id)
+        REFERENCES users(id)
+        ON DELETE CASCADE,
+    FOREIGN KEY (broadcast_id)
+        REFERENCES mail_broadcasts(id)
+        ON DELETE CASCADE
+);
+
+INSERT OR IGNORE INTO mail_templates (id, sender, sender_type, title, content, attachment, expire_hours)
+VALUES (
+    1,
+    '{"en":"System","zh":"系统的回响","jp":"システム"}',
+    2,
+    '{"en":"Maintenance Compensation","zh":"维护补偿","jp":"メンテナンス補填"}',
+    '{"en":"Thank you for your patience during the maintenance. {0}","zh":"感谢你在维护期间的耐心等待。{0}","jp":"メンテナンス中のご理解ありがとうございました。{0}"}',
+    '2#2#300',
+    168
+);
@@ -0,0 +1,13 @@
+-- Fights whose stored oper log disagrees with what the client reported
+CREATE TABLE IF NOT EXISTS battle_flags (
+    id          INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id     INTEGER NOT NULL,
+    episode_id  INTEGER NOT NULL,
+    battle_id   INTEGER NOT NULL,
+    reason      TEXT NOT NULL,
+    created_at  INTEGER NOT NULL,               -- unix ms
+
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_battle_flags_user ON battle_flags(user_id, created_at);
@@ -0,0 +1,352 @@
+use super::starter_data::{
+    insert_starter_equipment, insert_starter_hero, is_starter_equipment, is_starter_hero,
+};
+use super::starter_profile::{self, StarterProfile};
+use config::configs;
+use sqlx::{Sqlite, SqlitePool, Transaction};
+use std::collections::{HashMap, HashSet};
+use std::sync::OnceLock;
+
+/// Content granted to an account by a sync
+#[derive(Debug, Default)]
+pub struct ContentSync {
+    pub heroes: usize,
+    pub equipment: usize,
+    pub skins: usize,
+    pub bgm: usize,
+    pub episodes: usize,
+    pub stories: usize,
+}
+
+/// Fingerprint of the content tables the sync reads, changes whenever
+/// `sonetto-data` adds or removes heroes, skins, psychubes, BGM or episodes
+pub fn content_version() -> &'static str {
+    static VERSION: OnceLock<String> = OnceLock::new();
+
+    VERSION.get_or_init(|| {
+        let tables = configs::get();
+        let mut ids: Vec<(u8, i32)> = Vec::new();
+        ids.extend(tables.character.iter().map(|c| (1, c.id)));
+        ids.extend(tables.skin.iter().map(|s| (2, s.id)));
+        ids.extend(tables.equip.iter().map(|e| (3, e.id)));
+        ids.extend(tables.bgm_switch.iter().map(|b| (4, b.id)));
+        ids.extend(tables.episode.iter().map(|e| (5, e.id)));
+        ids.sort_unstable();
+
+        // FNV-1a, stable across builds unlike std's hasher
+        let hash = ids.iter().fold(0xcbf29ce484222325u64, |hash, (table, id)| {
+            [*table]
+                .into_iter()
+                .chain(id.to_le_bytes())
+                .fold(hash, |h, b| (h ^ b as u64).wrapping_mul(0x100000001b3))
+        });
+
+        format!("{:016x}", hash)
+    })
+}
+
+/// Marks an account as holding the current content
+pub async fn record_version(tx: &mut Transaction<'_, Sqlite>, user_id: i64) -> sqlx::Result<()> {
+    sqlx::query(
+        "INSERT INTO user_content_version (user_id, data_version, updated_at)
+         VALUES (?, ?, ?)
+         ON CONFLICT(user_id) DO UPDATE SET
+            data_version = excluded.data_version,
+            updated_at = excluded.updated_at",
+    )
+    .bind(user_id)
+    .bind(content_version())
+    .bind(common::time::ServerTime::now_ms())
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(())
+}
+
+/// Grants content added since the account was last synced, following the rules of
+/// the starter profile: only what the profile hands out in full (every hero, every
+/// psychube, every cleared episode, ...) is backfilled. Returns `None` when the
+/// account is already up to date.
+pub async fn sync_content(pool: &SqlitePool, user_id: i64) -> sqlx::Result<Option<ContentSync>> {
+    let stored: Option<String> =
+        sqlx::query_scalar("SELECT data_version FROM user_content_version WHERE user_id = ?")
+            .bind(user_id)
+            .fetch_optional(pool)
+            .await?;
+
+    if stored.as_deref() == Some(content_version()) {
+        return Ok(None);
+    }
+
+    let profile = starter_profile::current();
+    let mut tx = pool.begin().await?;
+
+    let report = ContentSync {
+        equipment: sync_equipment(&mut tx, user_id, profile).await?,
+        heroes: sync_heroes(&mut tx, user_id, profile).await?,
+        skins: sync_skins(&mut tx, user_id, profile).await?,
+        bgm: sync_bgm(&mut tx, user_id).await?,
+        episodes: sync_episodes(&mut tx, user_id, profile).await?,
+        stories: sync_stories(&mut tx, user_id, profile).await?,
+    };
+
+    record_version(&mut tx, user_id).await?;
+    tx.commit().await?;
+
+    tracing::info!(
+        "Synced content version {} for user {} (was {:?}): {:?}",
+        content_version(),
+        user_id,
+        stored,
+        report
+    );
+
+    Ok(Some(report))
+}
+
+async fn sync_equipment(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: i64,
+    profile: &StarterProfile,
+) -> sqlx::Result<usize> {
+    if !profile.all_equipment {
+        return Ok(0);
+    }
+
+    let owned: HashSet<i32> =
+        sqlx::query_scalar::<_, i32>("SELECT DISTINCT equip_id FROM equipment WHERE user_id = ?")
+            .bind(user_id)
+            .fetch_all(&mut **tx)
+            .await?
+            .into_iter()
+            .collect();
+
+    let mut next_uid: i64 =
+        sqlx::query_scalar("SELECT COALESCE(MAX(uid), 29999999) + 1 FROM equipment")
+            .fetch_one(&mut **tx)
+            .await?;
+
+    let mut added = 0;
+    for equip in configs::get()
+        .equip
+        .iter()
+        .filter(|e| is_starter_equipment(e, profile) && !owned.contains(&e.id))
+    {
+        insert_starter_equipment(tx, user_id, next_uid, equip, profile).await?;
+        next_uid += 1;
+        added += 1;
+    }
+
+    Ok(added)
+}
+
+async fn sync_heroes(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: i64,
+    profile: &StarterProfile,
+) -> sqlx::Result<usize> {
+    // A fixed roster is a starting point, later heroes have to be pulled
+    if profile.heroes.is_some() {
+        return Ok(0);
+    }
+
+    let owned: HashSet<i32> =
+        sqlx::query_scalar::<_, i32>("SELECT hero_id FROM heroes WHERE user_id = ?")
+            .bind(user_id)
+            .fetch_all(&mut **tx)
+            .await?
+            .into_iter()
+            .collect();
+
+    let equip_map: HashMap<i32, i64> = sqlx::query_as::<_, (i32, i64)>(
+        "SELECT equip_id, MIN(uid) FROM equipment WHERE user_id = ? GROUP BY equip_id",
+    )
+    .bind(user_id)
+    .fetch_all(&mut **tx)
+    .await?
+    .into_iter()
+    .collect();
+
+    let mut next_uid: i64 =
+        sqlx::query_scalar("SELECT COALESCE(MAX(uid), 20000000) + 1 FROM heroes")
+            .fetch_one(&mut **tx)
+            .await?;
+
+    let mut added = 0;
+    for character in configs::get()
+        .character
+        .iter()
+        .filter(|c| is_starter_hero(c.id, profile) && !owned.contains(&c.id))
+    {
+        insert_starter_hero(tx, user_id, next_uid, character, &equip_map, profile).await?;
+        next_uid += 1;
+        added += 1;
+
+        let rarity_column = match character.rare {
+            1 => "hero_rare_nn_count",
+            2 => "hero_rare_n_count",
+            3 => "hero_rare_r_count",
+            4 => "hero_rare_sr_count",
+            5 => "hero_rare_ssr_count",
+            _ => continue,
+        };
+
+        sqlx::query(&format!(
+            "UPDATE player_info SET {col} = {col} + 1 WHERE player_id = ?",
+            col = rarity_column
+        ))
+        .bind(user_id)
+        .execute(&mut **tx)
+        .await?;
+    }
+
+    Ok(added)
+}
+
+/// New skins of heroes the account already owns
+async fn sync_skins(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: i64,
+    profile: &StarterProfile,
+) -> sqlx::Result<usize> {
+    if !profile.max_heroes {
+        return Ok(0);
+    }
+
+    let heroes: HashMap<i32, i64> =
+        sqlx::query_as::<_, (i32, i64)>("SELECT hero_id, uid FROM heroes WHERE user_id = ?")
+            .bind(user_id)
+            .fetch_all(&mut **tx)
+            .await?
+            .into_iter()
+            .collect();
+
+    let tables = configs::get();
+    let mut added = 0;
+
+    for skin in tables.skin.iter().filter(|s| s.character_id != 0) {
+        let Some(&hero_uid) = heroes.get(&skin.character_id) else {
+            continue;
+        };
+
+        // The character's base skin isn't stored as an owned skin
+        let is_base = tables
+            .character
+            .get(skin.character_id)
+            .is_some_and(|c| c.skin_id == skin.id);
+        if is_base {
+            continue;
+        }
+
+        let result = sqlx::query(
+            "INSERT OR IGNORE INTO hero_skins (hero_uid, skin, expire_sec) VALUES (?, ?, 0)",
+        )
+        .bind(hero_uid)
+        .bind(skin.id)
+        .execute(&mut **tx)
+        .await?;
+
+        sqlx::query("INSERT OR IGNORE INTO hero_all_skins (user_id, skin_id) VALUES (?, ?)")
+            .bind(user_id)
+            .bind(skin.id)
+            .execute(&mut **tx)
+            .await?;
+
+        added += result.rows_affected() as usize;
+    }
+
+    Ok(added)
+}
+
+/// Every account starts with the whole jukebox
+async fn sync_bgm(tx: &mut Transaction<'_, Sqlite>, user_id: i64) -> sqlx::Result<usize> {
+    let now = common::time::ServerTime::now_ms();
+    let mut added = 0;
+
+    for bgm in configs::get().bgm_switch.iter() {
+        let result = sqlx::query(
+            "INSERT OR IGNORE INTO user_bgm (player_id, bgm_id, unlock_time, is_favorite, is_read)
+             VALUES (?, ?, ?, 0, 0)",
+        )
+        .bind(user_id)
+        .bind(bgm.id)
+        .bind(now)
+        .execute(&mut **tx)
+        .await?;
+
+        added += result.rows_affected() as usize;
+    }
+
+    Ok(added)
+}
+
+/// New episodes, cleared like the starter data clears them
+async fn sync_episodes(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: i64,
+    profile: &StarterProfile,
+) -> sqlx::Result<usize> {
+    if !profile.clear_dungeons {
+        return Ok(0);
+    }
+
+    let now = common::time::ServerTime::now_ms();
+    let mut added = 0;
+
+    for episode in configs::get().episode.iter() {
+        let result = sqlx::query(
+            r#"
+            INSERT OR IGNORE INTO user_dungeons (
+                user_id, chapter_id, episode_id, star, challenge_count,
+                has_record, left_return_all_num, today_pass_num, today_total_num,
+                created_at, updated_at
+            ) VALUES (?, ?, ?, ?, 0, 0, 1, 0, 0, ?, ?)
+            "#,
+        )
+        .bind(user_id)
+        .bind(episode.chapter_id)
+        .bind(episode.id)
+        .bind(if episode.battle_id != 0 { 2 } else { 1 })
+        .bind(now)
+        .bind(now)
+        .execute(&mut **tx)
+        .await?;
+
+        added += result.rows_affected() as usize;
+    }
+
+    Ok(added)
+}
+
+/// Stories before and after every episode, finished like the starter data finishes them
+async fn sync_stories(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: i64,
+    profile: &StarterProfile,
+) -> sqlx::Result<usize> {
+    if !profile.clear_stories {
+        return Ok(0);
+    }
+
+    let story_ids: HashSet<i32> = configs::get()
+        .episode
+        .iter()
+        .flat_map(|e| [e.before_story, e.after_story])
+        .filter(|&id| id != 0)
+        .collect();
+
+    let mut added = 0;
+    for story_id in story_ids {
+        let result = sqlx::query(
+            "INSERT OR IGNORE INTO user_finished_stories (user_id, story_id) VALUES (?, ?)",
+        )
+        .bind(user_id)
+        .bind(story_id)
+        .execute(&mut **tx)
+        .await?;
+
+        added += result.rows_affected() as usize;
+    }
+
+    Ok(added)
+}
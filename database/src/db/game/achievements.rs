@@ -1,6 +1,6 @@
 use crate::models::game::achievements::Achievement;
 use anyhow::Result;
-use sqlx::SqlitePool;
+use sqlx::{Sqlite, SqlitePool, Transaction};
 
 pub async fn get_achievements(pool: &SqlitePool, user_id: i64) -> Result<Vec<Achievement>> {
     let achievements = sqlx::query_as::<_, Achievement>(
@@ -13,7 +13,7 @@ pub async fn get_achievements(pool: &SqlitePool, user_id: i64) -> Result<Vec<Ach
 }
 
 pub async fn update_achievement_progress(
-    pool: &SqlitePool,
+    tx: &mut Transaction<'_, Sqlite>,
     user_id: i64,
     achievement_id: i32,
     progress: i32,
@@ -36,35 +36,36 @@ pub async fn update_achievement_progress(
     .bind(progress)
     .bind(now)
     .bind(now)
-    .execute(pool)
+    .execute(&mut **tx)
     .await?;
 
     Ok(())
 }
 
+/// Marks an achievement tier finished, false if it was finished already
 pub async fn finish_achievement(
-    pool: &SqlitePool,
+    tx: &mut Transaction<'_, Sqlite>,
     user_id: i64,
     achievement_id: i32,
-) -> Result<()> {
+) -> Result<bool> {
     let now = chrono::Utc::now().timestamp();
     let finish_time = now as i32;
 
-    sqlx::query(
+    let result = sqlx::query(
         r#"
         UPDATE user_achievements
         SET has_finish = 1, finish_time = ?, is_new = 1, updated_at = ?
-        WHERE user_id = ? AND achievement_id = ?
+        WHERE user_id = ? AND achievement_id = ? AND has_finish = 0
         "#,
     )
     .bind(finish_time)
     .bind(now)
     .bind(user_id)
     .bind(achievement_id)
-    .execute(pool)
+    .execute(&mut **tx)
     .await?;
 
-    Ok(())
+    Ok(result.rows_affected() > 0)
 }
 
 pub async fn clear_new_flag(pool: &SqlitePool, user_id: i64, achievement_id: i32) -> Result<()> {
@@ -81,3 +82,43 @@ pub async fn clear_new_flag(pool: &SqlitePool, user_id: i64, achievement_id: i32
 
     Ok(())
 }
+
+/// `(hero_id, rank)` of every owned hero
+pub async fn get_hero_ranks(pool: &SqlitePool, user_id: i64) -> Result<Vec<(i32, i32)>> {
+    let heroes = sqlx::query_as("SELECT hero_id, rank FROM heroes WHERE user_id = ?")
+        .bind(user_id)
+        .fetch_all(pool)
+        .await?;
+    Ok(heroes)
+}
+
+/// `(chapter_id, episode_id)` of every cleared episode
+pub async fn get_cleared_episodes(pool: &SqlitePool, user_id: i64) -> Result<Vec<(i32, i32)>> {
+    let episodes = sqlx::query_as(
+        "SELECT chapter_id, episode_id FROM user_dungeons WHERE user_id = ? AND star > 0",
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(episodes)
+}
+
+/// Pulls across every banner
+pub async fn get_total_summons(pool: &SqlitePool, user_id: i64) -> Result<i32> {
+    let total = sqlx::query_scalar(
+        "SELECT COALESCE(SUM(summon_count), 0) FROM user_summon_pools WHERE user_id = ?",
+    )
+    .bind(user_id)
+    .fetch_one(pool)
+    .await?;
+    Ok(total)
+}
+
+/// Level of every owned psychube
+pub async fn get_equipment_levels(pool: &SqlitePool, user_id: i64) -> Result<Vec<i32>> {
+    let levels = sqlx::query_scalar("SELECT level FROM equipment WHERE user_id = ?")
+        .bind(user_id)
+        .fetch_all(pool)
+        .await?;
+    Ok(levels)
+}
@@ -1,5 +1,5 @@
 use anyhow::Result;
-use sqlx::SqlitePool;
+use sqlx::{Sqlite, SqlitePool, Transaction};
 
 pub async fn save_round_operations(
     pool: &SqlitePool,
@@ -31,6 +31,87 @@ pub async fn save_round_operations(
     Ok(())
 }
 
+/// Oper log of a single fight, ordered by round. A round stored twice keeps its
+/// newest row.
+pub async fn load_round_operations(
+    pool: &SqlitePool,
+    user_id: i64,
+    episode_id: i32,
+    battle_id: i64,
+) -> Result<Vec<(i32, sonettobuf::FightRoundOperRecord)>> {
+    let rows: Vec<(i32, String, String)> = sqlx::query_as(
+        "SELECT round_number, cloth_skill_opers, opers
+         FROM battle_replays
+         WHERE user_id = ? AND episode_id = ? AND battle_id = ?
+         ORDER BY round_number, created_at, rowid",
+    )
+    .bind(user_id)
+    .bind(episode_id)
+    .bind(battle_id)
+    .fetch_all(pool)
+    .await?;
+
+    let mut rounds: Vec<(i32, sonettobuf::FightRoundOperRecord)> = Vec::with_capacity(rows.len());
+    for (round_number, cloth_skill_opers, opers) in rows {
+        let record = sonettobuf::FightRoundOperRecord {
+            cloth_skill_opers: serde_json::from_str(&cloth_skill_opers)?,
+            opers: serde_json::from_str(&opers)?,
+        };
+
+        match rounds.last_mut() {
+            Some(last) if last.0 == round_number => last.1 = record,
+            _ => rounds.push((round_number, record)),
+        }
+    }
+
+    Ok(rounds)
+}
+
+/// Drops the stored rounds from `round_number` on, after the fight was rewound to it
+pub async fn delete_round_operations_from(
+    pool: &SqlitePool,
+    user_id: i64,
+    episode_id: i32,
+    battle_id: i64,
+    round_number: i32,
+) -> Result<()> {
+    sqlx::query(
+        "DELETE FROM battle_replays
+         WHERE user_id = ? AND episode_id = ? AND battle_id = ? AND round_number >= ?",
+    )
+    .bind(user_id)
+    .bind(episode_id)
+    .bind(battle_id)
+    .bind(round_number)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Records a fight that failed replay verification
+pub async fn flag_battle(
+    pool: &SqlitePool,
+    user_id: i64,
+    episode_id: i32,
+    battle_id: i64,
+    reason: &str,
+) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO battle_flags (user_id, episode_id, battle_id, reason, created_at)
+         VALUES (?, ?, ?, ?, ?)",
+    )
+    .bind(user_id)
+    .bind(episode_id)
+    .bind(battle_id)
+    .bind(reason)
+    .bind(chrono::Utc::now().timestamp_millis())
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
 pub async fn load_battle_replay(
     pool: &SqlitePool,
     user_id: i64,
@@ -69,3 +150,76 @@ pub async fn load_battle_replay(
 
     Ok(records)
 }
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct ActiveBattleRow {
+    pub user_id: i64,
+    pub episode_id: i32,
+    pub fight_id: i64,
+    pub round_number: i32,
+    pub fight: String,
+    pub last_round: String,
+    pub current_deck: String,
+    pub ai_deck: String,
+    pub manager_state: String,
+    pub battle_info: String,
+}
+
+pub async fn save_active_battle(pool: &SqlitePool, row: &ActiveBattleRow) -> Result<()> {
+    let mut tx = pool.begin().await?;
+    save_active_battle_in(&mut tx, row).await?;
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Writes the active battle as part of `tx`
+pub async fn save_active_battle_in(
+    tx: &mut Transaction<'_, Sqlite>,
+    row: &ActiveBattleRow,
+) -> Result<()> {
+    sqlx::query(
+        "INSERT OR REPLACE INTO user_active_battles
+         (user_id, episode_id, fight_id, round_number, fight, last_round,
+          current_deck, ai_deck, manager_state, battle_info, updated_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(row.user_id)
+    .bind(row.episode_id)
+    .bind(row.fight_id)
+    .bind(row.round_number)
+    .bind(&row.fight)
+    .bind(&row.last_round)
+    .bind(&row.current_deck)
+    .bind(&row.ai_deck)
+    .bind(&row.manager_state)
+    .bind(&row.battle_info)
+    .bind(chrono::Utc::now().timestamp())
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn get_active_battle(pool: &SqlitePool, user_id: i64) -> Result<Option<ActiveBattleRow>> {
+    let row = sqlx::query_as::<_, ActiveBattleRow>(
+        "SELECT user_id, episode_id, fight_id, round_number, fight, last_round,
+                current_deck, ai_deck, manager_state, battle_info
+         FROM user_active_battles
+         WHERE user_id = ?",
+    )
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row)
+}
+
+pub async fn delete_active_battle(pool: &SqlitePool, user_id: i64) -> Result<()> {
+    sqlx::query("DELETE FROM user_active_battles WHERE user_id = ?")
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,182 @@
+use crate::models::game::battle_pass::{UserBattlePass, UserBpBonus};
+use anyhow::Result;
+use sqlx::{Sqlite, SqlitePool, Transaction};
+
+const PASS_COLUMNS: &str = "bp_id, season_start, score, weekly_score, score_week, pay_status, \
+                            first_show, sp_first_show";
+const BONUS_COLUMNS: &str =
+    "level, free_bonus, pay_bonus, sp_free_bonus, sp_pay_bonus, select_index";
+
+pub async fn get_battle_pass(pool: &SqlitePool, user_id: i64) -> Result<Option<UserBattlePass>> {
+    let pass = sqlx::query_as::<_, UserBattlePass>(&format!(
+        "SELECT {} FROM user_battle_pass WHERE user_id = ?",
+        PASS_COLUMNS
+    ))
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(pass)
+}
+
+/// Replaces the user's pass with a fresh one for a new season, dropping the claimed levels
+pub async fn start_season(
+    pool: &SqlitePool,
+    user_id: i64,
+    bp_id: i32,
+    season_start: i64,
+    week: i32,
+) -> Result<UserBattlePass> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query("DELETE FROM user_bp_bonus WHERE user_id = ?")
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await?;
+
+    let pass = sqlx::query_as::<_, UserBattlePass>(&format!(
+        "INSERT OR REPLACE INTO user_battle_pass (user_id, bp_id, season_start, score_week)
+         VALUES (?, ?, ?, ?)
+         RETURNING {}",
+        PASS_COLUMNS
+    ))
+    .bind(user_id)
+    .bind(bp_id)
+    .bind(season_start)
+    .bind(week)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(pass)
+}
+
+pub async fn reset_weekly_score(pool: &SqlitePool, user_id: i64, week: i32) -> Result<()> {
+    sqlx::query("UPDATE user_battle_pass SET weekly_score = 0, score_week = ? WHERE user_id = ?")
+        .bind(week)
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Adds to the score, `weekly` is the part counting towards the weekly cap
+pub async fn add_score(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: i64,
+    score: i32,
+    weekly: i32,
+) -> Result<Option<UserBattlePass>> {
+    let pass = sqlx::query_as::<_, UserBattlePass>(&format!(
+        "UPDATE user_battle_pass SET score = score + ?, weekly_score = weekly_score + ?
+         WHERE user_id = ?
+         RETURNING {}",
+        PASS_COLUMNS
+    ))
+    .bind(score)
+    .bind(weekly)
+    .bind(user_id)
+    .fetch_optional(&mut **tx)
+    .await?;
+
+    Ok(pass)
+}
+
+pub async fn set_pay_status(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: i64,
+    pay_status: i32,
+) -> Result<()> {
+    sqlx::query("UPDATE user_battle_pass SET pay_status = ? WHERE user_id = ?")
+        .bind(pay_status)
+        .bind(user_id)
+        .execute(&mut **tx)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn mark_first_show(pool: &SqlitePool, user_id: i64, is_sp: bool) -> Result<()> {
+    let column = if is_sp { "sp_first_show" } else { "first_show" };
+
+    sqlx::query(&format!(
+        "UPDATE user_battle_pass SET {} = 0 WHERE user_id = ?",
+        column
+    ))
+    .bind(user_id)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn get_bonuses(pool: &SqlitePool, user_id: i64) -> Result<Vec<UserBpBonus>> {
+    let bonuses = sqlx::query_as::<_, UserBpBonus>(&format!(
+        "SELECT {} FROM user_bp_bonus WHERE user_id = ? ORDER BY level",
+        BONUS_COLUMNS
+    ))
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(bonuses)
+}
+
+/// Writes the claim flags of the given levels
+pub async fn save_bonuses(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: i64,
+    bonuses: &[UserBpBonus],
+) -> Result<()> {
+    for bonus in bonuses {
+        sqlx::query(
+            r#"
+            INSERT INTO user_bp_bonus
+                (user_id, level, free_bonus, pay_bonus, sp_free_bonus, sp_pay_bonus, select_index)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT(user_id, level) DO UPDATE SET
+                free_bonus = excluded.free_bonus,
+                pay_bonus = excluded.pay_bonus,
+                sp_free_bonus = excluded.sp_free_bonus,
+                sp_pay_bonus = excluded.sp_pay_bonus,
+                select_index = excluded.select_index
+            "#,
+        )
+        .bind(user_id)
+        .bind(bonus.level)
+        .bind(bonus.free_bonus)
+        .bind(bonus.pay_bonus)
+        .bind(bonus.sp_free_bonus)
+        .bind(bonus.sp_pay_bonus)
+        .bind(bonus.select_index)
+        .execute(&mut **tx)
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Records the self-select reward picked at a level, false if one was picked already
+pub async fn select_bonus(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: i64,
+    level: i32,
+    index: i32,
+) -> Result<bool> {
+    let result = sqlx::query(
+        r#"
+        INSERT INTO user_bp_bonus (user_id, level, select_index)
+        VALUES (?, ?, ?)
+        ON CONFLICT(user_id, level) DO UPDATE SET select_index = excluded.select_index
+        WHERE select_index IS NULL
+        "#,
+    )
+    .bind(user_id)
+    .bind(level)
+    .bind(index)
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
@@ -1,6 +1,7 @@
+use super::materials;
 use crate::models::game::currencies::Currency;
-use common::time::ServerTime;
-use sqlx::SqlitePool;
+use crate::models::game::ledger::LedgerOrigin;
+use sqlx::{Sqlite, SqlitePool, Transaction};
 
 pub async fn get_currencies(
     pool: &SqlitePool,
@@ -48,23 +49,20 @@ pub async fn get_currency(
     .await
 }
 
-pub async fn save_currency(pool: &SqlitePool, currency: &Currency) -> sqlx::Result<()> {
-    sqlx::query(
-        "INSERT INTO currencies (user_id, currency_id, quantity, last_recover_time, expired_time)
-         VALUES (?, ?, ?, ?, ?)
-         ON CONFLICT(user_id, currency_id) DO UPDATE SET
-             quantity = excluded.quantity,
-             last_recover_time = excluded.last_recover_time,
-             expired_time = excluded.expired_time",
-    )
-    .bind(currency.user_id)
-    .bind(currency.currency_id)
-    .bind(currency.quantity)
-    .bind(currency.last_recover_time)
-    .bind(currency.expired_time)
-    .execute(pool)
-    .await?;
-    Ok(())
+/// Quantity of a currency as `tx` sees it, 0 without a row
+pub async fn get_currency_quantity(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: i64,
+    currency_id: i32,
+) -> sqlx::Result<i32> {
+    let quantity: Option<i32> =
+        sqlx::query_scalar("SELECT quantity FROM currencies WHERE user_id = ? AND currency_id = ?")
+            .bind(user_id)
+            .bind(currency_id)
+            .fetch_optional(&mut **tx)
+            .await?;
+
+    Ok(quantity.unwrap_or(0))
 }
 
 pub async fn add_currency(
@@ -72,22 +70,11 @@ pub async fn add_currency(
     user_id: i64,
     currency_id: i32,
     amount: i32,
-) -> sqlx::Result<()> {
-    let timestamp = ServerTime::now_ms();
-
-    sqlx::query(
-        "INSERT INTO currencies (user_id, currency_id, quantity, last_recover_time, expired_time)
-         VALUES (?, ?, ?, ?, 0)
-         ON CONFLICT(user_id, currency_id) DO UPDATE SET
-             quantity = quantity + excluded.quantity,
-             last_recover_time = excluded.last_recover_time",
-    )
-    .bind(user_id)
-    .bind(currency_id)
-    .bind(amount)
-    .bind(timestamp as i64)
-    .execute(pool)
-    .await?;
+    origin: LedgerOrigin,
+) -> anyhow::Result<()> {
+    let mut tx = pool.begin().await?;
+    materials::give_currency(&mut tx, user_id, currency_id, amount, origin).await?;
+    tx.commit().await?;
     Ok(())
 }
 
@@ -96,54 +83,46 @@ pub async fn remove_currency(
     user_id: i64,
     currency_id: i32,
     amount: i32,
-) -> sqlx::Result<bool> {
-    let current: Option<i32> =
-        sqlx::query_scalar("SELECT quantity FROM currencies WHERE user_id = ? AND currency_id = ?")
-            .bind(user_id)
-            .bind(currency_id)
-            .fetch_optional(pool)
-            .await?;
-
-    if current.unwrap_or(0) < amount {
+    origin: LedgerOrigin,
+) -> anyhow::Result<bool> {
+    let mut tx = pool.begin().await?;
+    if !materials::take_currency(&mut tx, user_id, currency_id, amount, origin).await? {
         return Ok(false);
     }
+    tx.commit().await?;
+    Ok(true)
+}
 
-    let timestamp = ServerTime::now_ms();
-    sqlx::query(
-        "UPDATE currencies
-         SET quantity = quantity - ?, last_recover_time = ?
-         WHERE user_id = ? AND currency_id = ?",
-    )
-    .bind(amount)
-    .bind(timestamp as i64)
-    .bind(user_id)
-    .bind(currency_id)
-    .execute(pool)
-    .await?;
+/// Stamina purchases made since the last daily reset
+pub async fn get_power_buy_count(pool: &SqlitePool, user_id: i64) -> sqlx::Result<i32> {
+    let count: Option<i32> =
+        sqlx::query_scalar("SELECT buy_count FROM user_power_buy WHERE user_id = ?")
+            .bind(user_id)
+            .fetch_optional(pool)
+            .await?;
 
-    Ok(true)
+    Ok(count.unwrap_or(0))
 }
 
-pub async fn set_currency(
-    pool: &SqlitePool,
+/// Counts one more stamina purchase, `None` once `limit` purchases were made
+pub async fn add_power_buy_count(
+    tx: &mut Transaction<'_, Sqlite>,
     user_id: i64,
-    currency_id: i32,
-    quantity: i32,
-) -> sqlx::Result<()> {
-    let timestamp = ServerTime::now_ms();
+    limit: i32,
+) -> sqlx::Result<Option<i32>> {
+    if limit <= 0 {
+        return Ok(None);
+    }
 
-    sqlx::query(
-        "INSERT INTO currencies (user_id, currency_id, quantity, last_recover_time, expired_time)
-         VALUES (?, ?, ?, ?, 0)
-         ON CONFLICT(user_id, currency_id) DO UPDATE SET
-             quantity = excluded.quantity,
-             last_recover_time = excluded.last_recover_time",
+    sqlx::query_scalar(
+        "INSERT INTO user_power_buy (user_id, buy_count)
+         VALUES (?, 1)
+         ON CONFLICT(user_id) DO UPDATE SET buy_count = buy_count + 1
+         WHERE buy_count < ?
+         RETURNING buy_count",
     )
     .bind(user_id)
-    .bind(currency_id)
-    .bind(quantity)
-    .bind(timestamp as i64)
-    .execute(pool)
-    .await?;
-    Ok(())
+    .bind(limit)
+    .fetch_optional(&mut **tx)
+    .await
 }
@@ -39,6 +39,8 @@ pub struct UserDungeonInfo {
     pub star: i32,
     pub challenge_count: i32,
     pub has_record: bool,
+    pub today_pass_num: i32,
+    pub today_total_num: i32,
 }
 
 pub async fn get_user_dungeon(
@@ -48,7 +50,7 @@ pub async fn get_user_dungeon(
     episode_id: i32,
 ) -> Result<UserDungeonInfo> {
     let dungeon = sqlx::query_as::<_, UserDungeonInfo>(
-        "SELECT star, challenge_count, has_record
+        "SELECT star, challenge_count, has_record, today_pass_num, today_total_num
          FROM user_dungeons
          WHERE user_id = ? AND chapter_id = ? AND episode_id = ?",
     )
@@ -61,6 +63,26 @@ pub async fn get_user_dungeon(
     Ok(dungeon)
 }
 
+/// Best star grade reached on an episode, 0 if never cleared
+pub async fn get_dungeon_star(
+    pool: &SqlitePool,
+    user_id: i64,
+    chapter_id: i32,
+    episode_id: i32,
+) -> Result<i32> {
+    let star = sqlx::query_scalar(
+        "SELECT star FROM user_dungeons
+         WHERE user_id = ? AND chapter_id = ? AND episode_id = ?",
+    )
+    .bind(user_id)
+    .bind(chapter_id)
+    .bind(episode_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(star.unwrap_or(0))
+}
+
 pub async fn get_dungeon_last_hero_groups(
     pool: &SqlitePool,
     user_id: i64,
@@ -170,6 +192,34 @@ pub async fn get_chapter_type_nums(
     Ok(nums)
 }
 
+/// Counts a clear towards today's totals of the chapter type
+pub async fn add_chapter_type_pass(
+    pool: &SqlitePool,
+    user_id: i64,
+    chapter_type: i32,
+) -> Result<UserChapterTypeNum> {
+    let now = common::time::ServerTime::now_ms();
+
+    let nums = sqlx::query_as::<_, UserChapterTypeNum>(
+        r#"
+        INSERT INTO user_chapter_type_nums
+        (user_id, chapter_type, today_pass_num, today_total_num, last_reset_date)
+        VALUES (?, ?, 1, 1, ?)
+        ON CONFLICT(user_id, chapter_type) DO UPDATE SET
+            today_pass_num = today_pass_num + 1,
+            today_total_num = today_total_num + 1
+        RETURNING chapter_type, today_pass_num, today_total_num
+        "#,
+    )
+    .bind(user_id)
+    .bind(chapter_type)
+    .bind(now)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(nums)
+}
+
 pub async fn get_finished_puzzles(pool: &SqlitePool, user_id: i64) -> Result<Vec<i32>> {
     let puzzles =
         sqlx::query_scalar("SELECT puzzle_id FROM user_dungeon_finished_puzzles WHERE user_id = ?")
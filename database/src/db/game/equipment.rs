@@ -1,6 +1,6 @@
 use anyhow::Result;
 use sonettobuf::FightEquipRecord;
-use sqlx::SqlitePool;
+use sqlx::{Sqlite, SqlitePool, Transaction};
 
 pub use crate::models::game::equipment::Equipment;
 
@@ -58,6 +58,29 @@ pub async fn get_equipment_by_uid(
     Ok(equip)
 }
 
+/// Set level, breakthrough and refinement directly, used when building fixed loadouts
+pub async fn set_equipment_progress(
+    pool: &SqlitePool,
+    user_id: i64,
+    equip_uid: i64,
+    level: i32,
+    break_lv: i32,
+    refine_lv: i32,
+) -> Result<()> {
+    sqlx::query(
+        "UPDATE equipment SET level = ?, break_lv = ?, refine_lv = ? WHERE uid = ? AND user_id = ?",
+    )
+    .bind(level)
+    .bind(break_lv)
+    .bind(refine_lv)
+    .bind(equip_uid)
+    .bind(user_id)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
 pub async fn update_equipment_lock(
     pool: &SqlitePool,
     user_id: i64,
@@ -119,8 +142,10 @@ pub async fn build_equip_records(
     Ok(equip_records)
 }
 
-pub async fn add_equipment(
-    pool: &SqlitePool,
+/// Adds `count` of an equipment on the caller's transaction. Stackable exp equipment goes
+/// into one row, everything else gets a row per copy. Returns the changed uids.
+pub async fn insert_equipment(
+    tx: &mut Transaction<'_, Sqlite>,
     user_id: i64,
     equip_id: i32,
     count: i32,
@@ -153,7 +178,7 @@ pub async fn add_equipment(
         )
         .bind(user_id)
         .bind(equip_id)
-        .fetch_optional(pool)
+        .fetch_optional(&mut **tx)
         .await?
         {
             sqlx::query(
@@ -167,7 +192,7 @@ pub async fn add_equipment(
             .bind(now)
             .bind(uid)
             .bind(user_id)
-            .execute(pool)
+            .execute(&mut **tx)
             .await?;
 
             uids.push(uid);
@@ -176,7 +201,7 @@ pub async fn add_equipment(
                 "SELECT COALESCE(MAX(uid), 29999999) + 1 FROM equipment WHERE user_id = ?",
             )
             .bind(user_id)
-            .fetch_one(pool)
+            .fetch_one(&mut **tx)
             .await?;
 
             sqlx::query(
@@ -197,7 +222,7 @@ pub async fn add_equipment(
             .bind(refine_lv)
             .bind(now)
             .bind(now)
-            .execute(pool)
+            .execute(&mut **tx)
             .await?;
 
             uids.push(uid);
@@ -207,7 +232,7 @@ pub async fn add_equipment(
             "SELECT COALESCE(MAX(uid), 29999999) + 1 FROM equipment WHERE user_id = ?",
         )
         .bind(user_id)
-        .fetch_one(pool)
+        .fetch_one(&mut **tx)
         .await?;
 
         for _ in 0..count {
@@ -228,7 +253,7 @@ pub async fn add_equipment(
             .bind(refine_lv)
             .bind(now)
             .bind(now)
-            .execute(pool)
+            .execute(&mut **tx)
             .await?;
 
             uids.push(next_uid);
@@ -239,82 +264,72 @@ pub async fn add_equipment(
     Ok(uids)
 }
 
-/// Get total count of equipment by equip_id (counts all matching rows)
-pub async fn get_equipment_count(pool: &SqlitePool, user_id: i64, equip_id: i32) -> Result<i32> {
-    let count: i64 =
-        sqlx::query_scalar("SELECT COUNT(*) FROM equipment WHERE user_id = ? AND equip_id = ?")
-            .bind(user_id)
-            .bind(equip_id)
-            .fetch_one(pool)
-            .await?;
+/// Writes the level and exp of a strengthened equipment on the caller's transaction
+pub async fn set_equipment_level(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: i64,
+    uid: i64,
+    level: i32,
+    exp: i32,
+) -> Result<()> {
+    sqlx::query(
+        "UPDATE equipment SET level = ?, exp = ?, updated_at = ? WHERE uid = ? AND user_id = ?",
+    )
+    .bind(level)
+    .bind(exp)
+    .bind(common::time::ServerTime::now_ms())
+    .bind(uid)
+    .bind(user_id)
+    .execute(&mut **tx)
+    .await?;
 
-    Ok(count as i32)
+    Ok(())
 }
 
-pub async fn update_equipment_count(
-    pool: &SqlitePool,
+/// Writes the refine level of an equipment on the caller's transaction
+pub async fn set_equipment_refine_level(
+    tx: &mut Transaction<'_, Sqlite>,
     user_id: i64,
-    equip_id: i32,
-    amount: i32,
-) -> Result<Vec<i64>> {
-    let now = common::time::ServerTime::now_ms();
+    uid: i64,
+    refine_lv: i32,
+) -> Result<()> {
+    sqlx::query("UPDATE equipment SET refine_lv = ?, updated_at = ? WHERE uid = ? AND user_id = ?")
+        .bind(refine_lv)
+        .bind(common::time::ServerTime::now_ms())
+        .bind(uid)
+        .bind(user_id)
+        .execute(&mut **tx)
+        .await?;
 
-    let uid = sqlx::query_scalar::<_, i64>(
-        "SELECT uid FROM equipment WHERE user_id = ? AND equip_id = ? LIMIT 1",
-    )
-    .bind(user_id)
-    .bind(equip_id)
-    .fetch_one(pool)
-    .await?;
+    Ok(())
+}
 
-    sqlx::query(
-        r#"
-        UPDATE equipment
-        SET count = count + ?, updated_at = ?
-        WHERE uid = ? AND user_id = ?
-        "#,
+/// Raises the break level of an equipment by one on the caller's transaction
+pub async fn increment_break_level(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: i64,
+    uid: i64,
+) -> Result<bool> {
+    let result = sqlx::query(
+        "UPDATE equipment SET break_lv = break_lv + 1, updated_at = ? WHERE uid = ? AND user_id = ?",
     )
-    .bind(amount)
-    .bind(now)
+    .bind(common::time::ServerTime::now_ms())
     .bind(uid)
     .bind(user_id)
-    .execute(pool)
+    .execute(&mut **tx)
     .await?;
 
-    Ok(vec![uid])
+    Ok(result.rows_affected() > 0)
 }
 
-pub async fn add_equipments(
-    pool: &SqlitePool,
-    user_id: i64,
-    equips: &[(i32, i32)],
-) -> Result<Vec<i64>> {
-    let mut changed_uids = Vec::new();
-
-    for (equip_id, count) in equips {
-        let is_stackable = matches!(*equip_id, 1002 | 1003 | 1004 | 1005);
-
-        if is_stackable {
-            if let Some(uid) = sqlx::query_scalar::<_, i64>(
-                "SELECT uid FROM equipment WHERE user_id = ? AND equip_id = ? LIMIT 1",
-            )
+/// Get total count of equipment by equip_id (counts all matching rows)
+pub async fn get_equipment_count(pool: &SqlitePool, user_id: i64, equip_id: i32) -> Result<i32> {
+    let count: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM equipment WHERE user_id = ? AND equip_id = ?")
             .bind(user_id)
             .bind(equip_id)
-            .fetch_optional(pool)
-            .await?
-            {
-                update_equipment_count(pool, user_id, *equip_id, *count).await?;
-                changed_uids.push(uid);
-            } else {
-                let uids = add_equipment(pool, user_id, *equip_id, *count).await?;
-                debug_assert_eq!(uids.len(), 1);
-                changed_uids.push(uids[0]);
-            }
-        } else {
-            let uids = add_equipment(pool, user_id, *equip_id, *count).await?;
-            changed_uids.extend(uids);
-        }
-    }
+            .fetch_one(pool)
+            .await?;
 
-    Ok(changed_uids)
+    Ok(count as i32)
 }
@@ -0,0 +1,93 @@
+use crate::models::game::ledger::{LedgerEntry, LedgerOrigin, LedgerSource};
+use anyhow::Result;
+use common::time::ServerTime;
+use sqlx::{Sqlite, SqlitePool, Transaction};
+
+/// Appends a change to the economy ledger, inside the transaction that made it
+pub async fn record(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: i64,
+    resource_type: u32,
+    resource_id: i64,
+    delta: i32,
+    balance_after: i64,
+    origin: LedgerOrigin,
+) -> sqlx::Result<()> {
+    sqlx::query(
+        "INSERT INTO economy_ledger
+         (user_id, resource_type, resource_id, delta, balance_after, source, reference_id, created_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(user_id)
+    .bind(resource_type as i64)
+    .bind(resource_id)
+    .bind(delta)
+    .bind(balance_after)
+    .bind(origin.source.as_str())
+    .bind(origin.reference_id)
+    .bind(ServerTime::now_ms())
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(())
+}
+
+/// Filter of [`query_ledger`], unset fields match everything
+#[derive(Debug, Clone)]
+pub struct LedgerFilter {
+    pub user_id: Option<i64>,
+    pub resource_type: Option<u32>,
+    pub resource_id: Option<i64>,
+    pub source: Option<LedgerSource>,
+    pub reference_id: Option<i64>,
+    /// Unix ms, inclusive
+    pub since: Option<i64>,
+    /// Unix ms, exclusive
+    pub until: Option<i64>,
+    pub limit: i64,
+}
+
+impl Default for LedgerFilter {
+    fn default() -> Self {
+        Self {
+            user_id: None,
+            resource_type: None,
+            resource_id: None,
+            source: None,
+            reference_id: None,
+            since: None,
+            until: None,
+            limit: 100,
+        }
+    }
+}
+
+/// Matching ledger entries, newest first
+pub async fn query_ledger(pool: &SqlitePool, filter: &LedgerFilter) -> Result<Vec<LedgerEntry>> {
+    let entries = sqlx::query_as::<_, LedgerEntry>(
+        "SELECT id, user_id, resource_type, resource_id, delta, balance_after, source,
+                reference_id, created_at
+         FROM economy_ledger
+         WHERE (?1 IS NULL OR user_id = ?1)
+           AND (?2 IS NULL OR resource_type = ?2)
+           AND (?3 IS NULL OR resource_id = ?3)
+           AND (?4 IS NULL OR source = ?4)
+           AND (?5 IS NULL OR reference_id = ?5)
+           AND (?6 IS NULL OR created_at >= ?6)
+           AND (?7 IS NULL OR created_at < ?7)
+         ORDER BY id DESC
+         LIMIT ?8",
+    )
+    .bind(filter.user_id)
+    .bind(filter.resource_type.map(|t| t as i64))
+    .bind(filter.resource_id)
+    .bind(filter.source.map(LedgerSource::as_str))
+    .bind(filter.reference_id)
+    .bind(filter.since)
+    .bind(filter.until)
+    .bind(filter.limit)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(entries)
+}
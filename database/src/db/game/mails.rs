@@ -0,0 +1,371 @@
+use crate::models::game::mails::{MAIL_READ, MAIL_UNREAD, MailTemplate, NewMail, UserMail};
+use anyhow::Result;
+use common::time::ServerTime;
+use sqlx::{Sqlite, SqlitePool, Transaction};
+
+const MAIL_COLUMNS: &str = "incr_id, user_id, mail_id, params, attachment, state, create_time,
+     sender, title, content, copy, expire_time, sender_type, jump_title, jump";
+
+/// Appends a mail action to `user_mail_history`
+async fn record_history(
+    tx: &mut Transaction<'_, Sqlite>,
+    mail: &UserMail,
+    action: &str,
+    now: i64,
+) -> sqlx::Result<()> {
+    sqlx::query(
+        "INSERT INTO user_mail_history
+         (user_id, mail_incr_id, mail_id, attachment, action, action_time, state_at_action)
+         VALUES (?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(mail.user_id)
+    .bind(mail.incr_id)
+    .bind(mail.mail_id)
+    .bind(&mail.attachment)
+    .bind(action)
+    .bind(now)
+    .bind(mail.state)
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(())
+}
+
+/// All mails of a user, newest first
+pub async fn get_mails(pool: &SqlitePool, user_id: i64) -> Result<Vec<UserMail>> {
+    let mails = sqlx::query_as::<_, UserMail>(&format!(
+        "SELECT {} FROM user_mails WHERE user_id = ? ORDER BY create_time DESC",
+        MAIL_COLUMNS
+    ))
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(mails)
+}
+
+/// Unread mails of a user that have not expired yet, oldest first
+pub async fn get_unread_mails(pool: &SqlitePool, user_id: i64, now: i64) -> Result<Vec<UserMail>> {
+    let mails = sqlx::query_as::<_, UserMail>(&format!(
+        "SELECT {} FROM user_mails
+         WHERE user_id = ? AND state = ? AND (expire_time = 0 OR expire_time > ?)
+         ORDER BY create_time ASC",
+        MAIL_COLUMNS
+    ))
+    .bind(user_id)
+    .bind(MAIL_UNREAD)
+    .bind(now)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(mails)
+}
+
+/// Inserts a mail inside `tx` and records it as created
+pub async fn insert_mail(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: i64,
+    mail: &NewMail,
+    now: i64,
+) -> Result<UserMail> {
+    let inserted = sqlx::query_as::<_, UserMail>(&format!(
+        "INSERT INTO user_mails
+         (user_id, mail_id, params, attachment, state, create_time, sender, title,
+          content, copy, expire_time, sender_type, jump_title, jump)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+         RETURNING {}",
+        MAIL_COLUMNS
+    ))
+    .bind(user_id)
+    .bind(mail.mail_id)
+    .bind(&mail.params)
+    .bind(&mail.attachment)
+    .bind(MAIL_UNREAD)
+    .bind(now)
+    .bind(&mail.sender)
+    .bind(&mail.title)
+    .bind(&mail.content)
+    .bind(&mail.copy)
+    .bind(mail.expire_time)
+    .bind(mail.sender_type)
+    .bind(&mail.jump_title)
+    .bind(&mail.jump)
+    .fetch_one(&mut **tx)
+    .await?;
+
+    record_history(tx, &inserted, "created", now).await?;
+
+    Ok(inserted)
+}
+
+pub async fn send_mail(pool: &SqlitePool, user_id: i64, mail: &NewMail) -> Result<UserMail> {
+    let mut tx = pool.begin().await?;
+    let inserted = insert_mail(&mut tx, user_id, mail, ServerTime::now_ms()).await?;
+    tx.commit().await?;
+
+    Ok(inserted)
+}
+
+pub async fn get_template(pool: &SqlitePool, template_id: i32) -> Result<Option<MailTemplate>> {
+    let template = sqlx::query_as::<_, MailTemplate>(
+        "SELECT id, sender, sender_type, title, content, attachment, expire_hours,
+                jump_title, jump
+         FROM mail_templates WHERE id = ?",
+    )
+    .bind(template_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(template)
+}
+
+/// Delivers a broadcast to one user, unless it already was
+async fn deliver_broadcast(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: i64,
+    broadcast_id: i64,
+    mail: &NewMail,
+    now: i64,
+) -> Result<Option<UserMail>> {
+    let claimed = sqlx::query(
+        "INSERT INTO user_mail_broadcasts (user_id, broadcast_id, mail_incr_id, delivered_at)
+         VALUES (?, ?, 0, ?)
+         ON CONFLICT(user_id, broadcast_id) DO NOTHING",
+    )
+    .bind(user_id)
+    .bind(broadcast_id)
+    .bind(now)
+    .execute(&mut **tx)
+    .await?;
+
+    if claimed.rows_affected() == 0 {
+        return Ok(None);
+    }
+
+    let inserted = insert_mail(tx, user_id, mail, now).await?;
+
+    sqlx::query(
+        "UPDATE user_mail_broadcasts SET mail_incr_id = ? WHERE user_id = ? AND broadcast_id = ?",
+    )
+    .bind(inserted.incr_id)
+    .bind(user_id)
+    .bind(broadcast_id)
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(Some(inserted))
+}
+
+/// Sends a mail to every account, accounts created before `end_time` (unix ms)
+/// get it through [`deliver_broadcasts`].
+pub async fn create_broadcast(
+    pool: &SqlitePool,
+    mail: &NewMail,
+    end_time: i64,
+) -> Result<(i64, Vec<UserMail>)> {
+    let now = ServerTime::now_ms();
+    let mut tx = pool.begin().await?;
+
+    let broadcast_id: i64 = sqlx::query_scalar(
+        "INSERT INTO mail_broadcasts
+         (mail_id, params, attachment, sender, title, content, copy, expire_time,
+          sender_type, jump_title, jump, start_time, end_time)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+         RETURNING id",
+    )
+    .bind(mail.mail_id)
+    .bind(&mail.params)
+    .bind(&mail.attachment)
+    .bind(&mail.sender)
+    .bind(&mail.title)
+    .bind(&mail.content)
+    .bind(&mail.copy)
+    .bind(mail.expire_time)
+    .bind(mail.sender_type)
+    .bind(&mail.jump_title)
+    .bind(&mail.jump)
+    .bind(now)
+    .bind(end_time)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let user_ids: Vec<i64> = sqlx::query_scalar("SELECT id FROM users")
+        .fetch_all(&mut *tx)
+        .await?;
+
+    let mut delivered = Vec::with_capacity(user_ids.len());
+    for user_id in user_ids {
+        if let Some(m) = deliver_broadcast(&mut tx, user_id, broadcast_id, mail, now).await? {
+            delivered.push(m);
+        }
+    }
+
+    tx.commit().await?;
+
+    Ok((broadcast_id, delivered))
+}
+
+/// Delivers the open broadcasts a user has not received yet
+pub async fn deliver_broadcasts(pool: &SqlitePool, user_id: i64) -> Result<Vec<UserMail>> {
+    let now = ServerTime::now_ms();
+
+    let pending = sqlx::query_as::<
+        _,
+        (
+            i64,
+            i32,
+            String,
+            String,
+            String,
+            String,
+            String,
+            String,
+            i64,
+            i32,
+            String,
+            String,
+        ),
+    >(
+        "SELECT b.id, b.mail_id, b.params, b.attachment, b.sender, b.title, b.content, b.copy,
+                b.expire_time, b.sender_type, b.jump_title, b.jump
+         FROM mail_broadcasts b
+         WHERE b.start_time <= ?1 AND b.end_time > ?1
+           AND (b.expire_time = 0 OR b.expire_time > ?1)
+           AND NOT EXISTS (
+               SELECT 1 FROM user_mail_broadcasts d
+               WHERE d.user_id = ?2 AND d.broadcast_id = b.id
+           )
+         ORDER BY b.id",
+    )
+    .bind(now)
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    if pending.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut tx = pool.begin().await?;
+    let mut delivered = Vec::with_capacity(pending.len());
+
+    for (
+        broadcast_id,
+        mail_id,
+        params,
+        attachment,
+        sender,
+        title,
+        content,
+        copy,
+        expire_time,
+        sender_type,
+        jump_title,
+        jump,
+    ) in pending
+    {
+        let mail = NewMail {
+            mail_id,
+            params,
+            attachment,
+            sender,
+            title,
+            content,
+            copy,
+            expire_time,
+            sender_type,
+            jump_title,
+            jump,
+        };
+
+        if let Some(m) = deliver_broadcast(&mut tx, user_id, broadcast_id, &mail, now).await? {
+            delivered.push(m);
+        }
+    }
+
+    tx.commit().await?;
+
+    Ok(delivered)
+}
+
+/// Deletes expired mails of one user, or of everyone when `user_id` is `None`.
+/// Returns the deleted mails.
+pub async fn expire_mails(
+    pool: &SqlitePool,
+    user_id: Option<i64>,
+    now: i64,
+) -> Result<Vec<UserMail>> {
+    let mut tx = pool.begin().await?;
+
+    let expired = sqlx::query_as::<_, UserMail>(&format!(
+        "DELETE FROM user_mails
+         WHERE (?1 IS NULL OR user_id = ?1) AND expire_time > 0 AND expire_time < ?2
+         RETURNING {}",
+        MAIL_COLUMNS
+    ))
+    .bind(user_id)
+    .bind(now)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    for mail in &expired {
+        record_history(&mut tx, mail, "expired", now).await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(expired)
+}
+
+/// Deletes the read mails of a user, unclaimed attachments are kept.
+/// Returns the deleted mail ids.
+pub async fn delete_read_mails(pool: &SqlitePool, user_id: i64) -> Result<Vec<i64>> {
+    let now = ServerTime::now_ms();
+    let mut tx = pool.begin().await?;
+
+    let deleted = sqlx::query_as::<_, UserMail>(&format!(
+        "DELETE FROM user_mails WHERE user_id = ? AND state = ? RETURNING {}",
+        MAIL_COLUMNS
+    ))
+    .bind(user_id)
+    .bind(MAIL_READ)
+    .fetch_all(&mut *tx)
+    .await?;
+
+    for mail in &deleted {
+        record_history(&mut tx, mail, "deleted", now).await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(deleted.into_iter().map(|m| m.incr_id).collect())
+}
+
+/// Marks a mail without attachment as read once its jump was followed.
+/// Returns false when the mail is missing, already read or still has an attachment.
+pub async fn mark_mail_read(pool: &SqlitePool, user_id: i64, incr_id: i64) -> Result<bool> {
+    let now = ServerTime::now_ms();
+    let mut tx = pool.begin().await?;
+
+    let mail = sqlx::query_as::<_, UserMail>(&format!(
+        "UPDATE user_mails SET state = ?
+         WHERE user_id = ? AND incr_id = ? AND state = ? AND attachment = ''
+         RETURNING {}",
+        MAIL_COLUMNS
+    ))
+    .bind(MAIL_READ)
+    .bind(user_id)
+    .bind(incr_id)
+    .bind(MAIL_UNREAD)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    let Some(mail) = mail else {
+        return Ok(false);
+    };
+
+    record_history(&mut tx, &mail, "read", now).await?;
+    tx.commit().await?;
+
+    Ok(true)
+}
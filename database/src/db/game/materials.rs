@@ -0,0 +1,753 @@
+use super::{equipment::insert_equipment, ledger};
+use crate::models::game::items::PowerItem;
+use crate::models::game::ledger::LedgerOrigin;
+use anyhow::Result;
+use common::time::ServerTime;
+use config::character_level::CharacterLevel;
+use sqlx::{Sqlite, Transaction};
+
+pub use crate::models::game::heros::insert_hero;
+
+/// `materilType` of the ledger rows
+const LEDGER_ITEM: u32 = 1;
+const LEDGER_CURRENCY: u32 = 2;
+const LEDGER_EQUIP: u32 = 9;
+const LEDGER_POWER_ITEM: u32 = 10;
+const LEDGER_INSIGHT_ITEM: u32 = 24;
+
+/// Removes `amount` of an item, `false` without touching it when the user has less
+pub async fn take_item(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: i64,
+    item_id: u32,
+    amount: i32,
+    origin: LedgerOrigin,
+) -> Result<bool> {
+    let now = ServerTime::now_ms();
+
+    let left: Option<i64> = sqlx::query_scalar(
+        "UPDATE items SET quantity = quantity - ?, last_use_time = ?, last_update_time = ?
+         WHERE user_id = ? AND item_id = ? AND quantity >= ?
+         RETURNING quantity",
+    )
+    .bind(amount)
+    .bind(now)
+    .bind(now)
+    .bind(user_id)
+    .bind(item_id as i64)
+    .bind(amount)
+    .fetch_optional(&mut **tx)
+    .await?;
+
+    let Some(left) = left else {
+        return Ok(false);
+    };
+
+    ledger::record(
+        tx,
+        user_id,
+        LEDGER_ITEM,
+        item_id as i64,
+        -amount,
+        left,
+        origin,
+    )
+    .await?;
+    Ok(true)
+}
+
+pub async fn give_item(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: i64,
+    item_id: u32,
+    amount: i32,
+    origin: LedgerOrigin,
+) -> Result<()> {
+    let balance: i64 = sqlx::query_scalar(
+        "INSERT INTO items (user_id, item_id, quantity, last_update_time, total_gain_count)
+         VALUES (?, ?, ?, ?, ?)
+         ON CONFLICT(user_id, item_id) DO UPDATE SET
+             quantity = quantity + excluded.quantity,
+             last_update_time = excluded.last_update_time,
+             total_gain_count = total_gain_count + excluded.total_gain_count
+         RETURNING quantity",
+    )
+    .bind(user_id)
+    .bind(item_id as i64)
+    .bind(amount)
+    .bind(ServerTime::now_ms())
+    .bind(amount as i64)
+    .fetch_one(&mut **tx)
+    .await?;
+
+    ledger::record(
+        tx,
+        user_id,
+        LEDGER_ITEM,
+        item_id as i64,
+        amount,
+        balance,
+        origin,
+    )
+    .await?;
+    Ok(())
+}
+
+/// Removes `amount` of a currency, `false` without touching it when the user has less
+pub async fn take_currency(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: i64,
+    currency_id: i32,
+    amount: i32,
+    origin: LedgerOrigin,
+) -> Result<bool> {
+    let left: Option<i64> = sqlx::query_scalar(
+        "UPDATE currencies SET quantity = quantity - ?, last_recover_time = ?
+         WHERE user_id = ? AND currency_id = ? AND quantity >= ?
+         RETURNING quantity",
+    )
+    .bind(amount)
+    .bind(ServerTime::now_ms())
+    .bind(user_id)
+    .bind(currency_id)
+    .bind(amount)
+    .fetch_optional(&mut **tx)
+    .await?;
+
+    let Some(left) = left else {
+        return Ok(false);
+    };
+
+    let id = currency_id as i64;
+    ledger::record(tx, user_id, LEDGER_CURRENCY, id, -amount, left, origin).await?;
+    Ok(true)
+}
+
+pub async fn give_currency(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: i64,
+    currency_id: i32,
+    amount: i32,
+    origin: LedgerOrigin,
+) -> Result<()> {
+    let balance: i64 = sqlx::query_scalar(
+        "INSERT INTO currencies (user_id, currency_id, quantity, last_recover_time, expired_time)
+         VALUES (?, ?, ?, ?, 0)
+         ON CONFLICT(user_id, currency_id) DO UPDATE SET
+             quantity = quantity + excluded.quantity,
+             last_recover_time = excluded.last_recover_time
+         RETURNING quantity",
+    )
+    .bind(user_id)
+    .bind(currency_id)
+    .bind(amount)
+    .bind(ServerTime::now_ms())
+    .fetch_one(&mut **tx)
+    .await?;
+
+    let id = currency_id as i64;
+    ledger::record(tx, user_id, LEDGER_CURRENCY, id, amount, balance, origin).await?;
+    Ok(())
+}
+
+/// Adds `delta` (negative spends) to a regenerating currency and stores the time its
+/// regeneration counts from. Returns the new amount, `None` without touching it when
+/// there isn't enough to spend.
+pub async fn change_regen_currency(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: i64,
+    currency_id: i32,
+    delta: i32,
+    recover_time: i64,
+    origin: LedgerOrigin,
+) -> Result<Option<i32>> {
+    let balance: Option<i64> = if delta >= 0 {
+        sqlx::query_scalar(
+            "INSERT INTO currencies
+             (user_id, currency_id, quantity, last_recover_time, expired_time)
+             VALUES (?, ?, ?, ?, 0)
+             ON CONFLICT(user_id, currency_id) DO UPDATE SET
+                 quantity = quantity + excluded.quantity,
+                 last_recover_time = excluded.last_recover_time
+             RETURNING quantity",
+        )
+        .bind(user_id)
+        .bind(currency_id)
+        .bind(delta)
+        .bind(recover_time)
+        .fetch_optional(&mut **tx)
+        .await?
+    } else {
+        sqlx::query_scalar(
+            "UPDATE currencies SET quantity = quantity + ?, last_recover_time = ?
+             WHERE user_id = ? AND currency_id = ? AND quantity + ? >= 0
+             RETURNING quantity",
+        )
+        .bind(delta)
+        .bind(recover_time)
+        .bind(user_id)
+        .bind(currency_id)
+        .bind(delta)
+        .fetch_optional(&mut **tx)
+        .await?
+    };
+
+    let Some(balance) = balance else {
+        return Ok(None);
+    };
+
+    if delta != 0 {
+        let id = currency_id as i64;
+        ledger::record(tx, user_id, LEDGER_CURRENCY, id, delta, balance, origin).await?;
+    }
+
+    Ok(Some(balance as i32))
+}
+
+/// Adds `count` stamina items, one row each. Items with an `expireType` last 10 days.
+pub async fn give_power_item(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: i64,
+    item_id: i32,
+    count: i32,
+    origin: LedgerOrigin,
+) -> Result<()> {
+    let now = ServerTime::now_ms();
+    let expire_time = match config::configs::get().power_item.get(item_id) {
+        Some(p) if (1..=3).contains(&p.expire_type) => (now / 1000) + 10 * 24 * 60 * 60,
+        _ => 0,
+    };
+
+    for _ in 0..count {
+        sqlx::query(
+            "INSERT INTO power_items (user_id, item_id, quantity, expire_time, created_at)
+             VALUES (?, ?, 1, ?, ?)",
+        )
+        .bind(user_id)
+        .bind(item_id)
+        .bind(expire_time)
+        .bind(now)
+        .execute(&mut **tx)
+        .await?;
+    }
+
+    let balance = power_item_count(tx, user_id, item_id).await?;
+    let id = item_id as i64;
+    ledger::record(tx, user_id, LEDGER_POWER_ITEM, id, count, balance, origin).await?;
+    Ok(())
+}
+
+/// Uses `num` power items from the stack `uid` belongs to (same item and expiry).
+/// Returns the item id, or `None` if the stack is missing, expired or too small.
+pub async fn take_power_items(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: i64,
+    uid: i64,
+    num: i32,
+    origin: LedgerOrigin,
+) -> Result<Option<i64>> {
+    let item: Option<PowerItem> =
+        sqlx::query_as("SELECT * FROM power_items WHERE user_id = ? AND uid = ?")
+            .bind(user_id)
+            .bind(uid)
+            .fetch_optional(&mut **tx)
+            .await?;
+
+    let Some(item) = item else {
+        return Ok(None);
+    };
+
+    let now = ServerTime::now_ms() / 1000;
+    if item.expire_time != 0 && (item.expire_time as i64) <= now {
+        return Ok(None);
+    }
+
+    let stack: Vec<PowerItem> = sqlx::query_as(
+        "SELECT * FROM power_items
+         WHERE user_id = ? AND item_id = ? AND expire_time = ?
+         ORDER BY uid = ? DESC, uid",
+    )
+    .bind(user_id)
+    .bind(item.item_id)
+    .bind(item.expire_time)
+    .bind(uid)
+    .fetch_all(&mut **tx)
+    .await?;
+
+    if stack.iter().map(|i| i.quantity).sum::<i32>() < num {
+        return Ok(None);
+    }
+
+    let mut left = num;
+    for row in stack {
+        if left <= 0 {
+            break;
+        }
+
+        let used = row.quantity.min(left);
+        left -= used;
+
+        if used == row.quantity {
+            sqlx::query("DELETE FROM power_items WHERE uid = ?")
+                .bind(row.uid)
+                .execute(&mut **tx)
+                .await?;
+        } else {
+            sqlx::query("UPDATE power_items SET quantity = quantity - ? WHERE uid = ?")
+                .bind(used)
+                .bind(row.uid)
+                .execute(&mut **tx)
+                .await?;
+        }
+    }
+
+    let balance = power_item_count(tx, user_id, item.item_id as i32).await?;
+    let id = item.item_id;
+    ledger::record(tx, user_id, LEDGER_POWER_ITEM, id, -num, balance, origin).await?;
+
+    Ok(Some(item.item_id))
+}
+
+/// Deletes the power item row `uid` once it expired. Returns its item id and quantity,
+/// `None` when the row is missing or still valid.
+pub async fn take_expired_power_item(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: i64,
+    uid: i64,
+    origin: LedgerOrigin,
+) -> Result<Option<(i64, i32)>> {
+    let now = ServerTime::now_ms() / 1000;
+
+    let removed: Option<(i64, i32)> = sqlx::query_as(
+        "DELETE FROM power_items
+         WHERE user_id = ? AND uid = ? AND expire_time > 0 AND expire_time < ?
+         RETURNING item_id, quantity",
+    )
+    .bind(user_id)
+    .bind(uid)
+    .bind(now)
+    .fetch_optional(&mut **tx)
+    .await?;
+
+    let Some((item_id, quantity)) = removed else {
+        return Ok(None);
+    };
+
+    let balance = power_item_count(tx, user_id, item_id as i32).await?;
+    ledger::record(
+        tx,
+        user_id,
+        LEDGER_POWER_ITEM,
+        item_id,
+        -quantity,
+        balance,
+        origin,
+    )
+    .await?;
+
+    Ok(Some((item_id, quantity)))
+}
+
+/// Stamina items of one kind the user holds, over all stacks
+pub async fn power_item_count(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: i64,
+    item_id: i32,
+) -> Result<i64> {
+    let count = sqlx::query_scalar(
+        "SELECT COALESCE(SUM(quantity), 0) FROM power_items WHERE user_id = ? AND item_id = ?",
+    )
+    .bind(user_id)
+    .bind(item_id)
+    .fetch_one(&mut **tx)
+    .await?;
+
+    Ok(count)
+}
+
+/// Adds `count` insight items, one row each, expiring after the item's `expireHours`
+pub async fn give_insight_item(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: i64,
+    item_id: i32,
+    count: i32,
+    origin: LedgerOrigin,
+) -> Result<()> {
+    let now = ServerTime::now_ms();
+    let expire_time = config::configs::get()
+        .insight_item
+        .iter()
+        .find(|i| i.id == item_id)
+        .map(|i| ((now / 1000) + i.expire_hours as i64 * 60 * 60) as i32)
+        .unwrap_or(0);
+
+    for _ in 0..count {
+        sqlx::query(
+            "INSERT INTO insight_items (user_id, item_id, quantity, expire_time)
+             VALUES (?, ?, 1, ?)",
+        )
+        .bind(user_id)
+        .bind(item_id)
+        .bind(expire_time)
+        .execute(&mut **tx)
+        .await?;
+    }
+
+    let balance = insight_item_count(tx, user_id, item_id).await?;
+    let id = item_id as i64;
+    ledger::record(tx, user_id, LEDGER_INSIGHT_ITEM, id, count, balance, origin).await?;
+    Ok(())
+}
+
+/// Uses one insight item of the row `uid`, returns its item id or `None` if the row is
+/// missing or empty
+pub async fn take_insight_item(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: i64,
+    uid: i64,
+    origin: LedgerOrigin,
+) -> Result<Option<i32>> {
+    let item_id: Option<i32> = sqlx::query_scalar(
+        "UPDATE insight_items SET quantity = quantity - 1
+         WHERE uid = ? AND user_id = ? AND quantity > 0
+         RETURNING item_id",
+    )
+    .bind(uid)
+    .bind(user_id)
+    .fetch_optional(&mut **tx)
+    .await?;
+
+    let Some(item_id) = item_id else {
+        return Ok(None);
+    };
+
+    let balance = insight_item_count(tx, user_id, item_id).await?;
+    let id = item_id as i64;
+    ledger::record(tx, user_id, LEDGER_INSIGHT_ITEM, id, -1, balance, origin).await?;
+    Ok(Some(item_id))
+}
+
+async fn insight_item_count(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: i64,
+    item_id: i32,
+) -> Result<i64> {
+    let count = sqlx::query_scalar(
+        "SELECT COALESCE(SUM(quantity), 0) FROM insight_items WHERE user_id = ? AND item_id = ?",
+    )
+    .bind(user_id)
+    .bind(item_id)
+    .fetch_one(&mut **tx)
+    .await?;
+
+    Ok(count)
+}
+
+/// Adds `count` copies of an equipment, returns the uids of the new or grown rows
+pub async fn give_equipment(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: i64,
+    equip_id: i32,
+    count: i32,
+    origin: LedgerOrigin,
+) -> Result<Vec<i64>> {
+    let uids = insert_equipment(tx, user_id, equip_id, count).await?;
+
+    let balance = equipment_count(tx, user_id, equip_id).await?;
+    let id = equip_id as i64;
+    ledger::record(tx, user_id, LEDGER_EQUIP, id, count, balance, origin).await?;
+    Ok(uids)
+}
+
+async fn equipment_count(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: i64,
+    equip_id: i32,
+) -> Result<i64> {
+    let count = sqlx::query_scalar(
+        "SELECT COALESCE(SUM(count), 0) FROM equipment WHERE user_id = ? AND equip_id = ?",
+    )
+    .bind(user_id)
+    .bind(equip_id)
+    .fetch_one(&mut **tx)
+    .await?;
+
+    Ok(count)
+}
+
+/// Removes `count` copies from the equipment row `uid`. Emptied exp equipment stacks stay
+/// at 0, other emptied rows are deleted. Returns the equip id and the copies left, `None`
+/// without touching anything when the row is missing, locked or too small.
+pub async fn take_equipment(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: i64,
+    uid: i64,
+    count: i32,
+    origin: LedgerOrigin,
+) -> Result<Option<(i32, i32)>> {
+    let row: Option<(i32, i32, bool)> = sqlx::query_as(
+        "SELECT equip_id, count, is_lock FROM equipment WHERE uid = ? AND user_id = ?",
+    )
+    .bind(uid)
+    .bind(user_id)
+    .fetch_optional(&mut **tx)
+    .await?;
+
+    let Some((equip_id, current, is_lock)) = row else {
+        return Ok(None);
+    };
+    if is_lock || current < count {
+        return Ok(None);
+    }
+
+    let is_stackable = (1002..=1005).contains(&equip_id);
+    if current == count && !is_stackable {
+        sqlx::query("DELETE FROM equipment WHERE uid = ? AND user_id = ?")
+            .bind(uid)
+            .bind(user_id)
+            .execute(&mut **tx)
+            .await?;
+    } else {
+        sqlx::query(
+            "UPDATE equipment SET count = count - ?, updated_at = ? WHERE uid = ? AND user_id = ?",
+        )
+        .bind(count)
+        .bind(ServerTime::now_ms())
+        .bind(uid)
+        .bind(user_id)
+        .execute(&mut **tx)
+        .await?;
+    }
+
+    let balance = equipment_count(tx, user_id, equip_id).await?;
+    let id = equip_id as i64;
+    ledger::record(tx, user_id, LEDGER_EQUIP, id, -count, balance, origin).await?;
+    Ok(Some((equip_id, current - count)))
+}
+
+/// Uid of the user's copy of a hero
+pub async fn find_hero(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: i64,
+    hero_id: i32,
+) -> Result<Option<i64>> {
+    let uid = sqlx::query_scalar("SELECT uid FROM heroes WHERE user_id = ? AND hero_id = ?")
+        .bind(user_id)
+        .bind(hero_id)
+        .fetch_optional(&mut **tx)
+        .await?;
+
+    Ok(uid)
+}
+
+/// Counts another copy of an owned hero, returns the new duplicate count
+pub async fn add_hero_duplicate(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: i64,
+    hero_id: i32,
+) -> Result<i32> {
+    let count = sqlx::query_scalar(
+        "UPDATE heroes SET duplicate_count = duplicate_count + 1
+         WHERE user_id = ? AND hero_id = ?
+         RETURNING duplicate_count",
+    )
+    .bind(user_id)
+    .bind(hero_id)
+    .fetch_one(&mut **tx)
+    .await?;
+
+    Ok(count)
+}
+
+/// Unlocks a skin, and adds it to the wardrobe of its hero when the user owns them.
+/// Returns `false` if the skin was unlocked already.
+pub async fn give_skin(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: i64,
+    skin_id: i32,
+) -> Result<bool> {
+    let inserted =
+        sqlx::query("INSERT OR IGNORE INTO hero_all_skins (user_id, skin_id) VALUES (?, ?)")
+            .bind(user_id)
+            .bind(skin_id)
+            .execute(&mut **tx)
+            .await?
+            .rows_affected();
+
+    if inserted == 0 {
+        return Ok(false);
+    }
+
+    let hero_id = config::configs::get()
+        .skin
+        .iter()
+        .find(|s| s.id == skin_id)
+        .map(|s| s.character_id);
+
+    if let Some(hero_id) = hero_id
+        && let Some(hero_uid) = find_hero(tx, user_id, hero_id).await?
+    {
+        sqlx::query(
+            "INSERT OR IGNORE INTO hero_skins (hero_uid, skin, expire_sec) VALUES (?, ?, 0)",
+        )
+        .bind(hero_uid)
+        .bind(skin_id)
+        .execute(&mut **tx)
+        .await?;
+    }
+
+    Ok(true)
+}
+
+/// The Insight II skin of a hero, unlocked when they reach Insight II
+pub fn insight_skin_id(hero_id: i32) -> Option<i32> {
+    config::configs::get()
+        .skin
+        .iter()
+        .find(|s| s.character_id == hero_id && s.id % 100 == 2 && s.gain_approach == 1)
+        .map(|s| s.id)
+}
+
+/// Puts an unlocked skin on its hero
+pub async fn wear_skin(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: i64,
+    hero_id: i32,
+    skin_id: i32,
+) -> Result<()> {
+    sqlx::query("UPDATE heroes SET skin = ? WHERE user_id = ? AND hero_id = ?")
+        .bind(skin_id)
+        .bind(user_id)
+        .bind(hero_id)
+        .execute(&mut **tx)
+        .await?;
+
+    Ok(())
+}
+
+/// Sets a hero's level and the base stats of its `character_level` milestone
+pub async fn set_hero_level(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: i64,
+    hero_id: i32,
+    level: i32,
+    stats: &CharacterLevel,
+) -> Result<()> {
+    sqlx::query(
+        r#"UPDATE heroes
+               SET level = ?,
+                   base_hp = ?,
+                   base_attack = ?,
+                   base_defense = ?,
+                   base_mdefense = ?,
+                   base_technic = ?,
+                   ex_cri = ?,
+                   ex_recri = ?,
+                   ex_cri_dmg = ?,
+                   ex_cri_def = ?,
+                   ex_add_dmg = ?,
+                   ex_drop_dmg = ?
+               WHERE user_id = ? AND hero_id = ?"#,
+    )
+    .bind(level)
+    .bind(stats.hp)
+    .bind(stats.atk)
+    .bind(stats.def)
+    .bind(stats.mdef)
+    .bind(stats.technic)
+    .bind(stats.cri)
+    .bind(stats.recri)
+    .bind(stats.cri_dmg)
+    .bind(stats.cri_def)
+    .bind(stats.add_dmg)
+    .bind(stats.drop_dmg)
+    .bind(user_id)
+    .bind(hero_id)
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(())
+}
+
+/// Raises a hero's ex skill by `levels`, `false` without touching it past `max_level`
+pub async fn add_hero_ex_skill(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: i64,
+    hero_id: i32,
+    levels: i32,
+    max_level: i32,
+) -> Result<bool> {
+    let updated = sqlx::query(
+        "UPDATE heroes SET ex_skill_level = ex_skill_level + ?
+         WHERE user_id = ? AND hero_id = ? AND ex_skill_level + ? <= ?",
+    )
+    .bind(levels)
+    .bind(user_id)
+    .bind(hero_id)
+    .bind(levels)
+    .bind(max_level)
+    .execute(&mut **tx)
+    .await?
+    .rows_affected();
+
+    Ok(updated > 0)
+}
+
+/// Moves a hero's talent from `from` to `to`, `false` if it isn't at `from` anymore
+pub async fn set_hero_talent(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: i64,
+    hero_id: i32,
+    from: i32,
+    to: i32,
+) -> Result<bool> {
+    let updated = sqlx::query(
+        "UPDATE heroes SET talent = ? WHERE user_id = ? AND hero_id = ? AND talent = ?",
+    )
+    .bind(to)
+    .bind(user_id)
+    .bind(hero_id)
+    .bind(from)
+    .execute(&mut **tx)
+    .await?
+    .rows_affected();
+
+    Ok(updated > 0)
+}
+
+/// Unlocks a talent style of a hero, `false` if it was unlocked already
+pub async fn unlock_talent_style(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: i64,
+    hero_id: i32,
+    style: i32,
+) -> Result<bool> {
+    let Some(hero_uid) = find_hero(tx, user_id, hero_id).await? else {
+        return Ok(false);
+    };
+
+    let inserted = sqlx::query(
+        "INSERT INTO hero_talent_styles (hero_uid, style_id)
+         SELECT ?, ? WHERE NOT EXISTS
+             (SELECT 1 FROM hero_talent_styles WHERE hero_uid = ? AND style_id = ?)",
+    )
+    .bind(hero_uid)
+    .bind(style)
+    .bind(hero_uid)
+    .bind(style)
+    .execute(&mut **tx)
+    .await?
+    .rows_affected();
+
+    if inserted == 0 {
+        return Ok(false);
+    }
+
+    sqlx::query("UPDATE heroes SET talent_style_unlock = talent_style_unlock | ? WHERE uid = ?")
+        .bind(1 << style)
+        .bind(hero_uid)
+        .execute(&mut **tx)
+        .await?;
+
+    Ok(true)
+}
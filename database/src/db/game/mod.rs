@@ -2,6 +2,7 @@ pub mod achievements;
 pub mod activity101;
 pub mod antiques;
 pub mod battle;
+pub mod battle_pass;
 pub mod bgm;
 pub mod block_packages;
 pub mod buildings;
@@ -20,6 +21,9 @@ pub mod hero_group_snapshots;
 pub mod hero_groups;
 
 pub mod items;
+pub mod ledger;
+pub mod mails;
+pub mod materials;
 pub mod player_card;
 pub mod player_infos;
 pub mod red_dots;
@@ -27,6 +31,7 @@ pub mod sign_in;
 pub mod simple_property;
 pub mod stories;
 pub mod summon;
+pub mod tasks;
 pub mod tower;
 pub mod user_stats;
 pub mod weekwalk;
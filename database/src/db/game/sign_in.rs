@@ -173,6 +173,12 @@ pub async fn reset_daily_counters(pool: &SqlitePool, user_id: i64) -> Result<()>
     .execute(pool)
     .await?;
 
+    // Reset stamina purchases
+    sqlx::query("UPDATE user_power_buy SET buy_count = 0 WHERE user_id = ?")
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+
     // Reset hero touch count
     sqlx::query(
         r#"
@@ -0,0 +1,292 @@
+use crate::models::game::tasks::{UserTask, UserTaskActivity};
+use anyhow::Result;
+use sqlx::{Sqlite, SqlitePool, Transaction};
+
+const TASK_COLUMNS: &str = "task_id, task_type, progress, has_finished, finish_count, expiry_time";
+const ACTIVITY_COLUMNS: &str = "type_id, define_id, value, gain_value, expiry_time";
+
+/// Tasks of the given types, every tracked task when `task_types` is empty
+pub async fn get_tasks(
+    pool: &SqlitePool,
+    user_id: i64,
+    task_types: &[i32],
+) -> Result<Vec<UserTask>> {
+    let tasks = sqlx::query_as::<_, UserTask>(&format!(
+        "SELECT {} FROM user_tasks WHERE user_id = ? ORDER BY task_id",
+        TASK_COLUMNS
+    ))
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(tasks
+        .into_iter()
+        .filter(|t| task_types.is_empty() || task_types.contains(&t.task_type))
+        .collect())
+}
+
+/// Starts tracking `(task_id, task_type, expiry_time)` tasks the user doesn't have yet
+pub async fn insert_missing_tasks(
+    pool: &SqlitePool,
+    user_id: i64,
+    tasks: &[(i32, i32, i32)],
+) -> Result<u64> {
+    let mut tx = pool.begin().await?;
+    let mut inserted = 0;
+
+    for &(task_id, task_type, expiry_time) in tasks {
+        let result = sqlx::query(
+            "INSERT OR IGNORE INTO user_tasks (user_id, task_id, task_type, expiry_time)
+             VALUES (?, ?, ?, ?)",
+        )
+        .bind(user_id)
+        .bind(task_id)
+        .bind(task_type)
+        .bind(expiry_time)
+        .execute(&mut *tx)
+        .await?;
+
+        inserted += result.rows_affected();
+    }
+
+    tx.commit().await?;
+    Ok(inserted)
+}
+
+pub async fn delete_tasks(pool: &SqlitePool, user_id: i64, task_ids: &[i32]) -> Result<()> {
+    let mut tx = pool.begin().await?;
+
+    for task_id in task_ids {
+        sqlx::query("DELETE FROM user_tasks WHERE user_id = ? AND task_id = ?")
+            .bind(user_id)
+            .bind(task_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Tasks whose cycle ended before `now_sec`
+pub async fn get_expired_tasks(
+    pool: &SqlitePool,
+    user_id: i64,
+    now_sec: i32,
+) -> Result<Vec<UserTask>> {
+    let tasks = sqlx::query_as::<_, UserTask>(&format!(
+        "SELECT {} FROM user_tasks WHERE user_id = ? AND expiry_time > 0 AND expiry_time <= ?",
+        TASK_COLUMNS
+    ))
+    .bind(user_id)
+    .bind(now_sec)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(tasks)
+}
+
+/// Restarts `(task_id, next_expiry)` tasks from zero progress
+pub async fn reset_tasks(pool: &SqlitePool, user_id: i64, tasks: &[(i32, i32)]) -> Result<()> {
+    let mut tx = pool.begin().await?;
+
+    for &(task_id, next_expiry) in tasks {
+        sqlx::query(
+            r#"
+            UPDATE user_tasks
+            SET progress = 0, has_finished = 0, finish_count = 0, expiry_time = ?
+            WHERE user_id = ? AND task_id = ?
+            "#,
+        )
+        .bind(next_expiry)
+        .bind(user_id)
+        .bind(task_id)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Restarts the activity of a type whose cycle ended before `now_sec`
+pub async fn reset_expired_activity(
+    pool: &SqlitePool,
+    user_id: i64,
+    type_id: i32,
+    now_sec: i32,
+    next_expiry: i32,
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        UPDATE user_task_activity
+        SET define_id = 0, value = 0, gain_value = 0, expiry_time = ?
+        WHERE user_id = ? AND type_id = ? AND expiry_time > 0 AND expiry_time <= ?
+        "#,
+    )
+    .bind(next_expiry)
+    .bind(user_id)
+    .bind(type_id)
+    .bind(now_sec)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Adds progress to an unclaimed task, capped at `max_progress`.
+/// Returns the updated task, `None` if it was already complete.
+pub async fn add_task_progress(
+    pool: &SqlitePool,
+    user_id: i64,
+    task_id: i32,
+    amount: i32,
+    max_progress: i32,
+) -> Result<Option<UserTask>> {
+    let task = sqlx::query_as::<_, UserTask>(&format!(
+        r#"
+        UPDATE user_tasks
+        SET progress = MIN(progress + ?, ?)
+        WHERE user_id = ? AND task_id = ? AND has_finished = 0 AND progress < ?
+        RETURNING {}
+        "#,
+        TASK_COLUMNS
+    ))
+    .bind(amount)
+    .bind(max_progress)
+    .bind(user_id)
+    .bind(task_id)
+    .bind(max_progress)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(task)
+}
+
+/// Marks a completed task as claimed, `None` if it isn't complete or was claimed already
+pub async fn finish_task(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: i64,
+    task_id: i32,
+    max_progress: i32,
+) -> Result<Option<UserTask>> {
+    let task = sqlx::query_as::<_, UserTask>(&format!(
+        r#"
+        UPDATE user_tasks
+        SET has_finished = 1, finish_count = finish_count + 1
+        WHERE user_id = ? AND task_id = ? AND has_finished = 0 AND progress >= ?
+        RETURNING {}
+        "#,
+        TASK_COLUMNS
+    ))
+    .bind(user_id)
+    .bind(task_id)
+    .bind(max_progress)
+    .fetch_optional(&mut **tx)
+    .await?;
+
+    Ok(task)
+}
+
+pub async fn get_activities(
+    pool: &SqlitePool,
+    user_id: i64,
+    type_ids: &[i32],
+) -> Result<Vec<UserTaskActivity>> {
+    let activities = sqlx::query_as::<_, UserTaskActivity>(&format!(
+        "SELECT {} FROM user_task_activity WHERE user_id = ? ORDER BY type_id",
+        ACTIVITY_COLUMNS
+    ))
+    .bind(user_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(activities
+        .into_iter()
+        .filter(|a| type_ids.is_empty() || type_ids.contains(&a.type_id))
+        .collect())
+}
+
+pub async fn get_activity(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: i64,
+    type_id: i32,
+) -> Result<Option<UserTaskActivity>> {
+    let activity = sqlx::query_as::<_, UserTaskActivity>(&format!(
+        "SELECT {} FROM user_task_activity WHERE user_id = ? AND type_id = ?",
+        ACTIVITY_COLUMNS
+    ))
+    .bind(user_id)
+    .bind(type_id)
+    .fetch_optional(&mut **tx)
+    .await?;
+
+    Ok(activity)
+}
+
+pub async fn insert_missing_activity(
+    pool: &SqlitePool,
+    user_id: i64,
+    type_id: i32,
+    expiry_time: i32,
+) -> Result<()> {
+    sqlx::query(
+        "INSERT OR IGNORE INTO user_task_activity (user_id, type_id, expiry_time) VALUES (?, ?, ?)",
+    )
+    .bind(user_id)
+    .bind(type_id)
+    .bind(expiry_time)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+pub async fn add_activity(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: i64,
+    type_id: i32,
+    amount: i32,
+) -> Result<Option<UserTaskActivity>> {
+    let activity = sqlx::query_as::<_, UserTaskActivity>(&format!(
+        "UPDATE user_task_activity SET value = value + ? WHERE user_id = ? AND type_id = ?
+         RETURNING {}",
+        ACTIVITY_COLUMNS
+    ))
+    .bind(amount)
+    .bind(user_id)
+    .bind(type_id)
+    .fetch_optional(&mut **tx)
+    .await?;
+
+    Ok(activity)
+}
+
+/// Records `define_id` as the last claimed activity bonus stage, `None` if a later
+/// stage was claimed already
+pub async fn claim_activity_bonus(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: i64,
+    type_id: i32,
+    define_id: i32,
+    gain_value: i32,
+) -> Result<Option<UserTaskActivity>> {
+    let activity = sqlx::query_as::<_, UserTaskActivity>(&format!(
+        r#"
+        UPDATE user_task_activity
+        SET define_id = ?, gain_value = ?
+        WHERE user_id = ? AND type_id = ? AND define_id < ?
+        RETURNING {}
+        "#,
+        ACTIVITY_COLUMNS
+    ))
+    .bind(define_id)
+    .bind(gain_value)
+    .bind(user_id)
+    .bind(type_id)
+    .bind(define_id)
+    .fetch_optional(&mut **tx)
+    .await?;
+
+    Ok(activity)
+}
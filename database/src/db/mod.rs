@@ -1,3 +1,5 @@
+pub mod content_sync;
 pub mod game;
 pub mod starter_data;
+pub mod starter_profile;
 pub mod user;
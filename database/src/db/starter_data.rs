@@ -1,7 +1,8 @@
+use super::starter_profile::{self, StarterProfile};
 use config::configs;
 use serde_json::Value;
 use sqlx::{Sqlite, SqlitePool, Transaction};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::atomic::{AtomicI64, Ordering};
 
 /// Load CritterInfo starter data from critter_info.json
@@ -143,7 +144,11 @@ pub async fn load_critter_info(tx: &mut Transaction<'_, Sqlite>, uid: i64) -> sq
 }
 
 /// Load default player info for new user
-pub async fn load_player_info(tx: &mut Transaction<'_, Sqlite>, uid: i64) -> sqlx::Result<()> {
+pub async fn load_player_info(
+    tx: &mut Transaction<'_, Sqlite>,
+    uid: i64,
+    profile: &StarterProfile,
+) -> sqlx::Result<()> {
     let now = common::time::ServerTime::now_ms();
 
     // Create player_info record
@@ -182,7 +187,12 @@ pub async fn load_player_info(tx: &mut Transaction<'_, Sqlite>, uid: i64) -> sql
         (3095, 180, 4, 5, 309502),
     ];
 
-    for (i, (hero_id, level, rank, ex_skill_level, skin)) in default_heroes.iter().enumerate() {
+    // Only maxed out heroes the account owns can be shown
+    let default_heroes = default_heroes
+        .iter()
+        .filter(|(hero_id, ..)| profile.max_heroes && profile.owns_hero(*hero_id));
+
+    for (i, (hero_id, level, rank, ex_skill_level, skin)) in default_heroes.enumerate() {
         sqlx::query(
             "INSERT INTO player_show_heroes
              (player_id, hero_id, level, rank, ex_skill_level, skin, display_order)
@@ -202,11 +212,13 @@ pub async fn load_player_info(tx: &mut Transaction<'_, Sqlite>, uid: i64) -> sql
     Ok(())
 }
 
-/// Generate and insert starter heroes from ExcelDB
+/// Generate and insert starter heroes from ExcelDB, maxed out or at level 1
+/// depending on the profile
 pub async fn load_hero_list(
     tx: &mut Transaction<'_, Sqlite>,
     uid: i64,
     equip_map: &HashMap<i32, i64>,
+    profile: &StarterProfile,
 ) -> sqlx::Result<()> {
     let game_data = configs::get();
     let now = common::time::ServerTime::now_ms();
@@ -221,455 +233,489 @@ pub async fn load_hero_list(
         .character
         .iter()
         //.filter(|c| c.rare >= 1 && c.rare <= 5) // Valid rarity (1-5 stars)
-        .filter(|c| c.id != 3029)
-        .filter(|c| c.id != 9998)
-        .filter(|c| c.id != 3125) // testing leveling logic
+        .filter(|c| is_starter_hero(c.id, profile))
         .collect();
 
     for character in characters.clone() {
         let hero_uid = HERO_UID_COUNTER.fetch_add(1, Ordering::SeqCst);
-        let hero_id = character.id;
-        let hero_skin = character.skin_id;
         let rare = character.rare as usize;
 
         if (1..=5).contains(&rare) {
             rarity_counts[rare] += 1;
         }
 
-        // Get MAX LEVEL stats (highest level available for this hero)
-        let max_stats = game_data
-            .character_level
-            .iter()
-            .filter(|s| s.hero_id == hero_id)
-            .max_by_key(|s| s.level); // Get the highest level entry
-
-        let (level, hp, atk, def, mdef, technic, cri, recri, cri_dmg, cri_def, add_dmg, drop_dmg) =
-            if let Some(stats) = max_stats {
-                (
-                    stats.level,
-                    stats.hp,
-                    stats.atk,
-                    stats.def,
-                    stats.mdef,
-                    stats.technic,
-                    stats.cri,
-                    stats.recri,
-                    stats.cri_dmg,
-                    stats.cri_def,
-                    stats.add_dmg,
-                    stats.drop_dmg,
-                )
-            } else {
-                // Fallback values if no stats found
-                (1, 1000, 100, 100, 100, 100, 0, 0, 1300, 0, 0, 0)
-            };
+        insert_starter_hero(tx, uid, hero_uid, character, equip_map, profile).await?;
+    }
+    // Initialize touch count
+    sqlx::query("INSERT INTO hero_touch_count (user_id, touch_count_left) VALUES (?, ?)")
+        .bind(uid)
+        .bind(5) // Default 5 touches per day
+        .execute(&mut **tx)
+        .await?;
 
-        let max_ranks = game_data
-            .character_rank
-            .iter()
-            .filter(|s| s.hero_id == hero_id)
-            .max_by_key(|s| s.rank);
+    // update hero count for player info
+    sqlx::query(
+        r#"
+        UPDATE player_info
+        SET
+            hero_rare_nn_count  = ?,
+            hero_rare_n_count   = ?,
+            hero_rare_r_count   = ?,
+            hero_rare_sr_count  = ?,
+            hero_rare_ssr_count = ?,
+            updated_at = ?
+        WHERE player_id = ?
+        "#,
+    )
+    .bind(rarity_counts[1]) // NN
+    .bind(rarity_counts[2]) // N
+    .bind(rarity_counts[3]) // R
+    .bind(rarity_counts[4]) // SR
+    .bind(rarity_counts[5]) // SSR
+    .bind(now)
+    .bind(uid)
+    .execute(&mut **tx)
+    .await?;
 
-        // Determine max rank
-        let max_rank = if let Some(max) = max_ranks {
-            max.rank
+    tracing::info!(
+        "Generated {} starter heroes from excel data for uid {}",
+        characters.len(),
+        uid
+    );
+    Ok(())
+}
+
+/// Playable heroes the profile hands out
+pub(crate) fn is_starter_hero(hero_id: i32, profile: &StarterProfile) -> bool {
+    hero_id != 3029
+        && hero_id != 9998
+        && hero_id != 3125 // testing leveling logic
+        && profile.owns_hero(hero_id)
+}
+
+/// Inserts one hero with its skills, voices, skins and talents as the profile
+/// starts it
+pub(crate) async fn insert_starter_hero(
+    tx: &mut Transaction<'_, Sqlite>,
+    uid: i64,
+    hero_uid: i64,
+    character: &config::character::Character,
+    equip_map: &HashMap<i32, i64>,
+    profile: &StarterProfile,
+) -> sqlx::Result<()> {
+    let game_data = configs::get();
+    let now = common::time::ServerTime::now_ms();
+    let hero_id = character.id;
+    let hero_skin = character.skin_id;
+
+    // Get MAX LEVEL stats (highest level available for this hero)
+    let levels = game_data
+        .character_level
+        .iter()
+        .filter(|s| s.hero_id == hero_id);
+    let start_stats = if profile.max_heroes {
+        levels.max_by_key(|s| s.level) // Get the highest level entry
+    } else {
+        levels.min_by_key(|s| s.level)
+    };
+
+    let (level, hp, atk, def, mdef, technic, cri, recri, cri_dmg, cri_def, add_dmg, drop_dmg) =
+        if let Some(stats) = start_stats {
+            (
+                stats.level,
+                stats.hp,
+                stats.atk,
+                stats.def,
+                stats.mdef,
+                stats.technic,
+                stats.cri,
+                stats.recri,
+                stats.cri_dmg,
+                stats.cri_def,
+                stats.add_dmg,
+                stats.drop_dmg,
+            )
         } else {
-            3
+            // Fallback values if no stats found
+            (1, 1000, 100, 100, 100, 100, 0, 0, 1300, 0, 0, 0)
         };
 
-        // Get default skin - default is always heroId * 100 + 01
-        let default_skin = game_data
-            .skin
-            .iter()
-            .filter(|s| s.character_id != 0)
-            .filter(|s| s.character_id == hero_id)
-            .max_by_key(|s| s.id) // Latest skin ID will be the default
-            .map(|s| s.id)
-            .unwrap_or(hero_skin); // Fallback to i1 skin
-
-        // Get default destiny stone from character_destiny table
-        let destiny_data = game_data
-            .character_destiny
-            .iter()
-            .find(|d| d.hero_id == hero_id);
-
-        // Calculate destiny values ONLY if hero has destiny system
-        let (destiny_rank, destiny_level, destiny_stone, red_dot_type) =
-            if let Some(d) = destiny_data {
-                // Hero has destiny - set to max
-                let rank = max_rank; // Match hero's max rank
-                let level = 10; // Max destiny level
-                let stone = d
-                    .facets_id
-                    .split('#')
-                    .next()
-                    .and_then(|s| s.parse::<i32>().ok())
-                    .unwrap_or(0);
-                let red_dot_type = 6;
-                (rank, level, stone, red_dot_type)
-            } else {
-                // Hero doesn't have destiny system - all zeros
-                (0, 0, 0, 0)
-            };
+    let max_ranks = game_data
+        .character_rank
+        .iter()
+        .filter(|s| s.hero_id == hero_id)
+        .max_by_key(|s| s.rank);
+
+    // Determine max rank
+    let max_rank = if let Some(max) = max_ranks {
+        max.rank
+    } else {
+        3
+    };
+    let rank = if profile.max_heroes { max_rank } else { 1 };
 
-        // equipRec can be "1527" or "1530#1428" (multiple options); take first
-        let equip_id = character
-            .equip_rec
+    // Get default skin - default is always heroId * 100 + 01
+    let default_skin = game_data
+        .skin
+        .iter()
+        .filter(|s| s.character_id != 0)
+        .filter(|s| s.character_id == hero_id)
+        .max_by_key(|s| s.id) // Latest skin ID will be the default
+        .map(|s| s.id)
+        .filter(|_| profile.max_heroes)
+        .unwrap_or(hero_skin); // Fallback to i1 skin
+
+    // Get default destiny stone from character_destiny table
+    let destiny_data = game_data
+        .character_destiny
+        .iter()
+        .find(|d| d.hero_id == hero_id)
+        .filter(|_| profile.max_heroes);
+
+    // Calculate destiny values ONLY if hero has destiny system
+    let (destiny_rank, destiny_level, destiny_stone, red_dot_type) = if let Some(d) = destiny_data {
+        // Hero has destiny - set to max
+        let rank = max_rank; // Match hero's max rank
+        let level = 10; // Max destiny level
+        let stone = d
+            .facets_id
             .split('#')
             .next()
             .and_then(|s| s.parse::<i32>().ok())
-            .unwrap_or(1501);
+            .unwrap_or(0);
+        let red_dot_type = 6;
+        (rank, level, stone, red_dot_type)
+    } else {
+        // Hero doesn't have destiny system - all zeros
+        (0, 0, 0, 0)
+    };
 
-        // Get default equipment UID from the map using equip_id
-        let default_equip_uid = equip_map.get(&equip_id).copied().unwrap_or(0);
+    // equipRec can be "1527" or "1530#1428" (multiple options); take first
+    let equip_id = character
+        .equip_rec
+        .split('#')
+        .next()
+        .and_then(|s| s.parse::<i32>().ok())
+        .unwrap_or(1501);
 
-        let strengthen_stats = game_data
-            .equip_strengthen
-            .iter()
-            .find(|s| s.strength_type == equip_id);
-
-        let (
-            final_hp,
-            final_atk,
-            final_def,
-            final_mdef,
-            final_technic,
-            final_cri,
-            final_recri,
-            final_cri_dmg,
-            final_cri_def,
-            final_add_dmg,
-            final_drop_dmg,
-        ) = if let Some(s) = strengthen_stats {
-            (
-                hp + s.hp,
-                atk + s.atk,
-                def + s.def,
-                mdef + s.mdef,
-                technic,
-                cri,
-                recri,
-                cri_dmg,
-                cri_def,
-                add_dmg,
-                drop_dmg,
-            )
-        } else {
-            (
-                hp, atk, def, mdef, technic, cri, recri, cri_dmg, cri_def, add_dmg, drop_dmg,
-            )
-        };
+    // Get default equipment UID from the map using equip_id
+    let default_equip_uid = equip_map.get(&equip_id).copied().unwrap_or(0);
 
-        let extra_str = if let 3123 = hero_id {
-            "1003#2003"
-        } else if hero_id == 3124 {
-            "2#21,22|3#32,33,31"
-        } else {
-            ""
-        };
+    let strengthen_stats = game_data
+        .equip_strengthen
+        .iter()
+        .find(|s| s.strength_type == equip_id)
+        .filter(|_| profile.max_heroes);
+
+    let (
+        final_hp,
+        final_atk,
+        final_def,
+        final_mdef,
+        final_technic,
+        final_cri,
+        final_recri,
+        final_cri_dmg,
+        final_cri_def,
+        final_add_dmg,
+        final_drop_dmg,
+    ) = if let Some(s) = strengthen_stats {
+        (
+            hp + s.hp,
+            atk + s.atk,
+            def + s.def,
+            mdef + s.mdef,
+            technic,
+            cri,
+            recri,
+            cri_dmg,
+            cri_def,
+            add_dmg,
+            drop_dmg,
+        )
+    } else {
+        (
+            hp, atk, def, mdef, technic, cri, recri, cri_dmg, cri_def, add_dmg, drop_dmg,
+        )
+    };
 
-        let max_talent_id = game_data
-            .character_talent
+    let extra_str = if let 3123 = hero_id {
+        "1003#2003"
+    } else if hero_id == 3124 {
+        "2#21,22|3#32,33,31"
+    } else {
+        ""
+    };
+
+    let talents = game_data
+        .character_talent
+        .iter()
+        .filter(|t| t.hero_id == hero_id)
+        .map(|t| t.talent_id);
+    let max_talent_id = if profile.max_heroes {
+        talents.max()
+    } else {
+        talents.min()
+    }
+    .expect("Hero has no talent data");
+    let maxed = |max: i32, start: i32| if profile.max_heroes { max } else { start };
+
+    // Insert main hero record at MAX LEVEL with MAX RANK
+    sqlx::query(
+        r#"
+        INSERT INTO heroes (
+            uid, user_id, hero_id, create_time,
+            level, exp, rank, breakthrough, skin, faith,
+            active_skill_level, ex_skill_level, is_new, talent,
+            default_equip_uid, duplicate_count, use_talent_template_id,
+            talent_style_unlock, talent_style_red, is_favor,
+            destiny_rank, destiny_level, destiny_stone, red_dot, extra_str,
+            base_hp, base_attack, base_defense, base_mdefense, base_technic,
+            base_multi_hp_idx, base_multi_hp_num,
+            ex_cri, ex_recri, ex_cri_dmg, ex_cri_def, ex_add_dmg, ex_drop_dmg
+        ) VALUES (
+            ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10,
+            ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20,
+            ?21, ?22, ?23, ?24, ?25, ?26, ?27, ?28, ?29, ?30,
+            ?31, ?32, ?33, ?34, ?35, ?36, ?37, ?38
+        )
+        "#,
+    )
+    .bind(hero_uid)
+    .bind(uid)
+    .bind(hero_id)
+    .bind(now)
+    .bind(level) // MAX level (e.g., 180)
+    .bind(0) // exp (maxed out, no exp needed)
+    .bind(rank) // MAX rank based on rarity
+    .bind(0) // breakthrough
+    .bind(default_skin)
+    .bind(maxed(10400, 0)) // faith (max)
+    .bind(1) // Active skill level (max)
+    .bind(maxed(5, 0)) // Ex skill level (max)
+    .bind(false) // is_new
+    .bind(max_talent_id) // talent
+    .bind(default_equip_uid) // default_equip_uid
+    .bind(maxed(5, 0)) // duplicate_count
+    .bind(1) // use_talent_template_id
+    .bind(1) // talent_style_unlock
+    .bind(0) // talent_style_red
+    .bind(false) // is_favor
+    .bind(destiny_rank) // destiny_rank
+    .bind(destiny_level) // destiny_level
+    .bind(destiny_stone) // destiny_stone (from character_destiny table)
+    .bind(red_dot_type) // red_dot
+    .bind(extra_str) // extra_str
+    // Base attributes (MAX level stats + equip strengthen bonuses)
+    .bind(final_hp)
+    .bind(final_atk)
+    .bind(final_def)
+    .bind(final_mdef)
+    .bind(final_technic)
+    .bind(0) // base_multi_hp_idx
+    .bind(0) // base_multi_hp_num
+    // Ex attributes
+    .bind(final_cri)
+    .bind(final_recri)
+    .bind(final_cri_dmg)
+    .bind(final_cri_def)
+    .bind(final_add_dmg)
+    .bind(final_drop_dmg)
+    .execute(&mut **tx)
+    .await?;
+
+    // Insert passive skill levels in order
+    // passiveSkillLevel is an array of levels: [1, 2, 3] means skill 0 is level 1, skill 1 is level 2, etc.
+    let max_skill_group = game_data
+        .skill_passive_level
+        .iter()
+        .filter(|s| s.hero_id == hero_id)
+        .map(|s| s.skill_group)
+        .max()
+        .unwrap_or(0);
+
+    // For each skill group (1, 2, 3...), get the max level; fresh heroes only
+    // have the first one
+    for skill_group in 1..=maxed(max_skill_group, max_skill_group.min(1)) {
+        let levels = game_data
+            .skill_passive_level
             .iter()
-            .filter(|t| t.hero_id == hero_id)
-            .map(|t| t.talent_id)
-            .max()
-            .expect("Hero has no talent data");
+            .filter(|s| s.hero_id == hero_id && s.skill_group == skill_group)
+            .map(|s| s.skill_level);
+        let max_level = if profile.max_heroes {
+            levels.max()
+        } else {
+            levels.min()
+        }
+        .unwrap_or(0);
 
-        // Insert main hero record at MAX LEVEL with MAX RANK
+        // skill_index is 0-based, so skill_group 1 -> index 0
         sqlx::query(
-            r#"
-            INSERT INTO heroes (
-                uid, user_id, hero_id, create_time,
-                level, exp, rank, breakthrough, skin, faith,
-                active_skill_level, ex_skill_level, is_new, talent,
-                default_equip_uid, duplicate_count, use_talent_template_id,
-                talent_style_unlock, talent_style_red, is_favor,
-                destiny_rank, destiny_level, destiny_stone, red_dot, extra_str,
-                base_hp, base_attack, base_defense, base_mdefense, base_technic,
-                base_multi_hp_idx, base_multi_hp_num,
-                ex_cri, ex_recri, ex_cri_dmg, ex_cri_def, ex_add_dmg, ex_drop_dmg
-            ) VALUES (
-                ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10,
-                ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20,
-                ?21, ?22, ?23, ?24, ?25, ?26, ?27, ?28, ?29, ?30,
-                ?31, ?32, ?33, ?34, ?35, ?36, ?37, ?38
-            )
-            "#,
+            "INSERT INTO hero_passive_skill_levels (hero_uid, skill_index, level) VALUES (?, ?, ?)",
         )
         .bind(hero_uid)
-        .bind(uid)
-        .bind(hero_id)
-        .bind(now)
-        .bind(level) // MAX level (e.g., 180)
-        .bind(0) // exp (maxed out, no exp needed)
-        .bind(max_rank) // MAX rank based on rarity
-        .bind(0) // breakthrough
-        .bind(default_skin)
-        .bind(10400) // faith (max)
-        .bind(1) // Active skill level (max)
-        .bind(5) // Ex skill level (max)
-        .bind(false) // is_new
-        .bind(max_talent_id) // talent
-        .bind(default_equip_uid) // default_equip_uid
-        .bind(5) // duplicate_count
-        .bind(1) // use_talent_template_id
-        .bind(1) // talent_style_unlock
-        .bind(0) // talent_style_red
-        .bind(false) // is_favor
-        .bind(destiny_rank) // destiny_rank
-        .bind(destiny_level) // destiny_level
-        .bind(destiny_stone) // destiny_stone (from character_destiny table)
-        .bind(red_dot_type) // red_dot
-        .bind(extra_str) // extra_str
-        // Base attributes (MAX level stats + equip strengthen bonuses)
-        .bind(final_hp)
-        .bind(final_atk)
-        .bind(final_def)
-        .bind(final_mdef)
-        .bind(final_technic)
-        .bind(0) // base_multi_hp_idx
-        .bind(0) // base_multi_hp_num
-        // Ex attributes
-        .bind(final_cri)
-        .bind(final_recri)
-        .bind(final_cri_dmg)
-        .bind(final_cri_def)
-        .bind(final_add_dmg)
-        .bind(final_drop_dmg)
+        .bind(skill_group - 1) // Convert 1-based to 0-based
+        .bind(max_level)
         .execute(&mut **tx)
         .await?;
+    }
 
-        // Insert passive skill levels in order
-        // passiveSkillLevel is an array of levels: [1, 2, 3] means skill 0 is level 1, skill 1 is level 2, etc.
-        let max_skill_group = game_data
-            .skill_passive_level
-            .iter()
-            .filter(|s| s.hero_id == hero_id)
-            .map(|s| s.skill_group)
-            .max()
-            .unwrap_or(0);
+    // No passive skills at level 1 (empty array in example)
+    // They unlock through leveling/breakthrough
 
-        // For each skill group (1, 2, 3...), get the max level
-        for skill_group in 1..=max_skill_group {
-            let max_level = game_data
-                .skill_passive_level
-                .iter()
-                .filter(|s| s.hero_id == hero_id && s.skill_group == skill_group)
-                .map(|s| s.skill_level)
-                .max()
-                .unwrap_or(0);
+    // Get all voices for this character from character_voice table
+    let character_voices: Vec<&config::character_voice::CharacterVoice> = game_data
+        .character_voice
+        .iter()
+        .filter(|v| v.hero_id == hero_id)
+        .filter(|t| t.r#type == 9 || t.r#type == 11)
+        .collect();
 
-            // skill_index is 0-based, so skill_group 1 -> index 0
-            sqlx::query(
-                "INSERT INTO hero_passive_skill_levels (hero_uid, skill_index, level) VALUES (?, ?, ?)"
-            )
+    // Insert unlocked voices (voices available by default)
+    for voice in &character_voices {
+        sqlx::query("INSERT INTO hero_voices (hero_uid, voice_id) VALUES (?, ?)")
             .bind(hero_uid)
-            .bind(skill_group - 1) // Convert 1-based to 0-based
-            .bind(max_level)
+            .bind(voice.audio)
             .execute(&mut **tx)
             .await?;
-        }
+    }
 
-        // No passive skills at level 1 (empty array in example)
-        // They unlock through leveling/breakthrough
+    // Get all NON-DEFAULT skins for this character from skin table
+    for skin in game_data
+        .skin
+        .iter()
+        .filter(|s| s.character_id != 0)
+        .filter(|s| s.character_id == hero_id)
+        .filter(|s| s.id != hero_skin)
+        .filter(|_| profile.max_heroes)
+    // Exclude default skin
+    {
+        // Add non-default skins to hero_skins table (per-hero instance)
+        sqlx::query("INSERT INTO hero_skins (hero_uid, skin, expire_sec) VALUES (?, ?, ?)")
+            .bind(hero_uid)
+            .bind(skin.id)
+            .bind(0) // No expiry
+            .execute(&mut **tx)
+            .await?;
 
-        // Get all voices for this character from character_voice table
-        let character_voices: Vec<&config::character_voice::CharacterVoice> = game_data
-            .character_voice
-            .iter()
-            .filter(|v| v.hero_id == hero_id)
-            .filter(|t| t.r#type == 9 || t.r#type == 11)
-            .collect();
+        // Also add to hero_all_skins (account-wide collection)
+        sqlx::query("INSERT OR IGNORE INTO hero_all_skins (user_id, skin_id) VALUES (?, ?)")
+            .bind(uid)
+            .bind(skin.id)
+            .execute(&mut **tx)
+            .await?;
+    }
 
-        // Insert unlocked voices (voices available by default)
-        for voice in &character_voices {
-            sqlx::query("INSERT INTO hero_voices (hero_uid, voice_id) VALUES (?, ?)")
-                .bind(hero_uid)
-                .bind(voice.audio)
-                .execute(&mut **tx)
-                .await?;
-        }
+    // Add item unlocks (from example: [6, 3, 7, 4])
+    // These seem to be default unlocked item categories
+    // TODO: Determine default item unlocks from excel data
+    for item_id in [6, 3, 7, 4] {
+        sqlx::query("INSERT INTO hero_item_unlocks (hero_uid, item_id) VALUES (?, ?)")
+            .bind(hero_uid)
+            .bind(item_id)
+            .execute(&mut **tx)
+            .await?;
+    }
 
-        // Get all NON-DEFAULT skins for this character from skin table
-        for skin in game_data
-            .skin
-            .iter()
-            .filter(|s| s.character_id != 0)
-            .filter(|s| s.character_id == hero_id)
-            .filter(|s| s.id != hero_skin)
-        // Exclude default skin
-        {
-            // Add non-default skins to hero_skins table (per-hero instance)
-            sqlx::query("INSERT INTO hero_skins (hero_uid, skin, expire_sec) VALUES (?, ?, ?)")
-                .bind(hero_uid)
-                .bind(skin.id)
-                .bind(0) // No expiry
-                .execute(&mut **tx)
-                .await?;
+    // Insert default sp_attrs (all zeros as shown in example)
+    sqlx::query(
+        r#"
+        INSERT INTO hero_sp_attrs (
+            hero_uid, revive, heal, absorb, defense_ignore, clutch,
+            final_add_dmg, final_drop_dmg, normal_skill_rate, play_add_rate, play_drop_rate,
+            dizzy_resistances, sleep_resistances, petrified_resistances, frozen_resistances,
+            disarm_resistances, forbid_resistances, seal_resistances, cant_get_exskill_resistances,
+            del_ex_point_resistances, stress_up_resistances, control_resilience,
+            del_ex_point_resilience, stress_up_resilience, charm_resistances,
+            rebound_dmg, extra_dmg, reuse_dmg, big_skill_rate, clutch_dmg
+        ) VALUES (
+            ?1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0
+        )
+        "#,
+    )
+    .bind(hero_uid)
+    .execute(&mut **tx)
+    .await?;
 
-            // Also add to hero_all_skins (account-wide collection)
-            sqlx::query("INSERT OR IGNORE INTO hero_all_skins (user_id, skin_id) VALUES (?, ?)")
-                .bind(uid)
-                .bind(skin.id)
-                .execute(&mut **tx)
-                .await?;
-        }
+    // Birthdays
+    sqlx::query(
+        "INSERT INTO hero_birthday_info (user_id, hero_id, birthday_count) VALUES (?, ?, ?)",
+    )
+    .bind(uid)
+    .bind(hero_id)
+    .bind(1)
+    .execute(&mut **tx)
+    .await?;
 
-        // Add item unlocks (from example: [6, 3, 7, 4])
-        // These seem to be default unlocked item categories
-        // TODO: Determine default item unlocks from excel data
-        for item_id in [6, 3, 7, 4] {
-            sqlx::query("INSERT INTO hero_item_unlocks (hero_uid, item_id) VALUES (?, ?)")
+    // Insert destiny stone unlocks from character_destiny table
+    if let Some(destiny_data) = destiny_data {
+        // Parse facetsId string (e.g., "300901" or "300301#300302")
+        // Split by '#' to get all stone IDs
+        for stone_str in destiny_data.facets_id.split('#') {
+            if let Ok(stone_id) = stone_str.parse::<i32>() {
+                sqlx::query(
+                    "INSERT INTO hero_destiny_stone_unlocks (hero_uid, stone_id) VALUES (?, ?)",
+                )
                 .bind(hero_uid)
-                .bind(item_id)
+                .bind(stone_id)
                 .execute(&mut **tx)
                 .await?;
-        }
-
-        // Insert default sp_attrs (all zeros as shown in example)
-        sqlx::query(
-            r#"
-            INSERT INTO hero_sp_attrs (
-                hero_uid, revive, heal, absorb, defense_ignore, clutch,
-                final_add_dmg, final_drop_dmg, normal_skill_rate, play_add_rate, play_drop_rate,
-                dizzy_resistances, sleep_resistances, petrified_resistances, frozen_resistances,
-                disarm_resistances, forbid_resistances, seal_resistances, cant_get_exskill_resistances,
-                del_ex_point_resistances, stress_up_resistances, control_resilience,
-                del_ex_point_resilience, stress_up_resilience, charm_resistances,
-                rebound_dmg, extra_dmg, reuse_dmg, big_skill_rate, clutch_dmg
-            ) VALUES (
-                ?1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-                0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0
-            )
-            "#,
-        )
-        .bind(hero_uid)
-        .execute(&mut **tx)
-        .await?;
-
-        // Birthdays
-        sqlx::query(
-            "INSERT INTO hero_birthday_info (user_id, hero_id, birthday_count) VALUES (?, ?, ?)",
-        )
-        .bind(uid)
-        .bind(hero_id)
-        .bind(1)
-        .execute(&mut **tx)
-        .await?;
-
-        // Insert destiny stone unlocks from character_destiny table
-        if let Some(destiny_data) = game_data
-            .character_destiny
-            .iter()
-            .find(|d| d.hero_id == hero_id)
-        {
-            // Parse facetsId string (e.g., "300901" or "300301#300302")
-            // Split by '#' to get all stone IDs
-            for stone_str in destiny_data.facets_id.split('#') {
-                if let Ok(stone_id) = stone_str.parse::<i32>() {
-                    sqlx::query(
-                        "INSERT INTO hero_destiny_stone_unlocks (hero_uid, stone_id) VALUES (?, ?)",
-                    )
-                    .bind(hero_uid)
-                    .bind(stone_id)
-                    .execute(&mut **tx)
-                    .await?;
-                }
             }
         }
+    }
 
-        // Insert talent unlocks from character_talent table
-        let char_talent = game_data
-            .character_talent
-            .iter()
-            .filter(|t| t.hero_id == hero_id)
-            .max_by_key(|t| t.talent_id);
-
-        if let Some(talent_config) = char_talent {
-            // Find the talent scheme
-            let talent_scheme = game_data.talent_scheme.iter().find(|s| {
-                s.talent_id == talent_config.talent_id
-                    && s.talent_mould == talent_config.talent_mould
-            });
-
-            if let Some(scheme) = talent_scheme {
-                // Parse talenScheme: "10,1,1,0#10,0,0,0#61,1,2,0#..."
-                // Format: cubeId,direction,posX,posY
-                let cubes: Vec<(i32, i32, i32, i32)> = scheme
-                    .talen_scheme
-                    .split('#')
-                    .filter_map(|cube_str| {
-                        let parts: Vec<&str> = cube_str.split(',').collect();
-                        if parts.len() == 4 {
-                            let cube_id = parts[0].parse::<i32>().ok()?;
-                            let direction = parts[1].parse::<i32>().ok()?;
-                            let pos_x = parts[2].parse::<i32>().ok()?;
-                            let pos_y = parts[3].parse::<i32>().ok()?;
-                            Some((cube_id, direction, pos_x, pos_y))
-                        } else {
-                            None
-                        }
-                    })
-                    .collect();
-
-                // Insert into hero_talent_cubes (active equipped cubes)
-                for (cube_id, direction, pos_x, pos_y) in &cubes {
-                    sqlx::query(
-                        "INSERT INTO hero_talent_cubes (hero_uid, cube_id, direction, pos_x, pos_y) VALUES (?, ?, ?, ?, ?)"
-                    )
-                    .bind(hero_uid)
-                    .bind(cube_id)
-                    .bind(direction)
-                    .bind(pos_x)
-                    .bind(pos_y)
-                    .execute(&mut **tx)
-                    .await?;
-                }
-
-                // Create 4 talent templates (ONLY ONCE, HERE)
-                for template_id in 1..=4 {
-                    let result = sqlx::query(
-                        "INSERT INTO hero_talent_templates (hero_uid, template_id, name, style) VALUES (?, ?, ?, ?)"
-                    )
-                    .bind(hero_uid)
-                    .bind(template_id)
-                    .bind("") // Empty name
-                    .bind(0) // Style 0
-                    .execute(&mut **tx)
-                    .await?;
-
-                    let template_row_id = result.last_insert_rowid();
+    // Insert talent unlocks from character_talent table
+    let char_talent = game_data
+        .character_talent
+        .iter()
+        .filter(|t| t.hero_id == hero_id)
+        .max_by_key(|t| t.talent_id)
+        .filter(|_| profile.max_heroes);
+
+    if let Some(talent_config) = char_talent {
+        // Find the talent scheme
+        let talent_scheme = game_data.talent_scheme.iter().find(|s| {
+            s.talent_id == talent_config.talent_id && s.talent_mould == talent_config.talent_mould
+        });
 
-                    // Template #1 gets the same cubes as active (saved preset)
-                    // Templates #2-4 are empty
-                    if template_id == 1 {
-                        for (cube_id, direction, pos_x, pos_y) in &cubes {
-                            sqlx::query(
-                                "INSERT INTO hero_talent_template_cubes (template_row_id, cube_id, direction, pos_x, pos_y) VALUES (?, ?, ?, ?, ?)"
-                            )
-                            .bind(template_row_id)
-                            .bind(cube_id)
-                            .bind(direction)
-                            .bind(pos_x)
-                            .bind(pos_y)
-                            .execute(&mut **tx)
-                            .await?;
-                        }
+        if let Some(scheme) = talent_scheme {
+            // Parse talenScheme: "10,1,1,0#10,0,0,0#61,1,2,0#..."
+            // Format: cubeId,direction,posX,posY
+            let cubes: Vec<(i32, i32, i32, i32)> = scheme
+                .talen_scheme
+                .split('#')
+                .filter_map(|cube_str| {
+                    let parts: Vec<&str> = cube_str.split(',').collect();
+                    if parts.len() == 4 {
+                        let cube_id = parts[0].parse::<i32>().ok()?;
+                        let direction = parts[1].parse::<i32>().ok()?;
+                        let pos_x = parts[2].parse::<i32>().ok()?;
+                        let pos_y = parts[3].parse::<i32>().ok()?;
+                        Some((cube_id, direction, pos_x, pos_y))
+                    } else {
+                        None
                     }
-                }
+                })
+                .collect();
 
-                tracing::debug!(
-                    "Loaded {} talent cubes for hero {} (uid {})",
-                    cubes.len(),
-                    hero_id,
-                    hero_uid
-                );
+            // Insert into hero_talent_cubes (active equipped cubes)
+            for (cube_id, direction, pos_x, pos_y) in &cubes {
+                sqlx::query(
+                    "INSERT INTO hero_talent_cubes (hero_uid, cube_id, direction, pos_x, pos_y) VALUES (?, ?, ?, ?, ?)"
+                )
+                .bind(hero_uid)
+                .bind(cube_id)
+                .bind(direction)
+                .bind(pos_x)
+                .bind(pos_y)
+                .execute(&mut **tx)
+                .await?;
             }
-        } else {
-            // IMPORTANT: Even if hero has no talent data, we still need to create 4 empty templates
+
+            // Create 4 talent templates (ONLY ONCE, HERE)
             for template_id in 1..=4 {
-                sqlx::query(
+                let result = sqlx::query(
                     "INSERT INTO hero_talent_templates (hero_uid, template_id, name, style) VALUES (?, ?, ?, ?)"
                 )
                 .bind(hero_uid)
@@ -678,45 +724,49 @@ pub async fn load_hero_list(
                 .bind(0) // Style 0
                 .execute(&mut **tx)
                 .await?;
+
+                let template_row_id = result.last_insert_rowid();
+
+                // Template #1 gets the same cubes as active (saved preset)
+                // Templates #2-4 are empty
+                if template_id == 1 {
+                    for (cube_id, direction, pos_x, pos_y) in &cubes {
+                        sqlx::query(
+                            "INSERT INTO hero_talent_template_cubes (template_row_id, cube_id, direction, pos_x, pos_y) VALUES (?, ?, ?, ?, ?)"
+                        )
+                        .bind(template_row_id)
+                        .bind(cube_id)
+                        .bind(direction)
+                        .bind(pos_x)
+                        .bind(pos_y)
+                        .execute(&mut **tx)
+                        .await?;
+                    }
+                }
             }
+
+            tracing::debug!(
+                "Loaded {} talent cubes for hero {} (uid {})",
+                cubes.len(),
+                hero_id,
+                hero_uid
+            );
+        }
+    } else {
+        // IMPORTANT: Even if hero has no talent data, we still need to create 4 empty templates
+        for template_id in 1..=4 {
+            sqlx::query(
+                "INSERT INTO hero_talent_templates (hero_uid, template_id, name, style) VALUES (?, ?, ?, ?)"
+            )
+            .bind(hero_uid)
+            .bind(template_id)
+            .bind("") // Empty name
+            .bind(0) // Style 0
+            .execute(&mut **tx)
+            .await?;
         }
     }
-    // Initialize touch count
-    sqlx::query("INSERT INTO hero_touch_count (user_id, touch_count_left) VALUES (?, ?)")
-        .bind(uid)
-        .bind(5) // Default 5 touches per day
-        .execute(&mut **tx)
-        .await?;
 
-    // update hero count for player info
-    sqlx::query(
-        r#"
-        UPDATE player_info
-        SET
-            hero_rare_nn_count  = ?,
-            hero_rare_n_count   = ?,
-            hero_rare_r_count   = ?,
-            hero_rare_sr_count  = ?,
-            hero_rare_ssr_count = ?,
-            updated_at = ?
-        WHERE player_id = ?
-        "#,
-    )
-    .bind(rarity_counts[1]) // NN
-    .bind(rarity_counts[2]) // N
-    .bind(rarity_counts[3]) // R
-    .bind(rarity_counts[4]) // SR
-    .bind(rarity_counts[5]) // SSR
-    .bind(now)
-    .bind(uid)
-    .execute(&mut **tx)
-    .await?;
-
-    tracing::info!(
-        "Generated {} level 1 starter heroes from excel data for uid {}",
-        characters.len(),
-        uid
-    );
     Ok(())
 }
 
@@ -724,9 +774,9 @@ pub async fn load_hero_list(
 pub async fn load_equipment(
     tx: &mut Transaction<'_, Sqlite>,
     uid: i64,
+    profile: &StarterProfile,
 ) -> sqlx::Result<HashMap<i32, i64>> {
     let game_data = configs::get();
-    let now = common::time::ServerTime::now_ms();
 
     // Starting seed for equipment UIDs
     static EQUIP_UID_COUNTER: AtomicI64 = AtomicI64::new(30000000);
@@ -738,48 +788,12 @@ pub async fn load_equipment(
     let equipment_list: Vec<_> = game_data
         .equip
         .iter()
-        .filter(|e| e.rare == 5 || e.rare == 4 || e.rare == 3 || e.rare == 2)
+        .filter(|e| is_starter_equipment(e, profile))
         .collect();
 
     for equip in equipment_list.clone() {
         let equip_uid = EQUIP_UID_COUNTER.fetch_add(1, Ordering::SeqCst);
-
-        // Determine max level and break level based on rarity
-        let (max_level, max_break_lv, max_refine_lv, is_locked) = match equip.rare {
-            5 => (60, 3, 5, true),  // SSR: Level 60, Break 3, Refine 5, LOCKED
-            4 => (60, 3, 5, true),  // SR: LOCKED
-            3 => (60, 3, 5, false), // R: Not locked
-            2 => (60, 3, 5, false), // N: Not locked
-            1 => (60, 3, 5, false), // Common: Not locked
-            _ => (60, 3, 5, false),
-        };
-
-        let mut is_locked = is_locked;
-
-        if equip.name_en == "Enlighten" || equip.name_en == "Gluttony" || equip.name_en == "Greed" {
-            is_locked = false;
-        }
-
-        sqlx::query(
-            r#"
-            INSERT INTO equipment (
-                uid, user_id, equip_id, level, exp, break_lv, count, is_lock, refine_lv, created_at, updated_at
-            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
-            "#,
-        )
-        .bind(equip_uid)
-        .bind(uid)
-        .bind(equip.id)
-        .bind(max_level)
-        .bind(0) // exp
-        .bind(max_break_lv)
-        .bind(1) // count
-        .bind(is_locked)
-        .bind(max_refine_lv)
-        .bind(now)
-        .bind(now)
-        .execute(&mut **tx)
-        .await?;
+        insert_starter_equipment(tx, uid, equip_uid, equip, profile).await?;
 
         // Store the mapping (equip_id -> first created uid for this equip)
         equip_map.entry(equip.id).or_insert(equip_uid);
@@ -789,13 +803,97 @@ pub async fn load_equipment(
     Ok(equip_map)
 }
 
+/// Psychubes the profile hands out
+pub(crate) fn is_starter_equipment(equip: &config::equip::Equip, profile: &StarterProfile) -> bool {
+    (equip.rare == 5 || equip.rare == 4 || equip.rare == 3 || equip.rare == 2)
+        && (profile.all_equipment || profile.equipment.contains(&equip.id))
+}
+
+/// Inserts one psychube, maxed out when the profile unlocks them all
+pub(crate) async fn insert_starter_equipment(
+    tx: &mut Transaction<'_, Sqlite>,
+    uid: i64,
+    equip_uid: i64,
+    equip: &config::equip::Equip,
+    profile: &StarterProfile,
+) -> sqlx::Result<()> {
+    let now = common::time::ServerTime::now_ms();
+
+    // Determine max level and break level based on rarity
+    let (max_level, max_break_lv, max_refine_lv, is_locked) = match equip.rare {
+        _ if !profile.all_equipment => (1, 0, 1, false),
+        5 => (60, 3, 5, true),  // SSR: Level 60, Break 3, Refine 5, LOCKED
+        4 => (60, 3, 5, true),  // SR: LOCKED
+        3 => (60, 3, 5, false), // R: Not locked
+        2 => (60, 3, 5, false), // N: Not locked
+        1 => (60, 3, 5, false), // Common: Not locked
+        _ => (60, 3, 5, false),
+    };
+
+    let mut is_locked = is_locked;
+
+    if equip.name_en == "Enlighten" || equip.name_en == "Gluttony" || equip.name_en == "Greed" {
+        is_locked = false;
+    }
+
+    sqlx::query(
+        r#"
+        INSERT INTO equipment (
+            uid, user_id, equip_id, level, exp, break_lv, count, is_lock, refine_lv, created_at, updated_at
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
+        "#,
+    )
+    .bind(equip_uid)
+    .bind(uid)
+    .bind(equip.id)
+    .bind(max_level)
+    .bind(0) // exp
+    .bind(max_break_lv)
+    .bind(1) // count
+    .bind(is_locked)
+    .bind(max_refine_lv)
+    .bind(now)
+    .bind(now)
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(())
+}
+
 pub async fn load_starter_items(
     tx: &mut Transaction<'_, Sqlite>,
     user_id: i64,
+    profile: &StarterProfile,
 ) -> sqlx::Result<()> {
     let game_data = configs::get();
     let now = common::time::ServerTime::now_ms();
 
+    if !profile.all_items {
+        for (item_id, quantity) in &profile.items {
+            sqlx::query(
+                r#"
+                INSERT INTO items (
+                    user_id, item_id, quantity, last_use_time, last_update_time, total_gain_count
+                ) VALUES (?1, ?2, ?3, NULL, ?4, ?5)
+                "#,
+            )
+            .bind(user_id)
+            .bind(item_id)
+            .bind(quantity)
+            .bind(now)
+            .bind(*quantity as i64)
+            .execute(&mut **tx)
+            .await?;
+        }
+
+        tracing::info!(
+            "Loaded {} profile items for user {}",
+            profile.items.len(),
+            user_id
+        );
+        return Ok(());
+    }
+
     static POWER_ITEM_UID_COUNTER: AtomicI64 = AtomicI64::new(40000000);
     static INSIGHT_ITEM_UID_COUNTER: AtomicI64 = AtomicI64::new(50000000);
 
@@ -944,6 +1042,7 @@ pub async fn load_starter_items(
 pub async fn load_starter_currencies(
     tx: &mut Transaction<'_, Sqlite>,
     user_id: i64,
+    profile: &StarterProfile,
 ) -> sqlx::Result<()> {
     let game_data = configs::get();
     let now = common::time::ServerTime::now_ms();
@@ -962,6 +1061,8 @@ pub async fn load_starter_currencies(
             quantity = 240;
         }
 
+        let quantity = profile.currency(currency.id, quantity);
+
         sqlx::query(
             "INSERT INTO currencies (user_id, currency_id, quantity, last_recover_time, expired_time)
              VALUES (?, ?, ?, ?, 0)"
@@ -1037,11 +1138,45 @@ pub async fn load_starter_user_stats(
     Ok(())
 }
 
+/// Hero and psychube uids an account owns. The starter team slots reference the
+/// units of the unlocked profile, slots pointing at anything else are left empty.
+struct OwnedUnits {
+    heroes: HashSet<i64>,
+    equips: HashSet<i64>,
+}
+
+impl OwnedUnits {
+    async fn load(tx: &mut Transaction<'_, Sqlite>, user_id: i64) -> sqlx::Result<Self> {
+        let heroes = sqlx::query_scalar("SELECT uid FROM heroes WHERE user_id = ?")
+            .bind(user_id)
+            .fetch_all(&mut **tx)
+            .await?;
+        let equips = sqlx::query_scalar("SELECT uid FROM equipment WHERE user_id = ?")
+            .bind(user_id)
+            .fetch_all(&mut **tx)
+            .await?;
+
+        Ok(Self {
+            heroes: heroes.into_iter().collect(),
+            equips: equips.into_iter().collect(),
+        })
+    }
+
+    fn hero(&self, uid: i64) -> i64 {
+        if self.heroes.contains(&uid) { uid } else { 0 }
+    }
+
+    fn equip(&self, uid: i64) -> i64 {
+        if self.equips.contains(&uid) { uid } else { 0 }
+    }
+}
+
 pub async fn load_starter_hero_groups(
     tx: &mut Transaction<'_, Sqlite>,
     user_id: i64,
 ) -> sqlx::Result<()> {
     let now = common::time::ServerTime::now_ms();
+    let owned = OwnedUnits::load(tx, user_id).await?;
 
     // Load hero_group_common_list.json
     let common_json = include_str!("../../../assets/static/heros/hero_group_common_list.json");
@@ -1090,7 +1225,7 @@ pub async fn load_starter_hero_groups(
             // Add heroes
             if let Some(hero_list) = group.get("heroList").and_then(|v| v.as_array()) {
                 for (position, hero_uid) in hero_list.iter().enumerate() {
-                    if let Some(uid) = hero_uid.as_i64() {
+                    if let Some(uid) = hero_uid.as_i64().map(|uid| owned.hero(uid)) {
                         sqlx::query(
                             "INSERT INTO hero_group_members (hero_group_id, hero_uid, position) VALUES (?, ?, ?)"
                         )
@@ -1109,7 +1244,7 @@ pub async fn load_starter_hero_groups(
                     let index = equip.get("index").and_then(|v| v.as_i64()).unwrap_or(0) as i32;
                     if let Some(equip_uids) = equip.get("equipUid").and_then(|v| v.as_array()) {
                         for equip_uid in equip_uids {
-                            if let Some(uid) = equip_uid.as_i64() {
+                            if let Some(uid) = equip_uid.as_i64().map(|uid| owned.equip(uid)) {
                                 sqlx::query(
                                     "INSERT INTO hero_group_equips (hero_group_id, index_slot, equip_uid) VALUES (?, ?, ?)"
                                 )
@@ -1130,7 +1265,7 @@ pub async fn load_starter_hero_groups(
                     let index = equip.get("index").and_then(|v| v.as_i64()).unwrap_or(0) as i32;
                     if let Some(equip_uids) = equip.get("equipUid").and_then(|v| v.as_array()) {
                         for equip_uid in equip_uids {
-                            if let Some(uid) = equip_uid.as_i64() {
+                            if let Some(uid) = equip_uid.as_i64().map(|uid| owned.equip(uid)) {
                                 sqlx::query(
                                     "INSERT INTO hero_group_activity104_equips (hero_group_id, index_slot, equip_uid) VALUES (?, ?, ?)"
                                 )
@@ -1187,6 +1322,7 @@ pub async fn load_hero_group_snapshots(
     user_id: i64,
 ) -> sqlx::Result<()> {
     let now = common::time::ServerTime::now_ms();
+    let owned = OwnedUnits::load(tx, user_id).await?;
 
     let json_str = include_str!("../../../assets/static/heros/hero_group_snapshots.json");
     let data: Value = match serde_json::from_str(json_str) {
@@ -1263,7 +1399,7 @@ pub async fn load_hero_group_snapshots(
                 // Insert heroes
                 if let Some(hero_list) = group.get("heroList").and_then(|v| v.as_array()) {
                     for (position, hero_uid) in hero_list.iter().enumerate() {
-                        if let Some(uid) = hero_uid.as_i64() {
+                        if let Some(uid) = hero_uid.as_i64().map(|uid| owned.hero(uid)) {
                             sqlx::query(
                                 "INSERT INTO hero_group_snapshot_members (snapshot_group_id, hero_uid, position)
                                  VALUES (?, ?, ?)"
@@ -1283,7 +1419,7 @@ pub async fn load_hero_group_snapshots(
                         let index = equip.get("index").and_then(|v| v.as_i64()).unwrap_or(0) as i32;
                         if let Some(equip_uids) = equip.get("equipUid").and_then(|v| v.as_array()) {
                             for equip_uid in equip_uids {
-                                if let Some(uid) = equip_uid.as_i64() {
+                                if let Some(uid) = equip_uid.as_i64().map(|uid| owned.equip(uid)) {
                                     sqlx::query(
                                         "INSERT INTO hero_group_snapshot_equips (snapshot_group_id, index_slot, equip_uid)
                                          VALUES (?, ?, ?)"
@@ -1305,7 +1441,7 @@ pub async fn load_hero_group_snapshots(
                         let index = equip.get("index").and_then(|v| v.as_i64()).unwrap_or(0) as i32;
                         if let Some(equip_uids) = equip.get("equipUid").and_then(|v| v.as_array()) {
                             for equip_uid in equip_uids {
-                                if let Some(uid) = equip_uid.as_i64() {
+                                if let Some(uid) = equip_uid.as_i64().map(|uid| owned.equip(uid)) {
                                     sqlx::query(
                                         "INSERT INTO hero_group_snapshot_activity104_equips (snapshot_group_id, index_slot, equip_uid)
                                          VALUES (?, ?, ?)"
@@ -4234,22 +4370,29 @@ pub async fn load_starter_mail(tx: &mut Transaction<'_, Sqlite>, uid: i64) -> sq
 pub async fn load_all_starter_data(pool: &SqlitePool, uid: i64) -> sqlx::Result<()> {
     tracing::info!("Loading all starter data for uid {uid} in a single transaction");
 
+    let profile = starter_profile::current();
     let mut tx = pool.begin().await?;
 
     load_critter_info(&mut tx, uid).await?;
-    load_player_info(&mut tx, uid).await?;
-    let equip_map = load_equipment(&mut tx, uid).await?;
+    load_player_info(&mut tx, uid, profile).await?;
+    let equip_map = load_equipment(&mut tx, uid, profile).await?;
     // Then load heroes with equipment references
-    load_hero_list(&mut tx, uid, &equip_map).await?;
-    load_starter_items(&mut tx, uid).await?;
-    load_starter_currencies(&mut tx, uid).await?;
-    load_starter_guides(&mut tx, uid).await?;
+    load_hero_list(&mut tx, uid, &equip_map, profile).await?;
+    load_starter_items(&mut tx, uid, profile).await?;
+    load_starter_currencies(&mut tx, uid, profile).await?;
+    if profile.complete_guides {
+        load_starter_guides(&mut tx, uid).await?;
+    }
     load_starter_user_stats(&mut tx, uid).await?;
     load_starter_hero_groups(&mut tx, uid).await?;
     load_hero_group_snapshots(&mut tx, uid).await?;
-    load_dungeon_info(&mut tx, uid).await?;
-    load_dungeon_infos(&mut tx, uid).await?;
-    load_story_data(&mut tx, uid).await?;
+    if profile.clear_dungeons {
+        load_dungeon_info(&mut tx, uid).await?;
+        load_dungeon_infos(&mut tx, uid).await?;
+    }
+    if profile.clear_stories {
+        load_story_data(&mut tx, uid).await?;
+    }
     load_charge_info(&mut tx, uid).await?;
     load_block_package_info(&mut tx, uid).await?;
     load_building_info(&mut tx, uid).await?;
@@ -4272,6 +4415,7 @@ pub async fn load_all_starter_data(pool: &SqlitePool, uid: i64) -> sqlx::Result<
     load_starter_bgm(&mut tx, uid).await?;
     load_room_info(&mut tx, uid).await?;
     load_starter_mail(&mut tx, uid).await?;
+    super::content_sync::record_version(&mut tx, uid).await?;
 
     tx.commit().await?;
 
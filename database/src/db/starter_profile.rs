@@ -0,0 +1,107 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+static PROFILE: OnceLock<StarterProfile> = OnceLock::new();
+
+/// What a new account starts with. Custom JSON profiles only need the fields they
+/// change, anything missing keeps the `unlocked` behavior.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct StarterProfile {
+    /// Hero ids granted on creation, `None` for every playable character
+    pub heroes: Option<Vec<i32>>,
+    /// Heroes start at max level, insight, resonance and portray
+    pub max_heroes: bool,
+    /// Every psychube at max level, otherwise only `equipment` at level 1
+    pub all_equipment: bool,
+    pub equipment: Vec<i32>,
+    /// Every shown item, otherwise only `items` (item id -> quantity)
+    pub all_items: bool,
+    pub items: HashMap<i32, i32>,
+    /// Amount of every currency, `None` keeps the unlocked amounts
+    pub currency_amount: Option<i32>,
+    /// Per currency overrides
+    pub currencies: HashMap<i32, i32>,
+    /// Mark every tutorial guide finished
+    pub complete_guides: bool,
+    /// Every episode cleared with full stars
+    pub clear_dungeons: bool,
+    /// Every story read
+    pub clear_stories: bool,
+}
+
+impl Default for StarterProfile {
+    fn default() -> Self {
+        Self::unlocked()
+    }
+}
+
+impl StarterProfile {
+    pub fn unlocked() -> Self {
+        Self {
+            heroes: None,
+            max_heroes: true,
+            all_equipment: true,
+            equipment: Vec::new(),
+            all_items: true,
+            items: HashMap::new(),
+            currency_amount: None,
+            currencies: HashMap::new(),
+            complete_guides: true,
+            clear_dungeons: true,
+            clear_stories: true,
+        }
+    }
+
+    pub fn fresh() -> Self {
+        serde_json::from_str(include_str!("../../../assets/starter/profiles/fresh.json"))
+            .expect("Invalid built-in fresh starter profile")
+    }
+
+    /// `unlocked`, `fresh` or the path of a custom JSON profile
+    pub fn load(name: &str) -> anyhow::Result<Self> {
+        match name {
+            "unlocked" => Ok(Self::unlocked()),
+            "fresh" => Ok(Self::fresh()),
+            path => {
+                let json = std::fs::read_to_string(path).map_err(|e| {
+                    anyhow::anyhow!("Failed to read starter profile '{}': {}", path, e)
+                })?;
+                serde_json::from_str(&json).map_err(|e| {
+                    anyhow::anyhow!("Failed to parse starter profile '{}': {}", path, e)
+                })
+            }
+        }
+    }
+
+    pub fn owns_hero(&self, hero_id: i32) -> bool {
+        self.heroes
+            .as_ref()
+            .is_none_or(|heroes| heroes.contains(&hero_id))
+    }
+
+    /// Starting amount of a currency, `unlocked` is the amount the unlocked profile uses
+    pub fn currency(&self, currency_id: i32, unlocked: i32) -> i32 {
+        self.currencies
+            .get(&currency_id)
+            .copied()
+            .or(self.currency_amount)
+            .unwrap_or(unlocked)
+    }
+}
+
+/// Selects the profile new accounts are created with
+pub fn init(name: &str) -> anyhow::Result<()> {
+    let profile = StarterProfile::load(name)?;
+    tracing::info!("Starter profile: {}", name);
+
+    PROFILE
+        .set(profile)
+        .map_err(|_| anyhow::anyhow!("Starter profile already initialized"))
+}
+
+/// The selected profile, `unlocked` if none was selected
+pub fn current() -> &'static StarterProfile {
+    PROFILE.get_or_init(StarterProfile::unlocked)
+}
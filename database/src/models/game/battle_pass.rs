@@ -0,0 +1,37 @@
+use sonettobuf;
+use sqlx::FromRow;
+
+#[derive(Debug, Clone, FromRow)]
+pub struct UserBattlePass {
+    pub bp_id: i32,
+    pub season_start: i64,
+    pub score: i32,
+    pub weekly_score: i32,
+    pub score_week: i32,
+    pub pay_status: i32,
+    pub first_show: bool,
+    pub sp_first_show: bool,
+}
+
+/// Rewards claimed at one pass level
+#[derive(Debug, Clone, Default, FromRow)]
+pub struct UserBpBonus {
+    pub level: i32,
+    pub free_bonus: bool,
+    pub pay_bonus: bool,
+    pub sp_free_bonus: bool,
+    pub sp_pay_bonus: bool,
+    pub select_index: Option<i32>,
+}
+
+impl From<&UserBpBonus> for sonettobuf::BpScoreBonusInfo {
+    fn from(b: &UserBpBonus) -> Self {
+        sonettobuf::BpScoreBonusInfo {
+            level: Some(b.level),
+            has_getfree_bonus: Some(b.free_bonus),
+            has_get_pay_bonus: Some(b.pay_bonus),
+            has_get_spfree_bonus: Some(b.sp_free_bonus),
+            has_get_sp_pay_bonus: Some(b.sp_pay_bonus),
+        }
+    }
+}
@@ -9,13 +9,11 @@ pub trait EquipmentModel<T> {
     async fn get_all(&self) -> Result<Vec<T>>;
     async fn break_level(&self, equip_uid: i64) -> Result<bool>;
     async fn lock(&self, equip_uid: i64, is_lock: bool) -> Result<bool>;
-    async fn refine_level(&self, equip_uid: i64, level: i32) -> Result<bool>;
-    async fn delete(&self, equip_uid: i64) -> Result<()>;
     async fn strengthen(
         &self,
         target_uid: i64,
         consume_items: Vec<(i64, i32)>,
-    ) -> Result<(i32, Vec<i32>)>;
+    ) -> Result<Option<StrengthenPlan>>;
 }
 
 pub struct UserEquipmentModel {
@@ -38,6 +36,19 @@ pub struct Equipment {
     pub updated_at: i64,
 }
 
+/// Result of feeding equipment into a target, written by the caller together with the
+/// consumed copies and the gold cost
+#[derive(Debug, Clone)]
+pub struct StrengthenPlan {
+    /// The target with its new level and leftover exp
+    pub target: Equipment,
+    /// `(uid, count)` copies eaten
+    pub consumed: Vec<(i64, i32)>,
+    pub total_exp: i32,
+    /// Summed `scoreCost` of the levels gained
+    pub score_cost: i32,
+}
+
 impl From<Equipment> for sonettobuf::Equip {
     fn from(e: Equipment) -> Self {
         sonettobuf::Equip {
@@ -70,7 +81,7 @@ impl UserEquipmentModel {
         &self,
         target_uid: i64,
         consume_items: Vec<(i64, i32)>,
-    ) -> Result<(i32, Vec<i32>)> {
+    ) -> Result<Option<StrengthenPlan>> {
         EquipmentModel::<Equipment>::strengthen(self, target_uid, consume_items).await
     }
 }
@@ -143,38 +154,11 @@ impl EquipmentModel<Equipment> for UserEquipmentModel {
         Ok(rows_affected > 0)
     }
 
-    async fn refine_level(&self, equip_uid: i64, level: i32) -> Result<bool> {
-        let now = common::time::ServerTime::now_ms();
-        let new_level = sqlx::query(
-            "UPDATE equipment
-             SET refine_lv = ?, updated_at = ?
-             WHERE uid = ? AND user_id = ?",
-        )
-        .bind(level)
-        .bind(now)
-        .bind(equip_uid)
-        .bind(self.user_id)
-        .execute(&self.pool)
-        .await?;
-
-        Ok(new_level.rows_affected() > 0)
-    }
-
-    async fn delete(&self, equip_uid: i64) -> Result<()> {
-        sqlx::query("DELETE FROM equipment WHERE uid = ? AND user_id = ?")
-            .bind(equip_uid)
-            .bind(self.user_id)
-            .execute(&self.pool)
-            .await?;
-
-        Ok(())
-    }
-
     async fn strengthen(
         &self,
         target_uid: i64,
         consume_items: Vec<(i64, i32)>,
-    ) -> Result<(i32, Vec<i32>)> {
+    ) -> Result<Option<StrengthenPlan>> {
         let mut target = self.get(target_uid).await?;
         let game_data = config::configs::get();
 
@@ -184,8 +168,7 @@ impl EquipmentModel<Equipment> for UserEquipmentModel {
             .ok_or_else(|| anyhow::anyhow!("Equipment data not found"))?;
 
         let mut total_exp = 0i32;
-        let mut equips_to_update = Vec::new();
-        let now = common::time::ServerTime::now_ms();
+        let mut consumed = Vec::new();
 
         for (eat_uid, consume_count) in &consume_items {
             let eat_equipment = match self.get(*eat_uid).await {
@@ -257,16 +240,11 @@ impl EquipmentModel<Equipment> for UserEquipmentModel {
             }
 
             total_exp += exp_per_equip * consume_count;
-            equips_to_update.push((
-                *eat_uid,
-                *consume_count,
-                eat_equipment.count,
-                eat_equipment.equip_id,
-            ));
+            consumed.push((*eat_uid, *consume_count));
         }
 
         if total_exp == 0 {
-            return Ok((0, vec![]));
+            return Ok(None);
         }
 
         target.exp += total_exp;
@@ -288,14 +266,14 @@ impl EquipmentModel<Equipment> for UserEquipmentModel {
             total_exp
         );
 
+        let mut score_cost = 0;
         while target.level < max_level {
             let next_level = target.level + 1;
-            let exp_required = game_data
+            let cost = game_data
                 .equip_strengthen_cost
                 .iter()
-                .find(|e| e.rare == rare && e.level == next_level)
-                .map(|c| c.exp)
-                .unwrap_or(999999);
+                .find(|e| e.rare == rare && e.level == next_level);
+            let exp_required = cost.map(|c| c.exp).unwrap_or(999999);
 
             tracing::info!(
                 "Trying to level {} -> {}: have {} exp, need {} exp",
@@ -308,74 +286,17 @@ impl EquipmentModel<Equipment> for UserEquipmentModel {
             if target.exp >= exp_required {
                 target.exp -= exp_required;
                 target.level += 1;
+                score_cost += cost.map_or(0, |c| c.score_cost);
             } else {
                 break;
             }
         }
 
-        let mut affected_equip_ids = vec![target.equip_id];
-        for (_, _, _, equip_id) in &equips_to_update {
-            if !affected_equip_ids.contains(equip_id) {
-                affected_equip_ids.push(*equip_id);
-            }
-        }
-
-        sqlx::query(
-            "UPDATE equipment
-                 SET level = ?, exp = ?, updated_at = ?
-                 WHERE uid = ? AND user_id = ?",
-        )
-        .bind(target.level)
-        .bind(target.exp)
-        .bind(now)
-        .bind(target_uid)
-        .bind(self.user_id)
-        .execute(&self.pool)
-        .await?;
-
-        for (eat_uid, consume_count, current_count, equip_id) in equips_to_update {
-            let new_count = current_count - consume_count;
-            let is_stackable =
-                equip_id == 1002 || equip_id == 1003 || equip_id == 1004 || equip_id == 1005;
-
-            if new_count <= 0 {
-                if is_stackable {
-                    sqlx::query(
-                            "UPDATE equipment SET count = 0, updated_at = ? WHERE uid = ? AND user_id = ?",
-                        )
-                        .bind(now)
-                        .bind(eat_uid)
-                        .bind(self.user_id)
-                        .execute(&self.pool)
-                        .await?;
-                } else {
-                    sqlx::query("DELETE FROM equipment WHERE uid = ? AND user_id = ?")
-                        .bind(eat_uid)
-                        .bind(self.user_id)
-                        .execute(&self.pool)
-                        .await?;
-                }
-            } else {
-                sqlx::query(
-                    "UPDATE equipment SET count = ?, updated_at = ? WHERE uid = ? AND user_id = ?",
-                )
-                .bind(new_count)
-                .bind(now)
-                .bind(eat_uid)
-                .bind(self.user_id)
-                .execute(&self.pool)
-                .await?;
-            }
-        }
-
-        tracing::info!(
-            "User {} strengthened equipment uid={} to level {} (exp: {})",
-            self.user_id,
-            target_uid,
-            target.level,
-            target.exp
-        );
-
-        Ok((total_exp, affected_equip_ids))
+        Ok(Some(StrengthenPlan {
+            target,
+            consumed,
+            total_exp,
+            score_cost,
+        }))
     }
 }
@@ -0,0 +1,272 @@
+use config::configs;
+
+use crate::models::game::{
+    equipment::Equipment,
+    heros::{Hero, HeroData, HeroTalentCube},
+};
+
+/// Final stats of a hero, shared by hero info replies and battle entities so the
+/// character panel and the fight agree on the numbers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HeroStats {
+    pub hp: i32,
+    pub attack: i32,
+    pub defense: i32,
+    pub mdefense: i32,
+    pub technic: i32,
+    pub cri: i32,
+    pub recri: i32,
+    pub cri_dmg: i32,
+    pub cri_def: i32,
+    pub add_dmg: i32,
+    pub drop_dmg: i32,
+    /// Penetration rate, permille of the target's defense ignored
+    pub defense_ignore: i32,
+}
+
+/// Insight effect type that raises the base stats, value in permille
+const RANK_EFFECT_ATTR: &str = "2";
+
+impl HeroStats {
+    /// Everything the character panel adds up: level curve, insight, the resonance cubes
+    /// of the template in use with its style, the equipped psychube and the special
+    /// attributes
+    pub fn for_hero_data(data: &HeroData) -> Self {
+        let record = &data.record;
+
+        let style = data
+            .talent_templates
+            .iter()
+            .find(|(t, _)| t.template_id == record.use_talent_template_id)
+            .map(|(t, _)| t.style)
+            .unwrap_or(0);
+
+        let mut stats = Self::for_hero(record).with_talent_cubes(record, &data.talent_cubes, style);
+
+        if let Some(equip) = &data.equip {
+            stats = stats.with_equip(equip);
+        }
+
+        if let Some(sp) = &data.sp_attr {
+            stats.defense_ignore += sp.defense_ignore;
+        }
+
+        stats
+    }
+
+    /// Stats from the level curve and insight bonuses. Heroes missing from
+    /// `character_level` fall back to the stored base attributes.
+    pub fn for_hero(hero: &Hero) -> Self {
+        let mut stats = level_stats(hero.hero_id, hero.level).unwrap_or(Self {
+            hp: hero.base_hp,
+            attack: hero.base_attack,
+            defense: hero.base_defense,
+            mdefense: hero.base_mdefense,
+            technic: hero.base_technic,
+            cri: hero.ex_cri,
+            recri: hero.ex_recri,
+            cri_dmg: hero.ex_cri_dmg,
+            cri_def: hero.ex_cri_def,
+            add_dmg: hero.ex_add_dmg,
+            drop_dmg: hero.ex_drop_dmg,
+            defense_ignore: 0,
+        });
+
+        let rank_permille = rank_bonus_permille(hero.hero_id, hero.rank);
+        if rank_permille > 0 {
+            stats.scale_base(rank_permille);
+        }
+
+        stats
+    }
+
+    /// Adds the resonance cubes at the hero's resonance level. Base attributes on a cube
+    /// are permille of the level and insight stats, the rest are flat. A style swaps the
+    /// exclusive cube for its styled variant, cube `exclusive * 10 + style`.
+    pub fn with_talent_cubes(mut self, hero: &Hero, cubes: &[HeroTalentCube], style: i32) -> Self {
+        let game_data = configs::get();
+        let base = self;
+
+        let exclusive = game_data
+            .character_talent
+            .iter()
+            .find(|t| t.hero_id == hero.hero_id && t.talent_id == hero.talent)
+            .and_then(|t| t.exclusive.split('#').next()?.parse::<i32>().ok());
+
+        for cube in cubes {
+            let cube_id = match exclusive {
+                Some(id) if style > 0 && cube.cube_id == id => id * 10 + style,
+                _ => cube.cube_id,
+            };
+
+            let Some(attr) = game_data
+                .talent_cube_attr
+                .iter()
+                .filter(|a| a.id == cube_id && a.level <= hero.talent)
+                .max_by_key(|a| a.level)
+            else {
+                continue;
+            };
+
+            let permille = |value: i32, rate: i32| (value as i64 * rate as i64 / 1000) as i32;
+
+            self.hp += permille(base.hp, attr.hp);
+            self.attack += permille(base.attack, attr.atk);
+            self.defense += permille(base.defense, attr.def);
+            self.mdefense += permille(base.mdefense, attr.mdef);
+            self.technic += permille(base.technic, attr.technic);
+            self.cri += attr.cri;
+            self.recri += attr.recri;
+            self.cri_dmg += attr.cri_dmg;
+            self.cri_def += attr.cri_def;
+            self.add_dmg += attr.add_dmg;
+            self.drop_dmg += attr.drop_dmg;
+            self.defense_ignore += attr.defense_ignore;
+        }
+
+        self
+    }
+
+    /// Adds the psychube's level stats and the refinement bonuses of its skill
+    pub fn with_equip(mut self, equip: &Equipment) -> Self {
+        let game_data = configs::get();
+
+        let Some(equip_cfg) = game_data.equip.get(equip.equip_id) else {
+            return self;
+        };
+
+        // `equip_strengthen` holds the max stats of a strength type, the level picks the
+        // permille of them reached from `equip_strengthen_cost`
+        let strengthen = game_data
+            .equip_strengthen
+            .iter()
+            .find(|s| s.strength_type == equip_cfg.strength_type);
+        let rate = game_data
+            .equip_strengthen_cost
+            .iter()
+            .find(|c| c.rare == equip_cfg.rare && c.level == equip.level.max(1))
+            .map(|c| c.attribute_rate);
+
+        if let (Some(strengthen), Some(rate)) = (strengthen, rate) {
+            let scale = |value: i32| (value as i64 * rate as i64 / 1000) as i32;
+
+            self.hp += scale(strengthen.hp);
+            self.attack += scale(strengthen.atk);
+            self.defense += scale(strengthen.def);
+            self.mdefense += scale(strengthen.mdef);
+        }
+
+        if let Some(skill) = game_data
+            .equip_skill
+            .iter()
+            .find(|s| s.id == equip_cfg.skill_type && s.skill_lv == equip.refine_lv.max(1))
+        {
+            // hp and attack on refinement are permille of the hero's own value
+            self.hp += self.hp * skill.hp / 1000;
+            self.attack += self.attack * skill.attack / 1000;
+            self.cri += skill.cri;
+            self.recri += skill.recri;
+            self.cri_dmg += skill.cri_dmg;
+            self.cri_def += skill.cri_def;
+            self.add_dmg += skill.add_dmg;
+            self.drop_dmg += skill.drop_dmg;
+        }
+
+        self
+    }
+
+    pub fn attribute(&self, hero: &Hero) -> sonettobuf::HeroAttribute {
+        sonettobuf::HeroAttribute {
+            hp: Some(self.hp),
+            attack: Some(self.attack),
+            defense: Some(self.defense),
+            mdefense: Some(self.mdefense),
+            technic: Some(self.technic),
+            multi_hp_idx: Some(hero.base_multi_hp_idx),
+            multi_hp_num: Some(hero.base_multi_hp_num),
+        }
+    }
+
+    pub fn ex_attribute(&self) -> sonettobuf::HeroExAttribute {
+        sonettobuf::HeroExAttribute {
+            cri: Some(self.cri),
+            recri: Some(self.recri),
+            cri_dmg: Some(self.cri_dmg),
+            cri_def: Some(self.cri_def),
+            add_dmg: Some(self.add_dmg),
+            drop_dmg: Some(self.drop_dmg),
+        }
+    }
+
+    fn scale_base(&mut self, permille: i32) {
+        let scale = |v: i32| v + (v as i64 * permille as i64 / 1000) as i32;
+
+        self.hp = scale(self.hp);
+        self.attack = scale(self.attack);
+        self.defense = scale(self.defense);
+        self.mdefense = scale(self.mdefense);
+        self.technic = scale(self.technic);
+    }
+}
+
+/// Level curve stats, `character_level` only lists milestone levels so anything in
+/// between is interpolated linearly.
+fn level_stats(hero_id: i32, level: i32) -> Option<HeroStats> {
+    let game_data = configs::get();
+
+    let mut rows: Vec<_> = game_data
+        .character_level
+        .iter()
+        .filter(|l| l.hero_id == hero_id)
+        .collect();
+    rows.sort_by_key(|l| l.level);
+
+    let lower = rows
+        .iter()
+        .rev()
+        .find(|l| l.level <= level)
+        .or(rows.first())?;
+    let upper = rows.iter().find(|l| l.level >= level).unwrap_or(lower);
+
+    let span = upper.level - lower.level;
+    let lerp = |a: i32, b: i32| {
+        if span <= 0 {
+            a
+        } else {
+            a + ((b - a) as i64 * (level - lower.level) as i64 / span as i64) as i32
+        }
+    };
+
+    Some(HeroStats {
+        hp: lerp(lower.hp, upper.hp),
+        attack: lerp(lower.atk, upper.atk),
+        defense: lerp(lower.def, upper.def),
+        mdefense: lerp(lower.mdef, upper.mdef),
+        technic: lerp(lower.technic, upper.technic),
+        cri: lerp(lower.cri, upper.cri),
+        recri: lerp(lower.recri, upper.recri),
+        cri_dmg: lerp(lower.cri_dmg, upper.cri_dmg),
+        cri_def: lerp(lower.cri_def, upper.cri_def),
+        add_dmg: lerp(lower.add_dmg, upper.add_dmg),
+        drop_dmg: lerp(lower.drop_dmg, upper.drop_dmg),
+        defense_ignore: 0,
+    })
+}
+
+/// Sum of the attribute bonuses granted by every insight reached so far
+fn rank_bonus_permille(hero_id: i32, rank: i32) -> i32 {
+    let game_data = configs::get();
+
+    game_data
+        .character_rank
+        .iter()
+        .filter(|r| r.hero_id == hero_id && r.rank <= rank)
+        .flat_map(|r| r.effect.split('|'))
+        .filter_map(|entry| {
+            let (kind, value) = entry.split_once('#')?;
+            (kind == RANK_EFFECT_ATTR)
+                .then(|| value.parse::<i32>().ok())
+                .flatten()
+        })
+        .sum()
+}
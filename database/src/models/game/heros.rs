@@ -1,8 +1,9 @@
 use anyhow::Result;
-use config::character_level::CharacterLevel;
 use serde::{Deserialize, Serialize};
 use sonettobuf;
-use sqlx::{FromRow, SqlitePool};
+use sqlx::{FromRow, Sqlite, SqlitePool, Transaction};
+
+use crate::models::game::{equipment::Equipment, hero_stats::HeroStats};
 
 #[allow(async_fn_in_trait)]
 pub trait HeroModel<T> {
@@ -21,16 +22,12 @@ pub trait HeroModel<T> {
     async fn skins(&self) -> Result<Vec<i32>>;
     async fn birthdays(&self) -> Result<Vec<(i32, i32)>>;
     async fn destiny_stone(&self, hero_id: i32, stone_id: i32) -> Result<()>;
-    async fn level_up(&self, hero_id: i32, new_level: i32, stats: &CharacterLevel) -> Result<()>;
     async fn rank_up(&self, hero_id: i32, new_rank: i32) -> Result<()>;
-    async fn unlock_insight_skin(&self, hero_id: i32, target_rank: i32) -> Result<bool>;
     async fn read_hero_red_dot(&self, hero_id: i32, red_dot: i32) -> Result<()>;
-    async fn upgrade_ex_skill(&self, hero_id: i32, levels: i32) -> Result<()>;
     async fn set_favor(&self, hero_id: i32, is_favor: bool) -> Result<()>;
     async fn unmark_new(&self, hero_id: i32) -> Result<()>;
     async fn set_show_hero(&self, hero_uids: &[i64]) -> Result<()>;
     async fn talent_style_read(&self, hero_id: i32) -> Result<()>;
-    async fn update_talent(&self, hero_id: i32, talent_id: i32) -> Result<()>;
     async fn remove_talent_cube(
         &self,
         hero_id: i32,
@@ -67,11 +64,6 @@ pub trait HeroModel<T> {
         template_id: i32,
     ) -> Result<sonettobuf::TalentTemplateInfo>;
     async fn has_talent_style(&self, hero_id: i32, style: i32) -> Result<bool>;
-    async fn unlock_talent_style(
-        &self,
-        hero_id: i32,
-        style: i32,
-    ) -> Result<(Vec<(u32, i32)>, Vec<(i32, i32)>)>;
     async fn apply_talent_style(&self, hero_id: i32, template_id: i32, style: i32) -> Result<()>;
     async fn switch_talent_template(
         &self,
@@ -288,10 +280,14 @@ pub struct HeroData {
     pub talent_cubes: Vec<HeroTalentCube>,
     pub talent_templates: Vec<(HeroTalentTemplate, Vec<HeroTalentCube>)>,
     pub destiny_stone_unlocks: Vec<i32>,
+    /// The equipped psychube
+    pub equip: Option<Equipment>,
 }
 
 impl From<HeroData> for sonettobuf::HeroInfo {
     fn from(h: HeroData) -> Self {
+        let stats = HeroStats::for_hero_data(&h);
+
         sonettobuf::HeroInfo {
             uid: h.record.uid,
             user_id: h.record.user_id,
@@ -309,23 +305,8 @@ impl From<HeroData> for sonettobuf::HeroInfo {
             voice: h.voices,
             voice_heard: h.voices_heard,
             skin_info_list: h.skin_list.into_iter().map(Into::into).collect(),
-            base_attr: Some(sonettobuf::HeroAttribute {
-                hp: Some(h.record.base_hp),
-                attack: Some(h.record.base_attack),
-                defense: Some(h.record.base_defense),
-                mdefense: Some(h.record.base_mdefense),
-                technic: Some(h.record.base_technic),
-                multi_hp_idx: Some(h.record.base_multi_hp_idx),
-                multi_hp_num: Some(h.record.base_multi_hp_num),
-            }),
-            ex_attr: Some(sonettobuf::HeroExAttribute {
-                cri: Some(h.record.ex_cri),
-                recri: Some(h.record.ex_recri),
-                cri_dmg: Some(h.record.ex_cri_dmg),
-                cri_def: Some(h.record.ex_cri_def),
-                add_dmg: Some(h.record.ex_add_dmg),
-                drop_dmg: Some(h.record.ex_drop_dmg),
-            }),
+            base_attr: Some(stats.attribute(&h.record)),
+            ex_attr: Some(stats.ex_attribute()),
             sp_attr: h.sp_attr.map(Into::into),
             equip_attr_list: h.equip_attrs.into_iter().map(Into::into).collect(),
             is_new: Some(h.record.is_new),
@@ -510,6 +491,15 @@ impl HeroModel<HeroData> for UserHeroModel {
         .fetch_all(&self.pool)
         .await?;
 
+        let equip = sqlx::query_as::<_, Equipment>(
+            "SELECT uid, user_id, equip_id, level, exp, break_lv, count, is_lock, refine_lv, created_at, updated_at
+             FROM equipment WHERE uid = ? AND user_id = ?",
+        )
+        .bind(hero_record.default_equip_uid)
+        .bind(self.user_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
         Ok(HeroData {
             record: hero_record,
             passive_skill_levels,
@@ -522,6 +512,7 @@ impl HeroModel<HeroData> for UserHeroModel {
             talent_cubes,
             talent_templates,
             destiny_stone_unlocks,
+            equip,
         })
     }
 
@@ -614,6 +605,15 @@ impl HeroModel<HeroData> for UserHeroModel {
         .fetch_all(&self.pool)
         .await?;
 
+        let equip = sqlx::query_as::<_, Equipment>(
+            "SELECT uid, user_id, equip_id, level, exp, break_lv, count, is_lock, refine_lv, created_at, updated_at
+             FROM equipment WHERE uid = ? AND user_id = ?",
+        )
+        .bind(hero_record.default_equip_uid)
+        .bind(self.user_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
         Ok(HeroData {
             record: hero_record,
             passive_skill_levels,
@@ -626,6 +626,7 @@ impl HeroModel<HeroData> for UserHeroModel {
             talent_cubes,
             talent_templates,
             destiny_stone_unlocks,
+            equip,
         })
     }
 
@@ -721,6 +722,15 @@ impl HeroModel<HeroData> for UserHeroModel {
             .fetch_all(&self.pool)
             .await?;
 
+            let equip = sqlx::query_as::<_, Equipment>(
+                "SELECT uid, user_id, equip_id, level, exp, break_lv, count, is_lock, refine_lv, created_at, updated_at
+                 FROM equipment WHERE uid = ?1 AND user_id = ?2",
+            )
+            .bind(hero_record.default_equip_uid)
+            .bind(self.user_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
             result.push(HeroData {
                 record: hero_record,
                 passive_skill_levels,
@@ -733,6 +743,7 @@ impl HeroModel<HeroData> for UserHeroModel {
                 talent_cubes,
                 talent_templates,
                 destiny_stone_unlocks,
+                equip,
             });
         }
 
@@ -776,451 +787,17 @@ impl HeroModel<HeroData> for UserHeroModel {
     }
 
     async fn create_hero(&self, hero_id: i32) -> Result<i64> {
-        let game_data = config::configs::get();
-        let now = common::time::ServerTime::now_ms();
-
-        let last_hero_uid: Option<i64> =
-            sqlx::query_scalar("SELECT uid FROM heroes ORDER BY uid DESC LIMIT 1")
-                .fetch_optional(&self.pool)
-                .await?;
-
-        let hero_uid = match last_hero_uid {
-            Some(uid) => uid + 1,
-            None => 20000001,
-        };
-
-        let character = game_data
-            .character
-            .iter()
-            .find(|c| c.id == hero_id && c.id != 3029 && c.id != 9998) //npc
-            .ok_or_else(|| sqlx::Error::RowNotFound)?;
-
-        let hero_skin = character.skin_id;
-        let rare = character.rare as usize;
-
-        let level1_stats = game_data
-            .character_level
-            .iter()
-            .filter(|s| s.hero_id == hero_id)
-            .min_by_key(|s| s.level);
-
-        let (level, hp, atk, def, mdef, technic, cri, recri, cri_dmg, cri_def, add_dmg, drop_dmg) =
-            if let Some(stats) = level1_stats {
-                (
-                    stats.level,
-                    stats.hp,
-                    stats.atk,
-                    stats.def,
-                    stats.mdef,
-                    stats.technic,
-                    stats.cri,
-                    stats.recri,
-                    stats.cri_dmg,
-                    stats.cri_def,
-                    stats.add_dmg,
-                    stats.drop_dmg,
-                )
-            } else {
-                (1, 1000, 100, 100, 100, 100, 0, 0, 1300, 0, 0, 0)
-            };
-
-        let min_ranks = game_data
-            .character_rank
-            .iter()
-            .filter(|s| s.hero_id == hero_id)
-            .min_by_key(|s| s.rank);
-
-        let min_rank = if let Some(min) = min_ranks {
-            min.rank
-        } else {
-            1
-        };
-
-        let default_skin = game_data
-            .skin
-            .iter()
-            .filter(|s| s.character_id != 0)
-            .filter(|s| s.character_id == hero_id)
-            .min_by_key(|s| s.id)
-            .map(|s| s.id)
-            .unwrap_or(hero_skin);
-
-        let destiny_data = game_data
-            .character_destiny
-            .iter()
-            .find(|d| d.hero_id == hero_id);
-
-        let (destiny_rank, destiny_level, destiny_stone, red_dot_type) =
-            if let Some(d) = destiny_data {
-                // Hero has destiny - start at 1
-                let rank = min_rank;
-                let level = 1;
-                let stone = d
-                    .facets_id
-                    .split('#')
-                    .next()
-                    .and_then(|s| s.parse::<i32>().ok())
-                    .unwrap_or(0);
-                let red_dot_type = 6;
-                (rank, level, stone, red_dot_type)
-            } else {
-                // Hero doesn't have destiny system
-                (0, 0, 0, 0)
-            };
-
-        let equip_id = character
-            .equip_rec
-            .split('#')
-            .next()
-            .and_then(|s| s.parse::<i32>().ok())
-            .unwrap_or(1501);
-
-        let strengthen_stats = game_data
-            .equip_strengthen
-            .iter()
-            .find(|s| s.strength_type == equip_id);
-
-        let (
-            final_hp,
-            final_atk,
-            final_def,
-            final_mdef,
-            final_technic,
-            final_cri,
-            final_recri,
-            final_cri_dmg,
-            final_cri_def,
-            final_add_dmg,
-            final_drop_dmg,
-        ) = if let Some(_) = strengthen_stats {
-            // For new heroes, don't add equipment bonuses
-            (
-                hp,   // No + s.hp
-                atk,  // No + s.atk
-                def,  // No + s.def
-                mdef, // No + s.mdef
-                technic, cri, recri, cri_dmg, cri_def, add_dmg, drop_dmg,
-            )
-        } else {
-            (
-                hp, atk, def, mdef, technic, cri, recri, cri_dmg, cri_def, add_dmg, drop_dmg,
-            )
-        };
-
-        let extra_str = if hero_id == 3123 {
-            "1003#2003"
-        } else if hero_id == 3124 {
-            "2#21,22|3#32,33,31"
-        } else {
-            ""
-        };
-
-        let starting_talent = game_data
-            .character_talent
-            .iter()
-            .filter(|t| t.hero_id == hero_id && t.talent_id == 1)
-            .map(|t| t.talent_id)
-            .next()
-            .unwrap_or(1);
-
-        sqlx::query(
-            r#"
-            INSERT INTO heroes (
-                uid, user_id, hero_id, create_time,
-                level, exp, rank, breakthrough, skin, faith,
-                active_skill_level, ex_skill_level, is_new, talent,
-                default_equip_uid, duplicate_count, use_talent_template_id,
-                talent_style_unlock, talent_style_red, is_favor,
-                destiny_rank, destiny_level, destiny_stone, red_dot, extra_str,
-                base_hp, base_attack, base_defense, base_mdefense, base_technic,
-                base_multi_hp_idx, base_multi_hp_num,
-                ex_cri, ex_recri, ex_cri_dmg, ex_cri_def, ex_add_dmg, ex_drop_dmg
-            ) VALUES (
-                ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10,
-                ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20,
-                ?21, ?22, ?23, ?24, ?25, ?26, ?27, ?28, ?29, ?30,
-                ?31, ?32, ?33, ?34, ?35, ?36, ?37, ?38
-            )
-            "#,
-        )
-        .bind(hero_uid)
-        .bind(self.user_id)
-        .bind(hero_id)
-        .bind(now)
-        .bind(level) // Level 1
-        .bind(0) // Starting exp
-        .bind(min_rank) // Starting rank
-        .bind(0) // No breakthrough
-        .bind(default_skin)
-        .bind(10400) // Starting faith
-        .bind(1) // Active skill level 1
-        .bind(0) // Ex skill level 0
-        .bind(true) // is_new (true for new heroes)
-        .bind(starting_talent) // talent
-        .bind(0) // default_equip_uid = 0
-        .bind(0) // duplicate_count
-        .bind(1) // use_talent_template_id
-        .bind(1) // talent_style_unlock
-        .bind(0) // talent_style_red
-        .bind(false) // is_favor
-        .bind(destiny_rank) // destiny_rank (0)
-        .bind(destiny_level) // destiny_level (0)
-        .bind(destiny_stone) // destiny_stone
-        .bind(red_dot_type) // red_dot
-        .bind(extra_str) // extra_str
-        // Base attributes (level 1 stats without equipment bonuses)
-        .bind(final_hp)
-        .bind(final_atk)
-        .bind(final_def)
-        .bind(final_mdef)
-        .bind(final_technic)
-        .bind(0) // base_multi_hp_idx
-        .bind(0) // base_multi_hp_num
-        // Ex attributes
-        .bind(final_cri)
-        .bind(final_recri)
-        .bind(final_cri_dmg)
-        .bind(final_cri_def)
-        .bind(final_add_dmg)
-        .bind(final_drop_dmg)
-        .execute(&self.pool)
-        .await?;
-
-        let max_skill_group = game_data
-            .skill_passive_level
-            .iter()
-            .filter(|s| s.hero_id == hero_id)
-            .map(|s| s.skill_group)
-            .max()
-            .unwrap_or(0);
-
-        for skill_group in 1..=max_skill_group {
-            let min_level = game_data
-                .skill_passive_level
-                .iter()
-                .filter(|s| s.hero_id == hero_id && s.skill_group == skill_group)
-                .map(|s| s.skill_level)
-                .min()
-                .unwrap_or(1);
-
-            sqlx::query(
-                "INSERT INTO hero_passive_skill_levels (hero_uid, skill_index, level) VALUES (?, ?, ?)",
-            )
-            .bind(hero_uid)
-            .bind(skill_group - 1)
-            .bind(min_level)
-            .execute(&self.pool)
-            .await?;
-        }
-
-        let character_voices: Vec<&config::character_voice::CharacterVoice> = game_data
-            .character_voice
-            .iter()
-            .filter(|v| v.hero_id == hero_id)
-            .filter(|t| t.r#type == 9 || t.r#type == 11)
-            .collect();
-
-        for voice in &character_voices {
-            sqlx::query("INSERT INTO hero_voices (hero_uid, voice_id) VALUES (?, ?)")
-                .bind(hero_uid)
-                .bind(voice.audio)
-                .execute(&self.pool)
-                .await?;
-        }
-
-        for item_id in [6, 3, 7, 4] {
-            sqlx::query("INSERT INTO hero_item_unlocks (hero_uid, item_id) VALUES (?, ?)")
-                .bind(hero_uid)
-                .bind(item_id)
-                .execute(&self.pool)
-                .await?;
-        }
-
-        sqlx::query(
-            r#"
-            INSERT INTO hero_sp_attrs (
-                hero_uid, revive, heal, absorb, defense_ignore, clutch,
-                final_add_dmg, final_drop_dmg, normal_skill_rate, play_add_rate, play_drop_rate,
-                dizzy_resistances, sleep_resistances, petrified_resistances, frozen_resistances,
-                disarm_resistances, forbid_resistances, seal_resistances, cant_get_exskill_resistances,
-                del_ex_point_resistances, stress_up_resistances, control_resilience,
-                del_ex_point_resilience, stress_up_resilience, charm_resistances,
-                rebound_dmg, extra_dmg, reuse_dmg, big_skill_rate, clutch_dmg
-            ) VALUES (
-                ?1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-                0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0
-            )
-            "#,
-        )
-        .bind(hero_uid)
-        .execute(&self.pool)
-        .await?;
-
-        sqlx::query(
-            "INSERT INTO hero_birthday_info (user_id, hero_id, birthday_count) VALUES (?, ?, ?)",
-        )
-        .bind(self.user_id)
-        .bind(hero_id)
-        .bind(0) // Starting at 0 birthday celebrations
-        .execute(&self.pool)
-        .await?;
-
-        if let Some(destiny_data) = destiny_data {
-            for stone_str in destiny_data.facets_id.split('#') {
-                if let Ok(stone_id) = stone_str.parse::<i32>() {
-                    sqlx::query(
-                        "INSERT INTO hero_destiny_stone_unlocks (hero_uid, stone_id) VALUES (?, ?)",
-                    )
-                    .bind(hero_uid)
-                    .bind(stone_id)
-                    .execute(&self.pool)
-                    .await?;
-                }
-            }
-        }
-
-        let talent_config = game_data
-            .character_talent
-            .iter()
-            .find(|t| t.hero_id == hero_id && t.talent_id == 1);
-
-        if let Some(talent) = talent_config {
-            let talent_scheme = game_data
-                .talent_scheme
-                .iter()
-                .find(|s| s.talent_id == talent.talent_id && s.talent_mould == talent.talent_mould);
-
-            if let Some(scheme) = talent_scheme {
-                let cubes: Vec<(i32, i32, i32, i32)> = scheme
-                    .talen_scheme
-                    .split('#')
-                    .filter_map(|cube_str| {
-                        let parts: Vec<&str> = cube_str.split(',').collect();
-                        if parts.len() == 4 {
-                            let cube_id = parts[0].parse::<i32>().ok()?;
-                            let direction = parts[1].parse::<i32>().ok()?;
-                            let pos_x = parts[2].parse::<i32>().ok()?;
-                            let pos_y = parts[3].parse::<i32>().ok()?;
-                            Some((cube_id, direction, pos_x, pos_y))
-                        } else {
-                            None
-                        }
-                    })
-                    .collect();
-
-                for (cube_id, direction, pos_x, pos_y) in &cubes {
-                    sqlx::query(
-                        "INSERT INTO hero_talent_cubes (hero_uid, cube_id, direction, pos_x, pos_y) VALUES (?, ?, ?, ?, ?)"
-                    )
-                    .bind(hero_uid)
-                    .bind(cube_id)
-                    .bind(direction)
-                    .bind(pos_x)
-                    .bind(pos_y)
-                    .execute(&self.pool)
-                    .await?;
-                }
-
-                tracing::info!(
-                    "Inserted {} talent cubes for hero {} talent 1",
-                    cubes.len(),
-                    hero_id
-                );
-            }
-        }
-
-        // Insert talent templates
-        for template_id in 1..=4 {
-            let result = sqlx::query(
-                "INSERT INTO hero_talent_templates (hero_uid, template_id, name, style) VALUES (?, ?, ?, ?)"
-            )
-            .bind(hero_uid)
-            .bind(template_id)
-            .bind("")
-            .bind(0)
-            .execute(&self.pool)
-            .await?;
-
-            let template_row_id = result.last_insert_rowid();
-
-            // Template #1 gets the same cubes as active (saved preset)
-            if template_id == 1 && talent_config.is_some() {
-                if let Some(talent) = talent_config {
-                    let talent_scheme = game_data.talent_scheme.iter().find(|s| {
-                        s.talent_id == talent.talent_id && s.talent_mould == talent.talent_mould
-                    });
-
-                    if let Some(scheme) = talent_scheme {
-                        let cubes: Vec<(i32, i32, i32, i32)> = scheme
-                            .talen_scheme
-                            .split('#')
-                            .filter_map(|cube_str| {
-                                let parts: Vec<&str> = cube_str.split(',').collect();
-                                if parts.len() == 4 {
-                                    Some((
-                                        parts[0].parse().ok()?,
-                                        parts[1].parse().ok()?,
-                                        parts[2].parse().ok()?,
-                                        parts[3].parse().ok()?,
-                                    ))
-                                } else {
-                                    None
-                                }
-                            })
-                            .collect();
-
-                        for (cube_id, direction, pos_x, pos_y) in &cubes {
-                            sqlx::query(
-                                "INSERT INTO hero_talent_template_cubes (template_row_id, cube_id, direction, pos_x, pos_y) VALUES (?, ?, ?, ?, ?)"
-                            )
-                            .bind(template_row_id)
-                            .bind(cube_id)
-                            .bind(direction)
-                            .bind(pos_x)
-                            .bind(pos_y)
-                            .execute(&self.pool)
-                            .await?;
-                        }
-                    }
-                }
-            }
-        }
-
-        self.player_hero_count(rare, now).await?;
-
-        tracing::info!(
-            "Created hero {} (uid {}) for user {}",
-            hero_id,
-            hero_uid,
-            self.user_id
-        );
+        let mut tx = self.pool.begin().await?;
+        let hero_uid = insert_hero(&mut tx, self.user_id, hero_id).await?;
+        tx.commit().await?;
 
         Ok(hero_uid)
     }
 
     async fn hero_count(&self, rarity: usize, now: i64) -> Result<()> {
-        let rarity_column = match rarity {
-            1 => "hero_rare_nn_count",
-            2 => "hero_rare_n_count",
-            3 => "hero_rare_r_count",
-            4 => "hero_rare_sr_count",
-            5 => "hero_rare_ssr_count",
-            _ => return Ok(()),
-        };
-
-        sqlx::query(&format!(
-            r#"
-            UPDATE player_info
-            SET {} = {} + 1,
-                updated_at = ?
-            WHERE player_id = ?
-            "#,
-            rarity_column, rarity_column
-        ))
-        .bind(now)
-        .bind(self.user_id)
-        .execute(&self.pool)
-        .await?;
+        let mut tx = self.pool.begin().await?;
+        increment_hero_count(&mut tx, self.user_id, rarity, now).await?;
+        tx.commit().await?;
 
         Ok(())
     }
@@ -1321,44 +898,6 @@ impl HeroModel<HeroData> for UserHeroModel {
         Ok(())
     }
 
-    async fn level_up(&self, hero_id: i32, new_level: i32, stats: &CharacterLevel) -> Result<()> {
-        let hero_data = self.get(hero_id).await?;
-
-        sqlx::query(
-            r#"UPDATE heroes
-                   SET level = ?,
-                       base_hp = ?,
-                       base_attack = ?,
-                       base_defense = ?,
-                       base_mdefense = ?,
-                       base_technic = ?,
-                       ex_cri = ?,
-                       ex_recri = ?,
-                       ex_cri_dmg = ?,
-                       ex_cri_def = ?,
-                       ex_add_dmg = ?,
-                       ex_drop_dmg = ?
-                   WHERE uid = ?"#,
-        )
-        .bind(new_level)
-        .bind(stats.hp)
-        .bind(stats.atk)
-        .bind(stats.def)
-        .bind(stats.mdef)
-        .bind(stats.technic)
-        .bind(stats.cri)
-        .bind(stats.recri)
-        .bind(stats.cri_dmg)
-        .bind(stats.cri_def)
-        .bind(stats.add_dmg)
-        .bind(stats.drop_dmg)
-        .bind(hero_data.record.uid)
-        .execute(&self.pool)
-        .await?;
-
-        Ok(())
-    }
-
     async fn rank_up(&self, hero_id: i32, new_rank: i32) -> Result<()> {
         let hero_data = self.get(hero_id).await?;
 
@@ -1372,64 +911,6 @@ impl HeroModel<HeroData> for UserHeroModel {
         Ok(())
     }
 
-    async fn unlock_insight_skin(&self, hero_id: i32, target_rank: i32) -> Result<bool> {
-        if target_rank < 3 {
-            return Ok(false);
-        }
-
-        let game_data = config::configs::get();
-        let insight_skin = game_data
-            .skin
-            .iter()
-            .find(|s| s.character_id == hero_id && s.id % 100 == 2 && s.gain_approach == 1);
-
-        let Some(skin) = insight_skin else {
-            return Ok(false);
-        };
-
-        let has_skin: Option<i32> =
-            sqlx::query_scalar("SELECT 1 FROM hero_all_skins WHERE user_id = ? AND skin_id = ?")
-                .bind(self.user_id)
-                .bind(skin.id)
-                .fetch_optional(&self.pool)
-                .await?;
-
-        if has_skin.is_some() {
-            return Ok(false);
-        }
-
-        let hero_data = self.get(hero_id).await?;
-
-        sqlx::query("INSERT INTO hero_all_skins (user_id, skin_id) VALUES (?, ?)")
-            .bind(self.user_id)
-            .bind(skin.id)
-            .execute(&self.pool)
-            .await?;
-
-        sqlx::query("INSERT INTO hero_skins (hero_uid, skin, expire_sec) VALUES (?, ?, ?)")
-            .bind(hero_data.record.uid)
-            .bind(skin.id)
-            .bind(0)
-            .execute(&self.pool)
-            .await?;
-
-        sqlx::query("UPDATE heroes SET skin = ? WHERE uid = ? AND user_id = ?")
-            .bind(skin.id)
-            .bind(hero_data.record.uid)
-            .bind(self.user_id)
-            .execute(&self.pool)
-            .await?;
-
-        tracing::info!(
-            "User {} unlocked and equipped Insight II skin {} for hero {}",
-            self.user_id,
-            skin.id,
-            hero_id
-        );
-
-        Ok(true)
-    }
-
     async fn read_hero_red_dot(&self, hero_id: i32, red_dot: i32) -> Result<()> {
         let hero_data = self.get(hero_id).await?;
         sqlx::query("UPDATE heroes SET red_dot = ? WHERE uid = ? AND user_id = ?")
@@ -1441,20 +922,6 @@ impl HeroModel<HeroData> for UserHeroModel {
         Ok(())
     }
 
-    async fn upgrade_ex_skill(&self, hero_id: i32, levels: i32) -> Result<()> {
-        let hero_data = self.get(hero_id).await?;
-        let new_level = (hero_data.record.ex_skill_level + levels).min(5);
-
-        sqlx::query("UPDATE heroes SET ex_skill_level = ? WHERE uid = ? AND user_id = ?")
-            .bind(new_level)
-            .bind(hero_data.record.uid)
-            .bind(self.user_id)
-            .execute(&self.pool)
-            .await?;
-
-        Ok(())
-    }
-
     async fn set_favor(&self, hero_id: i32, is_favor: bool) -> Result<()> {
         let hero_data = self.get(hero_id).await?;
 
@@ -1573,19 +1040,6 @@ impl HeroModel<HeroData> for UserHeroModel {
         Ok(())
     }
 
-    async fn update_talent(&self, hero_id: i32, talent_id: i32) -> Result<()> {
-        let hero_data = self.get(hero_id).await?;
-
-        sqlx::query("UPDATE heroes SET talent = ? WHERE uid = ? AND user_id = ?")
-            .bind(talent_id)
-            .bind(hero_data.record.uid)
-            .bind(self.user_id)
-            .execute(&self.pool)
-            .await?;
-
-        Ok(())
-    }
-
     async fn remove_talent_cube(
         &self,
         hero_id: i32,
@@ -1907,71 +1361,6 @@ impl HeroModel<HeroData> for UserHeroModel {
         Ok(has_style.is_some())
     }
 
-    async fn unlock_talent_style(
-        &self,
-        hero_id: i32,
-        style: i32,
-    ) -> Result<(Vec<(u32, i32)>, Vec<(i32, i32)>)> {
-        let hero_data = self.get(hero_id).await?;
-        let game_data = config::configs::get();
-
-        let style_cost = game_data
-            .talent_style_cost
-            .iter()
-            .find(|s| s.hero_id == hero_id && s.style_id == style)
-            .ok_or_else(|| {
-                tracing::error!("Style cost not found for hero {} style {}", hero_id, style);
-                anyhow::anyhow!("Style cost not found")
-            })?;
-
-        let mut cost_items = Vec::new();
-        let mut cost_currencies = Vec::new();
-
-        for cost_part in style_cost.consume.split('|') {
-            let parts: Vec<&str> = cost_part.split('#').collect();
-            if parts.len() >= 3 {
-                match parts[0] {
-                    "1" => {
-                        let item_id: u32 = parts[1].parse()?;
-                        let amount: i32 = parts[2].parse()?;
-                        cost_items.push((item_id, amount));
-                    }
-                    "2" => {
-                        let currency_id: i32 = parts[1].parse()?;
-                        let amount: i32 = parts[2].parse()?;
-                        cost_currencies.push((currency_id, amount));
-                    }
-                    _ => {}
-                }
-            }
-        }
-
-        sqlx::query("INSERT INTO hero_talent_styles (hero_uid, style_id) VALUES (?, ?)")
-            .bind(hero_data.record.uid)
-            .bind(style)
-            .execute(&self.pool)
-            .await?;
-
-        let style_bit = 1 << style;
-        let new_unlock = hero_data.record.talent_style_unlock | style_bit;
-
-        sqlx::query("UPDATE heroes SET talent_style_unlock = ? WHERE uid = ? AND user_id = ?")
-            .bind(new_unlock)
-            .bind(hero_data.record.uid)
-            .bind(self.user_id)
-            .execute(&self.pool)
-            .await?;
-
-        tracing::info!(
-            "User {} unlocked talent style {} for hero {}",
-            self.user_id,
-            style,
-            hero_id
-        );
-
-        Ok((cost_items, cost_currencies))
-    }
-
     async fn apply_talent_style(&self, hero_id: i32, template_id: i32, style: i32) -> Result<()> {
         let hero_data = self.get(hero_id).await?;
 
@@ -2104,3 +1493,463 @@ impl HeroModel<HeroData> for UserHeroModel {
         })
     }
 }
+
+/// Creates a hero with its skills, voices, skins and talent templates on the caller's
+/// transaction. Returns the new hero uid.
+pub async fn insert_hero(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: i64,
+    hero_id: i32,
+) -> Result<i64> {
+    let game_data = config::configs::get();
+    let now = common::time::ServerTime::now_ms();
+
+    let last_hero_uid: Option<i64> =
+        sqlx::query_scalar("SELECT uid FROM heroes ORDER BY uid DESC LIMIT 1")
+            .fetch_optional(&mut **tx)
+            .await?;
+
+    let hero_uid = match last_hero_uid {
+        Some(uid) => uid + 1,
+        None => 20000001,
+    };
+
+    let character = game_data
+        .character
+        .iter()
+        .find(|c| c.id == hero_id && c.id != 3029 && c.id != 9998) //npc
+        .ok_or_else(|| sqlx::Error::RowNotFound)?;
+
+    let hero_skin = character.skin_id;
+    let rare = character.rare as usize;
+
+    let level1_stats = game_data
+        .character_level
+        .iter()
+        .filter(|s| s.hero_id == hero_id)
+        .min_by_key(|s| s.level);
+
+    let (level, hp, atk, def, mdef, technic, cri, recri, cri_dmg, cri_def, add_dmg, drop_dmg) =
+        if let Some(stats) = level1_stats {
+            (
+                stats.level,
+                stats.hp,
+                stats.atk,
+                stats.def,
+                stats.mdef,
+                stats.technic,
+                stats.cri,
+                stats.recri,
+                stats.cri_dmg,
+                stats.cri_def,
+                stats.add_dmg,
+                stats.drop_dmg,
+            )
+        } else {
+            (1, 1000, 100, 100, 100, 100, 0, 0, 1300, 0, 0, 0)
+        };
+
+    let min_ranks = game_data
+        .character_rank
+        .iter()
+        .filter(|s| s.hero_id == hero_id)
+        .min_by_key(|s| s.rank);
+
+    let min_rank = if let Some(min) = min_ranks {
+        min.rank
+    } else {
+        1
+    };
+
+    let default_skin = game_data
+        .skin
+        .iter()
+        .filter(|s| s.character_id != 0)
+        .filter(|s| s.character_id == hero_id)
+        .min_by_key(|s| s.id)
+        .map(|s| s.id)
+        .unwrap_or(hero_skin);
+
+    let destiny_data = game_data
+        .character_destiny
+        .iter()
+        .find(|d| d.hero_id == hero_id);
+
+    let (destiny_rank, destiny_level, destiny_stone, red_dot_type) = if let Some(d) = destiny_data {
+        // Hero has destiny - start at 1
+        let rank = min_rank;
+        let level = 1;
+        let stone = d
+            .facets_id
+            .split('#')
+            .next()
+            .and_then(|s| s.parse::<i32>().ok())
+            .unwrap_or(0);
+        let red_dot_type = 6;
+        (rank, level, stone, red_dot_type)
+    } else {
+        // Hero doesn't have destiny system
+        (0, 0, 0, 0)
+    };
+
+    let equip_id = character
+        .equip_rec
+        .split('#')
+        .next()
+        .and_then(|s| s.parse::<i32>().ok())
+        .unwrap_or(1501);
+
+    let strengthen_stats = game_data
+        .equip_strengthen
+        .iter()
+        .find(|s| s.strength_type == equip_id);
+
+    let (
+        final_hp,
+        final_atk,
+        final_def,
+        final_mdef,
+        final_technic,
+        final_cri,
+        final_recri,
+        final_cri_dmg,
+        final_cri_def,
+        final_add_dmg,
+        final_drop_dmg,
+    ) = if let Some(_) = strengthen_stats {
+        // For new heroes, don't add equipment bonuses
+        (
+            hp,   // No + s.hp
+            atk,  // No + s.atk
+            def,  // No + s.def
+            mdef, // No + s.mdef
+            technic, cri, recri, cri_dmg, cri_def, add_dmg, drop_dmg,
+        )
+    } else {
+        (
+            hp, atk, def, mdef, technic, cri, recri, cri_dmg, cri_def, add_dmg, drop_dmg,
+        )
+    };
+
+    let extra_str = if hero_id == 3123 {
+        "1003#2003"
+    } else if hero_id == 3124 {
+        "2#21,22|3#32,33,31"
+    } else {
+        ""
+    };
+
+    let starting_talent = game_data
+        .character_talent
+        .iter()
+        .filter(|t| t.hero_id == hero_id && t.talent_id == 1)
+        .map(|t| t.talent_id)
+        .next()
+        .unwrap_or(1);
+
+    sqlx::query(
+        r#"
+        INSERT INTO heroes (
+            uid, user_id, hero_id, create_time,
+            level, exp, rank, breakthrough, skin, faith,
+            active_skill_level, ex_skill_level, is_new, talent,
+            default_equip_uid, duplicate_count, use_talent_template_id,
+            talent_style_unlock, talent_style_red, is_favor,
+            destiny_rank, destiny_level, destiny_stone, red_dot, extra_str,
+            base_hp, base_attack, base_defense, base_mdefense, base_technic,
+            base_multi_hp_idx, base_multi_hp_num,
+            ex_cri, ex_recri, ex_cri_dmg, ex_cri_def, ex_add_dmg, ex_drop_dmg
+        ) VALUES (
+            ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10,
+            ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20,
+            ?21, ?22, ?23, ?24, ?25, ?26, ?27, ?28, ?29, ?30,
+            ?31, ?32, ?33, ?34, ?35, ?36, ?37, ?38
+        )
+        "#,
+    )
+    .bind(hero_uid)
+    .bind(user_id)
+    .bind(hero_id)
+    .bind(now)
+    .bind(level) // Level 1
+    .bind(0) // Starting exp
+    .bind(min_rank) // Starting rank
+    .bind(0) // No breakthrough
+    .bind(default_skin)
+    .bind(10400) // Starting faith
+    .bind(1) // Active skill level 1
+    .bind(0) // Ex skill level 0
+    .bind(true) // is_new (true for new heroes)
+    .bind(starting_talent) // talent
+    .bind(0) // default_equip_uid = 0
+    .bind(0) // duplicate_count
+    .bind(1) // use_talent_template_id
+    .bind(1) // talent_style_unlock
+    .bind(0) // talent_style_red
+    .bind(false) // is_favor
+    .bind(destiny_rank) // destiny_rank (0)
+    .bind(destiny_level) // destiny_level (0)
+    .bind(destiny_stone) // destiny_stone
+    .bind(red_dot_type) // red_dot
+    .bind(extra_str) // extra_str
+    // Base attributes (level 1 stats without equipment bonuses)
+    .bind(final_hp)
+    .bind(final_atk)
+    .bind(final_def)
+    .bind(final_mdef)
+    .bind(final_technic)
+    .bind(0) // base_multi_hp_idx
+    .bind(0) // base_multi_hp_num
+    // Ex attributes
+    .bind(final_cri)
+    .bind(final_recri)
+    .bind(final_cri_dmg)
+    .bind(final_cri_def)
+    .bind(final_add_dmg)
+    .bind(final_drop_dmg)
+    .execute(&mut **tx)
+    .await?;
+
+    let max_skill_group = game_data
+        .skill_passive_level
+        .iter()
+        .filter(|s| s.hero_id == hero_id)
+        .map(|s| s.skill_group)
+        .max()
+        .unwrap_or(0);
+
+    for skill_group in 1..=max_skill_group {
+        let min_level = game_data
+            .skill_passive_level
+            .iter()
+            .filter(|s| s.hero_id == hero_id && s.skill_group == skill_group)
+            .map(|s| s.skill_level)
+            .min()
+            .unwrap_or(1);
+
+        sqlx::query(
+            "INSERT INTO hero_passive_skill_levels (hero_uid, skill_index, level) VALUES (?, ?, ?)",
+        )
+        .bind(hero_uid)
+        .bind(skill_group - 1)
+        .bind(min_level)
+        .execute(&mut **tx)
+        .await?;
+    }
+
+    let character_voices: Vec<&config::character_voice::CharacterVoice> = game_data
+        .character_voice
+        .iter()
+        .filter(|v| v.hero_id == hero_id)
+        .filter(|t| t.r#type == 9 || t.r#type == 11)
+        .collect();
+
+    for voice in &character_voices {
+        sqlx::query("INSERT INTO hero_voices (hero_uid, voice_id) VALUES (?, ?)")
+            .bind(hero_uid)
+            .bind(voice.audio)
+            .execute(&mut **tx)
+            .await?;
+    }
+
+    for item_id in [6, 3, 7, 4] {
+        sqlx::query("INSERT INTO hero_item_unlocks (hero_uid, item_id) VALUES (?, ?)")
+            .bind(hero_uid)
+            .bind(item_id)
+            .execute(&mut **tx)
+            .await?;
+    }
+
+    sqlx::query(
+        r#"
+        INSERT INTO hero_sp_attrs (
+            hero_uid, revive, heal, absorb, defense_ignore, clutch,
+            final_add_dmg, final_drop_dmg, normal_skill_rate, play_add_rate, play_drop_rate,
+            dizzy_resistances, sleep_resistances, petrified_resistances, frozen_resistances,
+            disarm_resistances, forbid_resistances, seal_resistances, cant_get_exskill_resistances,
+            del_ex_point_resistances, stress_up_resistances, control_resilience,
+            del_ex_point_resilience, stress_up_resilience, charm_resistances,
+            rebound_dmg, extra_dmg, reuse_dmg, big_skill_rate, clutch_dmg
+        ) VALUES (
+            ?1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0
+        )
+        "#,
+    )
+    .bind(hero_uid)
+    .execute(&mut **tx)
+    .await?;
+
+    sqlx::query(
+        "INSERT INTO hero_birthday_info (user_id, hero_id, birthday_count) VALUES (?, ?, ?)",
+    )
+    .bind(user_id)
+    .bind(hero_id)
+    .bind(0) // Starting at 0 birthday celebrations
+    .execute(&mut **tx)
+    .await?;
+
+    if let Some(destiny_data) = destiny_data {
+        for stone_str in destiny_data.facets_id.split('#') {
+            if let Ok(stone_id) = stone_str.parse::<i32>() {
+                sqlx::query(
+                    "INSERT INTO hero_destiny_stone_unlocks (hero_uid, stone_id) VALUES (?, ?)",
+                )
+                .bind(hero_uid)
+                .bind(stone_id)
+                .execute(&mut **tx)
+                .await?;
+            }
+        }
+    }
+
+    let talent_config = game_data
+        .character_talent
+        .iter()
+        .find(|t| t.hero_id == hero_id && t.talent_id == 1);
+
+    if let Some(talent) = talent_config {
+        let talent_scheme = game_data
+            .talent_scheme
+            .iter()
+            .find(|s| s.talent_id == talent.talent_id && s.talent_mould == talent.talent_mould);
+
+        if let Some(scheme) = talent_scheme {
+            let cubes: Vec<(i32, i32, i32, i32)> = scheme
+                .talen_scheme
+                .split('#')
+                .filter_map(|cube_str| {
+                    let parts: Vec<&str> = cube_str.split(',').collect();
+                    if parts.len() == 4 {
+                        let cube_id = parts[0].parse::<i32>().ok()?;
+                        let direction = parts[1].parse::<i32>().ok()?;
+                        let pos_x = parts[2].parse::<i32>().ok()?;
+                        let pos_y = parts[3].parse::<i32>().ok()?;
+                        Some((cube_id, direction, pos_x, pos_y))
+                    } else {
+                        None
+                    }
+                })
+                .collect();
+
+            for (cube_id, direction, pos_x, pos_y) in &cubes {
+                sqlx::query(
+                    "INSERT INTO hero_talent_cubes (hero_uid, cube_id, direction, pos_x, pos_y) VALUES (?, ?, ?, ?, ?)"
+                )
+                .bind(hero_uid)
+                .bind(cube_id)
+                .bind(direction)
+                .bind(pos_x)
+                .bind(pos_y)
+                .execute(&mut **tx)
+                .await?;
+            }
+
+            tracing::info!(
+                "Inserted {} talent cubes for hero {} talent 1",
+                cubes.len(),
+                hero_id
+            );
+        }
+    }
+
+    // Insert talent templates
+    for template_id in 1..=4 {
+        let result = sqlx::query(
+            "INSERT INTO hero_talent_templates (hero_uid, template_id, name, style) VALUES (?, ?, ?, ?)"
+        )
+        .bind(hero_uid)
+        .bind(template_id)
+        .bind("")
+        .bind(0)
+        .execute(&mut **tx)
+        .await?;
+
+        let template_row_id = result.last_insert_rowid();
+
+        // Template #1 gets the same cubes as active (saved preset)
+        if template_id == 1 && talent_config.is_some() {
+            if let Some(talent) = talent_config {
+                let talent_scheme = game_data.talent_scheme.iter().find(|s| {
+                    s.talent_id == talent.talent_id && s.talent_mould == talent.talent_mould
+                });
+
+                if let Some(scheme) = talent_scheme {
+                    let cubes: Vec<(i32, i32, i32, i32)> = scheme
+                        .talen_scheme
+                        .split('#')
+                        .filter_map(|cube_str| {
+                            let parts: Vec<&str> = cube_str.split(',').collect();
+                            if parts.len() == 4 {
+                                Some((
+                                    parts[0].parse().ok()?,
+                                    parts[1].parse().ok()?,
+                                    parts[2].parse().ok()?,
+                                    parts[3].parse().ok()?,
+                                ))
+                            } else {
+                                None
+                            }
+                        })
+                        .collect();
+
+                    for (cube_id, direction, pos_x, pos_y) in &cubes {
+                        sqlx::query(
+                            "INSERT INTO hero_talent_template_cubes (template_row_id, cube_id, direction, pos_x, pos_y) VALUES (?, ?, ?, ?, ?)"
+                        )
+                        .bind(template_row_id)
+                        .bind(cube_id)
+                        .bind(direction)
+                        .bind(pos_x)
+                        .bind(pos_y)
+                        .execute(&mut **tx)
+                        .await?;
+                    }
+                }
+            }
+        }
+    }
+
+    increment_hero_count(tx, user_id, rare, now).await?;
+
+    tracing::info!(
+        "Created hero {} (uid {}) for user {}",
+        hero_id,
+        hero_uid,
+        user_id
+    );
+
+    Ok(hero_uid)
+}
+
+async fn increment_hero_count(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: i64,
+    rarity: usize,
+    now: i64,
+) -> Result<()> {
+    let rarity_column = match rarity {
+        1 => "hero_rare_nn_count",
+        2 => "hero_rare_n_count",
+        3 => "hero_rare_r_count",
+        4 => "hero_rare_sr_count",
+        5 => "hero_rare_ssr_count",
+        _ => return Ok(()),
+    };
+
+    sqlx::query(&format!(
+        r#"
+        UPDATE player_info
+        SET {} = {} + 1,
+            updated_at = ?
+        WHERE player_id = ?
+        "#,
+        rarity_column, rarity_column
+    ))
+    .bind(now)
+    .bind(user_id)
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(())
+}
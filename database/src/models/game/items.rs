@@ -1,4 +1,3 @@
-use common::time::ServerTime;
 use sonettobuf;
 use sqlx::{FromRow, SqlitePool};
 
@@ -6,8 +5,6 @@ use sqlx::{FromRow, SqlitePool};
 pub trait ItemModel<T> {
     async fn get_all(&self) -> Result<Vec<T>, sqlx::Error>;
     async fn get(&self, item_id: i64) -> Result<Option<T>, sqlx::Error>;
-    async fn create(&self, item_id: i32, amount: i32) -> Result<Vec<i32>, sqlx::Error>;
-    async fn update_quantity(&self, item_id: i32, delta: i32) -> Result<bool, sqlx::Error>;
 }
 
 pub struct UserItemModel {
@@ -105,59 +102,6 @@ impl ItemModel<Item> for UserItemModel {
         .fetch_optional(&self.pool)
         .await
     }
-
-    async fn create(&self, item_id: i32, amount: i32) -> Result<Vec<i32>, sqlx::Error> {
-        sqlx::query(
-            "INSERT INTO items (user_id, item_id, quantity, last_update_time, total_gain_count)
-             VALUES (?, ?, ?, ?, ?)
-             ON CONFLICT(user_id, item_id) DO UPDATE SET
-                 quantity = quantity + excluded.quantity,
-                 last_update_time = excluded.last_update_time,
-                 total_gain_count = total_gain_count + excluded.total_gain_count",
-        )
-        .bind(self.user_id)
-        .bind(item_id)
-        .bind(amount)
-        .bind(ServerTime::now_ms())
-        .bind(amount)
-        .execute(&self.pool)
-        .await?;
-
-        Ok(vec![item_id])
-    }
-
-    async fn update_quantity(&self, item_id: i32, delta: i32) -> Result<bool, sqlx::Error> {
-        let current: Option<i32> =
-            sqlx::query_scalar("SELECT quantity FROM items WHERE user_id = ? AND item_id = ?")
-                .bind(self.user_id)
-                .bind(item_id as i64)
-                .fetch_optional(&self.pool)
-                .await?;
-
-        let current_qty = current.unwrap_or(0);
-
-        if delta < 0 && current_qty < delta.abs() {
-            return Ok(false);
-        }
-
-        let timestamp = ServerTime::now_ms();
-        sqlx::query(
-            "UPDATE items
-                 SET quantity = quantity + ?,
-                     last_use_time = ?,
-                     last_update_time = ?
-                 WHERE user_id = ? AND item_id = ?",
-        )
-        .bind(delta)
-        .bind(timestamp as i64)
-        .bind(timestamp as i64)
-        .bind(self.user_id)
-        .bind(item_id as i64)
-        .execute(&self.pool)
-        .await?;
-
-        Ok(true)
-    }
 }
 
 impl ItemModel<PowerItem> for UserItemModel {
@@ -208,85 +152,6 @@ impl ItemModel<PowerItem> for UserItemModel {
         .fetch_optional(&self.pool)
         .await
     }
-
-    async fn create(&self, item_id: i32, amount: i32) -> Result<Vec<i32>, sqlx::Error> {
-        let mut changed_item_ids = Vec::new();
-        let now = common::time::ServerTime::now_ms();
-        let game_data = config::configs::get();
-
-        let power_item_config = game_data.power_item.iter().find(|p| p.id == item_id);
-        let expire_time = if let Some(config) = power_item_config {
-            match config.expire_type {
-                0 => 0,
-                1 | 2 | 3 => (now / 1000) + (10 * 24 * 60 * 60),
-                _ => 0,
-            }
-        } else {
-            0
-        };
-
-        for _ in 0..amount {
-            sqlx::query(
-                "INSERT INTO power_items (user_id, item_id, quantity, expire_time, created_at)
-                     VALUES (?, ?, 1, ?, ?)",
-            )
-            .bind(self.user_id)
-            .bind(item_id)
-            .bind(expire_time)
-            .bind(now)
-            .execute(&self.pool)
-            .await?;
-        }
-
-        changed_item_ids.push(item_id);
-        tracing::info!(
-            "Added {} power items (id: {}) to user {} with expire_time: {}",
-            amount,
-            item_id,
-            self.user_id,
-            expire_time
-        );
-
-        Ok(changed_item_ids)
-    }
-
-    async fn update_quantity(&self, item_id: i32, delta: i32) -> Result<bool, sqlx::Error> {
-        let current: Option<i64> = sqlx::query_scalar(
-            "SELECT SUM(quantity) FROM power_items WHERE user_id = ? AND item_id = ?",
-        )
-        .bind(self.user_id)
-        .bind(item_id)
-        .fetch_optional(&self.pool)
-        .await?;
-
-        let current_qty = current.unwrap_or(0) as i32;
-
-        if delta < 0 && current_qty < delta.abs() {
-            return Ok(false);
-        }
-
-        if delta < 0 {
-            let to_remove = delta.abs();
-            sqlx::query(
-                "DELETE FROM power_items
-                     WHERE uid IN (
-                         SELECT uid FROM power_items
-                         WHERE user_id = ? AND item_id = ?
-                         ORDER BY created_at ASC
-                         LIMIT ?
-                     )",
-            )
-            .bind(self.user_id)
-            .bind(item_id)
-            .bind(to_remove)
-            .execute(&self.pool)
-            .await?;
-        } else if delta > 0 {
-            ItemModel::<PowerItem>::create(self, item_id, delta).await?;
-        }
-
-        Ok(true)
-    }
 }
 
 impl ItemModel<InsightItem> for UserItemModel {
@@ -311,79 +176,6 @@ impl ItemModel<InsightItem> for UserItemModel {
         .fetch_optional(&self.pool)
         .await
     }
-
-    async fn create(&self, item_id: i32, amount: i32) -> Result<Vec<i32>, sqlx::Error> {
-        let now = common::time::ServerTime::now_ms();
-        let game_data = config::configs::get();
-
-        let insight_config = game_data.insight_item.iter().find(|i| i.id == item_id);
-        let expire_time = if let Some(config) = insight_config {
-            let hours = config.expire_hours as i64;
-            ((now / 1000) + (hours * 60 * 60)) as i32
-        } else {
-            0
-        };
-
-        for _ in 0..amount {
-            sqlx::query(
-                "INSERT INTO insight_items (user_id, item_id, quantity, expire_time)
-                     VALUES (?, ?, 1, ?)",
-            )
-            .bind(self.user_id)
-            .bind(item_id)
-            .bind(expire_time)
-            .execute(&self.pool)
-            .await?;
-        }
-
-        tracing::info!(
-            "Added {} insight items (id: {}) to user {} with expire_time: {}",
-            amount,
-            item_id,
-            self.user_id,
-            expire_time
-        );
-
-        Ok(vec![item_id])
-    }
-
-    async fn update_quantity(&self, item_id: i32, delta: i32) -> Result<bool, sqlx::Error> {
-        let current: Option<i64> = sqlx::query_scalar(
-            "SELECT SUM(quantity) FROM insight_items WHERE user_id = ? AND item_id = ?",
-        )
-        .bind(self.user_id)
-        .bind(item_id)
-        .fetch_optional(&self.pool)
-        .await?;
-
-        let current_qty = current.unwrap_or(0) as i32;
-
-        if delta < 0 && current_qty < delta.abs() {
-            return Ok(false);
-        }
-
-        if delta < 0 {
-            let to_remove = delta.abs();
-            sqlx::query(
-                "DELETE FROM insight_items
-                     WHERE uid IN (
-                         SELECT uid FROM insight_items
-                         WHERE user_id = ? AND item_id = ?
-                         ORDER BY uid ASC
-                         LIMIT ?
-                     )",
-            )
-            .bind(self.user_id)
-            .bind(item_id)
-            .bind(to_remove)
-            .execute(&self.pool)
-            .await?;
-        } else if delta > 0 {
-            ItemModel::<InsightItem>::create(self, item_id, delta).await?;
-        }
-
-        Ok(true)
-    }
 }
 
 impl UserItemModel {
@@ -402,102 +194,4 @@ impl UserItemModel {
     pub async fn get_all_insight_items(&self) -> Result<Vec<InsightItem>, sqlx::Error> {
         ItemModel::<InsightItem>::get_all(self).await
     }
-
-    pub async fn create_items(&self, items: &[(u32, i32)]) -> Result<Vec<i32>, sqlx::Error> {
-        let mut all_changed_ids = Vec::new();
-        for (item_id, amount) in items {
-            let changed = ItemModel::<Item>::create(self, *item_id as i32, *amount).await?;
-            all_changed_ids.extend(changed);
-        }
-        Ok(all_changed_ids)
-    }
-
-    pub async fn create_power_items(&self, items: &[(i32, i32)]) -> Result<Vec<i32>, sqlx::Error> {
-        let mut all_changed_ids = Vec::new();
-        for (item_id, amount) in items {
-            let changed = ItemModel::<PowerItem>::create(self, *item_id, *amount).await?;
-            all_changed_ids.extend(changed);
-        }
-        Ok(all_changed_ids)
-    }
-
-    pub async fn create_insight_items(
-        &self,
-        items: &[(i32, i32)],
-    ) -> Result<Vec<i32>, sqlx::Error> {
-        let mut all_changed_ids = Vec::new();
-        for (item_id, amount) in items {
-            let changed = ItemModel::<InsightItem>::create(self, *item_id, *amount).await?;
-            all_changed_ids.extend(changed);
-        }
-        Ok(all_changed_ids)
-    }
-
-    pub async fn update_item_quantity(
-        &self,
-        item_id: i32,
-        delta: i32,
-    ) -> Result<bool, sqlx::Error> {
-        ItemModel::<Item>::update_quantity(self, item_id, delta).await
-    }
-
-    pub async fn add_item_quantity(&self, item_id: i32, amount: i32) -> Result<bool, sqlx::Error> {
-        self.update_item_quantity(item_id, amount).await
-    }
-
-    pub async fn remove_item_quantity(
-        &self,
-        item_id: u32,
-        amount: i32,
-    ) -> Result<bool, sqlx::Error> {
-        self.update_item_quantity(item_id as i32, -amount).await
-    }
-
-    pub async fn update_power_item_quantity(
-        &self,
-        item_id: i32,
-        delta: i32,
-    ) -> Result<bool, sqlx::Error> {
-        ItemModel::<PowerItem>::update_quantity(self, item_id, delta).await
-    }
-
-    pub async fn add_power_item_quantity(
-        &self,
-        item_id: i32,
-        amount: i32,
-    ) -> Result<bool, sqlx::Error> {
-        self.update_power_item_quantity(item_id, amount).await
-    }
-
-    pub async fn remove_power_item_quantity(
-        &self,
-        item_id: i32,
-        amount: i32,
-    ) -> Result<bool, sqlx::Error> {
-        self.update_power_item_quantity(item_id, -amount).await
-    }
-
-    pub async fn update_insight_item_quantity(
-        &self,
-        item_id: i32,
-        delta: i32,
-    ) -> Result<bool, sqlx::Error> {
-        ItemModel::<InsightItem>::update_quantity(self, item_id, delta).await
-    }
-
-    pub async fn add_insight_item_quantity(
-        &self,
-        item_id: i32,
-        amount: i32,
-    ) -> Result<bool, sqlx::Error> {
-        self.update_insight_item_quantity(item_id, amount).await
-    }
-
-    pub async fn remove_insight_item_quantity(
-        &self,
-        item_id: i32,
-        amount: i32,
-    ) -> Result<bool, sqlx::Error> {
-        self.update_insight_item_quantity(item_id, -amount).await
-    }
 }
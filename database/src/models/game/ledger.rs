@@ -0,0 +1,117 @@
+use sqlx::FromRow;
+use std::fmt;
+use std::str::FromStr;
+
+/// One row of the economy ledger
+#[derive(Debug, Clone, FromRow)]
+pub struct LedgerEntry {
+    pub id: i64,
+    pub user_id: i64,
+    pub resource_type: i32,
+    pub resource_id: i64,
+    pub delta: i32,
+    pub balance_after: i64,
+    pub source: String,
+    pub reference_id: Option<i64>,
+    pub created_at: i64,
+}
+
+/// What caused a ledger entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LedgerSource {
+    Gacha,
+    Store,
+    Dungeon,
+    Gm,
+    Mail,
+    Charge,
+    Task,
+    Achievement,
+    BattlePass,
+    Activity,
+    SignIn,
+    ItemUse,
+    Equip,
+    Hero,
+    Power,
+}
+
+impl LedgerSource {
+    pub const ALL: [LedgerSource; 15] = [
+        Self::Gacha,
+        Self::Store,
+        Self::Dungeon,
+        Self::Gm,
+        Self::Mail,
+        Self::Charge,
+        Self::Task,
+        Self::Achievement,
+        Self::BattlePass,
+        Self::Activity,
+        Self::SignIn,
+        Self::ItemUse,
+        Self::Equip,
+        Self::Hero,
+        Self::Power,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Gacha => "gacha",
+            Self::Store => "store",
+            Self::Dungeon => "dungeon",
+            Self::Gm => "gm",
+            Self::Mail => "mail",
+            Self::Charge => "charge",
+            Self::Task => "task",
+            Self::Achievement => "achievement",
+            Self::BattlePass => "battle_pass",
+            Self::Activity => "activity",
+            Self::SignIn => "sign_in",
+            Self::ItemUse => "item_use",
+            Self::Equip => "equip",
+            Self::Hero => "hero",
+            Self::Power => "power",
+        }
+    }
+}
+
+impl fmt::Display for LedgerSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for LedgerSource {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|source| source.as_str() == s)
+            .ok_or_else(|| format!("unknown ledger source '{}'", s))
+    }
+}
+
+/// Source and reference id written with every change of one action
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LedgerOrigin {
+    pub source: LedgerSource,
+    pub reference_id: Option<i64>,
+}
+
+impl LedgerOrigin {
+    pub fn new(source: LedgerSource) -> Self {
+        Self {
+            source,
+            reference_id: None,
+        }
+    }
+
+    pub fn with_reference(source: LedgerSource, reference_id: i64) -> Self {
+        Self {
+            source,
+            reference_id: Some(reference_id),
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use sonettobuf;
+use sqlx::FromRow;
+
+/// Mail that has not been read or claimed yet
+pub const MAIL_UNREAD: i32 = 0;
+/// Mail that was read, or whose attachment was claimed
+pub const MAIL_READ: i32 = 1;
+
+#[derive(Debug, Clone, FromRow)]
+pub struct UserMail {
+    pub incr_id: i64,
+    pub user_id: i64,
+    pub mail_id: i32,
+    pub params: String,
+    pub attachment: String,
+    pub state: i32,
+    pub create_time: i64,
+    pub sender: String,
+    pub title: String,
+    pub content: String,
+    pub copy: String,
+    pub expire_time: i64,
+    pub sender_type: i32,
+    pub jump_title: String,
+    pub jump: String,
+}
+
+impl From<UserMail> for sonettobuf::Mail {
+    fn from(m: UserMail) -> Self {
+        sonettobuf::Mail {
+            incr_id: Some(m.incr_id as u64),
+            mail_id: Some(m.mail_id as u32),
+            params: Some(m.params),
+            attachment: Some(m.attachment),
+            state: Some(m.state as u32),
+            create_time: Some(m.create_time as u64),
+            sender: Some(m.sender),
+            title: Some(m.title),
+            content: Some(m.content),
+            copy: Some(m.copy),
+            expire_time: Some(m.expire_time as u64),
+            sender_type: Some(m.sender_type),
+            jump_title: Some(m.jump_title),
+            jump: Some(m.jump),
+        }
+    }
+}
+
+/// Contents of a mail about to be sent
+#[derive(Debug, Clone, Default, FromRow)]
+pub struct NewMail {
+    pub mail_id: i32,
+    pub params: String,
+    pub attachment: String,
+    pub sender: String,
+    pub title: String,
+    pub content: String,
+    pub copy: String,
+    /// Unix ms, 0 never expires
+    pub expire_time: i64,
+    pub sender_type: i32,
+    pub jump_title: String,
+    pub jump: String,
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct MailTemplate {
+    pub id: i32,
+    pub sender: String,
+    pub sender_type: i32,
+    pub title: String,
+    pub content: String,
+    pub attachment: String,
+    pub expire_hours: i32,
+    pub jump_title: String,
+    pub jump: String,
+}
+
+impl MailTemplate {
+    /// Fills `{0}`, `{1}`, ... of the title and content with `params`.
+    /// Params are JSON escaped since the texts are localized JSON.
+    pub fn render(&self, params: &[String], now: i64) -> NewMail {
+        let fill = |text: &str| {
+            params
+                .iter()
+                .enumerate()
+                .fold(text.to_string(), |text, (i, param)| {
+                    let escaped = serde_json::Value::from(param.as_str()).to_string();
+                    text.replace(&format!("{{{}}}", i), &escaped[1..escaped.len() - 1])
+                })
+        };
+
+        NewMail {
+            mail_id: self.id,
+            params: params.join("|"),
+            attachment: self.attachment.clone(),
+            sender: self.sender.clone(),
+            title: fill(&self.title),
+            content: fill(&self.content),
+            copy: String::new(),
+            expire_time: if self.expire_hours > 0 {
+                now + self.expire_hours as i64 * 3_600_000
+            } else {
+                0
+            },
+            sender_type: self.sender_type,
+            jump_title: self.jump_title.clone(),
+            jump: self.jump.clone(),
+        }
+    }
+}
@@ -1,5 +1,6 @@
 pub mod achievements;
 pub mod antiques;
+pub mod battle_pass;
 
 pub mod bgm;
 pub mod block_packages;
@@ -15,8 +16,11 @@ pub mod explore;
 pub mod guides;
 pub mod hero_group_snapshots;
 pub mod hero_groups;
+pub mod hero_stats;
 pub mod heros;
 pub mod items;
+pub mod ledger;
+pub mod mails;
 pub mod player_card;
 pub mod player_infos;
 pub mod red_dots;
@@ -24,6 +28,7 @@ pub mod sign_in;
 pub mod simple_property;
 pub mod stories;
 pub mod summon;
+pub mod tasks;
 pub mod tower;
 pub mod user_stats;
 pub mod weekwalk;
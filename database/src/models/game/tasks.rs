@@ -0,0 +1,46 @@
+use sonettobuf;
+use sqlx::FromRow;
+
+#[derive(Debug, Clone, FromRow)]
+pub struct UserTask {
+    pub task_id: i32,
+    pub task_type: i32,
+    pub progress: i32,
+    pub has_finished: bool,
+    pub finish_count: i32,
+    pub expiry_time: i32,
+}
+
+impl From<UserTask> for sonettobuf::Task {
+    fn from(t: UserTask) -> Self {
+        sonettobuf::Task {
+            id: t.task_id,
+            progress: t.progress,
+            has_finished: t.has_finished,
+            finish_count: Some(t.finish_count),
+            r#type: Some(t.task_type),
+            expiry_time: Some(t.expiry_time),
+        }
+    }
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct UserTaskActivity {
+    pub type_id: i32,
+    pub define_id: i32,
+    pub value: i32,
+    pub gain_value: i32,
+    pub expiry_time: i32,
+}
+
+impl From<UserTaskActivity> for sonettobuf::TaskActivityInfo {
+    fn from(a: UserTaskActivity) -> Self {
+        sonettobuf::TaskActivityInfo {
+            type_id: a.type_id,
+            define_id: a.define_id,
+            value: a.value,
+            gain_value: Some(a.gain_value),
+            expiry_time: a.expiry_time,
+        }
+    }
+}
@@ -0,0 +1,91 @@
+//! Economy ledger viewer for tracking down balance bugs and GM abuse.
+//!
+//! Prints the newest ledger entries of the server database that match the filters.
+//!
+//! ```text
+//! sonetto-ledger [--db PATH] [--user ID] [--source S] [--type T] [--id ID] [--ref ID]
+//!                [--since UNIX_MS] [--until UNIX_MS] [--limit N]
+//! ```
+
+use anyhow::{Context, Result, bail};
+use database::{
+    DatabaseSettings,
+    db::game::ledger::{LedgerFilter, query_ledger},
+    models::game::ledger::LedgerSource,
+};
+
+const USAGE: &str = "usage: sonetto-ledger [--db PATH] [--user ID] [--source S] [--type T] \
+                     [--id ID] [--ref ID] [--since UNIX_MS] [--until UNIX_MS] [--limit N]";
+
+fn parse_args() -> Result<(DatabaseSettings, LedgerFilter)> {
+    let mut settings = DatabaseSettings::default();
+    let mut filter = LedgerFilter::default();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .with_context(|| format!("{} needs a value", arg))
+        };
+        match arg.as_str() {
+            "--db" => settings.db_name = value()?,
+            "--user" => filter.user_id = Some(value()?.parse()?),
+            "--source" => {
+                filter.source = Some(
+                    value()?
+                        .parse::<LedgerSource>()
+                        .map_err(anyhow::Error::msg)?,
+                )
+            }
+            "--type" => filter.resource_type = Some(value()?.parse()?),
+            "--id" => filter.resource_id = Some(value()?.parse()?),
+            "--ref" => filter.reference_id = Some(value()?.parse()?),
+            "--since" => filter.since = Some(value()?.parse()?),
+            "--until" => filter.until = Some(value()?.parse()?),
+            "--limit" => filter.limit = value()?.parse()?,
+            _ => bail!("{}", USAGE),
+        }
+    }
+
+    Ok((settings, filter))
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let (settings, filter) = parse_args()?;
+
+    if !std::path::Path::new(&settings.db_name).exists() {
+        bail!("database {} not found", settings.db_name);
+    }
+
+    let pool = database::connect_to(&settings).await?;
+    let entries = query_ledger(&pool, &filter).await?;
+
+    println!(
+        "{:>8} {:>8} {:>4} {:>10} {:>8} {:>10}  {:<12} {:>10}  time",
+        "id", "user", "type", "resource", "delta", "balance", "source", "ref"
+    );
+
+    for e in &entries {
+        let time = chrono::DateTime::from_timestamp_millis(e.created_at)
+            .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_default();
+
+        println!(
+            "{:>8} {:>8} {:>4} {:>10} {:>+8} {:>10}  {:<12} {:>10}  {}",
+            e.id,
+            e.user_id,
+            e.resource_type,
+            e.resource_id,
+            e.delta,
+            e.balance_after,
+            e.source,
+            e.reference_id.map(|r| r.to_string()).unwrap_or_default(),
+            time
+        );
+    }
+
+    eprintln!("{} entries", entries.len());
+
+    Ok(())
+}
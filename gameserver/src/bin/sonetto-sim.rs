@@ -0,0 +1,387 @@
+//! Headless battle simulator for balance testing.
+//!
+//! Builds the team from a JSON description in a throwaway in-memory database, then
+//! runs seeded auto-battles against an episode with the same code the server uses.
+//!
+//! ```text
+//! sonetto-sim <team.json> <episode_id> [--runs N] [--seed S] [--data DIR] [--verbose]
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::{Context, Result, bail};
+use config::configs;
+use database::{
+    db::{
+        game::{equipment::set_equipment_progress, materials},
+        user::account::{TokenInfo, create_user},
+    },
+    models::game::{
+        heros::{HeroData, HeroModel, UserHeroModel},
+        ledger::{LedgerOrigin, LedgerSource},
+    },
+    run_migrations,
+};
+use gameserver::state::{
+    BattleContext, BattleSimulator, create_battle, default_max_ap, generate_auto_opers,
+    generate_initial_deck, reset_uid_counters,
+};
+use rand::{SeedableRng, rngs::StdRng};
+use serde::Deserialize;
+use sonettobuf::{FightGroup, FightStep, effect_type_enum::EffectType};
+use sqlx::{SqlitePool, sqlite::SqlitePoolOptions};
+
+const SIM_USER_ID: i64 = 1;
+
+/// Safety net for fights that never resolve
+const MAX_ROUNDS: i32 = 100;
+
+#[derive(Debug, Deserialize)]
+struct TeamSpec {
+    heroes: Vec<HeroSpec>,
+    #[serde(default)]
+    sub_heroes: Vec<HeroSpec>,
+    #[serde(default)]
+    cloth_id: i32,
+}
+
+#[derive(Debug, Deserialize)]
+struct HeroSpec {
+    hero_id: i32,
+    #[serde(default = "default_level")]
+    level: i32,
+    #[serde(default = "default_rank")]
+    rank: i32,
+    /// Resonance level
+    #[serde(default)]
+    talent: i32,
+    #[serde(default)]
+    equip_id: Option<i32>,
+    #[serde(default = "default_level")]
+    equip_level: i32,
+    #[serde(default)]
+    equip_break: i32,
+    #[serde(default = "default_rank")]
+    equip_refine: i32,
+}
+
+fn default_level() -> i32 {
+    1
+}
+
+fn default_rank() -> i32 {
+    1
+}
+
+struct Args {
+    team_path: String,
+    episode_id: i32,
+    runs: u32,
+    seed: u64,
+    data_dir: String,
+    verbose: bool,
+}
+
+fn parse_args() -> Result<Args> {
+    let mut positional = Vec::new();
+    let mut runs = 100;
+    let mut seed = 1;
+    let mut data_dir = "data/excel2json".to_string();
+    let mut verbose = false;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--runs" => runs = args.next().context("--runs needs a value")?.parse()?,
+            "--seed" => seed = args.next().context("--seed needs a value")?.parse()?,
+            "--data" => data_dir = args.next().context("--data needs a value")?,
+            "--verbose" => verbose = true,
+            _ => positional.push(arg),
+        }
+    }
+
+    let [team_path, episode_id] = positional.as_slice() else {
+        bail!(
+            "usage: sonetto-sim <team.json> <episode_id> [--runs N] [--seed S] [--data DIR] [--verbose]"
+        );
+    };
+
+    Ok(Args {
+        team_path: team_path.clone(),
+        episode_id: episode_id.parse().context("episode_id must be a number")?,
+        runs,
+        seed,
+        data_dir,
+        verbose,
+    })
+}
+
+#[derive(Default)]
+struct Report {
+    wins: u32,
+    rounds: Vec<i32>,
+    damage: BTreeMap<i32, i64>,
+    unhandled: BTreeMap<String, u32>,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = parse_args()?;
+
+    if args.verbose {
+        common::init_tracing();
+    }
+
+    configs::init(&args.data_dir)?;
+
+    let team: TeamSpec = serde_json::from_str(
+        &std::fs::read_to_string(&args.team_path)
+            .with_context(|| format!("reading {}", args.team_path))?,
+    )?;
+
+    let episode = configs::get()
+        .episode
+        .iter()
+        .find(|e| e.id == args.episode_id)
+        .with_context(|| format!("episode {} not found", args.episode_id))?;
+
+    if episode.battle_id == 0 {
+        bail!("episode {} has no battle", args.episode_id);
+    }
+
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await?;
+    run_migrations(&pool).await?;
+
+    let fight_group = build_team(&pool, &team).await?;
+    let hero_count = fight_group.hero_list.len();
+
+    let mut report = Report::default();
+
+    for run in 0..args.runs {
+        let seed = args.seed + run as u64;
+        reset_uid_counters();
+
+        let battle_ctx = BattleContext {
+            player_id: SIM_USER_ID,
+            chapter_id: episode.chapter_id,
+            episode_id: args.episode_id,
+            battle_id: episode.battle_id,
+            max_ap: default_max_ap(args.episode_id, hero_count),
+        };
+
+        let max_ap = battle_ctx.max_ap;
+        let mut deck = generate_initial_deck(
+            &pool,
+            SIM_USER_ID,
+            &fight_group,
+            max_ap,
+            &mut StdRng::seed_from_u64(seed),
+        )
+        .await?
+        .card_group;
+
+        let (fight, _, fight_data_mgr, mut ai_deck) =
+            create_battle(&pool, battle_ctx, &fight_group, deck.clone()).await?;
+
+        let hero_ids: HashMap<i64, i32> = fight
+            .attacker
+            .iter()
+            .flat_map(|t| t.entitys.iter().chain(t.sub_entitys.iter()))
+            .filter_map(|e| Some((e.uid?, e.model_id?)))
+            .collect();
+
+        let mut simulator = BattleSimulator::with_seed(fight_data_mgr, seed);
+        let mut rounds = 0;
+
+        loop {
+            let opers = generate_auto_opers(&deck);
+            let round = simulator.process_round(opers, deck, ai_deck).await?;
+            rounds += 1;
+
+            for (key, count) in simulator.take_unhandled() {
+                *report.unhandled.entry(key).or_default() += count;
+            }
+
+            for step in &round.fight_step {
+                tally_damage(step, 0, &hero_ids, &mut report.damage);
+            }
+
+            deck = round.team_a_cards1;
+            ai_deck = round.ai_use_cards;
+
+            if round.is_finish.unwrap_or(false) || rounds >= MAX_ROUNDS {
+                break;
+            }
+        }
+
+        if simulator.data().defender_defeated() {
+            report.wins += 1;
+        }
+        report.rounds.push(rounds);
+    }
+
+    print_report(&args, &report);
+
+    Ok(())
+}
+
+async fn build_team(pool: &SqlitePool, team: &TeamSpec) -> Result<FightGroup> {
+    let now = common::time::ServerTime::now_ms();
+    let token = TokenInfo {
+        token: String::new(),
+        refresh_token: String::new(),
+        expires_at: 0,
+    };
+    create_user(pool, SIM_USER_ID, "sim@localhost", "sim", &token, now).await?;
+
+    let model = UserHeroModel::new(SIM_USER_ID, pool.clone());
+
+    let mut hero_list = Vec::new();
+    for spec in &team.heroes {
+        hero_list.push(add_hero(pool, &model, spec).await?);
+    }
+
+    let mut sub_hero_list = Vec::new();
+    for spec in &team.sub_heroes {
+        sub_hero_list.push(add_hero(pool, &model, spec).await?);
+    }
+
+    Ok(FightGroup {
+        hero_list,
+        sub_hero_list,
+        cloth_id: Some(team.cloth_id),
+        ..Default::default()
+    })
+}
+
+async fn add_hero(pool: &SqlitePool, model: &UserHeroModel, spec: &HeroSpec) -> Result<i64> {
+    let uid = model.create_hero(spec.hero_id).await?;
+
+    if spec.rank > 1 {
+        HeroModel::<HeroData>::rank_up(model, spec.hero_id, spec.rank).await?;
+    }
+
+    let talent = HeroModel::<HeroData>::get(model, spec.hero_id)
+        .await?
+        .record
+        .talent;
+    let mut tx = pool.begin().await?;
+
+    if let Some(stats) = configs::get()
+        .character_level
+        .iter()
+        .filter(|l| l.hero_id == spec.hero_id && l.level <= spec.level)
+        .max_by_key(|l| l.level)
+    {
+        materials::set_hero_level(&mut tx, SIM_USER_ID, spec.hero_id, spec.level, stats).await?;
+    }
+
+    if spec.talent > 0 {
+        materials::set_hero_talent(&mut tx, SIM_USER_ID, spec.hero_id, talent, spec.talent).await?;
+    }
+
+    let equip_uid = match spec.equip_id {
+        Some(equip_id) => {
+            let origin = LedgerOrigin::new(LedgerSource::Gm);
+            let uids = materials::give_equipment(&mut tx, SIM_USER_ID, equip_id, 1, origin).await?;
+            Some(
+                *uids
+                    .first()
+                    .with_context(|| format!("psychube {} was not created", equip_id))?,
+            )
+        }
+        None => None,
+    };
+
+    tx.commit().await?;
+
+    if let Some(equip_uid) = equip_uid {
+        set_equipment_progress(
+            pool,
+            SIM_USER_ID,
+            equip_uid,
+            spec.equip_level,
+            spec.equip_break,
+            spec.equip_refine,
+        )
+        .await?;
+
+        model.update_equipped_gear(spec.hero_id, equip_uid).await?;
+    }
+
+    Ok(uid)
+}
+
+/// Sums damage dealt to enemies per hero. Effects nested in containers without a
+/// caster are credited to the enclosing step's caster.
+fn tally_damage(
+    step: &FightStep,
+    parent_from: i64,
+    hero_ids: &HashMap<i64, i32>,
+    damage: &mut BTreeMap<i32, i64>,
+) {
+    let from_id = step.from_id.filter(|&id| id != 0).unwrap_or(parent_from);
+
+    for effect in &step.act_effect {
+        if let Some(nested) = &effect.fight_step {
+            tally_damage(nested, from_id, hero_ids, damage);
+            continue;
+        }
+
+        let is_damage = matches!(
+            effect.effect_type,
+            Some(t) if t == EffectType::Damage as i32 || t == EffectType::Crit as i32
+        );
+
+        if is_damage
+            && effect.target_id.unwrap_or(0) < 0
+            && let Some(&hero_id) = hero_ids.get(&from_id)
+        {
+            *damage.entry(hero_id).or_default() += effect.effect_num.unwrap_or(0) as i64;
+        }
+    }
+}
+
+fn print_report(args: &Args, report: &Report) {
+    let runs = report.rounds.len().max(1);
+    let avg_rounds = report.rounds.iter().sum::<i32>() as f64 / runs as f64;
+
+    println!(
+        "episode {} - {} runs (seed {})",
+        args.episode_id, args.runs, args.seed
+    );
+    println!(
+        "win rate: {:.1}% ({}/{})",
+        report.wins as f64 * 100.0 / runs as f64,
+        report.wins,
+        args.runs
+    );
+    println!(
+        "rounds: avg {:.1}, min {}, max {}",
+        avg_rounds,
+        report.rounds.iter().min().unwrap_or(&0),
+        report.rounds.iter().max().unwrap_or(&0)
+    );
+
+    println!("damage per hero (avg per run):");
+    for (hero_id, total) in &report.damage {
+        let name = configs::get()
+            .character
+            .get(*hero_id)
+            .map(|c| c.name.as_str())
+            .unwrap_or("?");
+        println!("  {:>6} {:<16} {}", hero_id, name, total / runs as i64);
+    }
+
+    if report.unhandled.is_empty() {
+        println!("unimplemented behaviors: none");
+    } else {
+        println!("unimplemented behaviors:");
+        for (key, count) in &report.unhandled {
+            println!("  {} x{}", key, count);
+        }
+    }
+}
@@ -1,8 +1,11 @@
 use crate::error::AppError;
 use crate::network::packet::ClientPacket;
-use crate::state::ConnectionContext;
+use crate::state::{ConnectionContext, refresh_achievement_summary};
 use database::db::game::achievements;
-use sonettobuf::{CmdId, GetAchievementInfoReply};
+use prost::Message;
+use sonettobuf::{
+    CmdId, GetAchievementInfoReply, ReadNewAchievementReply, ReadNewAchievementRequest,
+};
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
@@ -27,3 +30,32 @@ pub async fn on_get_achievement_info(
 
     Ok(())
 }
+
+pub async fn on_read_new_achievement(
+    ctx: Arc<Mutex<ConnectionContext>>,
+    req: ClientPacket,
+) -> Result<(), AppError> {
+    let request = ReadNewAchievementRequest::decode(&req.data[..])?;
+    tracing::info!("Received ReadNewAchievementRequest: {:?}", request);
+
+    let player_id = {
+        let conn = ctx.lock().await;
+        let player_id = conn.player_id.ok_or(AppError::NotLoggedIn)?;
+
+        for id in &request.ids {
+            achievements::clear_new_flag(&conn.state.db, player_id, *id).await?;
+        }
+
+        player_id
+    };
+
+    refresh_achievement_summary(ctx.clone(), player_id).await?;
+
+    let reply = ReadNewAchievementReply { ids: request.ids };
+
+    let mut conn = ctx.lock().await;
+    conn.send_reply(CmdId::ReadNewAchievementCmd, reply, 0, req.up_tag)
+        .await?;
+
+    Ok(())
+}
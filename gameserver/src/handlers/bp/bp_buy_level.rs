@@ -0,0 +1,37 @@
+use crate::error::AppError;
+use crate::network::packet::ClientPacket;
+use crate::state::{ConnectionContext, active_battle_pass, buy_bp_levels};
+use prost::Message;
+use sonettobuf::{BpBuyLevelReply, BpBuyLevelRequset, CmdId};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+pub async fn on_bp_buy_level(
+    ctx: Arc<Mutex<ConnectionContext>>,
+    req: ClientPacket,
+) -> Result<(), AppError> {
+    let request = BpBuyLevelRequset::decode(&req.data[..])?;
+    tracing::info!("Received BpBuyLevelRequset: {:?}", request);
+
+    let num = request.num.ok_or(AppError::InvalidRequest)?;
+
+    let (player_id, pool) = {
+        let conn = ctx.lock().await;
+        let player_id = conn.player_id.ok_or(AppError::NotLoggedIn)?;
+        (player_id, conn.state.db.clone())
+    };
+
+    let (_, bp, pass) = active_battle_pass(&pool, player_id, request.id).await?;
+    let score = buy_bp_levels(ctx.clone(), player_id, bp, &pass, num).await?;
+
+    let reply = BpBuyLevelReply {
+        id: Some(bp.bp_id),
+        score: Some(score),
+    };
+
+    let mut conn = ctx.lock().await;
+    conn.send_reply(CmdId::BpBuyLevelRequsetCmd, reply, 0, req.up_tag)
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,36 @@
+use crate::error::AppError;
+use crate::network::packet::ClientPacket;
+use crate::state::{ConnectionContext, active_battle_pass};
+use database::db::game::battle_pass;
+use prost::Message;
+use sonettobuf::{BpMarkFirstShowReply, BpMarkFirstShowRequest, CmdId};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+pub async fn on_bp_mark_first_show(
+    ctx: Arc<Mutex<ConnectionContext>>,
+    req: ClientPacket,
+) -> Result<(), AppError> {
+    let request = BpMarkFirstShowRequest::decode(&req.data[..])?;
+    tracing::info!("Received BpMarkFirstShowRequest: {:?}", request);
+
+    {
+        let conn = ctx.lock().await;
+        let player_id = conn.player_id.ok_or(AppError::NotLoggedIn)?;
+        let pool = &conn.state.db;
+
+        active_battle_pass(pool, player_id, request.id).await?;
+        battle_pass::mark_first_show(pool, player_id, request.is_sp.unwrap_or(false)).await?;
+    }
+
+    let reply = BpMarkFirstShowReply {
+        id: request.id,
+        is_sp: request.is_sp,
+    };
+
+    let mut conn = ctx.lock().await;
+    conn.send_reply(CmdId::BpMarkFirstShowCmd, reply, 0, req.up_tag)
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,130 @@
+use crate::error::AppError;
+use crate::network::packet::ClientPacket;
+use crate::state::{ConnectionContext, active_battle_pass, bp_level, parse_reward_string};
+use crate::util::transaction::Transaction;
+use config::configs;
+use database::db::game::battle_pass;
+use database::models::game::battle_pass::UserBpBonus;
+use database::models::game::ledger::LedgerSource;
+use prost::Message;
+use sonettobuf::{CmdId, GetBpBonusReply, GetBpBonusRequest};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+pub async fn on_get_bp_bonus(
+    ctx: Arc<Mutex<ConnectionContext>>,
+    req: ClientPacket,
+) -> Result<(), AppError> {
+    let request = GetBpBonusRequest::decode(&req.data[..])?;
+    tracing::info!("Received GetBpBonusRequest: {:?}", request);
+
+    // Level 0 claims every reached level on every unlocked track
+    let level = request.level.unwrap_or(0);
+    let pay_bonus = request.pay_bonus.unwrap_or(false);
+    let is_sp = request.is_sp.unwrap_or(false);
+
+    let (player_id, pool) = {
+        let conn = ctx.lock().await;
+        let player_id = conn.player_id.ok_or(AppError::NotLoggedIn)?;
+        (player_id, conn.state.db.clone())
+    };
+
+    let (_, bp, pass) = active_battle_pass(&pool, player_id, request.id).await?;
+    let reached = bp_level(bp, pass.score);
+    let paid = pass.pay_status > 0;
+
+    if level > reached || (level != 0 && pay_bonus && !paid) {
+        return Err(AppError::InvalidRequest);
+    }
+
+    let mut claimed = battle_pass::get_bonuses(&pool, player_id).await?;
+    let mut changed = Vec::new();
+    let mut rewards = Vec::new();
+
+    let mut rows: Vec<_> = configs::get()
+        .bp_lv_bonus
+        .iter()
+        .filter(|l| l.bp_id == bp.bp_id && l.level <= reached && (level == 0 || l.level == level))
+        .collect();
+    rows.sort_by_key(|l| l.level);
+
+    for row in rows {
+        let mut bonus = match claimed.iter().position(|b| b.level == row.level) {
+            Some(i) => claimed.swap_remove(i),
+            None => UserBpBonus {
+                level: row.level,
+                ..Default::default()
+            },
+        };
+
+        let (free_flag, free_rewards, pay_flag, pay_rewards) = if is_sp {
+            (
+                &mut bonus.sp_free_bonus,
+                &row.sp_free_bonus,
+                &mut bonus.sp_pay_bonus,
+                &row.sp_pay_bonus,
+            )
+        } else {
+            (
+                &mut bonus.free_bonus,
+                &row.free_bonus,
+                &mut bonus.pay_bonus,
+                &row.pay_bonus,
+            )
+        };
+
+        let mut changed_level = false;
+
+        if (level == 0 || !pay_bonus) && !*free_flag {
+            *free_flag = true;
+            rewards.extend(parse_reward_string(free_rewards, 1));
+            changed_level = true;
+        }
+        if (level == 0 || pay_bonus) && paid && !*pay_flag {
+            *pay_flag = true;
+            rewards.extend(parse_reward_string(pay_rewards, 1));
+            changed_level = true;
+        }
+
+        if changed_level {
+            changed.push(bonus);
+        }
+    }
+
+    if level != 0 && changed.is_empty() {
+        tracing::warn!(
+            "User {} already claimed battle pass level {} (pay: {}, sp: {})",
+            player_id,
+            level,
+            pay_bonus,
+            is_sp
+        );
+        return Err(AppError::InvalidRequest);
+    }
+
+    let mut applied = Transaction::new(player_id, LedgerSource::BattlePass)
+        .reference(bp.bp_id as i64)
+        .grants(&rewards)
+        .apply(ctx.clone())
+        .await?;
+    battle_pass::save_bonuses(&mut applied.db, player_id, &changed).await?;
+    applied.commit(ctx.clone()).await?;
+
+    tracing::info!(
+        "User {} claimed {} battle pass levels for {} rewards",
+        player_id,
+        changed.len(),
+        rewards.len()
+    );
+
+    let reply = GetBpBonusReply {
+        id: Some(bp.bp_id),
+        score_bonus_info: changed.iter().map(Into::into).collect(),
+    };
+
+    let mut conn = ctx.lock().await;
+    conn.send_reply(CmdId::GetBpBonusCmd, reply, 0, req.up_tag)
+        .await?;
+
+    Ok(())
+}
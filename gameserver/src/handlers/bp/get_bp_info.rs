@@ -1,7 +1,11 @@
 use crate::error::AppError;
 use crate::network::packet::ClientPacket;
-use crate::state::ConnectionContext;
-use sonettobuf::{CmdId, GetBpInfoReply};
+use crate::state::{
+    ConnectionContext, TASK_TYPE_BP, active_battle_pass, current_season, sync_tasks,
+};
+use common::time::ServerTime;
+use database::db::game::{battle_pass, tasks};
+use sonettobuf::{BpSelfSelectBonus, CmdId, GetBpInfoReply};
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
@@ -9,9 +13,47 @@ pub async fn on_get_bp_info(
     ctx: Arc<Mutex<ConnectionContext>>,
     req: ClientPacket,
 ) -> Result<(), AppError> {
-    let resp = GetBpInfoReply {
-        end_time: Some(0),
-        ..Default::default()
+    let (player_id, pool) = {
+        let conn = ctx.lock().await;
+        let player_id = conn.player_id.ok_or(AppError::NotLoggedIn)?;
+        (player_id, conn.state.db.clone())
+    };
+
+    // An end time of 0 tells the client no pass is running
+    let resp = if current_season(ServerTime::now_ms()).is_none() {
+        GetBpInfoReply {
+            end_time: Some(0),
+            ..Default::default()
+        }
+    } else {
+        let (season, _, pass) = active_battle_pass(&pool, player_id, None).await?;
+
+        // Picks up the tasks of a season that started since login
+        sync_tasks(&pool, player_id).await?;
+        let task_info = tasks::get_tasks(&pool, player_id, &[TASK_TYPE_BP]).await?;
+        let bonuses = battle_pass::get_bonuses(&pool, player_id).await?;
+
+        GetBpInfoReply {
+            id: Some(pass.bp_id),
+            score: Some(pass.score),
+            pay_status: Some(pass.pay_status),
+            start_time: Some(season.start_time as i32),
+            end_time: Some(season.end_time as i32),
+            task_info: task_info.into_iter().map(Into::into).collect(),
+            score_bonus_info: bonuses.iter().map(Into::into).collect(),
+            weekly_score: Some(pass.weekly_score),
+            first_show: Some(pass.first_show),
+            has_get_self_select_bonus: bonuses
+                .iter()
+                .filter_map(|b| {
+                    b.select_index.map(|index| BpSelfSelectBonus {
+                        level: Some(b.level),
+                        index: Some(index),
+                    })
+                })
+                .collect(),
+            sp_first_show: Some(pass.sp_first_show),
+        }
     };
 
     let mut conn = ctx.lock().await;
@@ -0,0 +1,85 @@
+use crate::error::AppError;
+use crate::network::packet::ClientPacket;
+use crate::state::{ConnectionContext, active_battle_pass, bp_level, parse_reward_string};
+use crate::util::transaction::Transaction;
+use config::configs;
+use database::db::game::battle_pass;
+use database::models::game::ledger::LedgerSource;
+use prost::Message;
+use sonettobuf::{CmdId, GetSelfSelectBonusReply, GetSelfSelectBonusRequest};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+pub async fn on_get_self_select_bonus(
+    ctx: Arc<Mutex<ConnectionContext>>,
+    req: ClientPacket,
+) -> Result<(), AppError> {
+    let request = GetSelfSelectBonusRequest::decode(&req.data[..])?;
+    tracing::info!("Received GetSelfSelectBonusRequest: {:?}", request);
+
+    let level = request.level.ok_or(AppError::InvalidRequest)?;
+    let index = request.index.ok_or(AppError::InvalidRequest)?;
+
+    let (player_id, pool) = {
+        let conn = ctx.lock().await;
+        let player_id = conn.player_id.ok_or(AppError::NotLoggedIn)?;
+        (player_id, conn.state.db.clone())
+    };
+
+    let (_, bp, pass) = active_battle_pass(&pool, player_id, request.id).await?;
+
+    if pass.pay_status == 0 || level > bp_level(bp, pass.score) {
+        return Err(AppError::InvalidRequest);
+    }
+
+    // selfSelectPayBonus lists the choices as `|` separated rewards
+    let choice = configs::get()
+        .bp_lv_bonus
+        .iter()
+        .find(|l| l.bp_id == bp.bp_id && l.level == level)
+        .and_then(|l| {
+            l.self_select_pay_bonus
+                .split('|')
+                .filter(|c| !c.trim().is_empty())
+                .nth(index as usize)
+        })
+        .ok_or(AppError::InvalidRequest)?;
+
+    let rewards = parse_reward_string(choice, 1);
+    let mut applied = Transaction::new(player_id, LedgerSource::BattlePass)
+        .reference(bp.bp_id as i64)
+        .grants(&rewards)
+        .apply(ctx.clone())
+        .await?;
+
+    if !battle_pass::select_bonus(&mut applied.db, player_id, level, index).await? {
+        tracing::warn!(
+            "User {} already picked the battle pass level {} reward",
+            player_id,
+            level
+        );
+        return Err(AppError::InvalidRequest);
+    }
+
+    applied.commit(ctx.clone()).await?;
+
+    tracing::info!(
+        "User {} picked reward {} ({}) of battle pass level {}",
+        player_id,
+        index,
+        choice,
+        level
+    );
+
+    let reply = GetSelfSelectBonusReply {
+        id: Some(bp.bp_id),
+        level: Some(level),
+        index: Some(index),
+    };
+
+    let mut conn = ctx.lock().await;
+    conn.send_reply(CmdId::GetSelfSelectBonusCmd, reply, 0, req.up_tag)
+        .await?;
+
+    Ok(())
+}
@@ -1,3 +1,11 @@
+mod bp_buy_level;
+mod bp_mark_first_show;
+mod get_bp_bonus;
 mod get_bp_info;
+mod get_self_select_bonus;
 
+pub use bp_buy_level::on_bp_buy_level;
+pub use bp_mark_first_show::on_bp_mark_first_show;
+pub use get_bp_bonus::on_get_bp_bonus;
 pub use get_bp_info::on_get_bp_info;
+pub use get_self_select_bonus::on_get_self_select_bonus;
@@ -0,0 +1,23 @@
+use crate::error::AppError;
+use crate::network::packet::ClientPacket;
+use crate::state::{ConnectionContext, buy_power};
+use sonettobuf::{BuyPowerReply, CmdId};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+pub async fn on_buy_power(
+    ctx: Arc<Mutex<ConnectionContext>>,
+    req: ClientPacket,
+) -> Result<(), AppError> {
+    let can_buy_count = buy_power(ctx.clone()).await?;
+
+    let reply = BuyPowerReply {
+        can_buy_count: Some(can_buy_count),
+    };
+
+    let mut conn = ctx.lock().await;
+    conn.send_reply(CmdId::BuyPowerCmd, reply, 0, req.up_tag)
+        .await?;
+
+    Ok(())
+}
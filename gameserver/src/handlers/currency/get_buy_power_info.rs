@@ -1,6 +1,6 @@
 use crate::error::AppError;
 use crate::network::packet::ClientPacket;
-use crate::state::ConnectionContext;
+use crate::state::{ConnectionContext, buy_power_left};
 use sonettobuf::{CmdId, GetBuyPowerInfoReply};
 use std::sync::Arc;
 use tokio::sync::Mutex;
@@ -9,11 +9,12 @@ pub async fn on_get_buy_power_info(
     ctx: Arc<Mutex<ConnectionContext>>,
     req: ClientPacket,
 ) -> Result<(), AppError> {
+    let mut conn = ctx.lock().await;
+
     let data = GetBuyPowerInfoReply {
-        can_buy_count: Some(8),
+        can_buy_count: Some(buy_power_left(&conn).await?),
     };
 
-    let mut conn = ctx.lock().await;
     conn.send_reply(CmdId::GetBuyPowerInfoCmd, data, 0, req.up_tag)
         .await?;
     Ok(())
@@ -1,6 +1,6 @@
 use crate::error::AppError;
 use crate::network::packet::ClientPacket;
-use crate::state::ConnectionContext;
+use crate::state::{ConnectionContext, POWER_CURRENCY_ID, refresh_power};
 use database::db::game::currencies;
 use prost::Message;
 use sonettobuf::{CmdId, GetCurrencyListReply, GetCurrencyListRequest};
@@ -16,9 +16,13 @@ pub async fn on_get_currency_list(
     tracing::info!("Requested currency_ids: {:?}", req_msg.currency_ids);
 
     let currency_list = {
-        let conn = ctx.lock().await;
+        let mut conn = ctx.lock().await;
         let player_id = conn.player_id.ok_or(AppError::NotLoggedIn)?;
 
+        if req_msg.currency_ids.contains(&POWER_CURRENCY_ID) {
+            refresh_power(&mut conn).await?;
+        }
+
         currencies::get_currencies(&conn.state.db, player_id, &req_msg.currency_ids).await?
     };
 
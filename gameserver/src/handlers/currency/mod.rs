@@ -1,5 +1,7 @@
+mod buy_power;
 mod get_buy_power_info;
 mod get_currency_list;
 
+pub use buy_power::on_buy_power;
 pub use get_buy_power_info::on_get_buy_power_info;
 pub use get_currency_list::on_get_currency_list;
@@ -1,20 +1,26 @@
 use crate::error::AppError;
 use crate::network::packet::ClientPacket;
 use crate::util::push::{send_dungeon_update_push, send_end_dungeon_push, send_red_dot_push};
+use crate::util::transaction::Transaction;
+use database::models::game::ledger::LedgerSource;
 
 use crate::send_push;
 use crate::state::{
-    BattleSimulator, ConnectionContext, generate_auto_opers, generate_dungeon_rewards,
-    send_end_fight_push,
+    BattleSimulator, ConnectionContext, DungeonClear, GameEvent, evaluate_stars,
+    generate_auto_opers, generate_dungeon_rewards, persist_active_battle, round_seed,
+    send_end_fight_push, verify_active_battle,
 };
 use database::db::game::dungeons::{
-    get_user_dungeon, should_update_dungeon_record, update_dungeon_progress,
+    add_chapter_type_pass, get_dungeon_star, get_user_dungeon, should_update_dungeon_record,
+    update_dungeon_progress,
 };
 use database::db::game::{
     battle::save_round_operations, dungeons::save_dungeon_record, equipment::build_equip_records,
 };
 use prost::Message;
-use sonettobuf::{AutoRoundReply, AutoRoundRequest, CmdId, InstructionDungeonInfoPush};
+use sonettobuf::{
+    AutoRoundReply, AutoRoundRequest, CmdId, FightWavePush, InstructionDungeonInfoPush,
+};
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
@@ -42,6 +48,7 @@ pub async fn on_auto_round(
         multiplication,
         ai_deck,
         fight_data_mgr,
+        cloth_skill_opers,
     ) = {
         let conn = ctx.lock().await;
         let battle = conn
@@ -60,6 +67,7 @@ pub async fn on_auto_round(
             battle.multiplication.unwrap_or(1),
             battle.ai_deck.clone(),
             battle.fight_data_mgr.clone().unwrap_or_default(),
+            battle.cloth_skill_opers.clone(),
         )
     };
 
@@ -75,12 +83,24 @@ pub async fn on_auto_round(
 
     tracing::info!("AutoRound server selected {} ops", auto_opers.len());
 
-    let mut simulator = BattleSimulator::new(fight_data_mgr);
-    let mut round = simulator
+    let wave_before = fight_data_mgr.fight().cur_wave;
+
+    let mut simulator =
+        BattleSimulator::with_seed(fight_data_mgr, round_seed(battle_id, round_num));
+    let round = simulator
         .process_round(auto_opers.clone(), current_deck, ai_deck)
         .await?;
 
-    round.is_finish = Some(true);
+    let unhandled = simulator.take_unhandled();
+    if !unhandled.is_empty() {
+        tracing::warn!(
+            "Fight {} round {} hit unhandled skill data: {:?}",
+            battle_id,
+            round_num,
+            unhandled
+        );
+    }
+
     let record_round = round.cur_round.unwrap_or(1);
 
     tracing::info!(
@@ -98,6 +118,14 @@ pub async fn on_auto_round(
 
     {
         let mut conn = ctx.lock().await;
+
+        if simulator.data().fight().cur_wave != wave_before {
+            let push = FightWavePush {
+                fight: Some(simulator.data().get_fight_owned()),
+            };
+            conn.notify(CmdId::FightWavePushCmd, push).await?;
+        }
+
         conn.send_reply(CmdId::AutoRoundCmd, reply, 0, req.up_tag)
             .await?;
     }
@@ -109,13 +137,66 @@ pub async fn on_auto_round(
             episode_id,
             battle_id,
             round_num,
-            vec![], // cloth ops (future)
+            cloth_skill_opers,
             auto_opers,
         )
         .await?;
+    }
 
-        let stars_earned = 2; // TODO real calc
-        update_dungeon_progress(&pool, player_id, chapter_id, episode_id, stars_earned).await?;
+    if !round.is_finish.unwrap_or(false) {
+        let mut conn = ctx.lock().await;
+        if let Some(battle) = conn.active_battle.as_mut() {
+            battle.apply_round(&round, simulator.into_data());
+            persist_active_battle(&pool, player_id, battle, &round).await?;
+        }
+        return Ok(());
+    }
+
+    let won = simulator.data().defender_defeated();
+    let (attacker_stats, defender_stats) = simulator.data().battle_stats();
+
+    if !won {
+        tracing::info!(
+            "Auto battle lost: episode={}, round={}",
+            episode_id,
+            record_round
+        );
+
+        send_end_fight_push(
+            ctx.clone(),
+            battle_id,
+            2, // Lose
+            fight_group.clone().unwrap_or_default(),
+            attacker_stats,
+            defender_stats,
+            false,
+        )
+        .await?;
+
+        return Ok(());
+    }
+
+    // The stored oper log has to win the same way before anything is granted
+    if !is_replay && !verify_active_battle(&ctx, &pool, player_id, true).await? {
+        send_end_fight_push(
+            ctx.clone(),
+            battle_id,
+            2, // Lose
+            fight_group.clone().unwrap_or_default(),
+            attacker_stats,
+            defender_stats,
+            false,
+        )
+        .await?;
+
+        return Ok(());
+    }
+
+    let star = evaluate_stars(simulator.data().fight());
+    let previous_star = get_dungeon_star(&pool, player_id, chapter_id, episode_id).await?;
+
+    if !is_replay {
+        update_dungeon_progress(&pool, player_id, chapter_id, episode_id, star).await?;
 
         let should_save_record =
             should_update_dungeon_record(&pool, player_id, episode_id, record_round, &fight_group)
@@ -147,8 +228,8 @@ pub async fn on_auto_round(
         battle_id,
         1,
         fight_group.clone().unwrap_or_default(),
-        vec![],
-        vec![],
+        attacker_stats,
+        defender_stats,
         !is_replay,
     )
     .await?;
@@ -170,28 +251,46 @@ pub async fn on_auto_round(
         .map(|c| c.r#type)
         .unwrap_or(6);
 
+    let chapter_type_num = add_chapter_type_pass(&pool, player_id, chapter_type).await?;
+
     send_dungeon_update_push(
         ctx.clone(),
         chapter_id,
         episode_id,
-        updated_dungeon.star,
-        updated_dungeon.challenge_count,
-        updated_dungeon.has_record,
-        chapter_type,
-        2,
-        2,
+        &updated_dungeon,
+        Some(chapter_type_num),
     )
     .await?;
 
-    let is_first_clear = updated_dungeon.challenge_count == 1;
-    let rewards = generate_dungeon_rewards(episode_id, is_first_clear, multiplication);
+    // A replayed record never progresses the stored grade
+    let clear = DungeonClear {
+        star,
+        previous_star: if is_replay {
+            previous_star.max(star)
+        } else {
+            previous_star
+        },
+    };
+
+    let rewards = generate_dungeon_rewards(episode_id, clear, multiplication);
+
+    Transaction::new(player_id, LedgerSource::Dungeon)
+        .reference(episode_id as i64)
+        .grants(&rewards.all())
+        .silent()
+        .commit(ctx.clone())
+        .await?;
 
-    let mut all_rewards = rewards.normal_bonus.clone();
-    all_rewards.extend(rewards.first_bonus);
-    all_rewards.extend(rewards.free_bonus);
+    send_end_dungeon_push(ctx.clone(), chapter_id, episode_id, star, &rewards).await?;
 
-    send_end_dungeon_push(ctx.clone(), chapter_id, episode_id, all_rewards).await?;
     send_red_dot_push(ctx.clone(), player_id, Some(vec![1027, 1047])).await?;
 
+    ctx.lock().await.emit(GameEvent::DungeonCleared {
+        chapter_id,
+        episode_id,
+        star,
+        multiplication,
+    });
+
     Ok(())
 }
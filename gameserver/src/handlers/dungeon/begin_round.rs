@@ -1,19 +1,27 @@
 use crate::error::AppError;
 use crate::network::packet::ClientPacket;
 use crate::util::push::{send_dungeon_update_push, send_end_dungeon_push, send_red_dot_push};
+use crate::util::transaction::Transaction;
+use database::models::game::ledger::LedgerSource;
 
 use crate::send_push;
 use crate::state::{
-    BattleSimulator, ConnectionContext, generate_dungeon_rewards, send_end_fight_push,
+    BattleSimulator, ConnectionContext, DungeonClear, FightDataMgr, GameEvent, evaluate_stars,
+    generate_dungeon_rewards, persist_active_battle, round_seed, send_end_fight_push,
+    verify_active_battle,
 };
 use database::db::game::dungeons::{
-    get_user_dungeon, should_update_dungeon_record, update_dungeon_progress,
+    add_chapter_type_pass, get_dungeon_star, get_user_dungeon, should_update_dungeon_record,
+    update_dungeon_progress,
 };
 use database::db::game::{
     battle::save_round_operations, dungeons::save_dungeon_record, equipment::build_equip_records,
 };
 use prost::Message;
-use sonettobuf::{BeginRoundReply, BeginRoundRequest, CmdId, InstructionDungeonInfoPush};
+use sonettobuf::{
+    BeginRoundReply, BeginRoundRequest, CmdId, FightWavePush, InstructionDungeonInfoPush,
+};
+use sqlx::SqlitePool;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
@@ -31,15 +39,13 @@ pub async fn on_begin_round(
 
     let (
         current_deck,
-        fight_group,
-        chapter_id,
         episode_id,
         is_replay,
         battle_id,
         round_num,
-        multiplication,
         ai_deck,
         fight_data_mgr,
+        cloth_skill_opers,
     ) = {
         let conn = ctx.lock().await;
         let battle = conn
@@ -49,15 +55,13 @@ pub async fn on_begin_round(
 
         (
             battle.current_deck.clone(),
-            battle.fight_group.clone(),
-            battle.chapter_id,
             battle.episode_id,
             battle.is_replay.unwrap_or(false),
             battle.fight_id.unwrap_or_default(),
             battle.current_round,
-            battle.multiplication.unwrap_or(1),
             battle.ai_deck.clone(),
             battle.fight_data_mgr.clone().unwrap_or_default(),
+            battle.cloth_skill_opers.clone(),
         )
     };
 
@@ -69,13 +73,24 @@ pub async fn on_begin_round(
         )
     };
 
-    let mut simulator = BattleSimulator::new(fight_data_mgr);
-    let mut round = simulator
+    let wave_before = fight_data_mgr.fight().cur_wave;
+
+    let mut simulator =
+        BattleSimulator::with_seed(fight_data_mgr, round_seed(battle_id, round_num));
+    let round = simulator
         .process_round(request.opers.clone(), current_deck, ai_deck)
         .await?;
 
-    // Auto-complete battle for now
-    round.is_finish = Some(true);
+    let unhandled = simulator.take_unhandled();
+    if !unhandled.is_empty() {
+        tracing::warn!(
+            "Fight {} round {} hit unhandled skill data: {:?}",
+            battle_id,
+            round_num,
+            unhandled
+        );
+    }
+
     let record_round = round.cur_round.unwrap_or(1);
 
     tracing::info!(
@@ -92,6 +107,14 @@ pub async fn on_begin_round(
 
     {
         let mut conn = ctx.lock().await;
+
+        if simulator.data().fight().cur_wave != wave_before {
+            let push = FightWavePush {
+                fight: Some(simulator.data().get_fight_owned()),
+            };
+            conn.notify(CmdId::FightWavePushCmd, push).await?;
+        }
+
         conn.send_reply(CmdId::BeginRoundCmd, reply, 0, req.up_tag)
             .await?;
     }
@@ -104,23 +127,104 @@ pub async fn on_begin_round(
             episode_id,
             battle_id,
             round_num,
-            vec![], // TODO: Extract cloth_skill_opers from request
+            cloth_skill_opers,
             request.opers,
         )
         .await?;
+    }
+
+    if !round.is_finish.unwrap_or(false) {
+        let mut conn = ctx.lock().await;
+        if let Some(battle) = conn.active_battle.as_mut() {
+            battle.apply_round(&round, simulator.into_data());
+            persist_active_battle(&pool, player_id, battle, &round).await?;
+        }
+        return Ok(());
+    }
+
+    settle_fight(ctx, &pool, player_id, simulator.data(), record_round).await
+}
+
+/// Settles a finished fight: the lose push, or for a win whose oper log verifies the
+/// dungeon progress, record and rewards. Shared with the key skills that end a fight.
+pub(crate) async fn settle_fight(
+    ctx: Arc<Mutex<ConnectionContext>>,
+    pool: &SqlitePool,
+    player_id: i64,
+    data: &FightDataMgr,
+    record_round: i32,
+) -> Result<(), AppError> {
+    let (fight_group, chapter_id, episode_id, is_replay, battle_id, multiplication) = {
+        let conn = ctx.lock().await;
+        let battle = conn
+            .active_battle
+            .as_ref()
+            .ok_or(AppError::InvalidRequest)?;
+
+        (
+            battle.fight_group.clone(),
+            battle.chapter_id,
+            battle.episode_id,
+            battle.is_replay.unwrap_or(false),
+            battle.fight_id.unwrap_or_default(),
+            battle.multiplication.unwrap_or(1),
+        )
+    };
+
+    let won = data.defender_defeated();
+    let (attacker_stats, defender_stats) = data.battle_stats();
+
+    if !won {
+        tracing::info!(
+            "Battle lost: episode={}, round={}",
+            episode_id,
+            record_round
+        );
+
+        send_end_fight_push(
+            ctx.clone(),
+            battle_id,
+            2, // Lose
+            fight_group.clone().unwrap_or_default(),
+            attacker_stats,
+            defender_stats,
+            false,
+        )
+        .await?;
+
+        return Ok(());
+    }
+
+    // The stored oper log has to win the same way before anything is granted
+    if !is_replay && !verify_active_battle(&ctx, pool, player_id, true).await? {
+        send_end_fight_push(
+            ctx.clone(),
+            battle_id,
+            2, // Lose
+            fight_group.clone().unwrap_or_default(),
+            attacker_stats,
+            defender_stats,
+            false,
+        )
+        .await?;
 
-        // Update player's dungeon progress
-        let stars_earned = 2; // TODO: Calculate based on performance
-        update_dungeon_progress(&pool, player_id, chapter_id, episode_id, stars_earned).await?;
+        return Ok(());
+    }
+
+    let star = evaluate_stars(data.fight());
+    let previous_star = get_dungeon_star(pool, player_id, chapter_id, episode_id).await?;
+
+    if !is_replay {
+        update_dungeon_progress(pool, player_id, chapter_id, episode_id, star).await?;
 
         let should_save_record =
-            should_update_dungeon_record(&pool, player_id, episode_id, record_round, &fight_group)
+            should_update_dungeon_record(pool, player_id, episode_id, record_round, &fight_group)
                 .await?;
 
         if should_save_record {
-            let equips = build_equip_records(&pool, player_id, &fight_group).await?;
+            let equips = build_equip_records(pool, player_id, &fight_group).await?;
             save_dungeon_record(
-                &pool,
+                pool,
                 player_id,
                 episode_id,
                 record_round,
@@ -149,8 +253,8 @@ pub async fn on_begin_round(
         battle_id,
         1, // Win
         fight_group.clone().unwrap_or_default(),
-        vec![],     // TODO: Actual battle stats
-        vec![],     // No defender stats
+        attacker_stats,
+        defender_stats,
         !is_replay, // is_record: only record real battles
     )
     .await?;
@@ -162,7 +266,7 @@ pub async fn on_begin_round(
         "dungeon/instruction_dungeon_info.json"
     );
 
-    let updated_dungeon = get_user_dungeon(&pool, player_id, chapter_id, episode_id).await?;
+    let updated_dungeon = get_user_dungeon(pool, player_id, chapter_id, episode_id).await?;
 
     let game_data = config::configs::get();
     let chapter_type = game_data
@@ -172,33 +276,46 @@ pub async fn on_begin_round(
         .map(|c| c.r#type)
         .unwrap_or(6);
 
+    let chapter_type_num = add_chapter_type_pass(pool, player_id, chapter_type).await?;
+
     send_dungeon_update_push(
         ctx.clone(),
         chapter_id,
         episode_id,
-        updated_dungeon.star,
-        updated_dungeon.challenge_count,
-        updated_dungeon.has_record,
-        chapter_type, // From chapter Excel data
-        2,            // TODO: Calculate today's chapter completions
-        2,            // TODO: Calculate today's chapter attempts
+        &updated_dungeon,
+        Some(chapter_type_num),
     )
     .await?;
 
-    // Generate rewards based on episode data
-
-    let is_first_clear = updated_dungeon.challenge_count == 1;
+    // A replayed record never progresses the stored grade
+    let clear = DungeonClear {
+        star,
+        previous_star: if is_replay {
+            previous_star.max(star)
+        } else {
+            previous_star
+        },
+    };
 
-    let rewards = generate_dungeon_rewards(episode_id, is_first_clear, multiplication);
+    let rewards = generate_dungeon_rewards(episode_id, clear, multiplication);
 
-    // Combine rewards for push
-    let mut all_rewards = rewards.normal_bonus.clone();
-    all_rewards.extend(rewards.first_bonus);
-    all_rewards.extend(rewards.free_bonus);
+    Transaction::new(player_id, LedgerSource::Dungeon)
+        .reference(episode_id as i64)
+        .grants(&rewards.all())
+        .silent()
+        .commit(ctx.clone())
+        .await?;
 
-    send_end_dungeon_push(ctx.clone(), chapter_id, episode_id, all_rewards).await?;
+    send_end_dungeon_push(ctx.clone(), chapter_id, episode_id, star, &rewards).await?;
 
     send_red_dot_push(Arc::clone(&ctx), player_id, Some(vec![1027, 1047])).await?;
 
+    ctx.lock().await.emit(GameEvent::DungeonCleared {
+        chapter_id,
+        episode_id,
+        star,
+        multiplication,
+    });
+
     Ok(())
 }
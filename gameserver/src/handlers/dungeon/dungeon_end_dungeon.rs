@@ -1,6 +1,11 @@
 use crate::network::packet::ClientPacket;
-use crate::state::ConnectionContext;
+use crate::state::{
+    ConnectionContext, POWER_CURRENCY_ID, episode_power_cost, refund_power, verify_active_battle,
+};
+use crate::util::push::send_currency_change_push;
 use crate::{error::AppError, state::send_end_fight_push};
+use config::configs;
+use database::models::game::ledger::{LedgerOrigin, LedgerSource};
 use prost::Message;
 use sonettobuf::{CmdId, EndDungeonReply, EndDungeonRequest};
 use std::sync::Arc;
@@ -16,7 +21,13 @@ pub async fn on_dungeon_end_dungeon(
 
     tracing::info!("Dungeon ended with is_abort: {}", is_abort);
 
-    let (fight_group, is_replay, battle_id) = {
+    let (
+        fight_group,
+        is_replay,
+        battle_id,
+        (attacker_stats, defender_stats),
+        (episode_id, multiplication, stamina_cost),
+    ) = {
         let conn = ctx.lock().await;
         let battle = conn
             .active_battle
@@ -27,20 +38,65 @@ pub async fn on_dungeon_end_dungeon(
             battle.fight_group.clone(),
             battle.is_replay.unwrap_or(false),
             battle.fight_id.unwrap_or_default(),
+            battle
+                .fight_data_mgr
+                .as_ref()
+                .map(|data| data.battle_stats())
+                .unwrap_or_default(),
+            (
+                battle.episode_id,
+                battle.multiplication.unwrap_or(1),
+                battle.stamina_cost,
+            ),
         )
     };
 
+    let (player_id, pool) = {
+        let conn = ctx.lock().await;
+        (
+            conn.player_id.ok_or(AppError::NotLoggedIn)?,
+            conn.state.db.clone(),
+        )
+    };
+
+    // An abort of a fight the oper log says already ended gets no refund
+    let verified = is_replay || verify_active_battle(&ctx, &pool, player_id, !is_abort).await?;
+
     if is_abort {
         send_end_fight_push(
             ctx.clone(),
             battle_id,
             -1, // abort
             fight_group.clone().unwrap_or_default(),
-            vec![],
-            vec![],
+            attacker_stats,
+            defender_stats,
             !is_replay,
         )
         .await?;
+
+        // Everything but the episode's fail cost goes back
+        let fail_cost = configs::get()
+            .episode
+            .get(episode_id)
+            .map(|e| episode_power_cost(&e.fail_cost, multiplication))
+            .unwrap_or(0);
+        let refund = (stamina_cost - fail_cost).max(0);
+
+        if refund > 0 && verified {
+            let refund = {
+                let mut conn = ctx.lock().await;
+                let origin = LedgerOrigin::with_reference(LedgerSource::Dungeon, episode_id as i64);
+                refund_power(&mut conn, refund, origin).await?
+            };
+
+            tracing::info!(
+                "Refunded {} stamina for aborted episode {}",
+                refund,
+                episode_id
+            );
+            send_currency_change_push(ctx.clone(), player_id, vec![(POWER_CURRENCY_ID, refund)])
+                .await?;
+        }
     }
 
     {
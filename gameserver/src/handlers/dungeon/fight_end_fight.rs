@@ -1,5 +1,5 @@
 use crate::network::packet::ClientPacket;
-use crate::state::ConnectionContext;
+use crate::state::{ConnectionContext, verify_active_battle};
 use crate::{error::AppError, state::send_end_fight_push};
 use prost::Message;
 use sonettobuf::{CmdId, EndFightReply, EndFightRequest};
@@ -16,7 +16,7 @@ pub async fn on_fight_end_fight(
 
     tracing::info!("Fight ended with is_abort: {}", is_abort);
 
-    let (fight_group, is_replay, battle_id) = {
+    let (fight_group, is_replay, battle_id, (attacker_stats, defender_stats)) = {
         let conn = ctx.lock().await;
         let battle = conn
             .active_battle
@@ -27,20 +27,47 @@ pub async fn on_fight_end_fight(
             battle.fight_group.clone(),
             battle.is_replay.unwrap_or(false),
             battle.fight_id.unwrap_or_default(),
+            battle
+                .fight_data_mgr
+                .as_ref()
+                .map(|data| data.battle_stats())
+                .unwrap_or_default(),
         )
     };
 
+    let (player_id, pool) = {
+        let conn = ctx.lock().await;
+        (
+            conn.player_id.ok_or(AppError::NotLoggedIn)?,
+            conn.state.db.clone(),
+        )
+    };
+
+    let verified = is_replay || verify_active_battle(&ctx, &pool, player_id, !is_abort).await?;
+
     if is_abort {
         send_end_fight_push(
             ctx.clone(),
             battle_id,
             -1, // abort
             fight_group.clone().unwrap_or_default(),
-            vec![],
-            vec![],
+            attacker_stats,
+            defender_stats,
             !is_replay,
         )
         .await?;
+    } else if !verified {
+        // A finish the oper log doesn't reach ends as a loss, nothing is recorded
+        send_end_fight_push(
+            ctx.clone(),
+            battle_id,
+            2, // Lose
+            fight_group.clone().unwrap_or_default(),
+            attacker_stats,
+            defender_stats,
+            false,
+        )
+        .await?;
     }
 
     // Clear battle
@@ -11,6 +11,7 @@ mod start_dungeon;
 
 pub use auto_round::on_auto_round;
 pub use begin_round::on_begin_round;
+pub(crate) use begin_round::settle_fight;
 pub use change_hero_group_select::on_change_hero_group_select;
 pub use dungeon_end_dungeon::on_dungeon_end_dungeon;
 pub use fight_end_fight::on_fight_end_fight;
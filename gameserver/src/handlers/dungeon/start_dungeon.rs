@@ -1,12 +1,15 @@
 use crate::error::AppError;
 use crate::network::packet::ClientPacket;
 use crate::state::{
-    ActiveBattle, BattleContext, ConnectionContext, create_battle, default_max_ap,
-    generate_initial_deck,
+    ActiveBattle, BattleContext, ConnectionContext, POWER_CURRENCY_ID, ReplayStart, create_battle,
+    default_max_ap, episode_power_cost, generate_initial_deck, persist_active_battle_in,
 };
+use crate::util::transaction::{MATERIAL_CURRENCY, Transaction};
 use config::configs;
 use database::db::game::dungeons::{get_user_dungeon, update_dungeon_progress};
+use database::models::game::ledger::LedgerSource;
 use prost::Message;
+use rand::{SeedableRng, rngs::StdRng};
 use sonettobuf::{CmdId, DungeonUpdatePush, StartDungeonReply, StartDungeonRequest, UserDungeon};
 use std::sync::Arc;
 use tokio::sync::Mutex;
@@ -58,37 +61,75 @@ pub async fn on_start_dungeon(
         max_ap,
     };
 
-    let card_push = generate_initial_deck(&pool, player_id, &fight_group, max_ap).await?;
+    let card_push = generate_initial_deck(
+        &pool,
+        player_id,
+        &fight_group,
+        max_ap,
+        &mut StdRng::from_entropy(),
+    )
+    .await?;
 
     let card_deck = card_push.card_group.clone();
 
     let (modified_fight, initial_round, fight_data_mgr, ai_deck) =
         create_battle(&pool, battle_ctx, &fight_group, card_deck.clone()).await?;
 
-    {
-        let mut conn = ctx.lock().await;
-        conn.active_battle = Some(ActiveBattle {
-            tower_type: None,
-            tower_id: None,
-            layer_id: None,
-            episode_id,
-            chapter_id,
-            difficulty: None,
-            talent_plan_id: None,
-            fight: Some(modified_fight.clone()),
-            current_round: 1,
-            act_point: max_ap,
-            power: 15,
-            current_deck: card_deck,
-            fight_group: Some(fight_group.clone()),
-            is_replay: Some(use_record),
-            replay_episode_id: Some(episode_id),
-            fight_id: Some(chrono::Utc::now().timestamp_millis()),
-            multiplication: Some(multiplication),
-            ai_deck,
-            fight_data_mgr: Some(fight_data_mgr),
-        });
+    let stamina_cost = episode_power_cost(&episode_cfg.cost, multiplication);
+
+    let spent = Transaction::new(player_id, LedgerSource::Dungeon)
+        .reference(episode_id as i64)
+        .cost(MATERIAL_CURRENCY, POWER_CURRENCY_ID as u32, stamina_cost)
+        .silent()
+        .apply(ctx.clone())
+        .await;
+    if let Err(AppError::InsufficientCurrency) = spent {
+        tracing::warn!(
+            "Player {} lacks {} stamina for episode {}",
+            player_id,
+            stamina_cost,
+            episode_id
+        );
     }
+    let mut spent = spent?;
+
+    let mut battle = ActiveBattle {
+        tower_type: None,
+        tower_id: None,
+        layer_id: None,
+        episode_id,
+        chapter_id,
+        difficulty: None,
+        talent_plan_id: None,
+        fight: Some(modified_fight.clone()),
+        current_round: 1,
+        act_point: max_ap,
+        power: initial_round.power.unwrap_or(15),
+        current_deck: card_deck.clone(),
+        fight_group: Some(fight_group.clone()),
+        is_replay: Some(use_record),
+        replay_episode_id: Some(episode_id),
+        fight_id: Some(chrono::Utc::now().timestamp_millis()),
+        multiplication: Some(multiplication),
+        replay_start: Some(ReplayStart {
+            data: fight_data_mgr.clone(),
+            deck: card_deck,
+            ai_deck: ai_deck.clone(),
+        }),
+        ai_deck,
+        fight_data_mgr: Some(fight_data_mgr),
+        cloth_skill_opers: vec![],
+        round_start: None,
+        stamina_cost,
+    };
+
+    battle.mark_round_start(&initial_round);
+
+    // The stamina is only taken together with the fight it pays for
+    persist_active_battle_in(&mut spent.db, player_id, &battle, &initial_round).await?;
+    spent.commit(ctx.clone()).await?;
+
+    ctx.lock().await.active_battle = Some(battle);
 
     let updated_dungeon = get_user_dungeon(&pool, player_id, chapter_id, episode_id).await?;
 
@@ -1,19 +1,20 @@
 use crate::error::AppError;
 use crate::network::packet::ClientPacket;
 use crate::state::ConnectionContext;
-use crate::util::push;
+use crate::util::transaction::{MATERIAL_CURRENCY, MATERIAL_ITEM, Transaction};
+use database::models::game::ledger::LedgerSource;
 
-use database::models::game::{
-    currencies::UserCurrencyModel,
-    equipment::{EquipmentModel, UserEquipmentModel},
-    items::UserItemModel,
-};
+use database::db::game::equipment;
+use database::models::game::equipment::UserEquipmentModel;
 
 use prost::Message;
 use sonettobuf::{CmdId, EquipBreakReply, EquipBreakRequest};
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
+/// Currency the `scoreCost` of `equip_break_cost` is paid in
+const BREAK_CURRENCY_ID: u32 = 1;
+
 pub async fn on_equip_break(
     ctx: Arc<Mutex<ConnectionContext>>,
     req: ClientPacket,
@@ -30,11 +31,9 @@ pub async fn on_equip_break(
         )
     };
 
-    let equip = UserEquipmentModel::new(player_id, pool.clone());
-    let item = UserItemModel::new(player_id, pool.clone());
-    let currency = UserCurrencyModel::new(player_id, pool.clone());
+    let equip = UserEquipmentModel::new(player_id, pool);
 
-    let mut target = equip.get_equip(target_uid).await?;
+    let target = equip.get_equip(target_uid).await?;
 
     let game_data = config::configs::get();
     let target_equip_data = game_data
@@ -90,13 +89,21 @@ pub async fn on_equip_break(
         return Ok(());
     };
 
-    if next_break.score_cost > 0 {
-        let success = currency.remove_currency(1, next_break.score_cost).await?;
-        if !success {
+    let (cost_items, _, _, _, _, _) = crate::state::parse_store_product(&next_break.cost);
+    let mut tx = Transaction::new(player_id, LedgerSource::Equip)
+        .reference(target_uid)
+        .cost(MATERIAL_CURRENCY, BREAK_CURRENCY_ID, next_break.score_cost);
+    for (item_id, amount) in cost_items {
+        tx = tx.cost(MATERIAL_ITEM, item_id, amount);
+    }
+
+    let mut applied = match tx.apply(ctx.clone()).await {
+        Ok(applied) => applied,
+        Err(AppError::InsufficientItems | AppError::InsufficientCurrency) => {
             tracing::warn!(
-                "User {} insufficient gold for break (needs {})",
+                "User {} can't pay the break of equipment uid={}",
                 player_id,
-                next_break.score_cost
+                target_uid
             );
 
             let reply = EquipBreakReply {};
@@ -107,49 +114,20 @@ pub async fn on_equip_break(
 
             return Ok(());
         }
-    }
-
-    if !next_break.cost.is_empty() {
-        let (items, _, _, _, _, _) = crate::state::parse_store_product(&next_break.cost);
-
-        for (item_id, amount) in &items {
-            let success = item.remove_item_quantity(*item_id, *amount).await?;
-            if !success {
-                tracing::warn!(
-                    "User {} insufficient items for break (item_id={}, needs={})",
-                    player_id,
-                    item_id,
-                    amount
-                );
-
-                let reply = EquipBreakReply {};
-
-                let mut conn = ctx.lock().await;
-                conn.send_reply(CmdId::EquipBreakCmd, reply, 0, req.up_tag)
-                    .await?;
-
-                return Ok(());
-            }
-        }
-    }
-
-    target.break_lv += 1;
+        Err(e) => return Err(e),
+    };
 
-    if equip.break_level(target_uid).await? {
+    if equipment::increment_break_level(&mut applied.db, player_id, target_uid).await? {
         tracing::info!(
             "User {} broke through equipment uid={} to break_lv {} (cost {} sharp)",
             player_id,
             target_uid,
-            target.break_lv,
+            target.break_lv + 1,
             next_break.score_cost
         );
     }
-
-    if next_break.score_cost > 0 {
-        push::send_currency_change_push(ctx.clone(), player_id, vec![(1, 0)]).await?;
-    }
-
-    push::send_equip_update_push(ctx.clone(), player_id, vec![target.equip_id]).await?;
+    applied.touch_equip(target_uid);
+    applied.commit(ctx.clone()).await?;
 
     let reply = EquipBreakReply {};
 
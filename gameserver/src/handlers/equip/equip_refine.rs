@@ -1,8 +1,10 @@
 use crate::error::AppError;
 use crate::network::packet::ClientPacket;
 use crate::state::ConnectionContext;
-use crate::util::push;
-use database::models::game::equipment::{EquipmentModel, UserEquipmentModel};
+use crate::util::transaction::Transaction;
+use database::db::game::equipment;
+use database::models::game::equipment::UserEquipmentModel;
+use database::models::game::ledger::LedgerSource;
 use prost::Message;
 use sonettobuf::{CmdId, EquipRefineReply, EquipRefineRequest};
 use std::sync::Arc;
@@ -91,13 +93,6 @@ pub async fn on_equip_refine(
         equips_to_delete.push((*eat_uid, eat_equipment.equip_id));
     }
 
-    let mut affected_uids = Vec::with_capacity(1 + equips_to_delete.len());
-    affected_uids.push(target_uid);
-
-    for (eat_uid, _) in &equips_to_delete {
-        affected_uids.push(*eat_uid);
-    }
-
     target.refine_lv += valid_consumed;
 
     let max_refine_lv = 5;
@@ -105,37 +100,40 @@ pub async fn on_equip_refine(
         target.refine_lv = max_refine_lv;
     }
 
-    if equip.refine_level(target_uid, target.refine_lv).await? {
-        tracing::info!(
-            "User {} refined equipment uid={} to refine_lv {} (consumed {} items)",
-            player_id,
-            target_uid,
-            target.refine_lv,
-            valid_consumed
-        );
+    let mut tx = Transaction::new(player_id, LedgerSource::Equip).reference(target_uid);
+    for (eat_uid, _) in &equips_to_delete {
+        tx = tx.cost_equip(*eat_uid, 1);
     }
 
-    let mut affected_equip_ids = vec![target.equip_id];
-    for (_, equip_id) in &equips_to_delete {
-        if !affected_equip_ids.contains(equip_id) {
-            affected_equip_ids.push(*equip_id);
-        }
-    }
+    let mut applied = match tx.apply(ctx.clone()).await {
+        Ok(applied) => applied,
+        Err(AppError::InsufficientItems) => {
+            let reply = EquipRefineReply {
+                target_uid: request.target_uid,
+                eat_uids: request.eat_uids,
+            };
 
-    for (eat_uid, _) in &equips_to_delete {
-        equip.delete(*eat_uid).await?;
-    }
+            let mut conn = ctx.lock().await;
+            conn.send_reply(CmdId::EquipRefineCmd, reply, 0, req.up_tag)
+                .await?;
 
-    let delete_uids: Vec<i64> = equips_to_delete.iter().map(|(uid, _)| *uid).collect();
-    if !delete_uids.is_empty() {
-        let delete_push = sonettobuf::EquipDeletePush {
-            uids: delete_uids.clone(),
-        };
-        let mut conn = ctx.lock().await;
-        conn.notify(CmdId::EquipDeletePushCmd, delete_push).await?;
-    }
+            return Ok(());
+        }
+        Err(e) => return Err(e),
+    };
 
-    push::send_equip_update_push(ctx.clone(), player_id, affected_equip_ids).await?;
+    equipment::set_equipment_refine_level(&mut applied.db, player_id, target_uid, target.refine_lv)
+        .await?;
+    applied.touch_equip(target_uid);
+    applied.commit(ctx.clone()).await?;
+
+    tracing::info!(
+        "User {} refined equipment uid={} to refine_lv {} (consumed {} items)",
+        player_id,
+        target_uid,
+        target.refine_lv,
+        valid_consumed
+    );
 
     let reply = EquipRefineReply {
         target_uid: request.target_uid,
@@ -1,13 +1,18 @@
 use crate::error::AppError;
 use crate::network::packet::ClientPacket;
-use crate::state::ConnectionContext;
-use crate::util::push;
+use crate::state::{ConnectionContext, GameEvent};
+use crate::util::transaction::{MATERIAL_CURRENCY, Transaction};
+use database::db::game::equipment;
 use database::models::game::equipment::UserEquipmentModel;
+use database::models::game::ledger::LedgerSource;
 use prost::Message;
 use sonettobuf::{CmdId, EquipStrengthenReply, EquipStrengthenRequest};
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
+/// Currency paid per level gained, the `scoreCost` of `equip_strengthen_cost`
+const STRENGTHEN_CURRENCY_ID: u32 = 1;
+
 pub async fn on_equip_strengthen(
     ctx: Arc<Mutex<ConnectionContext>>,
     req: ClientPacket,
@@ -40,9 +45,7 @@ pub async fn on_equip_strengthen(
         })
         .collect();
 
-    let (total_exp, affected_equip_ids) = equip.strengthen_equip(target_uid, consume_items).await?;
-
-    if total_exp == 0 {
+    let Some(plan) = equip.strengthen_equip(target_uid, consume_items).await? else {
         tracing::info!(
             "No valid equipment to consume for user {} target={}",
             player_id,
@@ -59,9 +62,52 @@ pub async fn on_equip_strengthen(
             .await?;
 
         return Ok(());
+    };
+
+    let mut tx = Transaction::new(player_id, LedgerSource::Equip)
+        .reference(target_uid)
+        .cost(MATERIAL_CURRENCY, STRENGTHEN_CURRENCY_ID, plan.score_cost);
+    for &(uid, count) in &plan.consumed {
+        tx = tx.cost_equip(uid, count);
     }
 
-    push::send_equip_update_push(ctx.clone(), player_id, affected_equip_ids).await?;
+    let mut applied = match tx.apply(ctx.clone()).await {
+        Ok(applied) => applied,
+        Err(AppError::InsufficientItems | AppError::InsufficientCurrency) => {
+            let reply = EquipStrengthenReply {
+                target_uid: request.target_uid,
+                eat_equips: request.eat_equips,
+            };
+
+            let mut conn = ctx.lock().await;
+            conn.send_reply(CmdId::EquipStrengthenCmd, reply, 0, req.up_tag)
+                .await?;
+
+            return Ok(());
+        }
+        Err(e) => return Err(e),
+    };
+
+    equipment::set_equipment_level(
+        &mut applied.db,
+        player_id,
+        target_uid,
+        plan.target.level,
+        plan.target.exp,
+    )
+    .await?;
+    applied.touch_equip(target_uid);
+    applied.commit(ctx.clone()).await?;
+
+    tracing::info!(
+        "User {} strengthened equipment uid={} to level {} (exp: {}, +{} exp, {} gold)",
+        player_id,
+        target_uid,
+        plan.target.level,
+        plan.target.exp,
+        plan.total_exp,
+        plan.score_cost
+    );
 
     let reply = EquipStrengthenReply {
         target_uid: request.target_uid,
@@ -69,6 +115,9 @@ pub async fn on_equip_strengthen(
     };
 
     let mut conn = ctx.lock().await;
+    conn.emit(GameEvent::EquipStrengthened {
+        equip_uid: target_uid,
+    });
     conn.send_reply(CmdId::EquipStrengthenCmd, reply, 0, req.up_tag)
         .await?;
 
@@ -3,12 +3,14 @@ use crate::send_reply;
 use crate::state::ConnectionContext;
 use crate::{
     error::AppError,
-    util::{data_loader::GameDataLoader, push},
+    util::{
+        data_loader::GameDataLoader,
+        push,
+        transaction::{MATERIAL_CURRENCY, MATERIAL_ITEM, Transaction},
+    },
 };
 use database::db::game::activity101;
-use database::models::game::{
-    currencies::UserCurrencyModel, heros::UserHeroModel, items::UserItemModel,
-};
+use database::models::game::ledger::LedgerSource;
 
 use prost::Message;
 use sonettobuf::{
@@ -133,137 +135,23 @@ pub async fn on_get101_bonus(
         .parse::<i32>()
         .map_err(|_| AppError::InvalidRequest)?;
 
-    // Separate reward containers based on type
-    let mut item_rewards = vec![];
-    let mut currency_rewards = vec![];
-    let mut equip_rewards = vec![];
-    let mut hero_rewards = vec![];
-    let mut power_item_rewards = vec![];
-    let mut insight_item_rewards = vec![];
-
-    match reward_type {
-        1 => item_rewards.push((item_id, quantity)), // Items
-        2 | 13 => currency_rewards.push((item_id as i32, quantity)), // Currency / Shop currency
-        4 => hero_rewards.push((item_id, quantity)), // Heroes
-        5 => {
-            // Skins
-            tracing::warn!("Skin rewards not yet implemented for activity101");
-        }
-        9 => equip_rewards.push((item_id, quantity)), // Psychubes/Equipment
-        10 => power_item_rewards.push((item_id, quantity)), // Power items/energy
-        11 => item_rewards.push((item_id, quantity)), // Room Buildings
-        14 => item_rewards.push((item_id, quantity)), // Special blocks
-        24 => insight_item_rewards.push((item_id, quantity)), // Insight items
+    let material_type = match reward_type {
+        2 | 13 => MATERIAL_CURRENCY, // Currency / Shop currency
+        11 | 14 => MATERIAL_ITEM,    // Room buildings / Special blocks
+        1 | 4 | 5 | 9 | 10 | 24 => reward_type,
         _ => {
             tracing::warn!("Unknown reward type: {}", reward_type);
             return Err(AppError::InvalidRequest);
         }
-    }
-
-    let (
-        changed_item_ids,
-        changed_currency_ids,
-        changed_equip_ids,
-        hero_dupe_items,
-        hero_dupe_currencies,
-    ) = {
-        let hero = UserHeroModel::new(player_id, pool.clone());
-        let item = UserItemModel::new(player_id, pool.clone());
-        let currency = UserCurrencyModel::new(player_id, pool.clone());
-
-        let mut item_ids = if !item_rewards.is_empty() {
-            item.create_items(&item_rewards).await?
-        } else {
-            vec![]
-        };
-
-        let mut currency_ids = if !currency_rewards.is_empty() {
-            currency.create_currencies(&currency_rewards).await?
-        } else {
-            vec![]
-        };
-
-        let equip_ids = if !equip_rewards.is_empty() {
-            database::db::game::equipment::add_equipments(
-                &pool,
-                player_id,
-                &equip_rewards
-                    .iter()
-                    .map(|(id, count)| (*id as i32, *count))
-                    .collect::<Vec<_>>(),
-            )
-            .await?
-        } else {
-            Vec::new()
-        };
-
-        if !power_item_rewards.is_empty() {
-            item.create_power_items(
-                &power_item_rewards
-                    .iter()
-                    .map(|(id, count)| (*id as i32, *count))
-                    .collect::<Vec<_>>(),
-            )
-            .await?;
-        }
-
-        if !insight_item_rewards.is_empty() {
-            let insight_item_ids = item
-                .create_insight_items(
-                    &insight_item_rewards
-                        .iter()
-                        .map(|(id, count)| (*id as i32, *count))
-                        .collect::<Vec<_>>(),
-                )
-                .await?;
-            item_ids.extend(insight_item_ids);
-        }
-
-        let mut hero_dupe_items = Vec::new();
-        let mut hero_dupe_currencies = Vec::new();
-
-        for (hero_id, _count) in &hero_rewards {
-            let hero_id = *hero_id as i32;
-
-            if hero.has_hero(hero_id).await? {
-                let duplicate_count = hero.add_hero_duplicate(hero_id).await?;
-
-                let (dupe_items, dupe_currencies) =
-                    crate::state::grant_dupe_rewards(hero_id, duplicate_count).await?;
-
-                hero_dupe_items.extend(dupe_items);
-                hero_dupe_currencies.extend(dupe_currencies);
-
-                tracing::info!(
-                    "User {} already has hero {}, granted dupe rewards (duplicate #{})",
-                    player_id,
-                    hero_id,
-                    duplicate_count
-                );
-            } else {
-                hero.create_hero(hero_id).await?;
-                tracing::info!("User {} received new hero {}", player_id, hero_id);
-            }
-        }
-
-        if !hero_dupe_items.is_empty() {
-            let dupe_item_ids = item.create_items(&hero_dupe_items).await?;
-            item_ids.extend(dupe_item_ids);
-        }
-        if !hero_dupe_currencies.is_empty() {
-            let dupe_currency_ids = currency.create_currencies(&hero_dupe_currencies).await?;
-            currency_ids.extend(dupe_currency_ids);
-        }
-
-        (
-            item_ids,
-            currency_ids,
-            equip_ids,
-            hero_dupe_items,
-            hero_dupe_currencies,
-        )
     };
 
+    Transaction::new(player_id, LedgerSource::Activity)
+        .reference(activity_id as i64)
+        .grant(material_type, item_id, quantity)
+        .approach(25) // 25 = activity source
+        .commit(ctx.clone())
+        .await?;
+
     tracing::info!(
         "User {} claimed day {} for activity {}: reward type {}#{}#{}",
         player_id,
@@ -274,62 +162,8 @@ pub async fn on_get101_bonus(
         quantity
     );
 
-    // Build material rewards for popup notification
-    let mut material_rewards = Vec::new();
-
-    for (item_id, amount) in &item_rewards {
-        material_rewards.push((1, *item_id, *amount));
-    }
-    for (item_id, amount) in &hero_dupe_items {
-        material_rewards.push((1, *item_id, *amount));
-    }
-    for (currency_id, amount) in &currency_rewards {
-        material_rewards.push((2, *currency_id as u32, *amount));
-    }
-    for (currency_id, amount) in &hero_dupe_currencies {
-        material_rewards.push((2, *currency_id as u32, *amount));
-    }
-    for (equip_id, amount) in &equip_rewards {
-        material_rewards.push((9, *equip_id, *amount));
-    }
-    for (power_item_id, amount) in &power_item_rewards {
-        material_rewards.push((10, *power_item_id, *amount));
-    }
-    for (insight_item_id, amount) in &insight_item_rewards {
-        material_rewards.push((24, *insight_item_id, *amount));
-    }
-
-    if hero_dupe_items.is_empty() && hero_dupe_currencies.is_empty() {
-        for (hero_id, amount) in &hero_rewards {
-            material_rewards.push((4, *hero_id, *amount));
-        }
-    }
-
-    if !changed_item_ids.is_empty() {
-        push::send_item_change_push(
-            ctx.clone(),
-            player_id,
-            changed_item_ids.into_iter().map(|id| id as u32).collect(),
-            vec![],
-            vec![],
-        )
-        .await?;
-    }
-
-    if !changed_currency_ids.is_empty() {
-        push::send_currency_change_push(ctx.clone(), player_id, changed_currency_ids).await?;
-    }
-
-    if !changed_equip_ids.is_empty() {
-        push::send_equip_update_push_by_uid(ctx.clone(), player_id, &changed_equip_ids).await?;
-    }
-
     push::send_red_dot_push(ctx.clone(), player_id, Some(vec![2240])).await?;
 
-    if !material_rewards.is_empty() {
-        push::send_material_change_push(ctx.clone(), material_rewards, Some(25)).await?; // 25 = activity source
-    }
-
     push::send_red_dot_push(ctx.clone(), player_id, Some(vec![1010])).await?;
     push::send_red_dot_push(ctx.clone(), player_id, Some(vec![30558, 30557])).await?;
 
@@ -0,0 +1,31 @@
+use crate::error::AppError;
+use crate::network::packet::ClientPacket;
+use crate::state::{ConnectionContext, entity_details};
+use sonettobuf::{CmdId, GetEntityDetailInfosReply};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+pub async fn on_get_entity_detail_infos(
+    ctx: Arc<Mutex<ConnectionContext>>,
+    req: ClientPacket,
+) -> Result<(), AppError> {
+    let mut conn = ctx.lock().await;
+
+    let data = conn
+        .active_battle
+        .as_ref()
+        .and_then(|b| b.fight_data_mgr.as_ref())
+        .ok_or(AppError::InvalidRequest)?;
+
+    let (team_a_infos, team_b_infos) = entity_details(data);
+
+    let reply = GetEntityDetailInfosReply {
+        team_a_infos,
+        team_b_infos,
+    };
+
+    conn.send_reply(CmdId::GetEntityDetailInfosCmd, reply, 0, req.up_tag)
+        .await?;
+
+    Ok(())
+}
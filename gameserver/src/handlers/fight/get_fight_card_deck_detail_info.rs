@@ -0,0 +1,40 @@
+use crate::error::AppError;
+use crate::network::packet::ClientPacket;
+use crate::state::{ConnectionContext, deck_contents};
+use prost::Message;
+use sonettobuf::{CmdId, GetFightCardDeckDetailInfoReply, GetFightCardDeckDetailInfoRequest};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+pub async fn on_get_fight_card_deck_detail_info(
+    ctx: Arc<Mutex<ConnectionContext>>,
+    req: ClientPacket,
+) -> Result<(), AppError> {
+    let request = GetFightCardDeckDetailInfoRequest::decode(&req.data[..])?;
+
+    let mut conn = ctx.lock().await;
+
+    let battle = conn
+        .active_battle
+        .as_ref()
+        .ok_or(AppError::InvalidRequest)?;
+    let data = battle
+        .fight_data_mgr
+        .as_ref()
+        .ok_or(AppError::InvalidRequest)?;
+
+    let deck_infos = deck_contents(data, &battle.current_deck, true);
+
+    tracing::debug!(
+        "GetFightCardDeckDetailInfo: type={:?}, {} cards",
+        request.r#type,
+        deck_infos.len()
+    );
+
+    let reply = GetFightCardDeckDetailInfoReply { deck_infos };
+
+    conn.send_reply(CmdId::GetFightCardDeckDetailInfoCmd, reply, 0, req.up_tag)
+        .await?;
+
+    Ok(())
+}
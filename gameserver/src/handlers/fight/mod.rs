@@ -1,3 +1,13 @@
+mod get_entity_detail_infos;
+mod get_fight_card_deck_detail_info;
+mod get_fight_card_deck_info;
 mod reconnect_fight;
+mod reset_round;
+mod use_cloth_skill;
 
+pub use get_entity_detail_infos::on_get_entity_detail_infos;
+pub use get_fight_card_deck_detail_info::on_get_fight_card_deck_detail_info;
+pub use get_fight_card_deck_info::on_get_fight_card_deck_info;
 pub use reconnect_fight::on_reconnect_fight;
+pub use reset_round::on_reset_round;
+pub use use_cloth_skill::on_use_cloth_skill;
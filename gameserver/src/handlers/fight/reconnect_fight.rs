@@ -1,7 +1,7 @@
 use crate::error::AppError;
 use crate::network::packet::ClientPacket;
-use crate::state::ConnectionContext;
-use sonettobuf::CmdId;
+use crate::state::{ConnectionContext, restore_active_battle};
+use sonettobuf::{CmdId, ReconnectFightReply};
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
@@ -9,8 +9,44 @@ pub async fn on_reconnect_fight(
     ctx: Arc<Mutex<ConnectionContext>>,
     req: ClientPacket,
 ) -> Result<(), AppError> {
+    let (player_id, pool) = {
+        let conn = ctx.lock().await;
+        (
+            conn.player_id.ok_or(AppError::NotLoggedIn)?,
+            conn.state.db.clone(),
+        )
+    };
+
+    // The stored row is rewritten after every round, so it always holds the last
+    // round the client saw even when the fight is still in memory.
+    let Some((battle, last_round)) = restore_active_battle(&pool, player_id).await? else {
+        let mut conn = ctx.lock().await;
+        conn.send_empty_reply(CmdId::ReconnectFightCmd, Vec::new(), 0, req.up_tag)
+            .await?;
+        return Ok(());
+    };
+
+    tracing::info!(
+        "Reconnecting user {} to fight {:?} (episode {}, round {})",
+        player_id,
+        battle.fight_id,
+        battle.episode_id,
+        battle.current_round
+    );
+
+    let reply = ReconnectFightReply {
+        fight: battle.fight.clone(),
+        last_round: Some(last_round),
+        fight_reason: None,
+        fight_group: battle.fight_group.clone(),
+    };
+
     let mut conn = ctx.lock().await;
-    conn.send_empty_reply(CmdId::ReconnectFightCmd, Vec::new(), 0, req.up_tag)
+    if conn.active_battle.is_none() {
+        conn.active_battle = Some(battle);
+    }
+    conn.send_reply(CmdId::ReconnectFightCmd, reply, 0, req.up_tag)
         .await?;
+
     Ok(())
 }
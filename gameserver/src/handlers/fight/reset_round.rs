@@ -0,0 +1,55 @@
+use crate::error::AppError;
+use crate::network::packet::ClientPacket;
+use crate::state::{ConnectionContext, persist_active_battle};
+use database::db::game::battle::delete_round_operations_from;
+use sonettobuf::{CmdId, ResetRoundReply};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+pub async fn on_reset_round(
+    ctx: Arc<Mutex<ConnectionContext>>,
+    req: ClientPacket,
+) -> Result<(), AppError> {
+    let mut conn = ctx.lock().await;
+    let player_id = conn.player_id.ok_or(AppError::NotLoggedIn)?;
+    let pool = conn.state.db.clone();
+
+    let battle = conn
+        .active_battle
+        .as_mut()
+        .ok_or(AppError::InvalidRequest)?;
+
+    let round = battle.reset_round().ok_or(AppError::InvalidRequest)?;
+
+    tracing::info!(
+        "ResetRound: fight {:?} back to start of round {}",
+        battle.fight_id,
+        battle.current_round
+    );
+
+    // The oper log must end where the fight now stands for replay verification
+    if battle.is_replay != Some(true) {
+        delete_round_operations_from(
+            &pool,
+            player_id,
+            battle.episode_id,
+            battle.fight_id.unwrap_or_default(),
+            battle.current_round,
+        )
+        .await?;
+    }
+
+    // A reconnect has to resume from the reset state, not the undone key skills
+    persist_active_battle(&pool, player_id, battle, &round).await?;
+
+    let reply = ResetRoundReply {
+        fight: battle.fight.clone(),
+        round: Some(round),
+        cards: battle.current_deck.clone(),
+    };
+
+    conn.send_reply(CmdId::ResetRoundCmd, reply, 0, req.up_tag)
+        .await?;
+
+    Ok(())
+}
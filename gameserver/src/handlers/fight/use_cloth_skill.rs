@@ -0,0 +1,105 @@
+use crate::error::AppError;
+use crate::handlers::dungeon::settle_fight;
+use crate::network::packet::ClientPacket;
+use crate::state::{ConnectionContext, persist_active_battle, use_cloth_skill};
+use database::db::game::battle::save_round_operations;
+use prost::Message;
+use sonettobuf::{
+    CmdId, FightRound, UseClothSkillOperRecord, UseClothSkillReply, UseClothSkillRequest,
+};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+pub async fn on_use_cloth_skill(
+    ctx: Arc<Mutex<ConnectionContext>>,
+    req: ClientPacket,
+) -> Result<(), AppError> {
+    let request = UseClothSkillRequest::decode(&req.data[..])?;
+
+    tracing::info!(
+        "UseClothSkill: skill={:?}, from={:?}, to={:?}",
+        request.skill_id,
+        request.from_id,
+        request.to_id
+    );
+
+    let oper = UseClothSkillOperRecord {
+        skill_id: request.skill_id,
+        from_id: request.from_id,
+        to_id: request.to_id,
+        r#type: request.r#type,
+    };
+
+    let mut conn = ctx.lock().await;
+    let player_id = conn.player_id.ok_or(AppError::NotLoggedIn)?;
+    let pool = conn.state.db.clone();
+
+    let battle = conn
+        .active_battle
+        .as_mut()
+        .ok_or(AppError::InvalidRequest)?;
+    let data = battle
+        .fight_data_mgr
+        .as_mut()
+        .ok_or(AppError::InvalidRequest)?;
+
+    let step = use_cloth_skill(data, &oper)?.ok_or(AppError::InvalidRequest)?;
+
+    // A key skill can land the last hit, or cost the last hero
+    let finished = data.fight_over().then(|| data.clone());
+
+    let fight = data.get_fight_owned();
+    let attacker = fight.attacker.clone().unwrap_or_default();
+
+    battle.power = attacker.power.unwrap_or(battle.power);
+    battle.fight = Some(fight);
+    battle.cloth_skill_opers.push(oper);
+
+    if finished.is_some() {
+        if !battle.is_replay.unwrap_or(false) {
+            // The ending round holds only key skills, the replay stops after them
+            save_round_operations(
+                &pool,
+                player_id,
+                battle.episode_id,
+                battle.fight_id.unwrap_or_default(),
+                battle.current_round,
+                battle.cloth_skill_opers.clone(),
+                vec![],
+            )
+            .await?;
+        }
+    } else {
+        // a resumed fight restarts from the round the client was last sent
+        let last_round = battle
+            .round_start
+            .as_ref()
+            .map(|start| start.round.clone())
+            .unwrap_or_default();
+        persist_active_battle(&pool, player_id, battle, &last_round).await?;
+    }
+
+    let record_round = battle.current_round;
+    let reply = UseClothSkillReply {
+        round: Some(FightRound {
+            fight_step: vec![step],
+            act_point: Some(battle.act_point),
+            is_finish: Some(finished.is_some()),
+            power: attacker.power,
+            skill_infos: attacker.skill_infos,
+            team_a_cards1: battle.current_deck.clone(),
+            cur_round: Some(record_round),
+            ..Default::default()
+        }),
+    };
+
+    conn.send_reply(CmdId::UseClothSkillCmd, reply, 0, req.up_tag)
+        .await?;
+    drop(conn);
+
+    if let Some(data) = finished {
+        settle_fight(ctx, &pool, player_id, &data, record_round).await?;
+    }
+
+    Ok(())
+}
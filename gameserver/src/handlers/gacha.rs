@@ -2,10 +2,13 @@ use crate::{
     error::AppError,
     network::packet::ClientPacket,
     state::{
-        BannerType, ConnectionContext, GachaResult, GachaState, build_gacha, grant_dupe_rewards,
+        BannerType, ConnectionContext, GachaResult, GachaState, GameEvent, build_gacha,
         load_gacha_state, save_gacha_state,
     },
-    util::{push, push::send_red_dot_push},
+    util::{
+        push::send_red_dot_push,
+        transaction::{MATERIAL_CURRENCY, MATERIAL_HERO, MATERIAL_ITEM, Transaction},
+    },
 };
 use config::configs;
 use database::{
@@ -16,7 +19,7 @@ use database::{
         },
         user::account::get_user_token,
     },
-    models::game::{currencies::UserCurrencyModel, heros::UserHeroModel, items::UserItemModel},
+    models::game::{currencies::UserCurrencyModel, items::UserItemModel, ledger::LedgerSource},
 };
 use prost::Message;
 use rand::thread_rng;
@@ -139,7 +142,6 @@ pub async fn on_summon(
         )
     };
 
-    let hero = UserHeroModel::new(user_id, db.clone());
     let item = UserItemModel::new(user_id, db.clone());
     let currency = UserCurrencyModel::new(user_id, db.clone());
 
@@ -248,20 +250,12 @@ pub async fn on_summon(
         }
     }
 
-    let needs_conversion = tickets_converted > 0;
-
-    if needs_conversion {
-        push::send_item_change_push(ctx.clone(), user_id, vec![140001], vec![], vec![]).await?;
-    }
-
-    {
-        for (item_id, amount) in &actual_cost_items {
-            item.remove_item_quantity(*item_id, *amount).await?;
-        }
-
-        for (currency_id, amount) in &actual_cost_currencies {
-            currency.remove_currency(*currency_id, *amount).await?;
-        }
+    if tickets_converted > 0 {
+        tracing::info!(
+            "User {} converts {} missing summon tickets to currency",
+            user_id,
+            tickets_converted
+        );
     }
 
     let pool_cfg = configs::get()
@@ -291,47 +285,38 @@ pub async fn on_summon(
         }
     };
 
+    let mut tx = Transaction::new(user_id, LedgerSource::Gacha)
+        .reference(pool_id as i64)
+        .silent();
+    for (item_id, amount) in &actual_cost_items {
+        tx = tx.cost(MATERIAL_ITEM, *item_id, *amount);
+    }
+    for (currency_id, amount) in &actual_cost_currencies {
+        tx = tx.cost(MATERIAL_CURRENCY, *currency_id as u32, *amount);
+    }
+    for result in &gacha_results {
+        match result {
+            GachaResult::Hero { hero_id, .. } => {
+                tx = tx.grant(MATERIAL_HERO, *hero_id as u32, 1);
+            }
+        }
+    }
+
+    let changes = tx.commit(ctx.clone()).await?;
+
     let mut reply_results = Vec::with_capacity(gacha_results.len());
-    let mut all_changed_item_ids: Vec<u32> = Vec::new();
-    let mut all_changed_currencies = Vec::new();
-    let mut new_heroes = Vec::new();
 
-    for result in gacha_results {
+    for (result, gain) in gacha_results.into_iter().zip(&changes.heroes) {
         match result {
             GachaResult::Hero {
                 hero_id,
                 rare,
                 is_up,
             } => {
-                let (is_new, duplicate_count) = if hero.has_hero(hero_id).await? {
-                    let dup = hero.add_hero_duplicate(hero_id).await?;
-                    (false, dup)
-                } else {
-                    hero.create_hero(hero_id).await?;
-                    new_heroes.push(hero_id);
-                    (true, 0)
-                };
-
-                if !is_new && duplicate_count > 0 {
-                    let (item_rewards, currency_rewards) =
-                        grant_dupe_rewards(hero_id, duplicate_count).await?;
-
-                    if !item_rewards.is_empty() {
-                        let item_ids = item.create_items(&item_rewards).await?;
-                        all_changed_item_ids.extend(item_ids.iter().map(|id| *id as u32));
-                    }
-
-                    if !currency_rewards.is_empty() {
-                        let currency_changes =
-                            currency.create_currencies(&currency_rewards).await?;
-                        all_changed_currencies.extend(currency_changes);
-                    }
-                }
-
                 reply_results.push(SummonResult {
                     hero_id: Some(hero_id),
-                    is_new: Some(is_new),
-                    duplicate_count: Some(duplicate_count),
+                    is_new: Some(gain.is_new),
+                    duplicate_count: Some(gain.duplicate_count),
                     equip_id: Some(0),
                     return_materials: Vec::new(),
                     lucky_bag_id: Some(0),
@@ -344,7 +329,7 @@ pub async fn on_summon(
                     hero_id,
                     rare,
                     is_up,
-                    is_new
+                    gain.is_new
                 );
             }
         }
@@ -362,37 +347,6 @@ pub async fn on_summon(
         tracing::warn!("Failed to increment summon count: {}", e);
     }
 
-    all_changed_item_ids.extend(actual_cost_items.iter().map(|(id, _)| *id));
-
-    if !all_changed_item_ids.is_empty() {
-        push::send_item_change_push(ctx.clone(), user_id, all_changed_item_ids, vec![], vec![])
-            .await?;
-    }
-
-    if !all_changed_currencies.is_empty() || !actual_cost_currencies.is_empty() {
-        all_changed_currencies.extend(actual_cost_currencies.iter().map(|(id, _)| (*id, 0)));
-        push::send_currency_change_push(ctx.clone(), user_id, all_changed_currencies).await?;
-    }
-
-    if !new_heroes.is_empty() {
-        let mut hero_infos = Vec::new();
-        for hero_id in new_heroes {
-            if let Ok(hero) = hero.get_hero(hero_id).await {
-                hero_infos.push(hero.into());
-            }
-        }
-
-        if !hero_infos.is_empty() {
-            let hero_push = sonettobuf::HeroUpdatePush {
-                hero_updates: hero_infos,
-            };
-
-            let mut conn = ctx.lock().await;
-            conn.notify(CmdId::HeroHeroUpdatePushCmd, hero_push).await?;
-            drop(conn);
-        }
-    }
-
     let summon_type = if count == 10 { 2 } else { 1 };
 
     add_summon_history(
@@ -414,6 +368,7 @@ pub async fn on_summon(
         let mut ctx = ctx.lock().await;
         ctx.send_reply(CmdId::SummonCmd, reply, 0, req.up_tag)
             .await?;
+        ctx.emit(GameEvent::Summon { pool_id, count });
     }
 
     Ok(())
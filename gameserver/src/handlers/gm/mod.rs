@@ -1,9 +1,11 @@
 use crate::error::AppError;
-use crate::state::ConnectionContext;
-use crate::util::inventory::{add_currencies, add_items};
-use crate::util::push;
-use database::db::{game, user};
+use crate::state::{ConnectionContext, broadcast_mail, render_template, send_mail};
+use crate::util::transaction::{
+    MATERIAL_CURRENCY, MATERIAL_EQUIP, MATERIAL_HERO, MATERIAL_ITEM, Transaction,
+};
+use database::db::{game::ledger, user};
 use database::models::game::heros::UserHeroModel;
+use database::models::game::ledger::LedgerSource;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
@@ -45,6 +47,9 @@ pub async fn execute_command(
         "/level" => cmd_level(cmd_ctx).await,
         "/hero" => cmd_hero(cmd_ctx).await,
         "/equip" => cmd_equip(cmd_ctx).await,
+        "/ledger" => cmd_ledger(cmd_ctx).await,
+        "/mail" => cmd_mail(cmd_ctx).await,
+        "/mailall" => cmd_mailall(cmd_ctx).await,
         _ => Ok(format!("Unknown command: {}", cmd)),
     }
 }
@@ -56,7 +61,10 @@ fn get_help() -> String {
 /currency <id> <amount> - Add currency
 /level <level> - Set player level
 /hero <id> - Add hero
-/equip <id> <amount> - Add equipment"#
+/equip <id> <amount> - Add equipment
+/ledger [count] [source] - Show your latest economy ledger entries
+/mail <template> [params...] - Send yourself a template mail
+/mailall <template> <hours> [params...] - Send a template mail to everyone, new accounts get it for <hours> (admins only)"#
         .to_string()
 }
 
@@ -80,16 +88,11 @@ async fn cmd_item(ctx: CommandContext) -> Result<String, AppError> {
         return Ok(format!("Invalid item ID: {}", item_id));
     }
 
-    let db = ctx.ctx.lock().await.state.db.clone();
-
-    add_items(&db, ctx.user_id, &[(item_id, amount)]).await?;
-
-    push::send_item_change_push(ctx.ctx.clone(), ctx.user_id, vec![item_id], vec![], vec![])
+    Transaction::new(ctx.user_id, LedgerSource::Gm)
+        .grant(MATERIAL_ITEM, item_id, amount)
+        .commit(ctx.ctx.clone())
         .await?;
 
-    let material_changes = vec![(1, item_id, amount)];
-    push::send_material_change_push(ctx.ctx.clone(), material_changes, None).await?;
-
     Ok(format!("Added {} of item {}", amount, item_id))
 }
 
@@ -108,16 +111,11 @@ async fn cmd_currency(ctx: CommandContext) -> Result<String, AppError> {
         Err(_) => return Ok(format!("Invalid amount: {}", ctx.args[1])),
     };
 
-    let db = ctx.ctx.lock().await.state.db.clone();
-
-    add_currencies(&db, ctx.user_id, &[(currency_id, amount)]).await?;
-
-    push::send_currency_change_push(ctx.ctx.clone(), ctx.user_id, vec![(currency_id, amount)])
+    Transaction::new(ctx.user_id, LedgerSource::Gm)
+        .grant(MATERIAL_CURRENCY, currency_id as u32, amount)
+        .commit(ctx.ctx.clone())
         .await?;
 
-    let material_changes = vec![(2, currency_id as u32, amount)];
-    push::send_material_change_push(ctx.ctx.clone(), material_changes, None).await?;
-
     Ok(format!("Added {} of currency {}", amount, currency_id))
 }
 
@@ -165,7 +163,10 @@ async fn cmd_hero(ctx: CommandContext) -> Result<String, AppError> {
         return Ok(format!("You already have hero {}", hero_id));
     }
 
-    hero.create_hero(hero_id).await?;
+    Transaction::new(ctx.user_id, LedgerSource::Gm)
+        .grant(MATERIAL_HERO, hero_id as u32, 1)
+        .commit(ctx.ctx.clone())
+        .await?;
 
     Ok(format!("Added hero {}", hero_id))
 }
@@ -190,19 +191,117 @@ async fn cmd_equip(ctx: CommandContext) -> Result<String, AppError> {
         return Ok(format!("Invalid equipment ID: {}", equip_id));
     }
 
+    Transaction::new(ctx.user_id, LedgerSource::Gm)
+        .grant(MATERIAL_EQUIP, equip_id as u32, amount)
+        .commit(ctx.ctx.clone())
+        .await?;
+
+    Ok(format!("Added {} of equipment {}", amount, equip_id))
+}
+
+async fn cmd_ledger(ctx: CommandContext) -> Result<String, AppError> {
+    let limit: i64 = match ctx.args.first().map(|a| a.parse()) {
+        None => 10,
+        Some(Ok(n)) if (1..=50).contains(&n) => n,
+        Some(_) => return Ok("Usage: /ledger [count 1-50] [source]".to_string()),
+    };
+
+    let source = match ctx.args.get(1).map(|a| a.parse::<LedgerSource>()) {
+        None => None,
+        Some(Ok(source)) => Some(source),
+        Some(Err(e)) => return Ok(e),
+    };
+
     let db = ctx.ctx.lock().await.state.db.clone();
 
-    let equip_uids: Vec<i64> =
-        if equip_id == 1002 || equip_id == 1003 || equip_id == 1004 || equip_id == 1005 {
-            game::equipment::update_equipment_count(&db, ctx.user_id, equip_id, amount).await?
-        } else {
-            game::equipment::add_equipments(&db, ctx.user_id, &[(equip_id, amount)]).await?
-        };
+    let filter = ledger::LedgerFilter {
+        user_id: Some(ctx.user_id),
+        source,
+        limit,
+        ..Default::default()
+    };
+    let entries = ledger::query_ledger(&db, &filter).await?;
 
-    push::send_equip_update_push_by_uid(ctx.ctx.clone(), ctx.user_id, &equip_uids).await?;
+    if entries.is_empty() {
+        return Ok("No ledger entries".to_string());
+    }
 
-    let material_changes = vec![(9, equip_id as u32, amount)];
-    push::send_material_change_push(ctx.ctx.clone(), material_changes, None).await?;
+    let lines: Vec<String> = entries
+        .iter()
+        .map(|e| {
+            format!(
+                "{}#{} {:+} -> {} ({}{})",
+                e.resource_type,
+                e.resource_id,
+                e.delta,
+                e.balance_after,
+                e.source,
+                e.reference_id
+                    .map(|r| format!(" {}", r))
+                    .unwrap_or_default()
+            )
+        })
+        .collect();
+
+    Ok(lines.join("\n"))
+}
 
-    Ok(format!("Added {} of equipment {}", amount, equip_id))
+async fn cmd_mail(ctx: CommandContext) -> Result<String, AppError> {
+    if ctx.args.is_empty() {
+        return Ok("Usage: /mail <template> [params...]".to_string());
+    }
+
+    let template_id: i32 = match ctx.args[0].parse() {
+        Ok(id) => id,
+        Err(_) => return Ok(format!("Invalid template ID: {}", ctx.args[0])),
+    };
+
+    let state = ctx.ctx.lock().await.state.clone();
+
+    let Some(mail) = render_template(&state, template_id, &ctx.args[1..]).await? else {
+        return Ok(format!("Mail template {} not found", template_id));
+    };
+
+    let incr_id = send_mail(&state, ctx.user_id, &mail).await?;
+
+    Ok(format!(
+        "Sent mail {} from template {}",
+        incr_id, template_id
+    ))
+}
+
+async fn cmd_mailall(ctx: CommandContext) -> Result<String, AppError> {
+    if !common::is_admin(ctx.user_id) {
+        return Ok("/mailall is limited to the [gameplay] admins".to_string());
+    }
+
+    if ctx.args.len() < 2 {
+        return Ok("Usage: /mailall <template> <hours> [params...]".to_string());
+    }
+
+    let template_id: i32 = match ctx.args[0].parse() {
+        Ok(id) => id,
+        Err(_) => return Ok(format!("Invalid template ID: {}", ctx.args[0])),
+    };
+
+    let (hours, open_ms) = match ctx.args[1].parse::<i64>() {
+        Ok(h) if h >= 0 => match h.checked_mul(3_600_000) {
+            Some(ms) => (h, ms),
+            None => return Ok(format!("Invalid hours: {}", ctx.args[1])),
+        },
+        _ => return Ok(format!("Invalid hours: {}", ctx.args[1])),
+    };
+
+    let state = ctx.ctx.lock().await.state.clone();
+
+    let Some(mail) = render_template(&state, template_id, &ctx.args[2..]).await? else {
+        return Ok(format!("Mail template {} not found", template_id));
+    };
+
+    let count = broadcast_mail(&state, &mail, open_ms).await?;
+
+    Ok(format!(
+        "Broadcast template {} to {} players, open for {} hours",
+        template_id, count, hours
+    ))
 }
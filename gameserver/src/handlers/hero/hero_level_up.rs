@@ -1,11 +1,10 @@
 use crate::error::AppError;
 use crate::network::packet::ClientPacket;
-use crate::state::ConnectionContext;
-use crate::util::push;
-use database::models::game::{
-    currencies::UserCurrencyModel,
-    heros::{HeroModel, UserHeroModel},
-};
+use crate::state::{ConnectionContext, GameEvent};
+use crate::util::transaction::{MATERIAL_CURRENCY, Transaction};
+use database::db::game::materials;
+use database::models::game::heros::{HeroModel, UserHeroModel};
+use database::models::game::ledger::LedgerSource;
 use prost::Message;
 use sonettobuf::{CmdId, HeroLevelUpReply, HeroLevelUpRequest};
 use std::{collections::HashMap, sync::Arc};
@@ -29,7 +28,6 @@ pub async fn on_hero_level_up(
     };
 
     let hero = UserHeroModel::new(player_id, pool.clone());
-    let currency = UserCurrencyModel::new(player_id, pool.clone());
     let hero_data = hero.get(hero_id).await?;
 
     if expect_level == hero_data.record.level {
@@ -122,28 +120,41 @@ pub async fn on_hero_level_up(
         }
     }
 
+    let level_stats = game_data
+        .character_level
+        .iter()
+        .filter(|l| l.hero_id == hero_id && l.level <= expect_level)
+        .max_by_key(|l| l.level)
+        .ok_or_else(|| {
+            tracing::error!(
+                "No level stats found for hero {} up to level {}",
+                hero_id,
+                expect_level
+            );
+            AppError::InvalidRequest
+        })?;
+
+    let mut tx = Transaction::new(player_id, LedgerSource::Hero)
+        .reference(hero_id as i64)
+        .silent();
     for (currency_id, amount) in &total_costs {
-        let current = currency
-            .get_currency(*currency_id)
-            .await?
-            .map(|c| c.quantity)
-            .unwrap_or(0);
+        tx = tx.cost(MATERIAL_CURRENCY, *currency_id as u32, *amount);
+    }
 
-        if current < *amount {
+    let mut applied = match tx.apply(ctx.clone()).await {
+        Ok(applied) => applied,
+        Err(AppError::InsufficientCurrency) => {
             tracing::info!(
-                "User {} insufficient currency {} for level up (has {}, needs {})",
+                "User {} can't pay level up of hero {} to {} (costs: {:?})",
                 player_id,
-                currency_id,
-                current,
-                amount
+                hero_id,
+                expect_level,
+                total_costs
             );
 
-            push::send_currency_change_push(ctx.clone(), player_id, vec![(*currency_id, 0)])
-                .await?;
-
             let data = HeroLevelUpReply {
                 hero_id: Some(hero_id),
-                new_level: Some(hero_data.record.level),
+                new_level: Some(old_level),
             };
 
             let mut conn = ctx.lock().await;
@@ -152,27 +163,18 @@ pub async fn on_hero_level_up(
 
             return Ok(());
         }
-    }
-
-    for (currency_id, amount) in &total_costs {
-        currency.remove_currency(*currency_id, *amount).await?;
-    }
-
-    let level_stats = game_data
-        .character_level
-        .iter()
-        .filter(|l| l.hero_id == hero_id && l.level <= expect_level)
-        .max_by_key(|l| l.level)
-        .ok_or_else(|| {
-            tracing::error!(
-                "No level stats found for hero {} up to level {}",
-                hero_id,
-                expect_level
-            );
-            AppError::InvalidRequest
-        })?;
+        Err(e) => return Err(e),
+    };
 
-    hero.level_up(hero_id, expect_level, level_stats).await?;
+    materials::set_hero_level(
+        &mut applied.db,
+        player_id,
+        hero_id,
+        expect_level,
+        level_stats,
+    )
+    .await?;
+    applied.commit(ctx.clone()).await?;
 
     tracing::info!(
         "User {} leveled hero {} from {} to {} using stats from milestone level {} (costs: {:?})",
@@ -184,17 +186,6 @@ pub async fn on_hero_level_up(
         total_costs
     );
 
-    let consumed: Vec<(i32, i32)> = total_costs.into_iter().collect();
-
-    if !consumed.is_empty() {
-        push::send_currency_change_push(
-            ctx.clone(),
-            player_id,
-            consumed.iter().map(|(id, _)| (*id, 0)).collect(),
-        )
-        .await?;
-    }
-
     let reply = HeroLevelUpReply {
         hero_id: Some(hero_id),
         new_level: Some(expect_level),
@@ -226,6 +217,12 @@ pub async fn on_hero_level_up(
         conn.send_reply(CmdId::HeroLevelUpCmd, reply, 0, req.up_tag)
             .await?;
 
+        conn.emit(GameEvent::HeroLevelUp {
+            hero_id,
+            old_level,
+            new_level: expect_level,
+        });
+
         tracing::info!(
             "Sent HeroLevelUpUpdatePush and HeroUpdatePush for hero {} to level {}",
             hero_id,
@@ -1,11 +1,10 @@
 use crate::error::AppError;
 use crate::network::packet::ClientPacket;
-use crate::state::ConnectionContext;
-use crate::util::push;
-use database::{
-    models::game::heros::{HeroModel, UserHeroModel},
-    models::game::{currencies::UserCurrencyModel, items::UserItemModel},
-};
+use crate::state::{ConnectionContext, GameEvent};
+use crate::util::transaction::{MATERIAL_CURRENCY, MATERIAL_ITEM, MATERIAL_SKIN, Transaction};
+use database::db::game::materials;
+use database::models::game::heros::{HeroModel, UserHeroModel};
+use database::models::game::ledger::LedgerSource;
 use prost::Message;
 use sonettobuf::{CmdId, HeroRankUpReply, HeroRankUpRequest, HeroUpdatePush};
 use std::sync::Arc;
@@ -28,11 +27,9 @@ pub async fn on_hero_rank_up(
         (player_id, pool.clone())
     };
 
-    let hero = UserHeroModel::new(player_id, pool.clone());
-    let item = UserItemModel::new(player_id, pool.clone());
-    let currency = UserCurrencyModel::new(player_id, pool.clone());
+    let hero = UserHeroModel::new(player_id, pool);
 
-    let (user_id, new_rank, consumed_items, consumed_currencies) = {
+    let new_rank = {
         let conn = ctx.lock().await;
 
         let hero_data = hero.get(hero_id).await?;
@@ -151,62 +148,30 @@ pub async fn on_hero_rank_up(
             }
         }
 
-        for (item_id, amount) in &cost_items {
-            let current = item
-                .get_item(*item_id)
-                .await?
-                .map(|i| i.quantity)
-                .unwrap_or(0);
-
-            if current < *amount {
-                tracing::info!(
-                    "User {} insufficient item {} (has {}, needs {})",
-                    player_id,
-                    item_id,
-                    current,
-                    amount
-                );
-
-                drop(conn);
-                push::send_item_change_push(ctx.clone(), player_id, vec![*item_id], vec![], vec![])
-                    .await?;
+        drop(conn);
 
-                let mut conn = ctx.lock().await;
-                conn.send_reply(
-                    CmdId::HeroRankUpCmd,
-                    HeroRankUpReply {
-                        hero_id: Some(hero_id),
-                        new_rank: Some(current_rank),
-                    },
-                    0,
-                    req.up_tag,
-                )
-                .await?;
-
-                return Ok(());
-            }
+        let mut tx = Transaction::new(player_id, LedgerSource::Hero)
+            .reference(hero_id as i64)
+            .silent();
+        for (item_id, amount) in &cost_items {
+            tx = tx.cost(MATERIAL_ITEM, *item_id, *amount);
         }
-
         for (currency_id, amount) in &cost_currencies {
-            let current = currency
-                .get_currency(*currency_id)
-                .await?
-                .map(|c| c.quantity)
-                .unwrap_or(0);
-
-            if current < *amount {
-                tracing::info!(
-                    "User {} insufficient currency {} (has {}, needs {})",
-                    player_id,
-                    currency_id,
-                    current,
-                    amount
-                );
+            tx = tx.cost(MATERIAL_CURRENCY, *currency_id as u32, *amount);
+        }
 
-                drop(conn);
-                push::send_currency_change_push(ctx.clone(), player_id, vec![(*currency_id, 0)])
-                    .await?;
+        let insight_skin = if target_rank >= 3 {
+            materials::insight_skin_id(hero_id)
+        } else {
+            None
+        };
+        if let Some(skin_id) = insight_skin {
+            tx = tx.grant(MATERIAL_SKIN, skin_id as u32, 1);
+        }
 
+        let mut applied = match tx.apply(ctx.clone()).await {
+            Ok(applied) => applied,
+            Err(AppError::InsufficientItems | AppError::InsufficientCurrency) => {
                 let mut conn = ctx.lock().await;
                 conn.send_reply(
                     CmdId::HeroRankUpCmd,
@@ -221,17 +186,30 @@ pub async fn on_hero_rank_up(
 
                 return Ok(());
             }
-        }
+            Err(e) => return Err(e),
+        };
 
-        for (item_id, amount) in &cost_items {
-            item.remove_item_quantity(*item_id, *amount).await?;
+        sqlx::query("UPDATE heroes SET rank = ?, level = 1 WHERE uid = ? AND user_id = ?")
+            .bind(target_rank)
+            .bind(hero_data.record.uid)
+            .bind(player_id)
+            .execute(&mut *applied.db)
+            .await?;
+
+        if let Some(skin_id) = insight_skin
+            && applied.changes().skins.contains(&skin_id)
+        {
+            materials::wear_skin(&mut applied.db, player_id, hero_id, skin_id).await?;
+            tracing::info!(
+                "User {} unlocked and equipped Insight II skin {} for hero {}",
+                player_id,
+                skin_id,
+                hero_id
+            );
         }
 
-        for (currency_id, amount) in &cost_currencies {
-            currency.remove_currency(*currency_id, *amount).await?;
-        }
-
-        hero.rank_up(hero_id, target_rank).await?;
+        applied.touch_hero(hero_id);
+        applied.commit(ctx.clone()).await?;
 
         tracing::info!(
             "User {} ranked up hero {} from rank {} to {} (level reset to 1)",
@@ -241,44 +219,11 @@ pub async fn on_hero_rank_up(
             target_rank
         );
 
-        hero.unlock_insight_skin(hero_id, target_rank).await?;
-
-        (player_id, target_rank, cost_items, cost_currencies)
+        target_rank
     };
 
-    if !consumed_items.is_empty() {
-        push::send_item_change_push(
-            ctx.clone(),
-            user_id,
-            consumed_items.iter().map(|(id, _)| *id).collect(),
-            vec![],
-            vec![],
-        )
-        .await?;
-    }
-
-    if !consumed_currencies.is_empty() {
-        push::send_currency_change_push(
-            ctx.clone(),
-            user_id,
-            consumed_currencies.iter().map(|(id, _)| (*id, 0)).collect(),
-        )
-        .await?;
-    }
-
     {
         let mut conn = ctx.lock().await;
-        let updated_hero_data = hero.get(hero_id).await?;
-        let updated_hero_info: sonettobuf::HeroInfo = updated_hero_data.into();
-
-        conn.notify(
-            CmdId::HeroHeroUpdatePushCmd,
-            HeroUpdatePush {
-                hero_updates: vec![updated_hero_info.into()],
-            },
-        )
-        .await?;
-
         conn.send_reply(
             CmdId::HeroRankUpCmd,
             HeroRankUpReply {
@@ -289,6 +234,8 @@ pub async fn on_hero_rank_up(
             req.up_tag,
         )
         .await?;
+
+        conn.emit(GameEvent::HeroRankUp { hero_id, new_rank });
     }
 
     tracing::info!("Hero {} ranked up to {}", hero_id, new_rank);
@@ -1,16 +1,18 @@
 use crate::error::AppError;
 use crate::network::packet::ClientPacket;
 use crate::state::ConnectionContext;
-
-use database::models::game::{
-    heros::{HeroModel, UserHeroModel},
-    items::UserItemModel,
-};
+use crate::util::transaction::{MATERIAL_ITEM, Transaction};
+use database::db::game::materials;
+use database::models::game::heros::{HeroModel, UserHeroModel};
+use database::models::game::ledger::LedgerSource;
 use prost::Message;
 use sonettobuf::{CmdId, HeroUpdatePush, HeroUpgradeSkillReply, HeroUpgradeSkillRequest};
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
+/// Portrait (ex skill) levels a hero can reach with duplicates
+const MAX_EX_SKILL_LEVEL: i32 = 5;
+
 pub async fn on_hero_upgrade_skill(
     ctx: Arc<Mutex<ConnectionContext>>,
     req: ClientPacket,
@@ -30,67 +32,72 @@ pub async fn on_hero_upgrade_skill(
     };
 
     let hero = UserHeroModel::new(player_id, pool.clone());
-    let item = UserItemModel::new(player_id, pool);
-
-    let consumed_item_id = {
-        let hero_data = hero.get(hero_id).await?;
-
-        // Check current skill level
-        if skill_type == 3 && hero_data.record.ex_skill_level >= 5 {
-            return Err(AppError::InvalidRequest);
-        }
-
-        let game_data = config::configs::get();
-        let character = game_data
-            .character
-            .iter()
-            .find(|c| c.id == hero_id)
-            .ok_or(AppError::InvalidRequest)?;
-
-        // Parse duplicateItem: "1#133125#1|2#11#12"
-        let dupe_item_id = character
-            .duplicate_item
-            .split('|')
-            .next()
-            .and_then(|part| {
-                let segments: Vec<&str> = part.split('#').collect();
-                if segments.len() >= 3 && segments[0] == "1" {
-                    segments[1].parse::<i32>().ok()
-                } else {
-                    None
-                }
-            })
-            .ok_or(AppError::InvalidRequest)?;
-
-        let success = item
-            .remove_item_quantity(dupe_item_id as u32, consume)
-            .await?;
-        if !success {
-            return Err(AppError::InsufficientItems);
-        }
-
-        if skill_type == 3 {
-            hero.upgrade_ex_skill(hero_id, consume).await?;
-
-            tracing::info!(
-                "User {} upgraded ex_skill by {} levels on hero {}",
-                player_id,
-                consume,
-                hero_id
-            );
-        }
-
-        dupe_item_id
-    };
+    let hero_data = hero.get(hero_id).await?;
 
-    crate::util::push::send_item_change_push(
-        ctx.clone(),
+    // Only the ex skill (type 3) is upgraded with duplicates
+    if skill_type != 3
+        || consume <= 0
+        || hero_data.record.ex_skill_level + consume > MAX_EX_SKILL_LEVEL
+    {
+        tracing::warn!(
+            "User {} can't upgrade skill type {} of hero {} by {} (ex skill level {})",
+            player_id,
+            skill_type,
+            hero_id,
+            consume,
+            hero_data.record.ex_skill_level
+        );
+        return Err(AppError::InvalidRequest);
+    }
+
+    let game_data = config::configs::get();
+    let character = game_data
+        .character
+        .iter()
+        .find(|c| c.id == hero_id)
+        .ok_or(AppError::InvalidRequest)?;
+
+    // Parse duplicateItem: "1#133125#1|2#11#12"
+    let dupe_item_id = character
+        .duplicate_item
+        .split('|')
+        .next()
+        .and_then(|part| {
+            let segments: Vec<&str> = part.split('#').collect();
+            if segments.len() >= 3 && segments[0] == "1" {
+                segments[1].parse::<u32>().ok()
+            } else {
+                None
+            }
+        })
+        .ok_or(AppError::InvalidRequest)?;
+
+    let mut applied = Transaction::new(player_id, LedgerSource::Hero)
+        .reference(hero_id as i64)
+        .cost(MATERIAL_ITEM, dupe_item_id, consume)
+        .silent()
+        .apply(ctx.clone())
+        .await?;
+
+    if !materials::add_hero_ex_skill(
+        &mut applied.db,
         player_id,
-        vec![consumed_item_id as u32],
-        vec![],
-        vec![],
+        hero_id,
+        consume,
+        MAX_EX_SKILL_LEVEL,
     )
-    .await?;
+    .await?
+    {
+        return Err(AppError::InvalidRequest);
+    }
+    applied.commit(ctx.clone()).await?;
+
+    tracing::info!(
+        "User {} upgraded ex_skill by {} levels on hero {}",
+        player_id,
+        consume,
+        hero_id
+    );
 
     {
         let conn = ctx.lock().await;
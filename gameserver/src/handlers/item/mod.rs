@@ -2,10 +2,14 @@ mod auto_use_expire_power_item;
 mod get_item_list;
 mod use_insight_item;
 mod use_item;
+mod use_power_item;
+mod use_power_item_list;
 mod util;
 
 pub use auto_use_expire_power_item::on_auto_use_expire_power_item;
 pub use get_item_list::on_get_item_list;
 pub use use_insight_item::on_use_insight_item;
 pub use use_item::on_use_item;
-pub use util::{apply_insight_item, process_item_use};
+pub use use_power_item::on_use_power_item;
+pub use use_power_item_list::on_use_power_item_list;
+pub use util::{apply_insight_item, apply_power_items, process_item_use};
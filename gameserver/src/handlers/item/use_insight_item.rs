@@ -1,6 +1,9 @@
 use crate::{
-    error::AppError, handlers::item::apply_insight_item, network::packet::ClientPacket,
-    state::ConnectionContext, util::push::send_item_change_push,
+    error::AppError,
+    handlers::item::apply_insight_item,
+    network::packet::ClientPacket,
+    state::{ConnectionContext, GameEvent},
+    util::push::send_item_change_push,
 };
 use database::models::game::heros::UserHeroModel;
 use prost::Message;
@@ -28,6 +31,7 @@ pub async fn on_use_insight_item(
 
     let hero = UserHeroModel::new(player_id, pool.clone());
 
+    let old_rank = hero.get_hero(hero_id).await.map(|h| h.record.rank).ok();
     let item_id = apply_insight_item(&pool, player_id, uid, hero_id).await?;
 
     send_item_change_push(ctx.clone(), player_id, vec![], vec![], vec![item_id as u32]).await?;
@@ -46,8 +50,17 @@ pub async fn on_use_insight_item(
         .await?;
     }
 
+    ctx.lock()
+        .await
+        .emit(GameEvent::ItemUsed { item_id, count: 1 });
+
     if let Ok(hero) = hero.get_hero(hero_id).await {
         let mut conn = ctx.lock().await;
+        let new_rank = hero.record.rank;
+        if old_rank.is_some_and(|rank| rank < new_rank) {
+            conn.emit(GameEvent::HeroRankUp { hero_id, new_rank });
+        }
+
         conn.notify(
             CmdId::HeroHeroUpdatePushCmd,
             sonettobuf::HeroUpdatePush {
@@ -0,0 +1,31 @@
+use crate::{
+    error::AppError, handlers::item::apply_power_items, network::packet::ClientPacket,
+    state::ConnectionContext,
+};
+use prost::Message;
+use sonettobuf::{CmdId, UsePowerItemReply, UsePowerItemRequest};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+pub async fn on_use_power_item(
+    ctx: Arc<Mutex<ConnectionContext>>,
+    req: ClientPacket,
+) -> Result<(), AppError> {
+    let request = UsePowerItemRequest::decode(&req.data[..])?;
+    tracing::info!("Received UsePowerItemRequest: {:?}", request);
+
+    let uid = request.uid.ok_or(AppError::InvalidRequest)?;
+
+    apply_power_items(ctx.clone(), &[(uid, 1)]).await?;
+
+    let mut conn = ctx.lock().await;
+    conn.send_reply(
+        CmdId::UsePowerItemCmd,
+        UsePowerItemReply { uid: Some(uid) },
+        0,
+        req.up_tag,
+    )
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,46 @@
+use crate::{
+    error::AppError, handlers::item::apply_power_items, network::packet::ClientPacket,
+    state::ConnectionContext,
+};
+use prost::Message;
+use sonettobuf::{CmdId, UsePowerItemListReply, UsePowerItemListRequest};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+pub async fn on_use_power_item_list(
+    ctx: Arc<Mutex<ConnectionContext>>,
+    req: ClientPacket,
+) -> Result<(), AppError> {
+    let request = UsePowerItemListRequest::decode(&req.data[..])?;
+    tracing::info!("Received UsePowerItemListRequest: {:?}", request);
+
+    if request.use_power_item_info.is_empty() {
+        return Err(AppError::InvalidRequest);
+    }
+
+    let uses = request
+        .use_power_item_info
+        .iter()
+        .map(|info| {
+            Ok((
+                info.uid.ok_or(AppError::InvalidRequest)?,
+                info.num.unwrap_or(1),
+            ))
+        })
+        .collect::<Result<Vec<_>, AppError>>()?;
+
+    apply_power_items(ctx.clone(), &uses).await?;
+
+    let mut conn = ctx.lock().await;
+    conn.send_reply(
+        CmdId::UsePowerItemListCmd,
+        UsePowerItemListReply {
+            use_power_item_info: request.use_power_item_info,
+        },
+        0,
+        req.up_tag,
+    )
+    .await?;
+
+    Ok(())
+}
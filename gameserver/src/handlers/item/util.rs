@@ -1,18 +1,18 @@
 use std::sync::Arc;
 
 use chrono::Datelike;
-use database::models::game::heros::UserHeroModel;
+use database::db::game::materials;
+use database::models::game::ledger::{LedgerOrigin, LedgerSource};
 use rand::{seq::SliceRandom, thread_rng};
 use sqlx::SqlitePool;
 use tokio::sync::Mutex;
 
 use crate::{
     error::AppError,
-    state::{ConnectionContext, get_rewards, parse_item},
-    util::{
-        inventory::{add_currencies, add_items},
-        push::{self, send_red_dot_push},
+    state::{
+        ConnectionContext, GameEvent, get_rewards, parse_item, parse_reward_string, use_power_items,
     },
+    util::{push::send_red_dot_push, transaction::Transaction},
 };
 
 pub fn process_item_use(
@@ -121,10 +121,6 @@ pub async fn apply_insight_item(
         .find(|i| i.id == item_id)
         .ok_or(AppError::InvalidRequest)?;
 
-    let hero = UserHeroModel::new(player_id, pool.clone());
-
-    let hero_data = hero.get_hero(hero_id).await?;
-
     let target_rank = insight_data.hero_rank + 1;
     let target_level = insight_data
         .effect
@@ -133,75 +129,66 @@ pub async fn apply_insight_item(
         .and_then(|v| v.parse::<i32>().ok())
         .unwrap_or(1);
 
-    sqlx::query("UPDATE heroes SET rank = ?, level = ? WHERE user_id = ? AND hero_id = ?")
-        .bind(target_rank)
-        .bind(target_level)
-        .bind(player_id)
-        .bind(hero_id)
-        .execute(pool)
-        .await?;
+    let mut tx = pool.begin().await?;
 
-    if target_rank >= 3 {
-        unlock_insight_skin(pool, player_id, hero_id, hero_data.record.uid).await?;
+    let ranked =
+        sqlx::query("UPDATE heroes SET rank = ?, level = ? WHERE user_id = ? AND hero_id = ?")
+            .bind(target_rank)
+            .bind(target_level)
+            .bind(player_id)
+            .bind(hero_id)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+    if ranked == 0 {
+        return Err(AppError::HeroNotFound);
     }
 
-    sqlx::query(
-        "UPDATE insight_items
-         SET quantity = quantity - 1
-         WHERE uid = ? AND user_id = ?",
-    )
-    .bind(uid)
-    .bind(player_id)
-    .execute(pool)
-    .await?;
+    if target_rank >= 3
+        && let Some(skin_id) = materials::insight_skin_id(hero_id)
+        && materials::give_skin(&mut tx, player_id, skin_id).await?
+    {
+        materials::wear_skin(&mut tx, player_id, hero_id, skin_id).await?;
+    }
+
+    let origin = LedgerOrigin::with_reference(LedgerSource::ItemUse, hero_id as i64);
+    if materials::take_insight_item(&mut tx, player_id, uid, origin)
+        .await?
+        .is_none()
+    {
+        return Err(AppError::InvalidRequest);
+    }
+
+    tx.commit().await?;
 
     Ok(item_id)
 }
 
-async fn unlock_insight_skin(
-    pool: &SqlitePool,
-    player_id: i64,
-    hero_id: i32,
-    hero_uid: i64,
+/// Uses power items, `(uid, num)` per stack, and converts them into stamina
+pub async fn apply_power_items(
+    ctx: Arc<Mutex<ConnectionContext>>,
+    uses: &[(i64, i32)],
 ) -> Result<(), AppError> {
-    let game_data = config::configs::get();
-    let Some(skin) = game_data
-        .skin
-        .iter()
-        .find(|s| s.character_id == hero_id && s.id % 100 == 2 && s.gain_approach == 1)
-    else {
-        return Ok(());
-    };
+    let used = use_power_items(ctx.clone(), uses).await?;
 
-    let has_skin: Option<i32> =
-        sqlx::query_scalar("SELECT 1 FROM hero_all_skins WHERE user_id = ? AND skin_id = ?")
-            .bind(player_id)
-            .bind(skin.id)
-            .fetch_optional(pool)
-            .await?;
+    let mut conn = ctx.lock().await;
+    let player_id = conn.player_id.ok_or(AppError::NotLoggedIn)?;
 
-    if has_skin.is_some() {
-        return Ok(());
-    }
-
-    sqlx::query("INSERT INTO hero_all_skins (user_id, skin_id) VALUES (?, ?)")
-        .bind(player_id)
-        .bind(skin.id)
-        .execute(pool)
-        .await?;
+    for (&(uid, num), &(item_id, gained)) in uses.iter().zip(&used) {
+        conn.emit(GameEvent::ItemUsed {
+            item_id: item_id as i32,
+            count: num,
+        });
 
-    sqlx::query("INSERT INTO hero_skins (hero_uid, skin, expire_sec) VALUES (?, ?, 0)")
-        .bind(hero_uid)
-        .bind(skin.id)
-        .execute(pool)
-        .await?;
-
-    sqlx::query("UPDATE heroes SET skin = ? WHERE uid = ? AND user_id = ?")
-        .bind(skin.id)
-        .bind(hero_uid)
-        .bind(player_id)
-        .execute(pool)
-        .await?;
+        tracing::info!(
+            "User {} used {} power item {} (uid: {}) for {} stamina",
+            player_id,
+            num,
+            item_id,
+            uid,
+            gained
+        );
+    }
 
     Ok(())
 }
@@ -297,46 +284,12 @@ pub async fn can_claim_month_card(
         }
     }
 
-    let (items, currencies, _, _, power_items, _) = crate::state::parse_store_product(&reward_str);
-
-    let mut material_changes = Vec::new();
-
-    if !items.is_empty() {
-        let ids = add_items(&pool, player_id, &items).await?;
-        for (id, amt) in items {
-            material_changes.push((1, id, amt));
-        }
-        push::send_item_change_push(ctx.clone(), player_id, ids, vec![], vec![]).await?;
-    }
-
-    if !currencies.is_empty() {
-        let ids = add_currencies(&pool, player_id, &currencies).await?;
-        for (id, amt) in currencies {
-            material_changes.push((2, id as u32, amt));
-        }
-        push::send_currency_change_push(ctx.clone(), player_id, ids).await?;
-    }
-
-    if !power_items.is_empty() {
-        database::db::game::items::add_power_items(
-            &pool,
-            player_id,
-            &power_items
-                .iter()
-                .map(|(id, count)| (*id as i32, *count))
-                .collect::<Vec<_>>(),
-        )
+    Transaction::new(player_id, LedgerSource::Charge)
+        .grants(&parse_reward_string(&reward_str, 1))
+        .approach(10)
+        .commit(ctx.clone())
         .await?;
 
-        for (id, amt) in power_items {
-            material_changes.push((10, id, amt));
-        }
-    }
-
-    if !material_changes.is_empty() {
-        push::send_material_change_push(ctx.clone(), material_changes, Some(10)).await?;
-    }
-
     {
         let mut conn = ctx.lock().await;
         conn.update_and_save_player_state(|state| {
@@ -0,0 +1,37 @@
+use crate::error::AppError;
+use crate::network::packet::ClientPacket;
+use crate::state::ConnectionContext;
+use database::db::game::mails;
+use prost::Message;
+use sonettobuf::{CmdId, DeleteMailBatchReply, DeleteMailBatchRequest};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+pub async fn on_delete_mail_batch(
+    ctx: Arc<Mutex<ConnectionContext>>,
+    req: ClientPacket,
+) -> Result<(), AppError> {
+    let request = DeleteMailBatchRequest::decode(&req.data[..])?;
+    tracing::info!("Received DeleteMailBatchRequest: {:?}", request);
+
+    let (player_id, pool) = {
+        let conn = ctx.lock().await;
+        let player_id = conn.player_id.ok_or(AppError::NotLoggedIn)?;
+        (player_id, conn.state.db.clone())
+    };
+
+    // Only read mails are deleted, mails with unclaimed attachments stay
+    let deleted = mails::delete_read_mails(&pool, player_id).await?;
+
+    tracing::info!("User {} deleted {} read mails", player_id, deleted.len());
+
+    let reply = DeleteMailBatchReply {
+        incr_ids: deleted.into_iter().map(|id| id as u64).collect(),
+    };
+
+    let mut conn = ctx.lock().await;
+    conn.send_reply(CmdId::DeleteMailBatchCmd, reply, 0, req.up_tag)
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,38 @@
+use crate::error::AppError;
+use crate::network::packet::ClientPacket;
+use crate::state::ConnectionContext;
+use database::db::game::mails;
+use prost::Message;
+use sonettobuf::{CmdId, MarkMailJumpReply, MarkMailJumpRequest};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+pub async fn on_mark_mail_jump(
+    ctx: Arc<Mutex<ConnectionContext>>,
+    req: ClientPacket,
+) -> Result<(), AppError> {
+    let request = MarkMailJumpRequest::decode(&req.data[..])?;
+    tracing::info!("Received MarkMailJumpRequest: {:?}", request);
+
+    let incr_id = request.incr_id.ok_or(AppError::InvalidRequest)?;
+
+    let (player_id, pool) = {
+        let conn = ctx.lock().await;
+        let player_id = conn.player_id.ok_or(AppError::NotLoggedIn)?;
+        (player_id, conn.state.db.clone())
+    };
+
+    if mails::mark_mail_read(&pool, player_id, incr_id as i64).await? {
+        tracing::info!("User {} followed the jump of mail {}", player_id, incr_id);
+    }
+
+    let reply = MarkMailJumpReply {
+        incr_id: Some(incr_id),
+    };
+
+    let mut conn = ctx.lock().await;
+    conn.send_reply(CmdId::MarkMailJumpCmd, reply, 0, req.up_tag)
+        .await?;
+
+    Ok(())
+}
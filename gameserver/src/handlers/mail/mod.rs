@@ -1,7 +1,14 @@
+mod delete_mail_batch;
 mod get_all_mails;
+mod mark_mail_jump;
 mod read_mail;
 mod read_mail_batch;
 
+pub use delete_mail_batch::on_delete_mail_batch;
 pub use get_all_mails::on_get_all_mails;
+pub use mark_mail_jump::on_mark_mail_jump;
 pub use read_mail::on_read_mail;
 pub use read_mail_batch::on_read_mail_batch;
+
+/// `getApproach` of mail attachments
+const MAIL_APPROACH: u32 = 10;
@@ -1,3 +1,5 @@
 mod get_player_card_info;
+mod set_player_card_show_achievement;
 
 pub use get_player_card_info::on_get_player_card_info;
+pub use set_player_card_show_achievement::on_set_player_card_show_achievement;
@@ -0,0 +1,83 @@
+use crate::error::AppError;
+use crate::network::packet::ClientPacket;
+use crate::state::ConnectionContext;
+use config::configs;
+use database::db::game::{achievements, player_card};
+use prost::Message;
+use sonettobuf::{CmdId, SetPlayerCardShowAchievementReply, SetPlayerCardShowAchievementRequest};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+pub async fn on_set_player_card_show_achievement(
+    ctx: Arc<Mutex<ConnectionContext>>,
+    req: ClientPacket,
+) -> Result<(), AppError> {
+    let request = SetPlayerCardShowAchievementRequest::decode(&req.data[..])?;
+    tracing::info!(
+        "Received SetPlayerCardShowAchievementRequest: {:?}",
+        request
+    );
+
+    let group_id = request.group_id.filter(|&g| g != 0);
+
+    {
+        let conn = ctx.lock().await;
+        let player_id = conn.player_id.ok_or(AppError::NotLoggedIn)?;
+        let pool = &conn.state.db;
+
+        let finished: Vec<i32> = achievements::get_achievements(pool, player_id)
+            .await?
+            .into_iter()
+            .filter(|a| a.has_finish)
+            .map(|a| a.achievement_id)
+            .collect();
+
+        if let Some(id) = request.ids.iter().find(|id| !finished.contains(id)) {
+            tracing::warn!(
+                "User {} tried to show unfinished achievement {}",
+                player_id,
+                id
+            );
+            return Err(AppError::InvalidRequest);
+        }
+
+        if let Some(group_id) = group_id
+            && !configs::get()
+                .achievement
+                .iter()
+                .any(|a| a.group_id == group_id)
+        {
+            return Err(AppError::InvalidRequest);
+        }
+
+        // "1:<id>#<id>..." for single tiers, "2:<group>" for an achievement group
+        let show_achievement = match group_id {
+            Some(group_id) => format!("2:{}", group_id),
+            None if request.ids.is_empty() => String::new(),
+            None => format!(
+                "1:{}",
+                request
+                    .ids
+                    .iter()
+                    .map(|id| id.to_string())
+                    .collect::<Vec<_>>()
+                    .join("#")
+            ),
+        };
+
+        let mut card = player_card::get_player_card_info(pool, player_id).await?;
+        card.show_achievement = show_achievement;
+        player_card::update_player_card_info(pool, &card).await?;
+    }
+
+    let reply = SetPlayerCardShowAchievementReply {
+        ids: request.ids,
+        group_id: request.group_id,
+    };
+
+    let mut conn = ctx.lock().await;
+    conn.send_reply(CmdId::SetPlayerCardShowAchievementCmd, reply, 0, req.up_tag)
+        .await?;
+
+    Ok(())
+}
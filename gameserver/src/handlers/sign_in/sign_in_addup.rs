@@ -1,7 +1,8 @@
+use crate::error::AppError;
 use crate::network::packet::ClientPacket;
 use crate::state::ConnectionContext;
-use crate::util::push;
-use crate::{error::AppError, util::inventory::add_currencies};
+use crate::util::transaction::{MATERIAL_CURRENCY, Transaction};
+use database::models::game::ledger::LedgerSource;
 
 use prost::Message;
 use sonettobuf::{CmdId, SignInAddupReply, SignInAddupRequest};
@@ -81,12 +82,21 @@ pub async fn on_sign_in_addup(
         return Ok(());
     }
 
+    let mut applied = Transaction::new(player_id, LedgerSource::SignIn)
+        .reference(day as i64)
+        .grant(MATERIAL_CURRENCY, 11, reward_amount)
+        .approach(14)
+        .apply(ctx.clone())
+        .await?;
+
     sqlx::query("INSERT INTO user_sign_in_addup_bonus (user_id, bonus_id) VALUES (?, ?)")
         .bind(player_id)
         .bind(day)
-        .execute(&pool)
+        .execute(&mut *applied.db)
         .await?;
 
+    applied.commit(ctx.clone()).await?;
+
     tracing::info!(
         "User {} claimed sign-in day {} bonus - granting {} currency 11",
         player_id,
@@ -94,14 +104,6 @@ pub async fn on_sign_in_addup(
         reward_amount
     );
 
-    let currencies = vec![(11, reward_amount)];
-    let ids = add_currencies(&pool, player_id, &currencies).await?;
-
-    push::send_currency_change_push(ctx.clone(), player_id, ids).await?;
-
-    let material_changes = vec![(2, 11u32, reward_amount)];
-    push::send_material_change_push(ctx.clone(), material_changes, Some(14)).await?;
-
     let reply = SignInAddupReply { id: Some(day) };
 
     let mut conn = ctx.lock().await;
@@ -5,3 +5,6 @@ mod new_order;
 pub use buy_goods::on_buy_goods;
 pub use get_store_infos::on_get_store_infos;
 pub use new_order::on_new_order;
+
+/// `getApproach` of store purchases
+const STORE_APPROACH: u32 = 27;
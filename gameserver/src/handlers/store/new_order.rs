@@ -1,11 +1,9 @@
+use super::STORE_APPROACH;
 use crate::error::AppError;
 use crate::network::packet::ClientPacket;
-use crate::state::ConnectionContext;
-
-use crate::util::push;
-use database::models::game::{
-    currencies::UserCurrencyModel, heros::UserHeroModel, items::UserItemModel,
-};
+use crate::state::{ConnectionContext, parse_reward_string};
+use crate::util::transaction::Transaction;
+use database::models::game::ledger::LedgerSource;
 
 use prost::Message;
 use sonettobuf::{CmdId, NewOrderReply, NewOrderRequest, OrderCompletePush, StatInfoPush};
@@ -25,27 +23,7 @@ pub async fn on_new_order(
     let now = common::time::ServerTime::now_ms();
     let game_order_id = now;
 
-    let (player_id, pool) = {
-        let conn = ctx.lock().await;
-        let player_id = conn.player_id.ok_or(AppError::NotLoggedIn)?;
-        let pool = conn.state.db.clone();
-        (player_id, pool)
-    };
-
-    let hero = UserHeroModel::new(player_id, pool.clone());
-    let item = UserItemModel::new(player_id, pool.clone());
-    let currency = UserCurrencyModel::new(player_id, pool.clone());
-
-    let (
-        user_id,
-        attachment,
-        changed_items,
-        changed_currencies,
-        first_charge,
-        total_charge,
-        user_tag,
-        _,
-    ) = {
+    let (user_id, attachment, first_charge, total_charge, user_tag, _) = {
         let conn = ctx.lock().await;
         let player_id = conn.player_id.ok_or(AppError::NotLoggedIn)?;
         let pool = &conn.state.db;
@@ -172,67 +150,6 @@ pub async fn on_new_order(
 
         let attachment = all_items.clone();
 
-        let (items, currencies, equips, heroes, power_items, insight_selectors) =
-            crate::state::parse_store_product(&all_items);
-
-        let mut item_ids = if !items.is_empty() {
-            item.create_items(&items).await?
-        } else {
-            vec![]
-        };
-
-        let currency_ids = if !currencies.is_empty() {
-            currency.create_currencies(&currencies).await?
-        } else {
-            vec![]
-        };
-
-        if !power_items.is_empty() {
-            item.create_power_items(
-                &power_items
-                    .iter()
-                    .map(|(id, count)| (*id as i32, *count))
-                    .collect::<Vec<_>>(),
-            )
-            .await?;
-        }
-
-        if !insight_selectors.is_empty() {
-            let insight_item_ids = item
-                .create_insight_items(
-                    &insight_selectors
-                        .iter()
-                        .map(|(id, count)| (*id as i32, *count))
-                        .collect::<Vec<_>>(),
-                )
-                .await?;
-            item_ids.extend(insight_item_ids);
-        }
-
-        if !equips.is_empty() {
-            database::db::game::equipment::add_equipments(
-                pool,
-                player_id,
-                &equips
-                    .iter()
-                    .map(|(id, count)| (*id as i32, *count))
-                    .collect::<Vec<_>>(),
-            )
-            .await?;
-        }
-
-        for (hero_id, _count) in &heroes {
-            let hero_id = *hero_id as i32;
-            if !hero.has_hero(hero_id).await? {
-                hero.create_hero(hero_id).await?;
-                tracing::info!(
-                    "User {} received new hero {} from charge pack",
-                    player_id,
-                    hero_id
-                );
-            }
-        }
-
         let (current_buy_count, current_first_charge): (i32, i32) = sqlx::query_as(
             "SELECT buy_count, first_charge FROM user_charge_info WHERE user_id = ? AND charge_id = ?"
         )
@@ -302,8 +219,6 @@ pub async fn on_new_order(
         (
             player_id,
             attachment,
-            item_ids,
-            currency_ids,
             new_stats_first_charge == 1,
             total_charge_amount,
             user_tag,
@@ -328,20 +243,12 @@ pub async fn on_new_order(
             .await?;
     }
 
-    if !changed_items.is_empty() {
-        push::send_item_change_push(
-            ctx.clone(),
-            user_id,
-            changed_items.into_iter().map(|id| id as u32).collect(),
-            vec![],
-            vec![],
-        )
+    Transaction::new(user_id, LedgerSource::Charge)
+        .reference(goods_id as i64)
+        .grants(&parse_reward_string(&attachment, 1))
+        .approach(STORE_APPROACH)
+        .commit(ctx.clone())
         .await?;
-    }
-
-    if !changed_currencies.is_empty() {
-        push::send_currency_change_push(ctx.clone(), user_id, changed_currencies).await?;
-    }
 
     let complete_push = OrderCompletePush {
         id: Some(goods_id),
@@ -367,32 +274,7 @@ pub async fn on_new_order(
         conn.notify(CmdId::StatInfoPushCmd, stat_push).await?;
     }
 
-    let mut material_changes = Vec::new();
-    let (items, currencies, equips, heroes, power_items, insight_selectors) =
-        crate::state::parse_store_product(&attachment);
-
-    for (item_id, amount) in items {
-        material_changes.push((1, item_id, amount));
-    }
-    for (currency_id, amount) in currencies {
-        material_changes.push((2, currency_id as u32, amount));
-    }
-    for (equip_id, amount) in equips {
-        material_changes.push((9, equip_id, amount));
-    }
-    for (hero_id, amount) in heroes {
-        material_changes.push((4, hero_id, amount));
-    }
-    for (power_item_id, amount) in power_items {
-        material_changes.push((10, power_item_id, amount));
-    }
-    for (insight_selector_id, amount) in insight_selectors {
-        material_changes.push((24, insight_selector_id, amount));
-    }
-
-    if !material_changes.is_empty() {
-        push::send_material_change_push(ctx.clone(), material_changes, Some(27)).await?;
-    }
+    crate::state::apply_bp_charge(ctx.clone(), user_id, goods_id).await?;
 
     tracing::info!(
         "Auto-completed order {} for goods {}",
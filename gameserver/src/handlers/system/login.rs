@@ -1,11 +1,12 @@
 use crate::error::AppError;
 use crate::handlers::system::util::*;
 use crate::network::packet::ClientPacket;
-use crate::state::ConnectionContext;
+use crate::state::{ConnectionContext, GameEvent, deliver_broadcasts};
 use crate::util::push::send_red_dot_push;
 use common::time::ServerTime;
-use database::db::game::sign_in;
-use sonettobuf::{CmdId, Mail, NewMailPush};
+use database::db::content_sync;
+use database::db::game::{mails, sign_in};
+use sonettobuf::{CmdId, NewMailPush};
 use sqlx::Row;
 use std::sync::Arc;
 use tokio::sync::Mutex;
@@ -67,6 +68,11 @@ pub async fn on_login(
         if is_new_month {
             sign_in::reset_monthly_counters(&db, user_id).await?;
         }
+
+        // A failed backfill is retried on the next login, it shouldn't lock the player out
+        if let Err(e) = content_sync::sync_content(&db, user_id).await {
+            tracing::error!("Content sync failed for user {}: {}", user_id, e);
+        }
     }
 
     {
@@ -97,6 +103,8 @@ pub async fn on_login(
             state.last_sign_in_time = Some(now);
         })
         .await?;
+
+        conn.emit(GameEvent::Login);
     }
 
     send_red_dot_push(Arc::clone(&ctx), user_id, Some(vec![2218, 2220, 2221])).await?;
@@ -105,84 +113,25 @@ pub async fn on_login(
     send_critter_push(Arc::clone(&ctx), user_id).await?;
 
     {
-        let conn = ctx.lock().await;
-        let pool = &conn.state.db;
-        let now = ServerTime::now_ms();
+        let state = ctx.lock().await.state.clone();
+        deliver_broadcasts(&state, user_id).await?;
 
-        let new_mails: Vec<(
-            i64,
-            i32,
-            String,
-            String,
-            i32,
-            i64,
-            String,
-            String,
-            String,
-            String,
-            i64,
-            i32,
-            String,
-            String,
-        )> = sqlx::query_as(
-            "SELECT incr_id, mail_id, params, attachment, state, create_time,
-                    sender, title, content, copy, expire_time, sender_type,
-                    jump_title, jump
-             FROM user_mails
-             WHERE user_id = ? AND state = 0 AND (expire_time = 0 OR expire_time > ?)",
-        )
-        .bind(user_id)
-        .bind(now)
-        .fetch_all(pool)
-        .await?;
-
-        drop(conn);
-
-        for (
-            incr_id,
-            mail_id,
-            params,
-            attachment,
-            state,
-            create_time,
-            sender,
-            title,
-            content,
-            copy,
-            expire_time,
-            sender_type,
-            jump_title,
-            jump,
-        ) in new_mails.clone()
-        {
-            let mail = Mail {
-                incr_id: Some(incr_id as u64),
-                mail_id: Some(mail_id as u32),
-                params: Some(params),
-                attachment: Some(attachment),
-                state: Some(state as u32),
-                create_time: Some(create_time as u64),
-                sender: Some(sender),
-                title: Some(title),
-                content: Some(content),
-                copy: Some(copy),
-                expire_time: Some(expire_time as u64),
-                sender_type: Some(sender_type),
-                jump_title: Some(jump_title),
-                jump: Some(jump),
-            };
+        let new_mails = mails::get_unread_mails(&state.db, user_id, ServerTime::now_ms()).await?;
+        let count = new_mails.len();
 
+        for m in new_mails {
             let mut conn = ctx.lock().await;
-            conn.notify(CmdId::NewMailPushCmd, NewMailPush { mail: Some(mail) })
-                .await?;
+            conn.notify(
+                CmdId::NewMailPushCmd,
+                NewMailPush {
+                    mail: Some(m.into()),
+                },
+            )
+            .await?;
         }
 
-        if !new_mails.is_empty() {
-            tracing::info!(
-                "Sent {} new mail notifications to user {}",
-                new_mails.len(),
-                user_id
-            );
+        if count > 0 {
+            tracing::info!("Sent {} new mail notifications to user {}", count, user_id);
         }
     }
 
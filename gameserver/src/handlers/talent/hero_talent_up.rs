@@ -1,10 +1,10 @@
 use crate::error::AppError;
 use crate::network::packet::ClientPacket;
 use crate::state::ConnectionContext;
-use database::models::game::{
-    heros::{HeroModel, UserHeroModel},
-    items::UserItemModel,
-};
+use crate::util::transaction::{MATERIAL_ITEM, Transaction};
+use database::db::game::materials;
+use database::models::game::heros::{HeroModel, UserHeroModel};
+use database::models::game::ledger::LedgerSource;
 use prost::Message;
 use sonettobuf::{CmdId, HeroTalentUpReply, HeroTalentUpRequest, HeroUpdatePush};
 use std::sync::Arc;
@@ -27,7 +27,6 @@ pub async fn on_hero_talent_up(
     };
 
     let hero = UserHeroModel::new(player_id, pool.clone());
-    let item = UserItemModel::new(player_id, pool.clone());
 
     let new_talent_id = {
         let conn = ctx.lock().await;
@@ -103,68 +102,62 @@ pub async fn on_hero_talent_up(
             return Ok(());
         }
 
-        if !talent_config.consume.is_empty() {
-            for cost_part in talent_config.consume.split('|') {
-                let parts: Vec<&str> = cost_part.split('#').collect();
-                if parts.len() >= 3 && parts[0] == "1" {
-                    let item_id: u32 = parts[1].parse().map_err(|_| AppError::InvalidRequest)?;
-                    let amount: i32 = parts[2].parse().map_err(|_| AppError::InvalidRequest)?;
-
-                    let current = item
-                        .get_item(item_id)
-                        .await?
-                        .map(|i| i.quantity)
-                        .unwrap_or(0);
-
-                    if current < amount {
-                        tracing::info!(
-                            "User {} insufficient item {} for talent up (has {}, needs {})",
-                            player_id,
-                            item_id,
-                            current,
-                            amount
-                        );
-
-                        drop(conn);
-
-                        crate::util::push::send_item_change_push(
-                            ctx.clone(),
-                            player_id,
-                            vec![item_id],
-                            vec![],
-                            vec![],
-                        )
-                        .await?;
-
-                        let mut conn = ctx.lock().await;
-                        conn.send_reply(
-                            CmdId::HeroTalentUpCmd,
-                            HeroTalentUpReply {
-                                hero_id: Some(hero_id),
-                                talent_id: Some(current_talent),
-                            },
-                            0,
-                            req.up_tag,
-                        )
-                        .await?;
-
-                        return Ok(());
-                    }
-                }
+        let mut costs = Vec::new();
+        for cost_part in talent_config.consume.split('|') {
+            let parts: Vec<&str> = cost_part.split('#').collect();
+            if parts.len() >= 3 && parts[0] == "1" {
+                let item_id: u32 = parts[1].parse().map_err(|_| AppError::InvalidRequest)?;
+                let amount: i32 = parts[2].parse().map_err(|_| AppError::InvalidRequest)?;
+                costs.push((MATERIAL_ITEM, item_id, amount));
             }
+        }
+
+        drop(conn);
+
+        let tx = Transaction::new(player_id, LedgerSource::Hero)
+            .reference(hero_id as i64)
+            .costs(&costs)
+            .silent();
+
+        let mut applied = match tx.apply(ctx.clone()).await {
+            Ok(applied) => applied,
+            Err(AppError::InsufficientItems) => {
+                tracing::info!(
+                    "User {} can't pay talent {} of hero {}",
+                    player_id,
+                    new_talent,
+                    hero_id
+                );
 
-            for cost_part in talent_config.consume.split('|') {
-                let parts: Vec<&str> = cost_part.split('#').collect();
-                if parts.len() >= 3 && parts[0] == "1" {
-                    let item_id: u32 = parts[1].parse().unwrap();
-                    let amount: i32 = parts[2].parse().unwrap();
+                let mut conn = ctx.lock().await;
+                conn.send_reply(
+                    CmdId::HeroTalentUpCmd,
+                    HeroTalentUpReply {
+                        hero_id: Some(hero_id),
+                        talent_id: Some(current_talent),
+                    },
+                    0,
+                    req.up_tag,
+                )
+                .await?;
 
-                    item.remove_item_quantity(item_id, amount).await?;
-                }
+                return Ok(());
             }
-        }
+            Err(e) => return Err(e),
+        };
 
-        hero.update_talent(hero_id, new_talent).await?;
+        if !materials::set_hero_talent(
+            &mut applied.db,
+            player_id,
+            hero_id,
+            current_talent,
+            new_talent,
+        )
+        .await?
+        {
+            return Err(AppError::InvalidRequest);
+        }
+        applied.commit(ctx.clone()).await?;
 
         tracing::info!(
             "User {} upgraded hero {} talent from {} to {}",
@@ -1,11 +1,10 @@
 use crate::error::AppError;
 use crate::network::packet::ClientPacket;
 use crate::state::ConnectionContext;
-use database::models::game::{
-    currencies::UserCurrencyModel,
-    heros::{HeroModel, UserHeroModel},
-    items::UserItemModel,
-};
+use crate::util::transaction::{MATERIAL_CURRENCY, MATERIAL_ITEM, Transaction};
+use database::db::game::materials;
+use database::models::game::heros::{HeroModel, UserHeroModel};
+use database::models::game::ledger::LedgerSource;
 use prost::Message;
 use sonettobuf::{CmdId, HeroUpdatePush, UnlockTalentStyleReply, UnlockTalentStyleRequest};
 use std::sync::Arc;
@@ -29,8 +28,6 @@ pub async fn on_unlock_talent_style(
     };
 
     let hero = UserHeroModel::new(user_id, pool.clone());
-    let item = UserItemModel::new(user_id, pool.clone());
-    let currency = UserCurrencyModel::new(user_id, pool.clone());
 
     if hero.has_talent_style(hero_id, style).await? {
         tracing::info!(
@@ -100,52 +97,25 @@ pub async fn on_unlock_talent_style(
         (items, currencies)
     };
 
+    let mut tx = Transaction::new(user_id, LedgerSource::Hero)
+        .reference(hero_id as i64)
+        .silent();
     for (item_id, amount) in &cost_items {
-        let success = item.remove_item_quantity(*item_id, *amount).await?;
-        if !success {
-            tracing::info!("User {} insufficient item {}", user_id, item_id);
-
-            crate::util::push::send_item_change_push(
-                ctx.clone(),
-                user_id,
-                vec![*item_id],
-                vec![],
-                vec![],
-            )
-            .await?;
-
-            let mut conn = ctx.lock().await;
-            conn.send_reply(
-                CmdId::UnlockTalentStyleCmd,
-                UnlockTalentStyleReply {
-                    hero_id: Some(hero_id),
-                    style: Some(style),
-                },
-                0,
-                req.up_tag,
-            )
-            .await?;
-
-            return Ok(());
-        }
+        tx = tx.cost(MATERIAL_ITEM, *item_id, *amount);
     }
-
     for (currency_id, amount) in &cost_currencies {
-        let current = currency
-            .get_currency(*currency_id)
-            .await?
-            .map(|c| c.quantity)
-            .unwrap_or(0);
-
-        if current < *amount {
-            tracing::info!("User {} insufficient currency {}", user_id, currency_id);
+        tx = tx.cost(MATERIAL_CURRENCY, *currency_id as u32, *amount);
+    }
 
-            crate::util::push::send_currency_change_push(
-                ctx.clone(),
+    let mut applied = match tx.apply(ctx.clone()).await {
+        Ok(applied) => applied,
+        Err(AppError::InsufficientItems | AppError::InsufficientCurrency) => {
+            tracing::info!(
+                "User {} can't pay talent style {} of hero {}",
                 user_id,
-                vec![(*currency_id, 0)],
-            )
-            .await?;
+                style,
+                hero_id
+            );
 
             let mut conn = ctx.lock().await;
             conn.send_reply(
@@ -161,13 +131,20 @@ pub async fn on_unlock_talent_style(
 
             return Ok(());
         }
-    }
+        Err(e) => return Err(e),
+    };
 
-    for (currency_id, amount) in &cost_currencies {
-        currency.remove_currency(*currency_id, *amount).await?;
+    if !materials::unlock_talent_style(&mut applied.db, user_id, hero_id, style).await? {
+        return Err(AppError::InvalidRequest);
     }
-
-    hero.unlock_talent_style(hero_id, style).await?;
+    applied.commit(ctx.clone()).await?;
+
+    tracing::info!(
+        "User {} unlocked talent style {} for hero {}",
+        user_id,
+        style,
+        hero_id
+    );
 
     let data = UnlockTalentStyleReply {
         hero_id: Some(hero_id),
@@ -0,0 +1,60 @@
+use crate::error::AppError;
+use crate::network::packet::ClientPacket;
+use crate::state::{
+    ConnectionContext, TaskClaim, claim_activity_bonuses, claim_task, grant_task_claim,
+    reset_expired_tasks, task_defs,
+};
+use prost::Message;
+use sonettobuf::{CmdId, FinishAllTaskReply, FinishAllTaskRequest};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+pub async fn on_finish_all_task(
+    ctx: Arc<Mutex<ConnectionContext>>,
+    req: ClientPacket,
+) -> Result<(), AppError> {
+    let request = FinishAllTaskRequest::decode(&req.data[..])?;
+    tracing::info!("Received FinishAllTaskRequest: {:?}", request);
+
+    let type_id = request.type_id.ok_or(AppError::InvalidRequest)?;
+    let min_type_id = request.min_type_id.filter(|&m| m != 0);
+
+    let (player_id, pool) = {
+        let conn = ctx.lock().await;
+        let player_id = conn.player_id.ok_or(AppError::NotLoggedIn)?;
+        (player_id, conn.state.db.clone())
+    };
+
+    reset_expired_tasks(&pool, player_id).await?;
+
+    let candidates = task_defs().filter(|def| {
+        def.task_type == type_id
+            && min_type_id.is_none_or(|m| def.min_type.parse::<i32>() == Ok(m))
+            && (request.task_ids.is_empty() || request.task_ids.contains(&def.id))
+    });
+
+    let mut db = pool.begin().await?;
+    let mut claim = TaskClaim::default();
+    for def in candidates {
+        claim_task(&mut db, player_id, &def, &mut claim).await?;
+    }
+
+    // Points from the claimed tasks may unlock activity bonus stages right away
+    claim_activity_bonuses(&mut db, player_id, type_id, None, &mut claim).await?;
+
+    let task_ids = claim.tasks.iter().map(|t| t.task_id).collect();
+    grant_task_claim(ctx.clone(), player_id, db, claim).await?;
+
+    let reply = FinishAllTaskReply {
+        type_id: Some(type_id),
+        min_type_id: request.min_type_id,
+        task_ids,
+        activity_id: request.activity_id,
+    };
+
+    let mut conn = ctx.lock().await;
+    conn.send_reply(CmdId::FinishAllTaskCmd, reply, 0, req.up_tag)
+        .await?;
+
+    Ok(())
+}
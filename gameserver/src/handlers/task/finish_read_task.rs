@@ -0,0 +1,51 @@
+use crate::error::AppError;
+use crate::network::packet::ClientPacket;
+use crate::state::{ConnectionContext, READ_TASK_LISTENER, reset_expired_tasks, task_def};
+use crate::util::push::send_update_task_push;
+use database::db::game::tasks;
+use prost::Message;
+use sonettobuf::{CmdId, FinishReadTaskReply, FinishReadTaskRequest};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+pub async fn on_finish_read_task(
+    ctx: Arc<Mutex<ConnectionContext>>,
+    req: ClientPacket,
+) -> Result<(), AppError> {
+    let request = FinishReadTaskRequest::decode(&req.data[..])?;
+    tracing::info!("Received FinishReadTaskRequest: {:?}", request);
+
+    let task_id = request.task_id.ok_or(AppError::InvalidRequest)?;
+    let def = task_def(task_id)
+        .filter(|def| def.listener_type == READ_TASK_LISTENER)
+        .ok_or(AppError::InvalidRequest)?;
+
+    let (player_id, pool) = {
+        let conn = ctx.lock().await;
+        let player_id = conn.player_id.ok_or(AppError::NotLoggedIn)?;
+        (player_id, conn.state.db.clone())
+    };
+
+    reset_expired_tasks(&pool, player_id).await?;
+
+    // Reading completes the task, claiming it is still up to FinishTask
+    let task = tasks::add_task_progress(
+        &pool,
+        player_id,
+        task_id,
+        def.max_progress,
+        def.max_progress,
+    )
+    .await?;
+    send_update_task_push(ctx.clone(), task.into_iter().collect(), Vec::new()).await?;
+
+    let reply = FinishReadTaskReply {
+        task_id: Some(task_id),
+    };
+
+    let mut conn = ctx.lock().await;
+    conn.send_reply(CmdId::FinishReadTaskCmd, reply, 0, req.up_tag)
+        .await?;
+
+    Ok(())
+}
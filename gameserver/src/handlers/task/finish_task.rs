@@ -0,0 +1,52 @@
+use crate::error::AppError;
+use crate::network::packet::ClientPacket;
+use crate::state::{
+    ConnectionContext, TaskClaim, claim_task, grant_task_claim, reset_expired_tasks, task_def,
+};
+use prost::Message;
+use sonettobuf::{CmdId, FinishTaskReply, FinishTaskRequest};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+pub async fn on_finish_task(
+    ctx: Arc<Mutex<ConnectionContext>>,
+    req: ClientPacket,
+) -> Result<(), AppError> {
+    let request = FinishTaskRequest::decode(&req.data[..])?;
+    tracing::info!("Received FinishTaskRequest: {:?}", request);
+
+    let def = task_def(request.id).ok_or(AppError::InvalidRequest)?;
+
+    let (player_id, pool) = {
+        let conn = ctx.lock().await;
+        let player_id = conn.player_id.ok_or(AppError::NotLoggedIn)?;
+        (player_id, conn.state.db.clone())
+    };
+
+    reset_expired_tasks(&pool, player_id).await?;
+
+    let mut db = pool.begin().await?;
+    let mut claim = TaskClaim::default();
+    if !claim_task(&mut db, player_id, &def, &mut claim).await? {
+        tracing::warn!(
+            "User {} tried to finish task {} which isn't claimable",
+            player_id,
+            def.id
+        );
+        return Err(AppError::InvalidRequest);
+    }
+
+    let finish_count = claim.tasks.first().map(|t| t.finish_count);
+    grant_task_claim(ctx.clone(), player_id, db, claim).await?;
+
+    let reply = FinishTaskReply {
+        id: Some(request.id),
+        finish_count,
+    };
+
+    let mut conn = ctx.lock().await;
+    conn.send_reply(CmdId::FinishTaskCmd, reply, 0, req.up_tag)
+        .await?;
+
+    Ok(())
+}
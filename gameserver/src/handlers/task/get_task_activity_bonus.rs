@@ -0,0 +1,53 @@
+use crate::error::AppError;
+use crate::network::packet::ClientPacket;
+use crate::state::{
+    ConnectionContext, TaskClaim, claim_activity_bonuses, grant_task_claim, reset_expired_tasks,
+};
+use prost::Message;
+use sonettobuf::{CmdId, GetTaskActivityBonusReply, GetTaskActivityBonusRequest};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+pub async fn on_get_task_activity_bonus(
+    ctx: Arc<Mutex<ConnectionContext>>,
+    req: ClientPacket,
+) -> Result<(), AppError> {
+    let request = GetTaskActivityBonusRequest::decode(&req.data[..])?;
+    tracing::info!("Received GetTaskActivityBonusRequest: {:?}", request);
+
+    let type_id = request.type_id.ok_or(AppError::InvalidRequest)?;
+    let define_id = request.define_id.ok_or(AppError::InvalidRequest)?;
+
+    let (player_id, pool) = {
+        let conn = ctx.lock().await;
+        let player_id = conn.player_id.ok_or(AppError::NotLoggedIn)?;
+        (player_id, conn.state.db.clone())
+    };
+
+    reset_expired_tasks(&pool, player_id).await?;
+
+    let mut db = pool.begin().await?;
+    let mut claim = TaskClaim::default();
+    if !claim_activity_bonuses(&mut db, player_id, type_id, Some(define_id), &mut claim).await? {
+        tracing::warn!(
+            "User {} has no activity bonus of type {} up to stage {} to claim",
+            player_id,
+            type_id,
+            define_id
+        );
+        return Err(AppError::InvalidRequest);
+    }
+
+    grant_task_claim(ctx.clone(), player_id, db, claim).await?;
+
+    let reply = GetTaskActivityBonusReply {
+        type_id: Some(type_id),
+        define_id: Some(define_id),
+    };
+
+    let mut conn = ctx.lock().await;
+    conn.send_reply(CmdId::GetTaskActivityBonusCmd, reply, 0, req.up_tag)
+        .await?;
+
+    Ok(())
+}
@@ -1,8 +1,10 @@
 use crate::error::AppError;
 use crate::network::packet::ClientPacket;
-use crate::send_reply;
-use crate::state::ConnectionContext;
-use sonettobuf::{CmdId, GetTaskInfoReply};
+use crate::state::{ConnectionContext, TASK_TYPES, sync_tasks};
+use crate::util::data_loader::GameDataLoader;
+use database::db::game::tasks;
+use prost::Message;
+use sonettobuf::{CmdId, GetTaskInfoReply, GetTaskInfoRequest};
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
@@ -10,12 +12,59 @@ pub async fn on_get_task_info(
     ctx: Arc<Mutex<ConnectionContext>>,
     req: ClientPacket,
 ) -> Result<(), AppError> {
-    send_reply!(
-        ctx,
-        req.up_tag,
-        CmdId::GetTaskInfoCmd,
-        GetTaskInfoReply,
-        "task/task_info.json"
+    let request = GetTaskInfoRequest::decode(&req.data[..])?;
+    tracing::info!("Received GetTaskInfoRequest: {:?}", request);
+
+    let (player_id, pool) = {
+        let conn = ctx.lock().await;
+        let player_id = conn.player_id.ok_or(AppError::NotLoggedIn)?;
+        (player_id, conn.state.db.clone())
+    };
+
+    // The reply replaces the client's task list, so dropped tasks need no push
+    sync_tasks(&pool, player_id).await?;
+
+    let type_ids: Vec<i32> = request.type_ids.iter().map(|&t| t as i32).collect();
+    let wanted = |task_type: i32| type_ids.is_empty() || type_ids.contains(&task_type);
+
+    let task_info = tasks::get_tasks(&pool, player_id, &type_ids).await?;
+    let activity_info = tasks::get_activities(&pool, player_id, &type_ids).await?;
+
+    // Types the task engine doesn't model yet keep their captured rows
+    let captured: GetTaskInfoReply = GameDataLoader::load_struct("task/task_info.json")?;
+    let is_captured = |task_type: i32| !TASK_TYPES.contains(&task_type) && wanted(task_type);
+
+    let mut reply = GetTaskInfoReply {
+        task_info: task_info.into_iter().map(Into::into).collect(),
+        activity_info: activity_info.into_iter().map(Into::into).collect(),
+        type_ids: request.type_ids,
+    };
+
+    reply.task_info.extend(
+        captured
+            .task_info
+            .into_iter()
+            .filter(|t| is_captured(t.r#type.unwrap_or(0))),
+    );
+    reply.activity_info.extend(
+        captured
+            .activity_info
+            .into_iter()
+            .filter(|a| is_captured(a.type_id)),
     );
+
+    if reply.type_ids.is_empty() {
+        reply.type_ids = TASK_TYPES.iter().map(|&t| t as u32).collect();
+        for type_id in captured.type_ids {
+            if !reply.type_ids.contains(&type_id) {
+                reply.type_ids.push(type_id);
+            }
+        }
+    }
+
+    let mut conn = ctx.lock().await;
+    conn.send_reply(CmdId::GetTaskInfoCmd, reply, 0, req.up_tag)
+        .await?;
+
     Ok(())
 }
@@ -1,3 +1,11 @@
+mod finish_all_task;
+mod finish_read_task;
+mod finish_task;
+mod get_task_activity_bonus;
 mod get_task_info;
 
+pub use finish_all_task::on_finish_all_task;
+pub use finish_read_task::on_finish_read_task;
+pub use finish_task::on_finish_task;
+pub use get_task_activity_bonus::on_get_task_activity_bonus;
 pub use get_task_info::on_get_task_info;
@@ -1,11 +1,12 @@
 use crate::error::AppError;
 use crate::network::packet::ClientPacket;
 use crate::state::{
-    ActiveBattle, BattleContext, ConnectionContext, create_battle, default_max_ap,
-    generate_initial_deck,
+    ActiveBattle, BattleContext, ConnectionContext, ReplayStart, create_battle, default_max_ap,
+    generate_initial_deck, persist_active_battle,
 };
 use config::configs;
 use prost::Message;
+use rand::{SeedableRng, rngs::StdRng};
 use sonettobuf::{
     CmdId, DungeonUpdatePush, StartDungeonReply, StartTowerBattleReply, StartTowerBattleRequest,
     UserDungeon,
@@ -78,37 +79,53 @@ pub async fn on_start_tower_battle(
         max_ap,
     };
 
-    let card_push = generate_initial_deck(&pool, player_id, &fight_group, max_ap).await?;
+    let card_push = generate_initial_deck(
+        &pool,
+        player_id,
+        &fight_group,
+        max_ap,
+        &mut StdRng::from_entropy(),
+    )
+    .await?;
 
     let card_deck = card_push.card_group.clone();
 
     let (modified_fight, initial_round, fight_data_mgr, ai_deck) =
         create_battle(&pool, battle_ctx, &fight_group, card_deck.clone()).await?;
 
-    {
-        let mut conn = ctx.lock().await;
-        conn.active_battle = Some(ActiveBattle {
-            tower_type: Some(dungeon_type),
-            tower_id: Some(tower_id),
-            layer_id: Some(layer_id),
-            episode_id,
-            chapter_id,
-            difficulty: Some(difficulty),
-            talent_plan_id: Some(talent_plan_id),
-            fight: Some(modified_fight.clone()),
-            current_round: 1,
-            act_point: max_ap,
-            power: 15,
-            current_deck: card_deck,
-            fight_group: Some(fight_group.clone()),
-            is_replay: None,
-            replay_episode_id: None,
-            fight_id: Some(chrono::Utc::now().timestamp_millis()),
-            multiplication: None,
-            ai_deck,
-            fight_data_mgr: Some(fight_data_mgr),
-        });
-    }
+    let mut battle = ActiveBattle {
+        tower_type: Some(dungeon_type),
+        tower_id: Some(tower_id),
+        layer_id: Some(layer_id),
+        episode_id,
+        chapter_id,
+        difficulty: Some(difficulty),
+        talent_plan_id: Some(talent_plan_id),
+        fight: Some(modified_fight.clone()),
+        current_round: 1,
+        act_point: max_ap,
+        power: 15,
+        current_deck: card_deck.clone(),
+        fight_group: Some(fight_group.clone()),
+        is_replay: None,
+        replay_episode_id: None,
+        fight_id: Some(chrono::Utc::now().timestamp_millis()),
+        multiplication: None,
+        replay_start: Some(ReplayStart {
+            data: fight_data_mgr.clone(),
+            deck: card_deck,
+            ai_deck: ai_deck.clone(),
+        }),
+        ai_deck,
+        fight_data_mgr: Some(fight_data_mgr),
+        cloth_skill_opers: vec![],
+        round_start: None,
+        stamina_cost: 0,
+    };
+
+    battle.mark_round_start(&initial_round);
+    persist_active_battle(&pool, player_id, &battle, &initial_round).await?;
+    ctx.lock().await.active_battle = Some(battle);
 
     {
         let mut conn = ctx.lock().await;
@@ -3,9 +3,13 @@ use crate::{
     state::{AppState, ConnectionContext},
 };
 use ::config::configs;
-use common::{config, excel_data_directory, game_port, host, init_config, init_tracing};
+use common::{
+    config, excel_data_directory, game_port, host, init_config, init_tracing, starter_profile,
+};
 use database::{
-    DatabaseSettings, connect_to, db::game::summon::sync_banner_schedule, run_migrations,
+    DatabaseSettings, connect_to,
+    db::{content_sync, game::summon::sync_banner_schedule, starter_profile as starter},
+    run_migrations,
 };
 use std::path::PathBuf;
 use std::sync::Arc;
@@ -57,7 +61,12 @@ async fn main() -> anyhow::Result<()> {
     configs::init(excel_data_directory().to_str().unwrap())?;
     info!("Game data loaded");
 
+    starter::init(starter_profile())?;
+    info!("Content version: {}", content_sync::content_version());
+
     let state = Arc::new(AppState::new(db));
+    tokio::spawn(state::run_mail_expiry(state.clone()));
+
     let addr = format!("{}:{}", host(), game_port());
     let listener = TcpListener::bind(&addr).await?;
     info!("Listening on tcp://{}", &addr);
@@ -1,14 +1,17 @@
 use crate::network::handler;
-use crate::state::ConnectionContext;
+use crate::state::{ConnectionContext, dispatch_events};
 use byteorder::{BE, ByteOrder};
 use std::sync::Arc;
 use tokio::{io::AsyncReadExt, sync::Mutex};
 
 pub async fn handle_client(ctx: Arc<Mutex<ConnectionContext>>) -> anyhow::Result<()> {
     loop {
+        // The connection context stays unlocked while waiting on the client,
+        // so other sessions and server services can queue pushes for it
+        let socket = ctx.lock().await.socket.clone();
+
         let packet = {
-            let conn = ctx.lock().await;
-            let mut socket = conn.socket.lock().await;
+            let mut socket = socket.lock().await;
 
             let mut header = [0u8; 4];
             if let Err(e) = socket.read_exact(&mut header).await {
@@ -34,6 +37,8 @@ pub async fn handle_client(ctx: Arc<Mutex<ConnectionContext>>) -> anyhow::Result
             break;
         }
 
+        dispatch_events(ctx.clone()).await;
+
         {
             let mut conn = ctx.lock().await;
             if let Err(e) = conn.flush_send_queue().await {
@@ -46,6 +46,7 @@ pub async fn dispatch_command(
         CmdId::MarkMainThumbnailCmd => misc::on_mark_main_thumbnail,
         CmdId::GetAssistBonusCmd => player::on_get_assist_bonus,
         CmdId::GetPlayerCardInfoCmd => player_card::on_get_player_card_info,
+        CmdId::SetPlayerCardShowAchievementCmd => player_card::on_set_player_card_show_achievement,
         CmdId::SetPortraitCmd => misc::on_set_portrait,
 
         // === Hero ===
@@ -75,6 +76,7 @@ pub async fn dispatch_command(
         // === Currency & Economy ===
         CmdId::GetCurrencyListCmd => currency::on_get_currency_list,
         CmdId::GetBuyPowerInfoCmd => currency::on_get_buy_power_info,
+        CmdId::BuyPowerCmd => currency::on_buy_power,
 
         // === Items & Equipment ===
         CmdId::GetItemListCmd => item::on_get_item_list,
@@ -83,6 +85,8 @@ pub async fn dispatch_command(
         CmdId::UseItemCmd => item::on_use_item,
         CmdId::EquipLockCmd => equip::on_equip_lock,
         CmdId::UseInsightItemCmd => item::on_use_insight_item,
+        CmdId::UsePowerItemCmd => item::on_use_power_item,
+        CmdId::UsePowerItemListCmd => item::on_use_power_item_list,
         CmdId::EquipStrengthenCmd => equip::on_equip_strengthen,
         CmdId::EquipBreakCmd => equip::on_equip_break,
         CmdId::EquipRefineCmd => equip::on_equip_refine,
@@ -109,6 +113,11 @@ pub async fn dispatch_command(
         CmdId::ChangeHeroGroupSelectCmd => dungeon::on_change_hero_group_select,
         CmdId::DungeonEndDungeonCmd => dungeon::on_dungeon_end_dungeon,
         CmdId::ReconnectFightCmd => fight::on_reconnect_fight,
+        CmdId::UseClothSkillCmd => fight::on_use_cloth_skill,
+        CmdId::ResetRoundCmd => fight::on_reset_round,
+        CmdId::GetFightCardDeckInfoCmd => fight::on_get_fight_card_deck_info,
+        CmdId::GetFightCardDeckDetailInfoCmd => fight::on_get_fight_card_deck_detail_info,
+        CmdId::GetEntityDetailInfosCmd => fight::on_get_entity_detail_infos,
 
         // === Tower ===
         CmdId::GetTowerInfoCmd => tower::on_get_tower_info,
@@ -139,6 +148,8 @@ pub async fn dispatch_command(
         CmdId::GetAllMailsCmd => mail::on_get_all_mails,
         CmdId::ReadMailBatchCmd => mail::on_read_mail_batch,
         CmdId::ReadMailCmd => mail::on_read_mail,
+        CmdId::DeleteMailBatchCmd => mail::on_delete_mail_batch,
+        CmdId::MarkMailJumpCmd => mail::on_mark_mail_jump,
 
         // === Charge & Monetization ===
         CmdId::GetChargeInfoCmd => charge::on_get_charge_info,
@@ -160,10 +171,19 @@ pub async fn dispatch_command(
 
         // === Achievements & Tasks ===
         CmdId::GetAchievementInfoCmd => achievements::on_get_achievement_info,
+        CmdId::ReadNewAchievementCmd => achievements::on_read_new_achievement,
         CmdId::GetTaskInfoCmd => task::on_get_task_info,
+        CmdId::FinishTaskCmd => task::on_finish_task,
+        CmdId::FinishAllTaskCmd => task::on_finish_all_task,
+        CmdId::FinishReadTaskCmd => task::on_finish_read_task,
+        CmdId::GetTaskActivityBonusCmd => task::on_get_task_activity_bonus,
 
         // === Battle Pass ===
         CmdId::GetBpInfoCmd => bp::on_get_bp_info,
+        CmdId::GetBpBonusCmd => bp::on_get_bp_bonus,
+        CmdId::GetSelfSelectBonusCmd => bp::on_get_self_select_bonus,
+        CmdId::BpBuyLevelRequsetCmd => bp::on_bp_buy_level,
+        CmdId::BpMarkFirstShowCmd => bp::on_bp_mark_first_show,
 
         // === Guides & Tutorials ===
         CmdId::GetGuideInfoCmd => guide::on_get_guide_info,
@@ -0,0 +1,322 @@
+use config::achievement_task::AchievementTask;
+use config::configs;
+use database::db::game::{achievements, player_card, red_dots::red_dots};
+use database::models::game::achievements::Achievement;
+use sonettobuf::{AchievementTaskInfo, CmdId, UpdateAchievementPush};
+use sqlx::{Acquire, SqlitePool};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use super::events::EventFuture;
+use crate::error::AppError;
+use crate::state::{ConnectionContext, GameEvent, parse_reward_string};
+use crate::util::push::send_red_dot_push;
+use crate::util::transaction::Transaction;
+use database::models::game::ledger::LedgerSource;
+
+/// Red dot of the achievement entry, its value is the number of unread finished tiers.
+/// Not part of the captured red dot list, taken from the client's red dot defines.
+pub const ACHIEVEMENT_RED_DOT: i32 = 1107;
+
+/// What an achievement tier counts, from its `listenerType`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Metric {
+    /// Owned heroes, param is the minimum rarity
+    Heroes,
+    /// Heroes at or above the insight rank in the param (default 2, Insight I)
+    InsightHeroes,
+    /// Cleared episodes, param limits them to a chapter
+    ClearedEpisodes,
+    /// Pulls across every banner
+    Pulls,
+    /// Psychubes at or above the level in the param
+    LeveledEquipment,
+}
+
+impl Metric {
+    fn from_listener(listener_type: &str) -> Option<Self> {
+        match listener_type {
+            "HeroCount" => Some(Self::Heroes),
+            "HeroRankCount" => Some(Self::InsightHeroes),
+            "EpisodeClearCount" => Some(Self::ClearedEpisodes),
+            "SummonCount" => Some(Self::Pulls),
+            "EquipLevelCount" => Some(Self::LeveledEquipment),
+            _ => None,
+        }
+    }
+
+    /// Whether the event can change the metric, login re-evaluates everything
+    fn affected_by(self, event: &GameEvent) -> bool {
+        match event {
+            GameEvent::Login => true,
+            GameEvent::Summon { .. } => self == Self::Pulls,
+            GameEvent::HeroesGained { .. } => self == Self::Heroes,
+            GameEvent::HeroRankUp { .. } => self == Self::InsightHeroes,
+            GameEvent::DungeonCleared { .. } => self == Self::ClearedEpisodes,
+            GameEvent::EquipStrengthened { .. } => self == Self::LeveledEquipment,
+            _ => false,
+        }
+    }
+}
+
+/// Player state the metrics are computed from, each part loaded on first use
+#[derive(Default)]
+struct Stats {
+    heroes: Option<Vec<(i32, i32)>>,
+    episodes: Option<Vec<(i32, i32)>>,
+    summons: Option<i32>,
+    equip_levels: Option<Vec<i32>>,
+}
+
+impl Stats {
+    async fn value(
+        &mut self,
+        pool: &SqlitePool,
+        user_id: i64,
+        metric: Metric,
+        param: &str,
+    ) -> Result<i32, AppError> {
+        let param = param.trim().parse::<i32>().ok();
+
+        let value = match metric {
+            Metric::Heroes | Metric::InsightHeroes => {
+                if self.heroes.is_none() {
+                    self.heroes = Some(achievements::get_hero_ranks(pool, user_id).await?);
+                }
+                let heroes = self.heroes.as_deref().unwrap_or_default();
+
+                if metric == Metric::Heroes {
+                    let min_rare = param.unwrap_or(0);
+                    let characters = &configs::get().character;
+                    heroes
+                        .iter()
+                        .filter(|(hero_id, _)| {
+                            characters.get(*hero_id).is_some_and(|c| c.rare >= min_rare)
+                        })
+                        .count()
+                } else {
+                    let min_rank = param.unwrap_or(2);
+                    heroes.iter().filter(|(_, rank)| *rank >= min_rank).count()
+                }
+            }
+            Metric::ClearedEpisodes => {
+                if self.episodes.is_none() {
+                    self.episodes = Some(achievements::get_cleared_episodes(pool, user_id).await?);
+                }
+                self.episodes
+                    .as_deref()
+                    .unwrap_or_default()
+                    .iter()
+                    .filter(|(chapter_id, _)| param.is_none_or(|c| c == *chapter_id))
+                    .count()
+            }
+            Metric::Pulls => {
+                if self.summons.is_none() {
+                    self.summons = Some(achievements::get_total_summons(pool, user_id).await?);
+                }
+                return Ok(self.summons.unwrap_or(0));
+            }
+            Metric::LeveledEquipment => {
+                if self.equip_levels.is_none() {
+                    self.equip_levels =
+                        Some(achievements::get_equipment_levels(pool, user_id).await?);
+                }
+                let min_level = param.unwrap_or(1);
+                self.equip_levels
+                    .as_deref()
+                    .unwrap_or_default()
+                    .iter()
+                    .filter(|&&level| level >= min_level)
+                    .count()
+            }
+        };
+
+        Ok(value as i32)
+    }
+}
+
+/// Logs the listener types of achievement tiers no metric understands. The names are
+/// matched against the exported achievement_task table, tiers using others never progress.
+pub fn warn_unhandled_listeners() {
+    let unhandled: BTreeSet<&str> = configs::get()
+        .achievement_task
+        .iter()
+        .map(|t| t.listener_type.as_str())
+        .filter(|l| Metric::from_listener(l).is_none())
+        .collect();
+
+    if !unhandled.is_empty() {
+        tracing::warn!(
+            "Achievement listener types without a metric: {:?}",
+            unhandled
+        );
+    }
+}
+
+/// Event subscriber advancing the achievement tiers whose metric the event may change
+pub fn on_achievement_event(
+    ctx: Arc<Mutex<ConnectionContext>>,
+    event: Arc<GameEvent>,
+) -> EventFuture {
+    Box::pin(evaluate_achievements(ctx, event))
+}
+
+async fn evaluate_achievements(
+    ctx: Arc<Mutex<ConnectionContext>>,
+    event: Arc<GameEvent>,
+) -> Result<(), AppError> {
+    let tiers: Vec<(&AchievementTask, Metric)> = configs::get()
+        .achievement_task
+        .iter()
+        .filter_map(|t| Metric::from_listener(&t.listener_type).map(|m| (t, m)))
+        .filter(|(_, metric)| metric.affected_by(&event))
+        .collect();
+
+    if tiers.is_empty() {
+        return Ok(());
+    }
+
+    let (player_id, pool) = {
+        let conn = ctx.lock().await;
+        let player_id = conn.player_id.ok_or(AppError::NotLoggedIn)?;
+        (player_id, conn.state.db.clone())
+    };
+
+    let existing: HashMap<i32, Achievement> = achievements::get_achievements(&pool, player_id)
+        .await?
+        .into_iter()
+        .map(|a| (a.achievement_id, a))
+        .collect();
+
+    let mut stats = Stats::default();
+    let mut candidates = Vec::new();
+
+    for (tier, metric) in tiers {
+        let current = existing.get(&tier.id);
+        if current.is_some_and(|a| a.has_finish) {
+            continue;
+        }
+
+        let progress = stats
+            .value(&pool, player_id, metric, &tier.listener_param)
+            .await?
+            .min(tier.max_progress);
+        if progress == current.map_or(0, |a| a.progress) {
+            continue;
+        }
+
+        candidates.push((tier, progress));
+    }
+
+    if candidates.is_empty() {
+        return Ok(());
+    }
+
+    // Progress, finish flags and rewards land in one commit
+    let mut db = pool.begin().await?;
+    let mut changed = Vec::new();
+    let mut finished = 0;
+    let mut rewards = Vec::new();
+
+    for (tier, progress) in candidates {
+        let mut tier_db = db.begin().await?;
+        achievements::update_achievement_progress(&mut tier_db, player_id, tier.id, progress)
+            .await?;
+
+        if progress >= tier.max_progress {
+            if !achievements::finish_achievement(&mut tier_db, player_id, tier.id).await? {
+                tracing::warn!(
+                    "User {} finished achievement tier {} twice, skipping it",
+                    player_id,
+                    tier.id
+                );
+                continue;
+            }
+
+            rewards.extend(parse_reward_string(&tier.bonus, 1));
+            finished += 1;
+        }
+
+        tier_db.commit().await?;
+        changed.push(tier.id);
+    }
+
+    if changed.is_empty() {
+        return Ok(());
+    }
+
+    Transaction::new(player_id, LedgerSource::Achievement)
+        .grants(&rewards)
+        .apply_on(ctx.clone(), db)
+        .await?
+        .commit(ctx.clone())
+        .await?;
+
+    let infos: Vec<AchievementTaskInfo> = achievements::get_achievements(&pool, player_id)
+        .await?
+        .into_iter()
+        .filter(|a| changed.contains(&a.achievement_id))
+        .map(Into::into)
+        .collect();
+
+    ctx.lock()
+        .await
+        .notify(
+            CmdId::UpdateAchievementPushCmd,
+            UpdateAchievementPush { infos },
+        )
+        .await?;
+
+    if finished > 0 {
+        tracing::info!(
+            "User {} finished {} achievement tiers on {:?}",
+            player_id,
+            finished,
+            event
+        );
+
+        refresh_achievement_summary(ctx, player_id).await?;
+    }
+
+    Ok(())
+}
+
+/// Syncs the achievement red dot and the player card's achievement count with the
+/// finished tiers
+pub async fn refresh_achievement_summary(
+    ctx: Arc<Mutex<ConnectionContext>>,
+    player_id: i64,
+) -> Result<(), AppError> {
+    let pool = ctx.lock().await.state.db.clone();
+
+    let finished: Vec<Achievement> = achievements::get_achievements(&pool, player_id)
+        .await?
+        .into_iter()
+        .filter(|a| a.has_finish)
+        .collect();
+    let unread = finished.iter().filter(|a| a.is_new).count() as i32;
+
+    red_dots::upsert_red_dot(
+        &pool,
+        player_id,
+        ACHIEVEMENT_RED_DOT,
+        0,
+        unread,
+        0,
+        "",
+        true,
+    )
+    .await?;
+
+    let count = finished.len() as i32;
+    let mut card = player_card::get_player_card_info(&pool, player_id).await?;
+    if card.achievement_count != count {
+        card.achievement_count = count;
+        player_card::update_player_card_info(&pool, &card).await?;
+    }
+
+    send_red_dot_push(ctx, player_id, Some(vec![ACHIEVEMENT_RED_DOT])).await?;
+
+    Ok(())
+}
@@ -2,22 +2,29 @@ use sqlx::SqlitePool;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
-use super::ConnectionContext;
+use super::{ConnectionContext, EventBus, achievements, tasks};
 
 /// App-level shared state
 pub struct AppState {
     next_down_tag: Mutex<u8>,
     pub db: SqlitePool,
     sessions: dashmap::DashMap<i64, Arc<Mutex<ConnectionContext>>>,
+    pub events: EventBus,
 }
 
 #[allow(dead_code)]
 impl AppState {
     pub fn new(db: SqlitePool) -> Self {
+        let mut events = EventBus::default();
+        events.subscribe("tasks", tasks::on_task_event);
+        events.subscribe("achievements", achievements::on_achievement_event);
+        achievements::warn_unhandled_listeners();
+
         Self {
             next_down_tag: Mutex::new(0),
             db,
             sessions: dashmap::DashMap::new(),
+            events,
         }
     }
 
@@ -0,0 +1,239 @@
+use config::configs;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use sonettobuf::{CardInfo, Fight, FightEntityInfo};
+
+use crate::state::battle::{manager::buff_rules::is_taunt_buff, round::RoundState};
+
+/// logicTarget code for a skill that hits the whole opposing team
+const LOGIC_TARGET_ALL: i32 = 3;
+/// logicTarget code for a skill aimed at the opposing entity with the least HP
+const LOGIC_TARGET_LOWEST_HP: i32 = 4;
+/// logicTarget code for a skill aimed at the opposing entity with the most attack
+const LOGIC_TARGET_HIGHEST_ATTACK: i32 = 5;
+/// Behavior target codes for all enemies, skill effects reuse them as logicTarget
+const LOGIC_TARGET_ALL_ENEMIES: [i32; 2] = [201, 202];
+
+/// How an enemy skill picks its target, from the skill effect's logicTarget
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AiTarget {
+    Random,
+    LowestHp,
+    HighestAttack,
+    /// Hits every hero. The intent points at the first one, the executor
+    /// widens the selected target to the whole team.
+    All,
+}
+
+impl AiTarget {
+    fn from_logic_target(logic_target: &str) -> Self {
+        let code = logic_target
+            .split('#')
+            .next()
+            .and_then(|v| v.parse::<i32>().ok())
+            .unwrap_or(0);
+
+        match code {
+            LOGIC_TARGET_ALL => Self::All,
+            LOGIC_TARGET_LOWEST_HP => Self::LowestHp,
+            LOGIC_TARGET_HIGHEST_ATTACK => Self::HighestAttack,
+            c if LOGIC_TARGET_ALL_ENEMIES.contains(&c) => Self::All,
+            _ => Self::Random,
+        }
+    }
+}
+
+pub fn target_rule(skill_id: i32) -> AiTarget {
+    let game_data = configs::get();
+
+    game_data
+        .skill
+        .get(skill_id)
+        .and_then(|s| game_data.skill_effect.get(s.skill_effect))
+        .map(|e| AiTarget::from_logic_target(&e.logic_target))
+        .unwrap_or(AiTarget::Random)
+}
+
+/// HP percent at or below which a monster switches to its second active skill group,
+/// the first mark of its hpSign. Monsters without one never switch on HP.
+fn phase_two_hp_percent(monster_id: i32) -> Option<i64> {
+    configs::get()
+        .monster
+        .get(monster_id)
+        .and_then(|m| m.hp_sign.split('#').next()?.parse::<i64>().ok())
+        .filter(|&p| p > 0)
+}
+
+/// Moxie a monster needs before it casts its ultimate, from its skill template
+pub fn monster_ex_threshold(monster_id: i32) -> Option<i32> {
+    let game_data = configs::get();
+
+    game_data
+        .monster
+        .get(monster_id)
+        .and_then(|m| game_data.monster_skill_template.get(m.skill_template))
+        .map(|t| t.unique_skill_point)
+        .filter(|&p| p > 0)
+}
+
+/// Plans what every living enemy does this round. The result is the enemy intent
+/// shown at round start and the cards `execute_ai_turn` plays afterwards.
+pub fn plan_enemy_turn(
+    rng: &mut StdRng,
+    fight: &Fight,
+    state: &RoundState,
+    round_num: i32,
+) -> Vec<CardInfo> {
+    let Some(defender) = &fight.defender else {
+        return vec![];
+    };
+
+    let mut cards = Vec::new();
+
+    for enemy in &defender.entitys {
+        let Some(enemy) = state.get_entity(enemy.uid.unwrap_or(0)) else {
+            continue;
+        };
+
+        let enemy_uid = enemy.uid.unwrap_or(0);
+        if enemy_uid >= 0 || enemy.current_hp.unwrap_or(0) <= 0 {
+            continue;
+        }
+
+        let Some(skill_id) = choose_skill(enemy, round_num) else {
+            continue;
+        };
+
+        let Some(target_uid) = pick_target(rng, fight, state, target_rule(skill_id)) else {
+            continue;
+        };
+
+        cards.push(CardInfo {
+            uid: Some(enemy_uid),
+            skill_id: Some(skill_id),
+            card_effect: Some(0),
+            temp_card: Some(false),
+            enchants: vec![],
+            card_type: Some(0),
+            hero_id: enemy.model_id,
+            status: Some(0),
+            target_uid: Some(target_uid),
+            extra_info: None,
+            energy: Some(0),
+            extra_infos: vec![],
+            area_red_or_blue: Some(0),
+            heat_id: Some(0),
+        });
+    }
+
+    cards
+}
+
+/// Target for a planned enemy card when it is actually played. Taunt overrides the plan,
+/// and a dead planned target is replaced following the skill's target rule.
+pub fn resolve_target(
+    rng: &mut StdRng,
+    fight: &Fight,
+    state: &RoundState,
+    skill_id: i32,
+    planned: Option<i64>,
+) -> Option<i64> {
+    if let Some(taunt) = taunting_hero(fight, state) {
+        return Some(taunt);
+    }
+
+    match planned {
+        Some(uid)
+            if uid != 0
+                && state
+                    .get_entity(uid)
+                    .is_some_and(|e| e.current_hp.unwrap_or(0) > 0) =>
+        {
+            Some(uid)
+        }
+        _ => pick_target(rng, fight, state, target_rule(skill_id)),
+    }
+}
+
+fn choose_skill(enemy: &FightEntityInfo, round_num: i32) -> Option<i32> {
+    let monster_id = enemy.model_id.unwrap_or(0);
+
+    // boss ultimate once the moxie bar is full
+    if let Some(ex_skill) = enemy.ex_skill.filter(|&s| s != 0)
+        && let Some(threshold) = monster_ex_threshold(monster_id)
+        && enemy.ex_point.unwrap_or(0) >= threshold
+    {
+        return Some(ex_skill);
+    }
+
+    let hp = enemy.current_hp.unwrap_or(0) as i64;
+    let max_hp = enemy.attr.as_ref().and_then(|a| a.hp).unwrap_or(0).max(1) as i64;
+
+    let in_phase_two =
+        phase_two_hp_percent(monster_id).is_some_and(|percent| hp * 100 <= max_hp * percent);
+
+    let group = if in_phase_two && !enemy.skill_group2.is_empty() {
+        &enemy.skill_group2
+    } else {
+        &enemy.skill_group1
+    };
+
+    if group.is_empty() {
+        return None;
+    }
+
+    // cycle through the group round by round
+    let idx = (round_num.max(1) as usize - 1) % group.len();
+
+    Some(group[idx])
+}
+
+fn living_heroes<'a>(fight: &Fight, state: &'a RoundState) -> Vec<&'a FightEntityInfo> {
+    fight
+        .attacker
+        .as_ref()
+        .map(|a| {
+            a.entitys
+                .iter()
+                .filter_map(|e| state.get_entity(e.uid.unwrap_or(0)))
+                .filter(|e| e.uid.unwrap_or(0) > 0 && e.current_hp.unwrap_or(0) > 0)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn taunting_hero(fight: &Fight, state: &RoundState) -> Option<i64> {
+    living_heroes(fight, state)
+        .into_iter()
+        .find(|e| {
+            state
+                .buff_mgr
+                .get_buffs(e.uid.unwrap_or(0))
+                .iter()
+                .any(|b| is_taunt_buff(b.buff_id))
+        })
+        .and_then(|e| e.uid)
+}
+
+fn pick_target(rng: &mut StdRng, fight: &Fight, state: &RoundState, rule: AiTarget) -> Option<i64> {
+    if let Some(taunt) = taunting_hero(fight, state) {
+        return Some(taunt);
+    }
+
+    let heroes = living_heroes(fight, state);
+
+    let target = match rule {
+        AiTarget::Random => heroes.choose(rng).copied(),
+        AiTarget::LowestHp => heroes
+            .iter()
+            .min_by_key(|e| e.current_hp.unwrap_or(0))
+            .copied(),
+        AiTarget::HighestAttack => heroes
+            .iter()
+            .max_by_key(|e| e.attr.as_ref().and_then(|a| a.attack).unwrap_or(0))
+            .copied(),
+        AiTarget::All => heroes.first().copied(),
+    };
+
+    target.and_then(|e| e.uid)
+}
@@ -7,16 +7,7 @@ pub fn generate_auto_opers(deck: &[CardInfo]) -> Vec<BeginRoundOper> {
     for (idx, _card) in deck.iter().take(3).enumerate() {
         let card_index = idx as i32;
 
-        // 1) Select card
-        opers.push(BeginRoundOper {
-            oper_type: Some(2), // select card
-            param1: Some(card_index),
-            param2: None,
-            to_id: None,
-            param3: None,
-        });
-
-        // 2) Play card
+        // 1) Play card
         opers.push(BeginRoundOper {
             oper_type: Some(1), // play card
             param1: Some(card_index),
@@ -26,7 +17,7 @@ pub fn generate_auto_opers(deck: &[CardInfo]) -> Vec<BeginRoundOper> {
         });
     }
 
-    // 3) End turn
+    // 2) End turn
     opers.push(BeginRoundOper {
         oper_type: Some(4),
         param1: None,
@@ -1,22 +1,35 @@
 use crate::error::AppError;
+use crate::state::battle::ai::plan_enemy_turn;
+use crate::state::battle::entity_builder::resolve_skill_group;
+use crate::state::battle::round::RoundState;
 use config::configs;
 use database::models::game::heros::{HeroModel, UserHeroModel};
 use once_cell::sync::Lazy;
 use rand::thread_rng;
-use rand::{Rng, SeedableRng, rngs::StdRng, seq::SliceRandom};
-use sonettobuf::{CardInfo, CardInfoPush, Fight, FightGroup};
+use rand::{SeedableRng, rngs::StdRng, seq::SliceRandom};
+use sonettobuf::{CardInfo, CardInfoPush, Fight, FightGroup, card_info::CardType};
 use sqlx::SqlitePool;
 use std::collections::HashMap;
 use std::sync::atomic::{AtomicI64, Ordering};
 
 static CARD_UID: AtomicI64 = AtomicI64::new(1);
 
+/// Moves the card uid counter past `max_uid`, see [`super::reserve_buff_uids`]
+pub(crate) fn reserve_card_uids(max_uid: i64) {
+    CARD_UID.fetch_max(max_uid + 1, Ordering::SeqCst);
+}
+
+pub(crate) fn reset_card_uids() {
+    CARD_UID.store(1, Ordering::SeqCst);
+}
+
 // Core deck generation
 pub async fn generate_card_deck(
     pool: &SqlitePool,
     user_id: i64,
     fight_group: &FightGroup,
     max_cards: usize,
+    rng: &mut StdRng,
 ) -> Result<Vec<CardInfo>, AppError> {
     let active_heroes: Vec<i64> = fight_group
         .hero_list
@@ -26,7 +39,7 @@ pub async fn generate_card_deck(
         .collect();
 
     let candidates = build_candidate_pool(pool, user_id, &active_heroes).await?;
-    let deck = draw_cards_with_merge(candidates, max_cards);
+    let deck = draw_cards_with_merge(rng, candidates, max_cards);
 
     Ok(deck)
 }
@@ -37,11 +50,12 @@ pub async fn generate_initial_deck(
     user_id: i64,
     fight_group: &FightGroup,
     act_point: i32,
+    rng: &mut StdRng,
 ) -> Result<CardInfoPush, AppError> {
     let hero_count = fight_group.hero_list.iter().filter(|&&u| u != 0).count();
     let max_cards = compute_max_cards(hero_count);
 
-    let deck = generate_card_deck(pool, user_id, fight_group, max_cards).await?;
+    let deck = generate_card_deck(pool, user_id, fight_group, max_cards, rng).await?;
 
     Ok(CardInfoPush {
         card_group: deck.clone(),
@@ -57,77 +71,11 @@ pub async fn generate_initial_deck(
 pub async fn generate_ai_initial_deck(fight: &Fight, seed: u64) -> Vec<CardInfo> {
     let mut rng = StdRng::seed_from_u64(seed);
 
-    let mut cards = Vec::new();
-
-    let Some(attacker) = &fight.attacker else {
-        return cards;
-    };
-    let Some(defender) = &fight.defender else {
-        return cards;
+    let Ok(state) = RoundState::new(fight) else {
+        return vec![];
     };
 
-    let players: Vec<i64> = attacker
-        .entitys
-        .iter()
-        .filter_map(|e| {
-            let uid = e.uid.unwrap_or(0);
-            let hp = e.current_hp.unwrap_or(0);
-            if uid > 0 && hp > 0 { Some(uid) } else { None }
-        })
-        .collect();
-
-    if players.is_empty() {
-        return cards;
-    }
-
-    for enemy in &defender.entitys {
-        let enemy_uid = enemy.uid.unwrap_or(0);
-        if enemy_uid >= 0 {
-            continue;
-        }
-        if enemy.current_hp.unwrap_or(0) <= 0 {
-            continue;
-        }
-
-        let skill_id = enemy.skill_group1.first().copied().unwrap_or(0);
-        if skill_id == 0 {
-            continue;
-        }
-
-        let target_uid = players[rng.gen_range(0..players.len())];
-
-        let game_data = config::configs::get();
-        let skill_effect_id = game_data
-            .skill
-            .iter()
-            .find(|s| s.id == skill_id)
-            .map(|s| s.skill_effect)
-            .unwrap_or(0);
-
-        if skill_effect_id == 0 {
-            tracing::warn!("AI: skill {} has no skillEffect, skipping", skill_id);
-            continue;
-        }
-
-        cards.push(CardInfo {
-            uid: Some(enemy_uid),
-            skill_id: Some(skill_id),
-            card_effect: Some(0),
-            temp_card: Some(false),
-            enchants: vec![],
-            card_type: Some(0),
-            hero_id: enemy.model_id,
-            status: Some(0),
-            target_uid: Some(target_uid),
-            extra_info: None,
-            energy: Some(0),
-            extra_infos: vec![],
-            area_red_or_blue: Some(0),
-            heat_id: Some(0),
-        });
-    }
-
-    cards
+    plan_enemy_turn(&mut rng, fight, &state, fight.cur_round.unwrap_or(1))
 }
 
 #[allow(dead_code)]
@@ -146,7 +94,7 @@ fn draw_cards_no_merge(candidates: Vec<CardInfo>, max_cards: usize) -> Vec<CardI
     deck
 }
 
-fn compute_max_cards(hero_count: usize) -> usize {
+pub(crate) fn compute_max_cards(hero_count: usize) -> usize {
     (hero_count * 3).min(9)
 }
 
@@ -214,74 +162,129 @@ async fn build_candidate_pool(
         let skills = get_hero_skills(hero_id);
 
         for skill_id in skills {
-            pool_cards.push(CardInfo {
-                uid: Some(CARD_UID.fetch_add(1, Ordering::SeqCst)),
-                hero_id: Some(hero_id),
-                skill_id: Some(skill_id),
-                card_type: Some(0),
-                status: Some(0),
-                temp_card: Some(hero_uid < 0), // Mark trial hero cards
-                enchants: vec![],
-                target_uid: Some(0),
-                energy: Some(0),
-                extra_infos: vec![],
-                area_red_or_blue: Some(0),
-                heat_id: Some(0),
-                card_effect: None,
-                extra_info: None,
-            });
+            // Mark trial hero cards
+            pool_cards.push(new_card(hero_id, skill_id, hero_uid < 0));
         }
     }
 
     Ok(pool_cards)
 }
 
-fn draw_cards_with_merge(candidates: Vec<CardInfo>, max_cards: usize) -> Vec<CardInfo> {
-    let mut rng = thread_rng();
+pub(crate) fn new_card(hero_id: i32, skill_id: i32, temp_card: bool) -> CardInfo {
+    CardInfo {
+        uid: Some(CARD_UID.fetch_add(1, Ordering::SeqCst)),
+        temp_card: Some(temp_card),
+        ..preview_card(hero_id, skill_id)
+    }
+}
+
+/// A card that is only shown, never dealt, so it takes no uid
+pub(crate) fn preview_card(hero_id: i32, skill_id: i32) -> CardInfo {
+    CardInfo {
+        uid: Some(0),
+        hero_id: Some(hero_id),
+        skill_id: Some(skill_id),
+        card_type: Some(0),
+        status: Some(0),
+        temp_card: Some(false),
+        enchants: vec![],
+        target_uid: Some(0),
+        energy: Some(0),
+        extra_infos: vec![],
+        area_red_or_blue: Some(0),
+        heat_id: Some(0),
+        card_effect: None,
+        extra_info: None,
+    }
+}
+
+fn draw_cards_with_merge(
+    rng: &mut StdRng,
+    candidates: Vec<CardInfo>,
+    max_cards: usize,
+) -> Vec<CardInfo> {
     let mut deck: Vec<CardInfo> = Vec::with_capacity(max_cards);
 
     while deck.len() < max_cards {
         let card = candidates
-            .choose(&mut rng)
+            .choose(rng)
             .expect("candidate pool empty")
             .clone();
 
-        if let Some(last) = deck.last_mut() {
-            // Check if same hero and same base skill (differ only in rank)
-            if last.hero_id == card.hero_id
-                && last.skill_id.unwrap_or(0) / 10 == card.skill_id.unwrap_or(0) / 10
-                && last.card_type == card.card_type
-            {
-                let new_rank = last.card_type.unwrap_or(1) + 1;
-
-                let game_data = configs::get();
-                if let Some(upgraded_skill) = game_data.skill.iter().find(|s| {
-                    s.hero_id == last.hero_id.unwrap_or(0)
-                        && s.skill_rank == new_rank
-                        && s.id / 10 == last.skill_id.unwrap_or(0) / 10
-                }) {
-                    last.card_type = Some(new_rank);
-                    last.skill_id = Some(upgraded_skill.id);
-
-                    tracing::debug!(
-                        "Merged: skill {} -> {}, rank {}",
-                        card.skill_id.unwrap_or(0),
-                        upgraded_skill.id,
-                        new_rank
-                    );
-
-                    continue;
-                }
-            }
-        }
-
         deck.push(card);
+        merge_adjacent(&mut deck);
     }
 
     deck
 }
 
-fn get_hero_skills(hero_id: i32) -> Vec<i32> {
+/// Next rank of a card skill (rank 1 -> 2 -> 3), resolved through the hero's skill groups.
+/// Cards are dealt from the base groups, so the ex level groups fall back to those.
+pub(crate) fn next_rank_skill(hero_id: i32, skill_id: i32, ex_level: i32) -> Option<i32> {
+    let hero_type = configs::get()
+        .character
+        .iter()
+        .find(|c| c.id == hero_id)
+        .map(|c| c.hero_type)
+        .unwrap_or(1);
+
+    let mut levels = vec![ex_level];
+    if ex_level != 0 {
+        levels.push(0);
+    }
+
+    levels.into_iter().find_map(|lvl| {
+        (1..=2).find_map(|group| {
+            let skills = resolve_skill_group(hero_id, group, lvl, hero_type);
+            let rank = skills.iter().position(|&s| s == skill_id)?;
+            skills.get(rank + 1).copied()
+        })
+    })
+}
+
+/// Merges neighbouring identical cards into their next rank until the hand is stable.
+/// Returns the hero id of every merged card, one entry per merge.
+pub(crate) fn merge_adjacent(deck: &mut Vec<CardInfo>) -> Vec<i32> {
+    let mut merges = Vec::new();
+
+    'scan: loop {
+        for i in 1..deck.len() {
+            let (left, right) = (&deck[i - 1], &deck[i]);
+
+            if left.hero_id != right.hero_id || left.skill_id != right.skill_id {
+                continue;
+            }
+
+            let hero_id = left.hero_id.unwrap_or(0);
+            let skill_id = left.skill_id.unwrap_or(0);
+
+            // rank 3 and ultimate cards have no next rank
+            let Some(upgraded) = next_rank_skill(hero_id, skill_id, 0) else {
+                continue;
+            };
+
+            tracing::debug!("Merged: skill {} -> {}", skill_id, upgraded);
+
+            // an upgrade without a next rank is the rank 3 card
+            let card_type = if next_rank_skill(hero_id, upgraded, 0).is_some() {
+                CardType::None
+            } else {
+                CardType::Skill3
+            };
+
+            deck[i - 1].skill_id = Some(upgraded);
+            deck[i - 1].card_type = Some(card_type as i32);
+            deck.remove(i);
+            merges.push(hero_id);
+
+            continue 'scan;
+        }
+
+        return merges;
+    }
+}
+
+pub(crate) fn get_hero_skills(hero_id: i32) -> Vec<i32> {
     let game_data = configs::get();
 
     let character = game_data.character.iter().find(|c| c.id == hero_id);
@@ -0,0 +1,150 @@
+use anyhow::Result;
+use config::configs;
+use once_cell::sync::Lazy;
+use sonettobuf::{Fight, FightStep, GetClothInfoReply, UseClothSkillOperRecord};
+use std::collections::HashMap;
+
+use crate::state::battle::{
+    manager::fight_data_mgr::FightDataMgr, round::RoundState, skill_executor::SkillExecutor,
+};
+use crate::util::data_loader::GameDataLoader;
+
+/// Wilderness Key levels as the client is told in `GetClothInfoReply`, loaded once
+static OWNED_LEVELS: Lazy<HashMap<i32, i32>> = Lazy::new(|| {
+    GameDataLoader::load_struct::<GetClothInfoReply>("player/cloth_info.json")
+        .inspect_err(|e| tracing::warn!("Failed to load cloth levels: {}", e))
+        .ok()
+        .and_then(|reply| reply.cloth_infos)
+        .map(|info| {
+            info.clothes
+                .into_iter()
+                .filter_map(|c| Some((c.cloth_id?, c.level?)))
+                .collect()
+        })
+        .unwrap_or_default()
+});
+
+/// Level of a Wilderness Key, 1 when the key is not listed
+fn owned_level(cloth_id: i32) -> i32 {
+    OWNED_LEVELS.get(&cloth_id).copied().unwrap_or(1)
+}
+
+/// `cloth_level` row of the key at the level the player has it
+pub fn cloth_config(cloth_id: i32) -> Option<&'static config::cloth_level::ClothLevel> {
+    let level = owned_level(cloth_id);
+
+    configs::get()
+        .cloth_level
+        .iter()
+        .find(|c| c.id == cloth_id && c.level == level)
+}
+
+/// Rounds a key skill stays unavailable after being used
+fn skill_cooldown(cloth_id: i32, skill_id: i32) -> i32 {
+    let Some(cloth) = cloth_config(cloth_id) else {
+        return 0;
+    };
+
+    [
+        (cloth.skill1, cloth.cd1),
+        (cloth.skill2, cloth.cd2),
+        (cloth.skill3, cloth.cd3),
+    ]
+    .into_iter()
+    .find(|&(id, _)| id == skill_id)
+    .map(|(_, cd)| cd)
+    .unwrap_or(0)
+}
+
+/// Plays a Wilderness Key skill on the live fight. The skill has to belong to the
+/// equipped key, be off cooldown and affordable; it then costs its power and starts
+/// its cooldown. Returns `None` when the use is rejected.
+pub fn use_cloth_skill(
+    data: &mut FightDataMgr,
+    oper: &UseClothSkillOperRecord,
+) -> Result<Option<FightStep>> {
+    let skill_id = oper.skill_id.unwrap_or(0);
+
+    let step = {
+        let (_, _, calc, fight, bloodtithe, buff_mgr) = data.split_all_mut();
+
+        let Some(attacker) = fight.attacker.as_mut() else {
+            return Ok(None);
+        };
+
+        let cloth_id = attacker.cloth_id.unwrap_or(0);
+        let power = attacker.power.unwrap_or(0);
+        let caster_uid = oper
+            .from_id
+            .or_else(|| attacker.player_entity.as_ref().and_then(|p| p.uid))
+            .unwrap_or(0);
+
+        let Some(skill) = attacker
+            .skill_infos
+            .iter_mut()
+            .find(|s| s.skill_id == Some(skill_id))
+        else {
+            tracing::warn!("Cloth {} has no skill {}", cloth_id, skill_id);
+            return Ok(None);
+        };
+
+        if skill.cd.unwrap_or(0) > 0 {
+            tracing::warn!(
+                "Cloth skill {} still on cooldown ({} rounds)",
+                skill_id,
+                skill.cd.unwrap_or(0)
+            );
+            return Ok(None);
+        }
+
+        let need_power = skill.need_power.unwrap_or(0);
+        if power < need_power {
+            tracing::warn!(
+                "Cloth skill {} needs {} power, have {}",
+                skill_id,
+                need_power,
+                power
+            );
+            return Ok(None);
+        }
+
+        skill.cd = Some(skill_cooldown(cloth_id, skill_id));
+        attacker.power = Some(power - need_power);
+
+        let executor = SkillExecutor::new(RoundState::new(fight)?.entities);
+        let step =
+            executor.execute_skill(caster_uid, oper.to_id.unwrap_or(0), skill_id, buff_mgr)?;
+
+        calc.play_step_data(&step, fight, bloodtithe, buff_mgr)
+            .map_err(anyhow::Error::msg)?;
+
+        step
+    };
+
+    data.update_managers();
+
+    Ok(Some(step))
+}
+
+/// End of round upkeep for the key: cooldowns tick down and power is gained for the
+/// cards used and moved this round, capped at the key's maximum.
+pub fn on_round_end(fight: &mut Fight, cards_used: i32, moves: i32) {
+    let Some(attacker) = fight.attacker.as_mut() else {
+        return;
+    };
+
+    for skill in &mut attacker.skill_infos {
+        skill.cd = Some((skill.cd.unwrap_or(0) - 1).max(0));
+    }
+
+    if let Some(cloth) = cloth_config(attacker.cloth_id.unwrap_or(0)) {
+        let gained = cards_used * cloth.r#use + moves * cloth.r#move;
+        let power = attacker.power.unwrap_or(0) + gained;
+
+        attacker.power = Some(if cloth.max_power > 0 {
+            power.min(cloth.max_power)
+        } else {
+            power
+        });
+    }
+}
@@ -1,6 +1,8 @@
 use crate::error::AppError;
 use crate::state::ConnectionContext;
 use anyhow::Result;
+use database::db::game::battle::delete_active_battle;
+use serde::{Deserialize, Serialize};
 use sonettobuf::{
     CmdId, EndFightPush, FightGroup, FightRecord, FightStatistics, UseCardStatistics,
 };
@@ -8,6 +10,7 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
 pub struct BattleStats {
     pub hero_uid: i64,
     pub harm: i64,                     // Damage dealt
@@ -56,6 +59,12 @@ pub async fn send_end_fight_push(
     };
 
     let mut conn = ctx.lock().await;
+
+    // The fight is over, nothing left to reconnect to
+    if let Some(player_id) = conn.player_id {
+        delete_active_battle(&conn.state.db, player_id).await?;
+    }
+
     conn.notify(CmdId::FightEndFightPushCmd, push).await?;
 
     Ok(())
@@ -81,24 +90,3 @@ fn build_fight_statistics(stats: BattleStats) -> FightStatistics {
         get_buffs: stats.buffs_received,
     }
 }
-
-// Helper to collect stats from simulator
-#[allow(dead_code)]
-pub fn collect_battle_stats(
-    fight_group: &FightGroup,
-    // TODO: Pass in actual battle state from simulator
-) -> Vec<BattleStats> {
-    // For now, return empty stats for each hero
-    fight_group
-        .hero_list
-        .iter()
-        .map(|&hero_uid| BattleStats {
-            hero_uid,
-            harm: 0,
-            hurt: 0,
-            heal: 0,
-            cards_used: HashMap::new(),
-            buffs_received: vec![],
-        })
-        .collect()
-}
@@ -1,25 +1,23 @@
+use crate::state::battle::ai::monster_ex_threshold;
+use crate::state::battle::manager::buff_mgr::EntityExAttr;
 use config::configs;
-use database::{
-    db::game::equipment::Equipment,
-    models::game::{equipment::UserEquipmentModel, heros::HeroData},
-};
+use database::models::game::{hero_stats::HeroStats, heros::HeroData};
 use sonettobuf::{EquipRecord, FightEntityInfo, HeroAttribute};
-use sqlx::SqlitePool;
 use std::collections::HashMap;
 
-pub async fn build_hero_entity(
-    pool: &SqlitePool,
+/// Builds a hero's entity with the same stats as its character panel, along with the
+/// ex attributes the entity proto has no room for
+pub fn build_hero_entity(
     hero_data: &HeroData,
     position: i32,
     team_type: i32,
     is_sub: bool,
-) -> FightEntityInfo {
+) -> (FightEntityInfo, EntityExAttr) {
     let record = &hero_data.record;
 
-    let equip_model = UserEquipmentModel::new(record.user_id, pool.clone());
-    let equip_data = equip_model.get_equip(record.default_equip_uid).await.ok();
+    let equip_data = hero_data.equip.as_ref();
 
-    let equip_id = equip_data.as_ref().map(|equip| equip.equip_id);
+    let equip_id = equip_data.map(|equip| equip.equip_id);
 
     let game = configs::get();
     let hero_type = game
@@ -64,12 +62,13 @@ pub async fn build_hero_entity(
         apply_destiny_exchange(&mut skill_group2, map);
     }
 
-    let attr = build_attr(hero_data, equip_data.as_ref());
+    let stats = HeroStats::for_hero_data(hero_data);
+    let attr = stats.attribute(record);
     let current_hp = attr.hp.unwrap_or(0);
 
     let initial_ex_point = calculate_initial_ex_point(record.hero_id, &passives);
 
-    FightEntityInfo {
+    let entity = FightEntityInfo {
         uid: Some(record.uid),
         model_id: Some(record.hero_id),
         skin: Some(record.skin),
@@ -117,48 +116,27 @@ pub async fn build_hero_entity(
         sub_cd: Some(0),
         ex_point_type: Some(detect_ex_point_type(record.hero_id)),
         equips: vec![sonettobuf::EquipRecord {
-            equip_uid: equip_data.as_ref().map(|e| e.uid),
-            equip_id: equip_data.as_ref().map(|e| e.equip_id),
-            equip_lv: equip_data.as_ref().map(|e| e.level),
-            refine_lv: equip_data.as_ref().map(|e| e.refine_lv),
+            equip_uid: equip_data.map(|e| e.uid),
+            equip_id: equip_data.map(|e| e.equip_id),
+            equip_lv: equip_data.map(|e| e.level),
+            refine_lv: equip_data.map(|e| e.refine_lv),
         }],
         destiny_stone: Some(record.destiny_stone),
         destiny_rank: Some(record.destiny_rank),
         custom_unit_id: Some(0),
-    }
-}
-
-fn build_attr(r: &HeroData, equip: Option<&Equipment>) -> HeroAttribute {
-    let mut hp = ((r.record.base_hp as f32) * 1.0986541).round() as i32;
-    let mut atk = ((r.record.base_attack as f32) * 1.0786).round() as i32;
-    let mut def = ((r.record.base_defense as f32) * 1.0942857).round() as i32;
-    let mut mdef = ((r.record.base_mdefense as f32) * 1.0942857).round() as i32;
-    let technic = ((r.record.base_technic as f32) * 1.395604).round() as i32;
-
-    if let Some(equip) = equip {
-        let game_data = configs::get();
+    };
 
-        if let Some(strengthen) = game_data
-            .equip_strengthen
-            .iter()
-            .find(|s| s.strength_type == equip.equip_id)
-        {
-            hp += strengthen.hp;
-            atk += strengthen.atk;
-            def += strengthen.def;
-            mdef += strengthen.mdef;
-        }
-    }
+    let ex_attr = EntityExAttr {
+        cri: stats.cri,
+        recri: stats.recri,
+        cri_dmg: stats.cri_dmg,
+        cri_def: stats.cri_def,
+        add_dmg: stats.add_dmg,
+        drop_dmg: stats.drop_dmg,
+        defense_ignore: stats.defense_ignore,
+    };
 
-    HeroAttribute {
-        hp: Some(hp),
-        attack: Some(atk),
-        defense: Some(def),
-        mdefense: Some(mdef),
-        technic: Some(technic),
-        multi_hp_idx: Some(r.record.base_multi_hp_idx),
-        multi_hp_num: Some(r.record.base_multi_hp_num),
-    }
+    (entity, ex_attr)
 }
 
 pub fn build_player_entity(user_id: i64, team_type: i32) -> FightEntityInfo {
@@ -283,7 +261,7 @@ fn calculate_initial_ex_point(hero_id: i32, _passives: &[i32]) -> i32 {
     }
 }
 
-fn detect_ex_point_type(hero_id: i32) -> i32 {
+pub(crate) fn detect_ex_point_type(hero_id: i32) -> i32 {
     match hero_id {
         3120 => 1,
         3123 => 2,
@@ -292,6 +270,31 @@ fn detect_ex_point_type(hero_id: i32) -> i32 {
     }
 }
 
+/// Moxie needed before the hero's ultimate is dealt.
+/// Uses the character's uniqueSkill_point ("5" or "5#..."), then the monster skill template,
+/// otherwise the default for its ex point type
+pub(crate) fn ex_point_threshold(hero_id: i32) -> i32 {
+    let configured = configs::get()
+        .character
+        .iter()
+        .find(|c| c.id == hero_id)
+        .and_then(|c| c.unique_skill_point.split('#').next()?.parse::<i32>().ok())
+        .filter(|&p| p > 0);
+
+    configured
+        .or_else(|| monster_ex_threshold(hero_id))
+        .unwrap_or(match detect_ex_point_type(hero_id) {
+            1 => 10,
+            _ => 5,
+        })
+}
+
+/// Whether moxie comes from playing/merging cards and taking hits.
+/// Sync and adrenaline heroes are driven by their own passives instead
+pub(crate) fn gains_moxie_from_cards(hero_id: i32) -> bool {
+    matches!(detect_ex_point_type(hero_id), 0 | 1)
+}
+
 fn lookup_ex_skill_group(hero_id: i32, group: i32, ex_level: i32) -> &'static str {
     let game = configs::get();
 
@@ -1,5 +1,7 @@
 use super::BattleContext;
+use super::cloth;
 use super::entity_builder;
+use super::manager::buff_mgr::EntityExAttr;
 use anyhow::Result;
 
 use database::models::game::heros::{HeroModel, UserHeroModel};
@@ -9,18 +11,19 @@ use sonettobuf::{Fight, FightTeam};
 use sqlx::SqlitePool;
 use std::collections::HashMap;
 
+/// Builds the fight, with the ex attributes of the player's heroes by entity uid
 pub async fn build_fight(
     pool: &SqlitePool,
     ctx: &BattleContext,
     fight_group: &sonettobuf::FightGroup,
-) -> Result<Fight> {
+) -> Result<(Fight, HashMap<i64, EntityExAttr>)> {
     // Build attacker team (player)
-    let attacker = build_attacker_team(pool, ctx.player_id, fight_group).await?;
+    let (attacker, ex_attrs) = build_attacker_team(pool, ctx.player_id, fight_group).await?;
 
     // Build defender team (enemies from episode config)
     let defender = build_defender_team(ctx.episode_id).await?;
 
-    Ok(Fight {
+    let fight = Fight {
         attacker: Some(attacker),
         defender: Some(defender.team),
         cur_round: Some(1),
@@ -40,7 +43,9 @@ pub async fn build_fight(
         custom_data: vec![],
         fight_task_box: Some(sonettobuf::FightTaskBox { tasks: vec![] }),
         progress_list: vec![],
-    })
+    };
+
+    Ok((fight, ex_attrs))
 }
 
 static TRIAL_UID_MAP: Lazy<HashMap<i64, i32>> = Lazy::new(|| {
@@ -62,9 +67,10 @@ async fn build_attacker_team(
     pool: &SqlitePool,
     user_id: i64,
     fight_group: &sonettobuf::FightGroup,
-) -> Result<FightTeam> {
+) -> Result<(FightTeam, HashMap<i64, EntityExAttr>)> {
     let mut entitys = Vec::new();
     let mut sub_entitys = Vec::new();
+    let mut ex_attrs = HashMap::new();
     let hero = UserHeroModel::new(user_id, pool.clone());
 
     // Main heroes
@@ -73,9 +79,9 @@ async fn build_attacker_team(
             continue;
         }
         let hero_data = hero.get_uid(*hero_uid as i32).await?;
-        let entity =
-            entity_builder::build_hero_entity(pool, &hero_data, (position + 1) as i32, 1, false)
-                .await;
+        let (entity, ex_attr) =
+            entity_builder::build_hero_entity(&hero_data, (position + 1) as i32, 1, false);
+        ex_attrs.insert(hero_data.record.uid, ex_attr);
         entitys.push(entity);
     }
 
@@ -85,20 +91,23 @@ async fn build_attacker_team(
             continue;
         }
         let hero_data = hero.get_uid(*hero_uid as i32).await?;
-        let entity = entity_builder::build_hero_entity(pool, &hero_data, -1, 1, true).await;
+        let (entity, ex_attr) = entity_builder::build_hero_entity(&hero_data, -1, 1, true);
+        ex_attrs.insert(hero_data.record.uid, ex_attr);
         sub_entitys.push(entity);
     }
 
     let player_entity = entity_builder::build_player_entity(user_id, 1);
 
-    Ok(build_fight_team(
+    let team = build_fight_team(
         entitys,
         sub_entitys,
         player_entity,
         Some(15),
         fight_group.cloth_id,
         build_player_skills(fight_group.cloth_id),
-    ))
+    );
+
+    Ok((team, ex_attrs))
 }
 
 #[allow(dead_code)]
@@ -255,7 +264,7 @@ fn build_trial_hero_entity(
         status: Some(0),
         guard: Some(-1),
         sub_cd: Some(0),
-        ex_point_type: Some(0),
+        ex_point_type: Some(entity_builder::detect_ex_point_type(trial_data.hero_id)),
         equips: vec![],
         destiny_stone: Some(0),
         destiny_rank: Some(0),
@@ -311,18 +320,53 @@ async fn build_defender_team(episode_id: i32) -> Result<BattleSetup> {
         max_round
     );
 
-    let monster_ids: Vec<i32> = battle
+    let team = build_wave_team(episode.battle_id, 1)?
+        .ok_or_else(|| anyhow::anyhow!("Battle {} has no monsters", episode.battle_id))?;
+
+    Ok(BattleSetup { max_round, team })
+}
+
+// Keeps enemy uids unique across waves: wave 1 is -1..-9, wave 2 -11..-19, ...
+const WAVE_UID_STRIDE: usize = 10;
+
+/// Enemy waves of a battle. "|" separates waves and "#" the monsters of one wave,
+/// so a plain "id#id#id" is a single wave.
+pub fn battle_waves(battle_id: i32) -> Vec<Vec<i32>> {
+    let game_data = config::configs::get();
+
+    let Some(battle) = game_data.battle.iter().find(|b| b.id == battle_id) else {
+        return vec![];
+    };
+
+    battle
         .monster_group_ids
-        .split('#')
-        .filter_map(|s| s.parse::<i32>().ok())
-        .collect();
+        .split('|')
+        .map(|wave| {
+            wave.split('#')
+                .filter_map(|s| s.parse::<i32>().ok())
+                .collect::<Vec<_>>()
+        })
+        .filter(|wave| !wave.is_empty())
+        .collect()
+}
+
+/// Defender team for a 1-based wave, `None` once the battle has no more waves
+pub fn build_wave_team(battle_id: i32, wave: i32) -> Result<Option<FightTeam>> {
+    let waves = battle_waves(battle_id);
+
+    let Some(monster_ids) = waves.get((wave.max(1) - 1) as usize) else {
+        return Ok(None);
+    };
+
+    let uid_offset = (wave.max(1) - 1) as usize * WAVE_UID_STRIDE;
 
     let mut entitys = Vec::new();
     for (idx, monster_id) in monster_ids.iter().enumerate() {
-        let entity = build_enemy_entity(*monster_id, idx, (idx + 1) as i32, 2)?;
+        let entity = build_enemy_entity(*monster_id, uid_offset + idx, (idx + 1) as i32, 2)?;
 
         tracing::info!(
-            "Enemy entity: monster_id={}, position={}, uid={:?}",
+            "Enemy entity: wave={}, monster_id={}, position={}, uid={:?}",
+            wave,
             monster_id,
             idx + 1,
             entity.uid
@@ -331,16 +375,23 @@ async fn build_defender_team(episode_id: i32) -> Result<BattleSetup> {
         entitys.push(entity);
     }
 
-    tracing::info!("Built {} enemy entities", entitys.len());
+    tracing::info!(
+        "Built {} enemy entities for wave {}/{}",
+        entitys.len(),
+        wave,
+        waves.len()
+    );
 
     let player_entity = entity_builder::build_player_entity(0, 2);
 
-    let fight_team = build_fight_team(entitys, vec![], player_entity, Some(0), Some(0), vec![]);
-
-    Ok(BattleSetup {
-        max_round,
-        team: fight_team,
-    })
+    Ok(Some(build_fight_team(
+        entitys,
+        vec![],
+        player_entity,
+        Some(0),
+        Some(0),
+        vec![],
+    )))
 }
 
 fn build_fight_team(
@@ -556,17 +607,7 @@ fn parse_monster_skill_group(active_skill: &str, target_group: i32) -> Vec<i32>
 }
 
 fn build_player_skills(cloth_id: Option<i32>) -> Vec<sonettobuf::PlayerSkillInfo> {
-    use config::configs;
-
-    let game_data = configs::get();
-    let cloth_id = cloth_id.unwrap_or(1);
-
-    let cloth_level = game_data
-        .cloth_level
-        .iter()
-        .find(|c| c.id == cloth_id && c.level == 1);
-
-    if let Some(cloth) = cloth_level {
+    if let Some(cloth) = cloth::cloth_config(cloth_id.unwrap_or(1)) {
         let mut skills = Vec::new();
 
         // Skill 1
@@ -0,0 +1,165 @@
+use config::configs;
+use sonettobuf::{
+    BuffInfo, CardInfo, FightEntityDetailInfo, FightEntityInfo, HeroAttribute, HeroExAttribute,
+};
+
+use crate::state::battle::manager::{
+    buff_mgr::{BuffInstance, BuffMgr, EntityExAttr},
+    fight_data_mgr::FightDataMgr,
+};
+
+/// Behavior types whose buff features carry attribute bonuses
+const ATTR_BEHAVIORS: [&str; 2] = ["AttrFix", "AttrFixBuff"];
+
+/// Detail views of both sides, as shown when tapping an entity in battle
+pub fn entity_details(
+    data: &FightDataMgr,
+) -> (Vec<FightEntityDetailInfo>, Vec<FightEntityDetailInfo>) {
+    let side = |is_attacker: bool| {
+        data.entity_mgr
+            .side_entities(is_attacker)
+            .into_iter()
+            .map(|entity| entity_detail(entity, &data.buff_mgr))
+            .collect()
+    };
+
+    (side(true), side(false))
+}
+
+/// Cards left to draw from. `with_hand` puts the cards currently held in front.
+pub fn deck_contents(data: &FightDataMgr, hand: &[CardInfo], with_hand: bool) -> Vec<CardInfo> {
+    let mut cards = if with_hand { hand.to_vec() } else { vec![] };
+    cards.extend(data.card_mgr.draw_pool());
+    cards
+}
+
+fn entity_detail(entity: &FightEntityInfo, buff_mgr: &BuffMgr) -> FightEntityDetailInfo {
+    let uid = entity.uid.unwrap_or(0);
+    let buffs = buff_mgr.get_buffs(uid);
+
+    let mut info = entity.clone();
+    info.buffs = buffs.iter().map(buff_info).collect();
+
+    let base = entity.attr.unwrap_or_default();
+    let (add_per, add_ex) = buff_bonuses(buffs);
+
+    let scale = |value: Option<i32>, permille: Option<i32>| {
+        let value = value.unwrap_or(0);
+        Some(value + (value as i64 * permille.unwrap_or(0) as i64 / 1000) as i32)
+    };
+
+    let base_ex = buff_mgr.ex_attr(uid);
+    let add = |base: i32, bonus: Option<i32>| Some(base + bonus.unwrap_or(0));
+
+    let final_ex = HeroExAttribute {
+        cri: add(base_ex.cri, add_ex.cri),
+        recri: add(base_ex.recri, add_ex.recri),
+        cri_dmg: add(base_ex.cri_dmg, add_ex.cri_dmg),
+        cri_def: add(base_ex.cri_def, add_ex.cri_def),
+        add_dmg: add(base_ex.add_dmg, add_ex.add_dmg),
+        drop_dmg: add(base_ex.drop_dmg, add_ex.drop_dmg),
+    };
+
+    let final_attr = HeroAttribute {
+        hp: scale(base.hp, add_per.hp),
+        attack: scale(base.attack, add_per.attack),
+        defense: scale(base.defense, add_per.defense),
+        mdefense: scale(base.mdefense, add_per.mdefense),
+        technic: scale(base.technic, add_per.technic),
+        multi_hp_idx: base.multi_hp_idx,
+        multi_hp_num: base.multi_hp_num,
+    };
+
+    FightEntityDetailInfo {
+        info: Some(info),
+        ex_attr: Some(ex_attribute(base_ex)),
+        add_attr_per: Some(add_per),
+        add_ex_attr: Some(add_ex),
+        final_attr_base: Some(final_attr),
+        final_ex_attr: Some(final_ex),
+        ..Default::default()
+    }
+}
+
+fn ex_attribute(ex: EntityExAttr) -> HeroExAttribute {
+    HeroExAttribute {
+        cri: Some(ex.cri),
+        recri: Some(ex.recri),
+        cri_dmg: Some(ex.cri_dmg),
+        cri_def: Some(ex.cri_def),
+        add_dmg: Some(ex.add_dmg),
+        drop_dmg: Some(ex.drop_dmg),
+    }
+}
+
+fn buff_info(buff: &BuffInstance) -> BuffInfo {
+    BuffInfo {
+        buff_id: Some(buff.buff_id),
+        duration: Some(buff.duration),
+        uid: Some(buff.uid),
+        from_uid: Some(buff.from_uid),
+        count: Some(buff.count),
+        layer: Some(buff.stacks),
+        r#type: Some(buff.type_id),
+        ..Default::default()
+    }
+}
+
+/// Attribute bonuses of the active buffs, scaled by their stacks. Attribute features
+/// read `behaviorId#attr,value[,attr,value...]`, base attributes in permille.
+fn buff_bonuses(buffs: &[BuffInstance]) -> (HeroAttribute, HeroExAttribute) {
+    let game_data = configs::get();
+
+    let mut base = HeroAttribute::default();
+    let mut ex = HeroExAttribute::default();
+
+    for buff in buffs {
+        let Some(cfg) = game_data.skill_buff.get(buff.buff_id) else {
+            continue;
+        };
+
+        for feature in cfg.features.split('|') {
+            let mut parts = feature.split('#');
+
+            let is_attr = parts
+                .next()
+                .and_then(|id| id.parse::<i32>().ok())
+                .and_then(|id| game_data.skill_behavior.get(id))
+                .is_some_and(|b| ATTR_BEHAVIORS.contains(&b.r#type.as_str()));
+
+            if !is_attr {
+                continue;
+            }
+
+            let values: Vec<&str> = parts.next().unwrap_or_default().split(',').collect();
+
+            for pair in values.chunks(2) {
+                let [attr, value] = pair else {
+                    continue;
+                };
+                let Ok(value) = value.parse::<i32>() else {
+                    continue;
+                };
+
+                let slot = match *attr {
+                    "hp" => &mut base.hp,
+                    "attack" => &mut base.attack,
+                    "defense" => &mut base.defense,
+                    "mdefense" => &mut base.mdefense,
+                    "technic" => &mut base.technic,
+                    "cri" => &mut ex.cri,
+                    "recri" => &mut ex.recri,
+                    "criDmg" => &mut ex.cri_dmg,
+                    "criDef" => &mut ex.cri_def,
+                    "addDmg" => &mut ex.add_dmg,
+                    "dropDmg" => &mut ex.drop_dmg,
+                    _ => continue,
+                };
+
+                *slot = Some(slot.unwrap_or(0) + value * buff.stacks.max(1));
+            }
+        }
+    }
+
+    (base, ex)
+}
@@ -1,17 +1,65 @@
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+use crate::state::battle::manager::buff_rules::{self, BuffOverlay};
+use crate::state::battle::skill_executor::UnhandledLog;
+
 #[allow(dead_code)]
-#[derive(Default, Debug, Clone)]
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
 pub struct BuffInstance {
+    pub uid: i64,
     pub buff_id: i32,
+    pub type_id: i32,
     pub from_uid: i64,
     pub duration: i32,
+    pub count: i32,
     pub stacks: i32,
 }
 
-#[derive(Default, Debug, Clone)]
+/// What adding a buff will do to the target, decided by the buff type rules
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BuffPlan {
+    /// New instance, after removing the listed buff uids
+    Add { removed: Vec<i64> },
+    /// Existing instance gets new values
+    Update {
+        uid: i64,
+        duration: i32,
+        count: i32,
+        stacks: i32,
+    },
+    Reject,
+}
+
+/// Which buffs a DecrDurationAndCount* behavior applies to
+#[derive(Debug, Clone, Copy)]
+pub enum BuffSelector<'a> {
+    /// isGoodBuff polarity (1 = good)
+    Polarity(i32),
+    TypeId(i32),
+    TypeGroup(&'a [i32]),
+}
+
+/// Crit, damage and penetration stats an entity entered the fight with, permille.
+/// Buff attribute features add on top of these.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EntityExAttr {
+    pub cri: i32,
+    pub recri: i32,
+    pub cri_dmg: i32,
+    pub cri_def: i32,
+    pub add_dmg: i32,
+    pub drop_dmg: i32,
+    pub defense_ignore: i32,
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
 pub struct BuffMgr {
     active: HashMap<i64, Vec<BuffInstance>>,
+    #[serde(default)]
+    ex_attrs: HashMap<i64, EntityExAttr>,
+    #[serde(skip)]
+    unhandled: UnhandledLog,
 }
 
 #[allow(dead_code)]
@@ -20,43 +68,187 @@ impl BuffMgr {
         Self::default()
     }
 
-    pub fn add_buff(&mut self, target_uid: i64, buff_id: i32, from_uid: i64) {
-        let entry = self.active.entry(target_uid).or_default();
-
+    pub fn plan_add(&self, target_uid: i64, buff_id: i32) -> BuffPlan {
         let game_data = config::configs::get();
 
-        let duration = game_data
-            .skill_buff
-            .iter()
-            .find(|b| b.id == buff_id)
-            .map(|b| b.during_time)
-            .unwrap_or(0);
-
-        if let Some(existing) = entry.iter_mut().find(|b| b.buff_id == buff_id) {
-            existing.stacks += 1;
-            existing.duration = existing.duration.max(duration);
-        } else {
-            entry.push(BuffInstance {
-                buff_id,
-                from_uid,
+        let Some(buff) = game_data.skill_buff.get(buff_id) else {
+            return BuffPlan::Add { removed: vec![] };
+        };
+
+        let rule = buff_rules::rule_for(buff);
+        let buffs = self.get_buffs(target_uid);
+
+        if rule.exclusive
+            && buffs.iter().any(|b| {
+                b.type_id == buff.type_id
+                    && b.buff_id != buff_id
+                    && buff_rules::is_good_buff(b.buff_id) != (buff.is_good_buff == 1)
+            })
+        {
+            return BuffPlan::Reject;
+        }
+
+        if let Some(existing) = buffs.iter().find(|b| b.buff_id == buff_id) {
+            let stacks = match rule.overlay {
+                BuffOverlay::Stack => (existing.stacks + 1).min(rule.max_layer),
+                BuffOverlay::Refresh | BuffOverlay::Replace => existing.stacks,
+            };
+
+            return BuffPlan::Update {
+                uid: existing.uid,
+                duration: buff.during_time,
+                count: buff.effect_count,
+                stacks,
+            };
+        }
+
+        let removed = match rule.overlay {
+            BuffOverlay::Replace => buffs
+                .iter()
+                .filter(|b| b.type_id == buff.type_id)
+                .map(|b| b.uid)
+                .collect(),
+            BuffOverlay::Stack | BuffOverlay::Refresh => vec![],
+        };
+
+        BuffPlan::Add { removed }
+    }
+
+    /// Carries out a plan from [`Self::plan_add`], `buff_uid` names a newly added buff
+    pub fn apply_plan(
+        &mut self,
+        target_uid: i64,
+        buff_id: i32,
+        from_uid: i64,
+        buff_uid: i64,
+        plan: &BuffPlan,
+    ) {
+        match plan {
+            BuffPlan::Add { removed } => {
+                for &uid in removed {
+                    self.remove_buff(target_uid, uid);
+                }
+                self.insert_buff(target_uid, buff_id, from_uid, buff_uid);
+            }
+            BuffPlan::Update {
+                uid,
                 duration,
-                stacks: 1,
-            });
+                count,
+                stacks,
+            } => {
+                self.update_buff(target_uid, *uid, *duration, *count, *stacks);
+            }
+            BuffPlan::Reject => {}
         }
 
         tracing::info!(
-            "[BuffMgr] Added buff {} to {} from {} (duration={})",
+            "[BuffMgr] Buff {} on {} from {}: {:?}",
             buff_id,
             target_uid,
             from_uid,
-            duration
+            plan
         );
     }
 
+    /// Puts a new instance of a buff on the target with its configured duration and
+    /// count, no stacking rules applied
+    pub fn insert_buff(&mut self, target_uid: i64, buff_id: i32, from_uid: i64, buff_uid: i64) {
+        let game_data = config::configs::get();
+        let buff = game_data.skill_buff.get(buff_id);
+
+        self.active.entry(target_uid).or_default().push(BuffInstance {
+            uid: buff_uid,
+            buff_id,
+            type_id: buff.map(|b| b.type_id).unwrap_or(0),
+            from_uid,
+            duration: buff.map(|b| b.during_time).unwrap_or(0),
+            count: buff.map(|b| b.effect_count).unwrap_or(0),
+            stacks: 1,
+        });
+    }
+
+    pub fn update_buff(
+        &mut self,
+        target_uid: i64,
+        buff_uid: i64,
+        duration: i32,
+        count: i32,
+        stacks: i32,
+    ) {
+        if let Some(existing) = self
+            .active
+            .get_mut(&target_uid)
+            .and_then(|buffs| buffs.iter_mut().find(|b| b.uid == buff_uid))
+        {
+            existing.duration = duration;
+            existing.count = count;
+            existing.stacks = stacks.max(1);
+        }
+    }
+
+    pub fn remove_buff(&mut self, target_uid: i64, buff_uid: i64) -> Option<BuffInstance> {
+        let buffs = self.active.get_mut(&target_uid)?;
+        let idx = buffs.iter().position(|b| b.uid == buff_uid)?;
+        Some(buffs.remove(idx))
+    }
+
+    /// Uses one charge of a count based buff, removing it once it runs out
+    pub fn consume_count(&mut self, target_uid: i64, buff_uid: i64) -> bool {
+        let Some(buffs) = self.active.get_mut(&target_uid) else {
+            return false;
+        };
+
+        let Some(buff) = buffs.iter_mut().find(|b| b.uid == buff_uid) else {
+            return false;
+        };
+
+        if buff.count <= 0 {
+            return false;
+        }
+
+        buff.count -= 1;
+        if buff.count == 0 {
+            buffs.retain(|b| b.uid != buff_uid);
+        }
+
+        true
+    }
+
+    /// Buffs on `target_uid` matched by a DecrDurationAndCount* selector
+    pub fn select(&self, target_uid: i64, selector: BuffSelector) -> Vec<&BuffInstance> {
+        self.get_buffs(target_uid)
+            .iter()
+            .filter(|b| match selector {
+                BuffSelector::Polarity(good) => buff_rules::is_good_buff(b.buff_id) == (good == 1),
+                BuffSelector::TypeId(type_id) => b.type_id == type_id,
+                BuffSelector::TypeGroup(types) => types.contains(&b.type_id),
+            })
+            .collect()
+    }
+
     pub fn get_buffs(&self, uid: i64) -> &[BuffInstance] {
         self.active.get(&uid).map(|v| v.as_slice()).unwrap_or(&[])
     }
 
+    pub fn set_ex_attr(&mut self, uid: i64, attr: EntityExAttr) {
+        self.ex_attrs.insert(uid, attr);
+    }
+
+    /// Ex attributes of an entity before buffs, zero for entities built without them
+    pub fn ex_attr(&self, uid: i64) -> EntityExAttr {
+        self.ex_attrs.get(&uid).copied().unwrap_or_default()
+    }
+
+    /// Skill data the executor skipped while running against this fight
+    pub fn unhandled(&self) -> &UnhandledLog {
+        &self.unhandled
+    }
+
+    /// Highest buff uid still active on any entity
+    pub fn max_uid(&self) -> i64 {
+        self.active.values().flatten().map(|b| b.uid).max().unwrap_or(0)
+    }
+
     pub fn has_buff(&self, uid: i64, buff_id: i32) -> bool {
         self.active
             .get(&uid)
@@ -66,10 +258,14 @@ impl BuffMgr {
 
     pub fn on_round_end(&mut self) {
         for buffs in self.active.values_mut() {
-            for b in buffs.iter_mut() {
+            // duration 0 means permanent (passives) or count based
+            buffs.retain_mut(|b| {
+                if b.duration <= 0 {
+                    return true;
+                }
                 b.duration -= 1;
-            }
-            buffs.retain(|b| b.duration > 0);
+                b.duration > 0
+            });
         }
     }
 
@@ -0,0 +1,108 @@
+use config::configs;
+use config::skill_buff::SkillBuff;
+use once_cell::sync::Lazy;
+use std::collections::{HashMap, HashSet};
+
+/// Layer cap for stackable buffs that don't belong to a replace group
+pub const DEFAULT_MAX_LAYER: i32 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuffOverlay {
+    /// Re-applying adds a layer (up to `max_layer`) and refreshes duration
+    Stack,
+    /// Re-applying resets duration and count without adding a layer
+    Refresh,
+    /// A different buff of the same type evicts the one already on the target
+    Replace,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BuffTypeRule {
+    pub overlay: BuffOverlay,
+    pub max_layer: i32,
+    /// Buffs of the same type with the opposite polarity block each other
+    pub exclusive: bool,
+}
+
+// type_id -> number of distinct buff ids sharing it
+static TYPE_MEMBERS: Lazy<HashMap<i32, usize>> = Lazy::new(|| {
+    let mut map = HashMap::new();
+
+    for buff in configs::get().skill_buff.iter() {
+        if buff.type_id != 0 {
+            *map.entry(buff.type_id).or_insert(0) += 1;
+        }
+    }
+
+    map
+});
+
+// buffs whose features carry a taunt behavior ("behaviorId#params|...")
+static TAUNT_BUFFS: Lazy<HashSet<i32>> = Lazy::new(|| {
+    let game_data = configs::get();
+
+    game_data
+        .skill_buff
+        .iter()
+        .filter(|buff| {
+            buff.features.split('|').any(|feature| {
+                feature
+                    .split('#')
+                    .next()
+                    .and_then(|id| id.parse::<i32>().ok())
+                    .and_then(|id| game_data.skill_behavior.get(id))
+                    .is_some_and(|b| b.r#type == "Taunt")
+            })
+        })
+        .map(|buff| buff.id)
+        .collect()
+});
+
+/// Stacking rule of a buff. The buff type table with the official overlay rules is not
+/// part of the exported configs, so the rule follows from `skill_buff` alone: charges
+/// (`effectCount`), the buffs sharing its `typeId`, or neither.
+pub fn rule_for(buff: &SkillBuff) -> BuffTypeRule {
+    // count based buffs (shields, "next N attacks", ...) only refresh their charges
+    if buff.effect_count > 0 {
+        return BuffTypeRule {
+            overlay: BuffOverlay::Refresh,
+            max_layer: 1,
+            exclusive: buff.type_id != 0,
+        };
+    }
+
+    if buff.type_id == 0 {
+        return BuffTypeRule {
+            overlay: BuffOverlay::Stack,
+            max_layer: DEFAULT_MAX_LAYER,
+            exclusive: false,
+        };
+    }
+
+    // several buffs share this type (e.g. the ranks of one stat up), newest one wins
+    if TYPE_MEMBERS.get(&buff.type_id).copied().unwrap_or(0) > 1 {
+        return BuffTypeRule {
+            overlay: BuffOverlay::Replace,
+            max_layer: 1,
+            exclusive: true,
+        };
+    }
+
+    BuffTypeRule {
+        overlay: BuffOverlay::Stack,
+        max_layer: DEFAULT_MAX_LAYER,
+        exclusive: true,
+    }
+}
+
+pub fn is_good_buff(buff_id: i32) -> bool {
+    configs::get()
+        .skill_buff
+        .get(buff_id)
+        .map(|b| b.is_good_buff == 1)
+        .unwrap_or(false)
+}
+
+pub fn is_taunt_buff(buff_id: i32) -> bool {
+    TAUNT_BUFFS.contains(&buff_id)
+}
@@ -1,11 +1,14 @@
 use sonettobuf::{
     ActEffect, Fight, FightExPointInfo, FightHeroSpAttributeInfo, FightStep, HeroSpAttribute,
-    PlayerSkillInfo,
+    fight_step,
 };
+use std::collections::BTreeMap;
 use std::sync::Arc;
 
 use crate::state::battle::{
     effects::effect_types::EffectType,
+    end_fight::BattleStats,
+    entity_builder::ex_point_threshold,
     manager::{
         buff_mgr::BuffMgr,
         entity_mgr::{FightEntityDataMgr, get_entity_mut_by_location},
@@ -17,7 +20,10 @@ use crate::state::battle::{
 pub struct FightCalculateDataMgr {
     fight: Arc<Fight>,
     entity_mgr: FightEntityDataMgr,
-    buff_mgr: BuffMgr,
+    /// Result screen counters per entity uid
+    stats: BTreeMap<i64, BattleStats>,
+    /// Caster of the step being played, effects are credited to it
+    current_from: i64,
 }
 
 impl FightCalculateDataMgr {
@@ -25,7 +31,8 @@ impl FightCalculateDataMgr {
         Self {
             fight: fight.clone(),
             entity_mgr: FightEntityDataMgr::new(fight.clone()),
-            buff_mgr: BuffMgr::new(),
+            stats: BTreeMap::new(),
+            current_from: 0,
         }
     }
 
@@ -36,10 +43,27 @@ impl FightCalculateDataMgr {
         bloodtithe: &mut BloodtitheState,
         buff_mgr: &mut BuffMgr,
     ) -> Result<(), String> {
-        for effect in &step.act_effect {
-            self.play_act_effect_data(effect, fight, bloodtithe, buff_mgr)?;
+        // nested containers without a caster belong to the enclosing step
+        let from = step
+            .from_id
+            .filter(|&id| id != 0)
+            .unwrap_or(self.current_from);
+        let parent = std::mem::replace(&mut self.current_from, from);
+
+        if step.act_type == Some(fight_step::ActType::Skill.into())
+            && from != 0
+            && let Some(skill_id) = step.act_id.filter(|&id| id != 0)
+        {
+            *self.stats_mut(from).cards_used.entry(skill_id).or_default() += 1;
         }
-        Ok(())
+
+        let result = step
+            .act_effect
+            .iter()
+            .try_for_each(|effect| self.play_act_effect_data(effect, fight, bloodtithe, buff_mgr));
+
+        self.current_from = parent;
+        result
     }
 
     pub fn play_step_data_list(
@@ -95,10 +119,15 @@ impl FightCalculateDataMgr {
             | EffectType::InjuryBankHeal
             | EffectType::SubHeroLifeChange => self.play_effect_heal(effect, fight),
 
-            EffectType::BuffAdd => self.play_effect_add_buff(effect),
+            EffectType::BuffAdd => self.play_effect_add_buff(effect, buff_mgr),
+            EffectType::BuffUpdate => self.play_effect_update_buff(effect, buff_mgr),
+            EffectType::BuffDel | EffectType::BuffDelNoEffect => {
+                self.play_effect_del_buff(effect, buff_mgr)
+            }
+            EffectType::BuffReject => Ok(()),
 
-            EffectType::Dead => self.play_effect_death(effect, fight),
-            EffectType::Kill => self.play_effect_kill(effect, fight),
+            EffectType::Dead => self.play_effect_death(effect, fight, buff_mgr),
+            EffectType::Kill => self.play_effect_kill(effect, fight, buff_mgr),
 
             EffectType::Shield => self.play_effect_shield(effect, fight),
 
@@ -158,6 +187,13 @@ impl FightCalculateDataMgr {
             bloodtithe.on_hp_lost(target_id, team_type, damage);
         }
 
+        if damage > 0 {
+            self.stats_mut(target_id).hurt += damage as i64;
+            if self.current_from != 0 && self.current_from != target_id {
+                self.stats_mut(self.current_from).harm += damage as i64;
+            }
+        }
+
         tracing::trace!("Damage applied: target={}, damage={}", target_id, damage);
         Ok(())
     }
@@ -180,24 +216,78 @@ impl FightCalculateDataMgr {
             .as_ref()
             .and_then(|a| a.hp)
             .unwrap_or(current_hp);
-        entity.current_hp = Some((current_hp + heal).min(max_hp));
+        let healed_hp = (current_hp + heal).min(max_hp);
+        entity.current_hp = Some(healed_hp);
+
+        // only what actually restored hp counts, overheal is dropped
+        let healer = if self.current_from != 0 {
+            self.current_from
+        } else {
+            target_id
+        };
+        self.stats_mut(healer).heal += (healed_hp - current_hp).max(0) as i64;
 
         tracing::trace!("Heal applied: target={}, heal={}", target_id, heal);
         Ok(())
     }
 
-    fn play_effect_add_buff(&mut self, effect: &ActEffect) -> Result<(), String> {
+    fn play_effect_add_buff(
+        &mut self,
+        effect: &ActEffect,
+        buff_mgr: &mut BuffMgr,
+    ) -> Result<(), String> {
         let target_id = effect.target_id.ok_or("No target ID")?;
         let buff_id = effect.effect_num.ok_or("No buff ID")?;
 
         let from_uid = effect.buff.as_ref().and_then(|b| b.from_uid).unwrap_or(0);
+        let buff_uid = effect.buff.as_ref().and_then(|b| b.uid).unwrap_or(0);
 
-        self.buff_mgr.add_buff(target_id, buff_id, from_uid);
+        // the executor already applied the stacking rules, evictions and refreshes
+        // arrive as their own effects
+        buff_mgr.insert_buff(target_id, buff_id, from_uid, buff_uid);
+        self.stats_mut(target_id).buffs_received.push(buff_id);
 
         Ok(())
     }
 
-    fn play_effect_death(&mut self, effect: &ActEffect, fight: &mut Fight) -> Result<(), String> {
+    fn play_effect_update_buff(
+        &mut self,
+        effect: &ActEffect,
+        buff_mgr: &mut BuffMgr,
+    ) -> Result<(), String> {
+        let target_id = effect.target_id.ok_or("No target ID")?;
+        let buff = effect.buff.as_ref().ok_or("No buff info")?;
+
+        buff_mgr.update_buff(
+            target_id,
+            buff.uid.unwrap_or(0),
+            buff.duration.unwrap_or(0),
+            buff.count.unwrap_or(0),
+            buff.layer.unwrap_or(1),
+        );
+
+        Ok(())
+    }
+
+    fn play_effect_del_buff(
+        &mut self,
+        effect: &ActEffect,
+        buff_mgr: &mut BuffMgr,
+    ) -> Result<(), String> {
+        let target_id = effect.target_id.ok_or("No target ID")?;
+        let buff_uid = effect.buff.as_ref().and_then(|b| b.uid).unwrap_or(0);
+
+        buff_mgr.remove_buff(target_id, buff_uid);
+
+        Ok(())
+    }
+
+    fn play_effect_death(
+        &mut self,
+        effect: &ActEffect,
+        fight: &mut Fight,
+        buff_mgr: &mut BuffMgr,
+    ) -> Result<(), String> {
         let target_id = effect.target_id.ok_or("No target ID")?;
 
         let location = self
@@ -209,13 +299,18 @@ impl FightCalculateDataMgr {
             .ok_or_else(|| format!("Failed to get entity {} mutably", target_id))?;
 
         entity.current_hp = Some(0);
-        self.buff_mgr.clear_dead(target_id);
+        buff_mgr.clear_dead(target_id);
 
         tracing::trace!("Entity died: target={}", target_id);
         Ok(())
     }
 
-    fn play_effect_kill(&mut self, effect: &ActEffect, fight: &mut Fight) -> Result<(), String> {
+    fn play_effect_kill(
+        &mut self,
+        effect: &ActEffect,
+        fight: &mut Fight,
+        buff_mgr: &mut BuffMgr,
+    ) -> Result<(), String> {
         let target_id = effect.target_id.ok_or("No target ID")?;
 
         let location = self
@@ -227,7 +322,7 @@ impl FightCalculateDataMgr {
             .ok_or_else(|| format!("Failed to get entity {} mutably", target_id))?;
 
         entity.current_hp = Some(0);
-        self.buff_mgr.clear_dead(target_id);
+        buff_mgr.clear_dead(target_id);
 
         tracing::trace!("Entity killed: target={}", target_id);
         Ok(())
@@ -298,13 +393,29 @@ impl FightCalculateDataMgr {
     fn play_effect_add_ex_point(
         &mut self,
         effect: &ActEffect,
-        _fight: &mut Fight,
+        fight: &mut Fight,
     ) -> Result<(), String> {
         let target_id = effect.target_id.ok_or("No target ID")?;
         let ex_point = effect.effect_num.unwrap_or(0);
 
-        tracing::trace!("EX point added: target={}, amount={}", target_id, ex_point);
-        // TODO: Add EX points to entity
+        let location = self
+            .entity_mgr
+            .get_location(target_id)
+            .ok_or_else(|| format!("Entity {} not found", target_id))?;
+
+        let entity = get_entity_mut_by_location(fight, location)
+            .ok_or_else(|| format!("Failed to get entity {} mutably", target_id))?;
+
+        let max = ex_point_threshold(entity.model_id.unwrap_or(0));
+        let current = entity.ex_point.unwrap_or(0);
+        entity.ex_point = Some((current + ex_point).clamp(0, max));
+
+        tracing::trace!(
+            "EX point added: target={}, amount={}, now={}",
+            target_id,
+            ex_point,
+            entity.ex_point.unwrap_or(0)
+        );
         Ok(())
     }
 
@@ -345,6 +456,58 @@ impl FightCalculateDataMgr {
         self.fight = fight.clone();
         self.entity_mgr.update_fight(fight);
     }
+
+    fn stats_mut(&mut self, uid: i64) -> &mut BattleStats {
+        self.stats.entry(uid).or_insert_with(|| BattleStats {
+            hero_uid: uid,
+            ..Default::default()
+        })
+    }
+
+    pub fn stats(&self) -> &BTreeMap<i64, BattleStats> {
+        &self.stats
+    }
+
+    pub fn set_stats(&mut self, stats: BTreeMap<i64, BattleStats>) {
+        self.stats = stats;
+    }
+
+    /// Counters for the end of fight push, attacker heroes first then every enemy
+    /// that took part, including earlier waves
+    pub fn battle_stats(&self, fight: &Fight) -> (Vec<BattleStats>, Vec<BattleStats>) {
+        let stats_for = |uid: i64| {
+            self.stats.get(&uid).cloned().unwrap_or(BattleStats {
+                hero_uid: uid,
+                ..Default::default()
+            })
+        };
+
+        let attacker = fight
+            .attacker
+            .iter()
+            .flat_map(|t| t.entitys.iter().chain(t.sub_entitys.iter()))
+            .filter_map(|e| e.uid)
+            .filter(|&uid| uid > 0)
+            .map(stats_for)
+            .collect();
+
+        let mut defender_uids: Vec<i64> =
+            self.stats.keys().copied().filter(|&uid| uid < 0).collect();
+        for uid in fight
+            .defender
+            .iter()
+            .flat_map(|t| t.entitys.iter())
+            .filter_map(|e| e.uid)
+        {
+            if !defender_uids.contains(&uid) {
+                defender_uids.push(uid);
+            }
+        }
+
+        let defender = defender_uids.into_iter().map(stats_for).collect();
+
+        (attacker, defender)
+    }
 }
 
 impl FightCalculateDataMgr {
@@ -358,11 +521,7 @@ impl FightCalculateDataMgr {
                     ex_point: entity.ex_point,
                     power_infos: vec![],
                     current_hp: entity.current_hp,
-                    ex_point_type: if entity.model_id == Some(3120) {
-                        Some(1)
-                    } else {
-                        Some(0)
-                    },
+                    ex_point_type: entity.ex_point_type.or(Some(0)),
                 });
             }
 
@@ -436,27 +595,10 @@ impl FightCalculateDataMgr {
 
         attrs
     }
-
-    pub fn build_player_skills(&mut self) -> Vec<PlayerSkillInfo> {
-        vec![
-            PlayerSkillInfo {
-                skill_id: Some(30010201),
-                cd: Some(0),
-                need_power: Some(40),
-                r#type: Some(0),
-            },
-            PlayerSkillInfo {
-                skill_id: Some(30010202),
-                cd: Some(0),
-                need_power: Some(25),
-                r#type: Some(0),
-            },
-        ]
-    }
 }
 
 impl FightCalculateDataMgr {
-    pub fn on_round_end(&mut self) {
-        self.buff_mgr.on_round_end();
+    pub fn on_round_end(&mut self, buff_mgr: &mut BuffMgr) {
+        buff_mgr.on_round_end();
     }
 }
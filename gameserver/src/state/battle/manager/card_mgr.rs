@@ -1,11 +1,17 @@
 use anyhow::Result;
-use rand::Rng;
 use rand::rngs::StdRng;
-use sonettobuf::{ActEffect, BeginRoundOper, Fight, FightStep, fight_step};
+use rand::seq::SliceRandom;
+use sonettobuf::{ActEffect, BeginRoundOper, CardInfo, Fight, FightStep, fight_step};
 use std::sync::Arc;
 
 use crate::state::battle::{
-    effects::effect_types::EffectType, manager::skill_mgr::FightSkillMgr, round::RoundState,
+    ai,
+    cards::{compute_max_cards, get_hero_skills, merge_adjacent, new_card, preview_card},
+    effects::effect_types::EffectType,
+    entity_builder::{ex_point_threshold, gains_moxie_from_cards},
+    manager::skill_mgr::FightSkillMgr,
+    round::RoundState,
+    utils::moxie_change,
 };
 
 #[derive(Default, Debug, Clone)]
@@ -61,33 +67,243 @@ impl FightCardMgr {
     ) -> Result<FightStep> {
         match oper.oper_type.unwrap_or(0) {
             1 => self.play_card(rng, state, oper).await,
-            2 => {
-                if oper.to_id.unwrap_or(0) != 0 {
-                    self.play_card(rng, state, oper).await
-                } else {
-                    Ok(self.select_card(oper))
-                }
-            }
+            2 => Ok(self.move_card(state, oper)),
             3 => Ok(self.change_hero()),
             4 => Ok(self.end_turn()),
             _ => Ok(FightStep::default()),
         }
     }
 
-    fn select_card(&self, oper: BeginRoundOper) -> FightStep {
-        let card_index = oper.param1.unwrap_or(0);
+    /// Moves a card from param1 to param2. Costs one AP, refunded if the hand ends up unchanged
+    fn move_card(&self, state: &mut RoundState, oper: BeginRoundOper) -> FightStep {
+        let from = oper.param1.unwrap_or(0) as usize;
+        let to = oper.param2.unwrap_or(0) as usize;
+        let len = state.player_deck.len();
+
+        if from >= len || to >= len || from == to {
+            return FightStep::default();
+        }
+
+        if state.act_point <= 0 {
+            tracing::warn!("Move card {} -> {} rejected: no AP left", from, to);
+            return FightStep::default();
+        }
+
+        let before: Vec<_> = state.player_deck.iter().map(|c| c.skill_id).collect();
+
+        state.act_point -= 1;
+
+        let card = state.player_deck.remove(from);
+        state.player_deck.insert(to, card);
+
+        let merges = merge_adjacent(&mut state.player_deck);
+
+        let after: Vec<_> = state.player_deck.iter().map(|c| c.skill_id).collect();
+        if before == after {
+            // swapping identical cards changes nothing
+            state.act_point += 1;
+            return FightStep::default();
+        }
+
+        state.move_num += 1;
+
+        let mut act_effect = vec![ActEffect {
+            effect_type: Some(EffectType::Move as i32), // 206
+            effect_num: Some(from as i32),
+            effect_num1: Some(to as i32),
+            card_info_list: state.player_deck.clone(),
+            team_type: Some(1),
+            ..Default::default()
+        }];
+
+        if !merges.is_empty() {
+            act_effect.push(self.compose_effect(state));
+            act_effect.extend(self.merge_moxie(state, &merges));
+        }
 
         FightStep {
             act_type: Some(fight_step::ActType::Effect.into()),
-            act_effect: vec![ActEffect {
-                effect_type: Some(EffectType::AddHandCard as i32), // 149
-                effect_num: Some(card_index),
-                team_type: Some(1),
-                ..Default::default()
-            }],
-            card_index: Some(card_index),
+            act_effect,
+            card_index: Some(to as i32),
+            ..Default::default()
+        }
+    }
+
+    fn compose_effect(&self, state: &RoundState) -> ActEffect {
+        ActEffect {
+            effect_type: Some(EffectType::CardsCompose as i32), // 153
+            card_info_list: state.player_deck.clone(),
+            team_type: Some(1),
+            ..Default::default()
+        }
+    }
+
+    /// Adds moxie to a player hero, returning the effect that replays it on the fight
+    fn gain_moxie(&self, state: &mut RoundState, uid: i64, amount: i32) -> Option<ActEffect> {
+        if uid <= 0 {
+            return None;
+        }
+
+        let entity = state.get_entity_mut(uid)?;
+        let hero_id = entity.model_id.unwrap_or(0);
+
+        if !gains_moxie_from_cards(hero_id) || entity.current_hp.unwrap_or(0) <= 0 {
+            return None;
+        }
+
+        let current = entity.ex_point.unwrap_or(0);
+        let next = (current + amount).clamp(0, ex_point_threshold(hero_id));
+        if next == current {
+            return None;
+        }
+
+        entity.ex_point = Some(next);
+        Some(moxie_change(uid, next - current))
+    }
+
+    /// One moxie per merge, to the hero owning the merged card
+    fn merge_moxie(&self, state: &mut RoundState, merged_heroes: &[i32]) -> Vec<ActEffect> {
+        merged_heroes
+            .iter()
+            .filter_map(|&hero_id| {
+                let uid = hero_uid(state, hero_id)?;
+                self.gain_moxie(state, uid, 1)
+            })
+            .collect()
+    }
+
+    /// One moxie for every player hero damaged by the step
+    fn hit_moxie(&self, state: &mut RoundState, step: &FightStep) -> Vec<ActEffect> {
+        let mut hit: Vec<i64> = step
+            .act_effect
+            .iter()
+            .filter(|e| {
+                matches!(
+                    EffectType::from(e.effect_type.unwrap_or(0)),
+                    EffectType::Damage | EffectType::Crit
+                )
+            })
+            .filter_map(|e| e.target_id)
+            .collect();
+
+        hit.sort_unstable();
+        hit.dedup();
+
+        hit.into_iter()
+            .filter_map(|uid| self.gain_moxie(state, uid, 1))
+            .collect()
+    }
+
+    /// Puts the ultimate of every hero at full moxie into the hand, once
+    pub fn deal_ultimates(&self, state: &mut RoundState) -> Option<ActEffect> {
+        let ready: Vec<(i32, i32)> = self
+            .fight
+            .attacker
+            .as_ref()
+            .map(|a| {
+                a.entitys
+                    .iter()
+                    .filter_map(|e| state.get_entity(e.uid.unwrap_or(0)))
+                    .filter(|e| e.uid.unwrap_or(0) > 0 && e.current_hp.unwrap_or(0) > 0)
+                    .filter_map(|e| {
+                        let hero_id = e.model_id?;
+                        let ex_skill = e.ex_skill.filter(|&s| s != 0)?;
+                        (e.ex_point.unwrap_or(0) >= ex_point_threshold(hero_id))
+                            .then_some((hero_id, ex_skill))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let dealt: Vec<_> = ready
+            .into_iter()
+            .filter(|&(hero_id, ex_skill)| {
+                !state
+                    .player_deck
+                    .iter()
+                    .any(|c| c.hero_id == Some(hero_id) && c.skill_id == Some(ex_skill))
+            })
+            .map(|(hero_id, ex_skill)| new_card(hero_id, ex_skill, false))
+            .collect();
+
+        if dealt.is_empty() {
+            return None;
+        }
+
+        state.player_deck.extend(dealt.iter().cloned());
+
+        Some(ActEffect {
+            effect_type: Some(EffectType::AddHandCard as i32), // 149
+            card_info_list: dealt,
+            team_type: Some(1),
             ..Default::default()
+        })
+    }
+
+    /// Cards the hand can still be refilled with: every skill card of each living hero
+    pub fn draw_pool(&self) -> Vec<CardInfo> {
+        self.fight
+            .attacker
+            .iter()
+            .flat_map(|a| a.entitys.iter())
+            .filter(|e| e.uid.unwrap_or(0) > 0 && e.current_hp.unwrap_or(0) > 0)
+            .filter_map(|e| e.model_id)
+            .flat_map(|hero_id| {
+                get_hero_skills(hero_id)
+                    .into_iter()
+                    .map(move |skill_id| preview_card(hero_id, skill_id))
+            })
+            .collect()
+    }
+
+    /// Deals cards for the next round until the hand is full again, merging as they land
+    pub fn refill_hand(&self, rng: &mut StdRng, state: &mut RoundState) -> Vec<ActEffect> {
+        let heroes: Vec<i32> = self
+            .fight
+            .attacker
+            .as_ref()
+            .map(|a| {
+                a.entitys
+                    .iter()
+                    .filter_map(|e| state.get_entity(e.uid.unwrap_or(0)))
+                    .filter(|e| e.current_hp.unwrap_or(0) > 0)
+                    .filter_map(|e| e.model_id)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let candidates: Vec<(i32, i32)> = heroes
+            .iter()
+            .flat_map(|&hero_id| {
+                get_hero_skills(hero_id)
+                    .into_iter()
+                    .map(move |skill_id| (hero_id, skill_id))
+            })
+            .collect();
+
+        let max_cards = compute_max_cards(heroes.len());
+        if candidates.is_empty() || state.player_deck.len() >= max_cards {
+            return vec![];
+        }
+
+        let mut merged = Vec::new();
+        while state.player_deck.len() < max_cards {
+            let Some(&(hero_id, skill_id)) = candidates.choose(rng) else {
+                break;
+            };
+            state.player_deck.push(new_card(hero_id, skill_id, false));
+            merged.extend(merge_adjacent(&mut state.player_deck));
         }
+
+        let mut effects = vec![ActEffect {
+            effect_type: Some(EffectType::UseCards as i32), // 159
+            card_info_list: state.player_deck.clone(),
+            team_type: Some(1),
+            ..Default::default()
+        }];
+        effects.extend(self.merge_moxie(state, &merged));
+
+        effects
     }
 
     async fn play_card(
@@ -104,15 +320,17 @@ impl FightCardMgr {
             None => return Ok(FightStep::default()),
         };
 
+        if state.act_point <= 0 {
+            tracing::warn!("Play card {} rejected: no AP left", card_index);
+            return Ok(FightStep::default());
+        }
+
         // remove it from hand
         state.player_deck.remove(card_index);
 
         let hero_id = card.hero_id.unwrap_or(0);
 
-        let caster_uid = state
-            .iter_entities()
-            .find(|e| e.model_id == Some(hero_id) && e.uid.unwrap_or(0) > 0)
-            .and_then(|e| e.uid)
+        let caster_uid = hero_uid(state, hero_id)
             .ok_or_else(|| anyhow::anyhow!("No entity for hero {}", hero_id))?;
 
         let skill_id = card.skill_id.unwrap_or(0);
@@ -122,7 +340,33 @@ impl FightCardMgr {
             .execute_skill(state, caster_uid, target_uid, skill_id)?;
 
         state.used_cards.push(card_index as i32);
-        state.act_point = (state.act_point - 1).max(0);
+        state.act_point -= 1;
+
+        let is_ultimate = state
+            .get_entity(caster_uid)
+            .and_then(|e| e.ex_skill)
+            .is_some_and(|ex| ex != 0 && ex == skill_id);
+
+        if is_ultimate {
+            // casting the ultimate spends all moxie
+            if let Some(entity) = state.get_entity_mut(caster_uid) {
+                let spent = entity.ex_point.unwrap_or(0);
+                entity.ex_point = Some(0);
+                if spent > 0 {
+                    step.act_effect.push(moxie_change(caster_uid, -spent));
+                }
+            }
+        } else if let Some(effect) = self.gain_moxie(state, caster_uid, 1) {
+            step.act_effect.push(effect);
+        }
+
+        // the neighbours of the played card may now be adjacent and merge for free
+        let merges = merge_adjacent(&mut state.player_deck);
+        if !merges.is_empty() {
+            step.act_effect.push(self.compose_effect(state));
+            let moxie = self.merge_moxie(state, &merges);
+            step.act_effect.extend(moxie);
+        }
 
         step.card_index = Some(card_index as i32);
         Ok(step)
@@ -135,27 +379,11 @@ impl FightCardMgr {
     ) -> Result<Vec<FightStep>> {
         let mut steps = Vec::new();
 
-        let players: Vec<i64> = state
-            .iter_entities()
-            .filter(|e| e.uid.unwrap_or(0) > 0 && e.current_hp.unwrap_or(0) > 0)
-            .filter_map(|e| e.uid)
-            .collect();
-
-        if players.is_empty() {
-            return Ok(steps);
-        }
+        for card in state.ai_cards.clone() {
+            let caster_uid = card.uid.unwrap_or(0);
+            let skill_id = card.skill_id.unwrap_or(0);
 
-        for i in 0..state.ai_cards.len() {
-            let (caster_uid, skill_id, target_uid_opt) = {
-                let card = &state.ai_cards[i];
-                (
-                    card.uid.unwrap_or(0),
-                    card.skill_id.unwrap_or(0),
-                    card.target_uid,
-                )
-            };
-
-            if caster_uid >= 0 {
+            if caster_uid >= 0 || skill_id == 0 {
                 continue;
             }
 
@@ -166,29 +394,62 @@ impl FightCardMgr {
                 continue;
             }
 
-            if skill_id == 0 {
-                continue;
-            }
+            let is_ultimate = caster.ex_skill == Some(skill_id);
 
-            let target_uid = match target_uid_opt {
-                Some(t) if t != 0 => t,
-                _ => {
-                    let t = players[rng.gen_range(0..players.len())];
-                    state.ai_cards[i].target_uid = Some(t);
-                    t
-                }
+            // no hero left to hit
+            let Some(target_uid) =
+                ai::resolve_target(rng, &self.fight, state, skill_id, card.target_uid)
+            else {
+                break;
             };
 
-            let step = self
+            let mut step = self
                 .skill_mgr
                 .execute_skill(state, caster_uid, target_uid, skill_id)?;
 
+            if let Some(effect) = self.enemy_moxie(state, caster_uid, is_ultimate) {
+                step.act_effect.push(effect);
+            }
+
+            let moxie = self.hit_moxie(state, &step);
+            step.act_effect.extend(moxie);
+
             steps.push(step);
         }
 
         Ok(steps)
     }
 
+    /// Enemy intent for the next round, shown to the client as `ai_use_cards`
+    pub fn plan_ai_turn(&self, rng: &mut StdRng, state: &RoundState) -> Vec<CardInfo> {
+        ai::plan_enemy_turn(rng, &self.fight, state, state.round_num + 1)
+    }
+
+    /// Monsters charge their ultimate by acting and spend it all on the cast
+    fn enemy_moxie(
+        &self,
+        state: &mut RoundState,
+        caster_uid: i64,
+        is_ultimate: bool,
+    ) -> Option<ActEffect> {
+        let entity = state.get_entity_mut(caster_uid)?;
+        let threshold = ai::monster_ex_threshold(entity.model_id.unwrap_or(0))?;
+
+        let current = entity.ex_point.unwrap_or(0);
+        let next = if is_ultimate {
+            0
+        } else {
+            (current + 1).min(threshold)
+        };
+
+        if next == current {
+            return None;
+        }
+
+        entity.ex_point = Some(next);
+        Some(moxie_change(caster_uid, next - current))
+    }
+
     fn change_hero(&self) -> FightStep {
         FightStep {
             act_type: Some(fight_step::ActType::Changehero.into()),
@@ -207,3 +468,10 @@ impl FightCardMgr {
         }
     }
 }
+
+fn hero_uid(state: &RoundState, hero_id: i32) -> Option<i64> {
+    state
+        .iter_entities()
+        .find(|e| e.model_id == Some(hero_id) && e.uid.unwrap_or(0) > 0)
+        .and_then(|e| e.uid)
+}
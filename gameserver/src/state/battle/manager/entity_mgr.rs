@@ -113,6 +113,18 @@ impl FightEntityDataMgr {
         entities
     }
 
+    /// Entities of one side, benched ones included
+    pub fn side_entities(&self, is_attacker: bool) -> Vec<&FightEntityInfo> {
+        let team = if is_attacker {
+            self.fight.attacker.as_ref()
+        } else {
+            self.fight.defender.as_ref()
+        };
+
+        team.map(|t| t.entitys.iter().chain(t.sub_entitys.iter()).collect())
+            .unwrap_or_default()
+    }
+
     pub fn update_fight(&mut self, fight: Arc<Fight>) {
         self.fight = fight;
         self.rebuild_cache();
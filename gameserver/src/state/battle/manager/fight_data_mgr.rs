@@ -1,18 +1,29 @@
+use std::collections::BTreeMap;
 use std::sync::Arc;
 
 use crate::state::battle::{
-    effects::effect_types::EffectType, manager::{
+    effects::effect_types::EffectType, end_fight::BattleStats, fight_builder, manager::{
         blood_pool_mgr::FightBloodPoolDataMgr, buff_mgr::BuffMgr,
         calculate_mgr::FightCalculateDataMgr, card_mgr::FightCardMgr,
         entity_mgr::FightEntityDataMgr, round_mgr::FightRoundMgr,
     }, mechanics::{
         Mechanics,
         bloodtithe::{BloodtitheState, fight_enables_bloodtithe},
-    }, passives, step_builder::FightStepBuilder
+    }, passives, reserve_buff_uids, step_builder::FightStepBuilder
 };
 use anyhow::Result;
+use serde::{Deserialize, Serialize};
 use sonettobuf::{ActEffect, CardInfo, Fight, FightRound, FightStep};
 
+/// Persisted part of [`FightDataMgr`], everything else is derived from the fight
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct FightDataState {
+    mechanics: Mechanics,
+    buff_mgr: BuffMgr,
+    #[serde(default)]
+    stats: BTreeMap<i64, BattleStats>,
+}
+
 #[derive(Default, Debug, Clone)]
 pub struct FightDataMgr {
     fight: Arc<Fight>,
@@ -88,6 +99,7 @@ impl FightDataMgr {
                             entity,
                             fight,
                             &mut self.mechanics.bloodtithe,
+                            &self.buff_mgr,
                         )?);
                     }
                     all
@@ -135,6 +147,7 @@ impl FightDataMgr {
                             entity,
                             fight,
                             &mut self.mechanics.bloodtithe,
+                            &self.buff_mgr,
                         )?);
                     }
                     all
@@ -174,7 +187,11 @@ impl FightDataMgr {
                 let post_power_effects: Vec<ActEffect> = if let Some(attacker) = &fight.attacker {
                     let mut all = Vec::new();
                     for entity in &attacker.entitys {
-                        all.extend(passives::build_post_power_passives(entity, fight)?);
+                        all.extend(passives::build_post_power_passives(
+                            entity,
+                            fight,
+                            &self.buff_mgr,
+                        )?);
                     }
                     all
                 } else {
@@ -202,8 +219,12 @@ impl FightDataMgr {
                     move_num: Some(0),
                     ex_point_info: self.calculate_mgr.build_ex_point_info(fight),
                     ai_use_cards: ai_deck,
-                    power: Some(20),
-                    skill_infos: self.calculate_mgr.build_player_skills(),
+                    power: fight.attacker.as_ref().and_then(|a| a.power),
+                    skill_infos: fight
+                        .attacker
+                        .as_ref()
+                        .map(|a| a.skill_infos.clone())
+                        .unwrap_or_default(),
                     before_cards1: vec![],
                     team_a_cards1: player_deck,
                     before_cards2: vec![],
@@ -241,6 +262,41 @@ impl FightDataMgr {
     pub fn get_fight_snapshot(&self) -> Arc<Fight> {
         self.get_fight()
     }
+
+    /// Manager state that can't be rebuilt from the fight alone
+    pub fn export_state(&self) -> FightDataState {
+        FightDataState {
+            mechanics: self.mechanics.clone(),
+            buff_mgr: self.buff_mgr.clone(),
+            stats: self.calculate_mgr.stats().clone(),
+        }
+    }
+
+    /// Rebuilds the managers around a persisted fight
+    pub fn restore(fight: Fight, state: FightDataState) -> Self {
+        let mut mgr = Self::new(fight);
+        mgr.mechanics = state.mechanics;
+        mgr.buff_mgr = state.buff_mgr;
+        mgr.calculate_mgr.set_stats(state.stats);
+
+        let fight_max = [&mgr.fight.attacker, &mgr.fight.defender]
+            .into_iter()
+            .flatten()
+            .flat_map(|team| {
+                team.entitys
+                    .iter()
+                    .chain(&team.sub_entitys)
+                    .chain(&team.sp_entitys)
+                    .chain(&team.player_entity)
+            })
+            .flat_map(|entity| &entity.buffs)
+            .filter_map(|buff| buff.uid)
+            .max()
+            .unwrap_or(0);
+        reserve_buff_uids(fight_max.max(mgr.buff_mgr.max_uid()));
+
+        mgr
+    }
 }
 
 #[allow(dead_code)]
@@ -253,6 +309,41 @@ impl FightDataMgr {
         Arc::make_mut(&mut self.fight)
     }
 
+    /// True once every enemy of the current (last) wave is down
+    pub fn defender_defeated(&self) -> bool {
+        !self
+            .fight
+            .defender
+            .as_ref()
+            .is_some_and(|d| d.entitys.iter().any(|e| e.current_hp.unwrap_or(0) > 0))
+    }
+
+    /// True once the fight can't go on: the heroes are wiped, or the last wave is down
+    pub fn fight_over(&self) -> bool {
+        let heroes_alive = self
+            .fight
+            .attacker
+            .as_ref()
+            .is_some_and(|a| a.entitys.iter().any(|e| e.current_hp.unwrap_or(0) > 0));
+        if !heroes_alive {
+            return true;
+        }
+        if !self.defender_defeated() {
+            return false;
+        }
+
+        let next_wave = self.fight.cur_wave.unwrap_or(1) + 1;
+        !matches!(
+            fight_builder::build_wave_team(self.fight.battle_id.unwrap_or(0), next_wave),
+            Ok(Some(_))
+        )
+    }
+
+    /// Per-entity result screen counters, attacker side then defender side
+    pub fn battle_stats(&self) -> (Vec<BattleStats>, Vec<BattleStats>) {
+        self.calculate_mgr.battle_stats(&self.fight)
+    }
+
     pub fn bloodtithe(&self) -> &BloodtitheState {
         &self.mechanics.bloodtithe
     }
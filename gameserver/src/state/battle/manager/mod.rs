@@ -1,5 +1,6 @@
 pub mod blood_pool_mgr;
 pub mod buff_mgr;
+pub mod buff_rules;
 pub mod calculate_mgr;
 pub mod card_mgr;
 pub mod entity_mgr;
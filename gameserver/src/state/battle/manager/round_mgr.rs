@@ -1,14 +1,17 @@
 use anyhow::Result;
 use rand::rngs::StdRng;
 use sonettobuf::{
-    ActEffect, BeginRoundOper, CardInfo, Fight, FightRound, FightStep,
+    ActEffect, BeginRoundOper, CardInfo, Fight, FightRound, FightStep, FightTeam,
     effect_type_enum::EffectType, fight_step,
 };
+use std::collections::HashSet;
 use std::sync::Arc;
 
 use crate::state::battle::{
+    ai, cloth, fight_builder,
     manager::{buff_mgr::BuffMgr, calculate_mgr::FightCalculateDataMgr, card_mgr::FightCardMgr},
     mechanics::bloodtithe::BloodtitheState,
+    passives,
     round::{RoundSnapshot, RoundState},
 };
 
@@ -38,11 +41,12 @@ impl FightRoundMgr {
         ai_deck: Vec<CardInfo>,
         buff_mgr: &mut BuffMgr,
     ) -> Result<FightRound> {
-        let (steps, round_snapshot) = {
+        let (mut steps, round_snapshot) = {
             let mut state = RoundState::new(&*fight)?;
 
             state.player_deck = current_deck.clone();
             state.ai_cards = ai_deck.clone();
+            state.buff_mgr = buff_mgr.clone();
 
             let mut steps = Vec::new();
 
@@ -63,14 +67,140 @@ impl FightRoundMgr {
 
             state.is_finish = self.check_battle_end(&state);
 
+            // deal the hand the next round starts with, ultimates first
+            if !state.is_finish {
+                let mut deal: Vec<ActEffect> =
+                    card_mgr.deal_ultimates(&mut state).into_iter().collect();
+                deal.extend(card_mgr.refill_hand(rng, &mut state));
+
+                if !deal.is_empty() {
+                    steps.push(FightStep {
+                        act_type: Some(fight_step::ActType::Effect.into()),
+                        act_effect: deal,
+                        ..Default::default()
+                    });
+                }
+
+                state.ai_cards = card_mgr.plan_ai_turn(rng, &state);
+            }
+
             (steps, state.export_snapshot())
         };
 
+        let alive_before: HashSet<i64> = fight
+            .attacker
+            .iter()
+            .chain(fight.defender.iter())
+            .flat_map(|t| t.entitys.iter())
+            .filter(|e| e.current_hp.unwrap_or(0) > 0)
+            .filter_map(|e| e.uid)
+            .collect();
+
         calc.play_step_data_list(&steps, fight, bloodtithe, buff_mgr)
             .map_err(anyhow::Error::msg)?;
-        calc.on_round_end();
 
-        Ok(self.build_round_response(steps, round_snapshot, current_deck))
+        if let Some(reactions) =
+            passives::build_reaction_passives(&steps, &alive_before, fight, buff_mgr)?
+        {
+            calc.play_step_data(&reactions, fight, bloodtithe, buff_mgr)
+                .map_err(anyhow::Error::msg)?;
+            steps.push(reactions);
+        }
+
+        calc.on_round_end(buff_mgr);
+
+        let mut round = self.build_round_response(steps, round_snapshot, current_deck);
+
+        if !round.is_finish.unwrap_or(false) {
+            self.advance_fight(rng, fight, buff_mgr, &mut round)?;
+
+            cloth::on_round_end(
+                fight,
+                round.use_card_list.len() as i32,
+                round.move_num.unwrap_or(0),
+            );
+        }
+
+        if let Some(attacker) = &fight.attacker {
+            round.power = attacker.power;
+            round.skill_infos = attacker.skill_infos.clone();
+        }
+
+        fight.is_finish = round.is_finish;
+
+        Ok(round)
+    }
+
+    /// Moves the fight past a played round: brings in the next wave once the current
+    /// one is cleared, and ends the fight after the last wave, a wipe or the round limit.
+    fn advance_fight(
+        &self,
+        rng: &mut StdRng,
+        fight: &mut Fight,
+        buff_mgr: &BuffMgr,
+        round: &mut FightRound,
+    ) -> Result<()> {
+        if !team_alive(fight.attacker.as_ref()) {
+            round.is_finish = Some(true);
+            return Ok(());
+        }
+
+        if !team_alive(fight.defender.as_ref()) {
+            match self.spawn_next_wave(rng, fight, buff_mgr)? {
+                Some(ai_cards) => round.ai_use_cards = ai_cards,
+                None => {
+                    round.is_finish = Some(true);
+                    return Ok(());
+                }
+            }
+        }
+
+        let next_round = fight.cur_round.unwrap_or(1) + 1;
+        if fight.max_round.unwrap_or(0) > 0 && next_round > fight.max_round.unwrap_or(0) {
+            round.is_finish = Some(true);
+            return Ok(());
+        }
+
+        fight.cur_round = Some(next_round);
+        round.cur_round = Some(next_round);
+
+        Ok(())
+    }
+
+    /// Replaces the cleared defender team with the next wave. Heroes keep their HP,
+    /// moxie and buffs since only the defender side is rebuilt.
+    fn spawn_next_wave(
+        &self,
+        rng: &mut StdRng,
+        fight: &mut Fight,
+        buff_mgr: &BuffMgr,
+    ) -> Result<Option<Vec<CardInfo>>> {
+        let next_wave = fight.cur_wave.unwrap_or(1) + 1;
+
+        let Some(team) = fight_builder::build_wave_team(fight.battle_id.unwrap_or(0), next_wave)?
+        else {
+            return Ok(None);
+        };
+
+        tracing::info!(
+            "Wave {} cleared, spawning wave {} ({} enemies)",
+            next_wave - 1,
+            next_wave,
+            team.entitys.len()
+        );
+
+        fight.defender = Some(team);
+        fight.cur_wave = Some(next_wave);
+
+        let mut state = RoundState::new(fight)?;
+        state.buff_mgr = buff_mgr.clone();
+
+        Ok(Some(ai::plan_enemy_turn(
+            rng,
+            fight,
+            &state,
+            state.round_num + 1,
+        )))
     }
 
     fn check_battle_end(&self, state: &RoundState) -> bool {
@@ -134,3 +264,7 @@ impl FightRoundMgr {
         }
     }
 }
+
+fn team_alive(team: Option<&FightTeam>) -> bool {
+    team.is_some_and(|t| t.entitys.iter().any(|e| e.current_hp.unwrap_or(0) > 0))
+}
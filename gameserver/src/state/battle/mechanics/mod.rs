@@ -1,8 +1,9 @@
 pub mod bloodtithe;
 
 use bloodtithe::BloodtitheState;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, PartialEq, Default)]
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
 pub struct Mechanics {
     pub bloodtithe: BloodtitheState,
 }
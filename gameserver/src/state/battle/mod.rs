@@ -1,18 +1,24 @@
+mod ai;
 mod auto;
 mod cards;
+mod cloth;
 mod passives;
 
 pub mod effects;
 pub mod end_fight;
 pub mod entity_builder;
 pub mod fight_builder;
+pub mod inspect;
 pub mod manager;
 pub mod mechanics;
+pub mod persist;
+pub mod replay;
 pub mod rewards;
 pub mod round;
 pub mod round_builder;
 pub mod simulator;
 pub mod skill_executor;
+pub mod stars;
 pub mod step_builder;
 pub mod utils;
 
@@ -22,12 +28,29 @@ use sonettobuf::Fight;
 use sonettobuf::FightRound;
 use sqlx::SqlitePool;
 
-use std::sync::atomic::AtomicI64;
+use std::sync::atomic::{AtomicI64, Ordering};
 
 pub static BUFF_UID_COUNTER: AtomicI64 = AtomicI64::new(2);
 
+/// Moves the buff uid counter past `max_uid`. The counter restarts with the server, so a
+/// restored fight would otherwise hand out uids its buffs already use.
+pub(crate) fn reserve_buff_uids(max_uid: i64) {
+    BUFF_UID_COUNTER.fetch_max(max_uid + 1, Ordering::SeqCst);
+}
+
+/// Restarts the buff and card uid counters so the uids of a fight follow from the fight
+/// alone. Only for tools running one fight at a time, the server shares the counters
+/// between every connection.
+#[allow(dead_code)]
+pub fn reset_uid_counters() {
+    BUFF_UID_COUNTER.store(2, Ordering::SeqCst);
+    cards::reset_card_uids();
+}
+
 pub use auto::generate_auto_opers;
 
+pub use cloth::use_cloth_skill;
+
 pub use cards::{default_max_ap, generate_ai_initial_deck, generate_initial_deck};
 
 use crate::state::battle::manager::fight_data_mgr::FightDataMgr;
@@ -47,13 +70,13 @@ pub async fn create_battle(
     fight_group: &sonettobuf::FightGroup,
     player_deck: Vec<sonettobuf::CardInfo>,
 ) -> Result<(Fight, FightRound, FightDataMgr, Vec<CardInfo>)> {
-    let fight = fight_builder::build_fight(pool, &ctx, fight_group).await?;
+    let (fight, ex_attrs) = fight_builder::build_fight(pool, &ctx, fight_group).await?;
 
     let seed = (ctx.player_id as u64) ^ (ctx.episode_id as u64) ^ 0xA11C;
     let ai_deck = generate_ai_initial_deck(&fight, seed).await;
 
     let (initial_round, modified_fight, fight_data_mgr) =
-        round_builder::build_initial_round(fight, player_deck, ai_deck.clone()).await?;
+        round_builder::build_initial_round(fight, ex_attrs, player_deck, ai_deck.clone()).await?;
 
     Ok((modified_fight, initial_round, fight_data_mgr, ai_deck))
 }
@@ -3,6 +3,9 @@ use crate::state::battle::{effects::effect_types::EffectType, step_builder::Figh
 use anyhow::Result;
 use sonettobuf::{ActEffect, Fight, FightEntityInfo};
 
+/// Psychube passives with hand-written effects, everything else goes through the generic engine
+pub const HANDLED: &[i32] = &[433011, 434811, 435611, 435621];
+
 pub fn build_battle_start(entity: &FightEntityInfo, fight: &Fight) -> Result<Vec<ActEffect>> {
     let uid = entity.uid.unwrap_or(0);
     let team_type = entity.team_type.unwrap_or(1);
@@ -0,0 +1,96 @@
+use anyhow::Result;
+use config::configs;
+use sonettobuf::{ActEffect, Fight, FightEntityInfo};
+
+use crate::state::battle::{
+    manager::buff_mgr::BuffMgr, round::RoundState, skill_executor::SkillExecutor,
+    step_builder::FightStepBuilder,
+};
+
+/// Fight timing a passive behavior slot fires on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PassiveTrigger {
+    BattleStart,
+    RoundStart,
+    PostPower,
+    /// The owner took damage, the attacker is the target
+    OnHit,
+    /// The owner died this round
+    OnDeath,
+}
+
+/// Slot condition ids known to carry a timing rather than a state check. The condition
+/// table is not exported, these are the ids the baseline executor's `check_condition`
+/// already treats as unconditional, so they fire once at battle start as they did when
+/// cast. Timings for the other triggers get added here once their ids are confirmed.
+const TIMING_CONDITIONS: &[(i32, PassiveTrigger)] = &[
+    (203, PassiveTrigger::BattleStart),
+    (208, PassiveTrigger::BattleStart),
+];
+
+impl PassiveTrigger {
+    /// Timing encoded by a slot's condition, `None` for ids not in [`TIMING_CONDITIONS`]
+    pub fn from_condition(condition: &str) -> Option<Self> {
+        let cond_id = condition
+            .split('#')
+            .next()
+            .and_then(|v| v.parse::<i32>().ok())?;
+
+        TIMING_CONDITIONS
+            .iter()
+            .find(|(id, _)| *id == cond_id)
+            .map(|&(_, trigger)| trigger)
+    }
+}
+
+/// Runs every passive of `entity` that has no hand-coded module through the skill
+/// executor, one battle container per passive that produced effects.
+pub fn build(
+    entity: &FightEntityInfo,
+    fight: &Fight,
+    buff_mgr: &BuffMgr,
+    trigger: PassiveTrigger,
+    target_uid: i64,
+    hand_coded: impl Fn(i32) -> bool,
+) -> Result<Vec<ActEffect>> {
+    let uid = entity.uid.unwrap_or(0);
+    let game_data = configs::get();
+
+    let passive_ids: Vec<i32> = entity
+        .passive_skill
+        .iter()
+        .copied()
+        .filter(|&id| !hand_coded(id) && game_data.skill_effect.get(id).is_some())
+        .collect();
+
+    if passive_ids.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let executor = SkillExecutor::new(RoundState::new(fight)?.entities);
+    let mut effects = vec![];
+
+    for passive_id in passive_ids {
+        let inner = executor.execute_passive(uid, target_uid, passive_id, trigger, buff_mgr)?;
+
+        if inner.is_empty() {
+            continue;
+        }
+
+        tracing::debug!(
+            "Passive {} of {} fired on {:?} ({} effects)",
+            passive_id,
+            uid,
+            trigger,
+            inner.len()
+        );
+
+        effects.push(
+            FightStepBuilder::new_effect()
+                .add_battle_container(uid, passive_id, inner)
+                .build_as_act_effect(),
+        );
+    }
+
+    Ok(effects)
+}
@@ -1,18 +1,47 @@
 mod activity;
 mod equipment;
+mod generic;
 mod hero_3088;
 mod hero_3120;
 mod hero_3125;
 
+use std::collections::{HashMap, HashSet};
+
 use anyhow::Result;
-use sonettobuf::{ActEffect, Fight, FightEntityInfo};
+use sonettobuf::{ActEffect, Fight, FightEntityInfo, FightStep, effect_type_enum::EffectType};
+
+use crate::state::battle::{
+    manager::buff_mgr::BuffMgr, mechanics::bloodtithe::BloodtitheState,
+    step_builder::FightStepBuilder,
+};
+
+pub use generic::PassiveTrigger;
+
+/// Heroes whose passives are fully hand-coded in their own module
+const HAND_CODED_HEROES: &[i32] = &[3088, 3120, 3125];
 
-use crate::state::battle::mechanics::bloodtithe::BloodtitheState;
+fn is_hand_coded(entity: &FightEntityInfo, passive_id: i32) -> bool {
+    HAND_CODED_HEROES.contains(&entity.model_id.unwrap_or(0))
+        || equipment::HANDLED.contains(&passive_id)
+}
+
+fn build_generic(
+    entity: &FightEntityInfo,
+    fight: &Fight,
+    buff_mgr: &BuffMgr,
+    trigger: PassiveTrigger,
+    target_uid: i64,
+) -> Result<Vec<ActEffect>> {
+    generic::build(entity, fight, buff_mgr, trigger, target_uid, |id| {
+        is_hand_coded(entity, id)
+    })
+}
 
 pub fn build_battle_start_passives(
     entity: &FightEntityInfo,
     fight: &Fight,
     bloodtithe: &mut BloodtitheState,
+    buff_mgr: &BuffMgr,
 ) -> Result<Vec<ActEffect>> {
     let model_id = entity.model_id.unwrap_or(0);
 
@@ -24,6 +53,13 @@ pub fn build_battle_start_passives(
     };
 
     effects.extend(equipment::build_battle_start(entity, fight)?);
+    effects.extend(build_generic(
+        entity,
+        fight,
+        buff_mgr,
+        PassiveTrigger::BattleStart,
+        0,
+    )?);
 
     Ok(effects)
 }
@@ -31,6 +67,7 @@ pub fn build_battle_start_passives(
 pub fn build_post_power_passives(
     entity: &FightEntityInfo,
     fight: &Fight,
+    buff_mgr: &BuffMgr,
 ) -> Result<Vec<ActEffect>> {
     let model_id = entity.model_id.unwrap_or(0);
 
@@ -38,7 +75,7 @@ pub fn build_post_power_passives(
         3088 => hero_3088::build_post_power(entity, fight),
         3120 => hero_3120::build_post_power(entity, fight),
         3125 => hero_3125::build_post_power(entity, fight),
-        _ => Ok(vec![]),
+        _ => build_generic(entity, fight, buff_mgr, PassiveTrigger::PostPower, 0),
     }
 }
 
@@ -46,6 +83,7 @@ pub fn build_round_start_passives(
     entity: &FightEntityInfo,
     fight: &Fight,
     bloodtithe: &mut BloodtitheState,
+    buff_mgr: &BuffMgr,
 ) -> Result<Vec<ActEffect>> {
     let model_id = entity.model_id.unwrap_or(0);
 
@@ -53,7 +91,85 @@ pub fn build_round_start_passives(
         3088 => hero_3088::build_round_start(entity, fight, bloodtithe),
         3120 => hero_3120::build_round_start(entity, fight),
         3125 => hero_3125::build_round_start(entity, fight),
-        _ => Ok(vec![]),
+        _ => build_generic(entity, fight, buff_mgr, PassiveTrigger::RoundStart, 0),
+    }
+}
+
+/// On-hit and on-death passives for a played round. `steps` have already been applied
+/// to `fight`, `alive_before` holds the uids that were alive when the round started.
+pub fn build_reaction_passives(
+    steps: &[FightStep],
+    alive_before: &HashSet<i64>,
+    fight: &Fight,
+    buff_mgr: &BuffMgr,
+) -> Result<Option<FightStep>> {
+    // hit entity -> last attacker
+    let mut hits: HashMap<i64, i64> = HashMap::new();
+    for step in steps {
+        collect_hits(step, &mut hits);
+    }
+
+    let entities = fight
+        .attacker
+        .iter()
+        .chain(fight.defender.iter())
+        .flat_map(|t| t.entitys.iter());
+
+    let mut effects = vec![];
+
+    for entity in entities {
+        let uid = entity.uid.unwrap_or(0);
+        let Some(&attacker_uid) = hits.get(&uid) else {
+            continue;
+        };
+
+        let trigger = if entity.current_hp.unwrap_or(0) > 0 {
+            PassiveTrigger::OnHit
+        } else if alive_before.contains(&uid) {
+            PassiveTrigger::OnDeath
+        } else {
+            continue;
+        };
+
+        effects.extend(build_generic(
+            entity,
+            fight,
+            buff_mgr,
+            trigger,
+            attacker_uid,
+        )?);
+    }
+
+    if effects.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(
+        FightStepBuilder::new_effect().add_effects(effects).build(),
+    ))
+}
+
+fn collect_hits(step: &FightStep, hits: &mut HashMap<i64, i64>) {
+    let from_id = step.from_id.unwrap_or(0);
+
+    for effect in &step.act_effect {
+        if let Some(nested) = &effect.fight_step {
+            collect_hits(nested, hits);
+            continue;
+        }
+
+        let is_damage = matches!(
+            effect.effect_type,
+            Some(t) if t == EffectType::Damage as i32 || t == EffectType::Crit as i32
+        );
+
+        if is_damage
+            && effect.effect_num.unwrap_or(0) > 0
+            && let Some(target_id) = effect.target_id
+            && target_id != from_id
+        {
+            hits.insert(target_id, from_id);
+        }
     }
 }
 
@@ -0,0 +1,184 @@
+use anyhow::Result;
+use database::db::game::battle::{
+    ActiveBattleRow, get_active_battle, save_active_battle, save_active_battle_in,
+};
+use serde::{Deserialize, Serialize};
+use sonettobuf::{CardInfo, Fight, FightGroup, FightRound, UseClothSkillOperRecord};
+use sqlx::{Sqlite, SqlitePool, Transaction};
+
+use crate::state::{
+    ActiveBattle,
+    battle::{
+        cards::reserve_card_uids,
+        manager::fight_data_mgr::{FightDataMgr, FightDataState},
+        replay::ReplayStart,
+    },
+};
+
+/// `ActiveBattle` fields that don't have their own column
+#[derive(Debug, Serialize, Deserialize)]
+struct BattleInfo {
+    tower_type: Option<i32>,
+    tower_id: Option<i32>,
+    layer_id: Option<i32>,
+    chapter_id: i32,
+    difficulty: Option<i32>,
+    talent_plan_id: Option<i32>,
+    act_point: i32,
+    power: i32,
+    fight_group: Option<FightGroup>,
+    is_replay: Option<bool>,
+    replay_episode_id: Option<i32>,
+    multiplication: Option<i32>,
+    replay_start: Option<ReplayStartInfo>,
+    #[serde(default)]
+    cloth_skill_opers: Vec<UseClothSkillOperRecord>,
+    #[serde(default)]
+    stamina_cost: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ReplayStartInfo {
+    fight: Fight,
+    state: FightDataState,
+    deck: Vec<CardInfo>,
+    ai_deck: Vec<CardInfo>,
+}
+
+/// Writes the fight as it stands after `last_round` so it can be resumed later
+pub async fn persist_active_battle(
+    pool: &SqlitePool,
+    user_id: i64,
+    battle: &ActiveBattle,
+    last_round: &FightRound,
+) -> Result<()> {
+    save_active_battle(pool, &active_battle_row(user_id, battle, last_round)?).await
+}
+
+/// [`persist_active_battle`] as part of `tx`, for fights that only exist once paid for
+pub async fn persist_active_battle_in(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: i64,
+    battle: &ActiveBattle,
+    last_round: &FightRound,
+) -> Result<()> {
+    save_active_battle_in(tx, &active_battle_row(user_id, battle, last_round)?).await
+}
+
+fn active_battle_row(
+    user_id: i64,
+    battle: &ActiveBattle,
+    last_round: &FightRound,
+) -> Result<ActiveBattleRow> {
+    let data = battle.fight_data_mgr.clone().unwrap_or_default();
+
+    let fight = match &battle.fight_data_mgr {
+        Some(mgr) => mgr.get_fight_owned(),
+        None => battle.fight.clone().unwrap_or_default(),
+    };
+
+    let info = BattleInfo {
+        tower_type: battle.tower_type,
+        tower_id: battle.tower_id,
+        layer_id: battle.layer_id,
+        chapter_id: battle.chapter_id,
+        difficulty: battle.difficulty,
+        talent_plan_id: battle.talent_plan_id,
+        act_point: battle.act_point,
+        power: battle.power,
+        fight_group: battle.fight_group.clone(),
+        is_replay: battle.is_replay,
+        replay_episode_id: battle.replay_episode_id,
+        multiplication: battle.multiplication,
+        replay_start: battle.replay_start.as_ref().map(|start| ReplayStartInfo {
+            fight: start.data.get_fight_owned(),
+            state: start.data.export_state(),
+            deck: start.deck.clone(),
+            ai_deck: start.ai_deck.clone(),
+        }),
+        cloth_skill_opers: battle.cloth_skill_opers.clone(),
+        stamina_cost: battle.stamina_cost,
+    };
+
+    Ok(ActiveBattleRow {
+        user_id,
+        episode_id: battle.episode_id,
+        fight_id: battle.fight_id.unwrap_or_default(),
+        round_number: battle.current_round,
+        fight: serde_json::to_string(&fight)?,
+        last_round: serde_json::to_string(last_round)?,
+        current_deck: serde_json::to_string(&battle.current_deck)?,
+        ai_deck: serde_json::to_string(&battle.ai_deck)?,
+        manager_state: serde_json::to_string(&data.export_state())?,
+        battle_info: serde_json::to_string(&info)?,
+    })
+}
+
+/// Loads a persisted fight and the last round played in it
+pub async fn restore_active_battle(
+    pool: &SqlitePool,
+    user_id: i64,
+) -> Result<Option<(ActiveBattle, FightRound)>> {
+    let Some(row) = get_active_battle(pool, user_id).await? else {
+        return Ok(None);
+    };
+
+    let fight: Fight = serde_json::from_str(&row.fight)?;
+    let state: FightDataState = serde_json::from_str(&row.manager_state)?;
+    let info: BattleInfo = serde_json::from_str(&row.battle_info)?;
+    let last_round: FightRound = serde_json::from_str(&row.last_round)?;
+
+    let mut battle = ActiveBattle {
+        tower_type: info.tower_type,
+        tower_id: info.tower_id,
+        layer_id: info.layer_id,
+        episode_id: row.episode_id,
+        chapter_id: info.chapter_id,
+        difficulty: info.difficulty,
+        talent_plan_id: info.talent_plan_id,
+        fight: Some(fight.clone()),
+        current_round: row.round_number,
+        act_point: info.act_point,
+        power: info.power,
+        current_deck: serde_json::from_str(&row.current_deck)?,
+        fight_group: info.fight_group,
+        fight_id: Some(row.fight_id),
+        is_replay: info.is_replay,
+        replay_episode_id: info.replay_episode_id,
+        multiplication: info.multiplication,
+        ai_deck: serde_json::from_str(&row.ai_deck)?,
+        fight_data_mgr: Some(FightDataMgr::restore(fight, state)),
+        replay_start: info.replay_start.map(|start| ReplayStart {
+            data: FightDataMgr::restore(start.fight, start.state),
+            deck: start.deck,
+            ai_deck: start.ai_deck,
+        }),
+        cloth_skill_opers: info.cloth_skill_opers,
+        round_start: None,
+        stamina_cost: info.stamina_cost,
+    };
+
+    battle.mark_round_start(&last_round);
+
+    let max_card_uid = battle
+        .current_deck
+        .iter()
+        .chain(&battle.ai_deck)
+        .chain(&last_round.ai_use_cards)
+        .chain(&last_round.before_cards1)
+        .chain(&last_round.team_a_cards1)
+        .chain(&last_round.before_cards2)
+        .chain(&last_round.team_a_cards2)
+        .chain(
+            battle
+                .replay_start
+                .iter()
+                .flat_map(|s| s.deck.iter().chain(&s.ai_deck)),
+        )
+        .filter_map(|card| card.uid)
+        .max()
+        .unwrap_or(0);
+    reserve_card_uids(max_card_uid);
+
+    Ok(Some((battle, last_round)))
+}
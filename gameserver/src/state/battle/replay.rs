@@ -0,0 +1,137 @@
+use anyhow::Result;
+use database::db::game::battle::{flag_battle, load_round_operations};
+use sonettobuf::CardInfo;
+use sqlx::SqlitePool;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::state::{
+    ConnectionContext,
+    battle::{
+        cloth::use_cloth_skill, manager::fight_data_mgr::FightDataMgr, simulator::BattleSimulator,
+    },
+};
+
+/// Everything needed to play a fight again from its first round
+#[derive(Debug, Clone)]
+pub struct ReplayStart {
+    pub data: FightDataMgr,
+    pub deck: Vec<CardInfo>,
+    pub ai_deck: Vec<CardInfo>,
+}
+
+/// Rng seed for one round. Derived from the fight so a stored oper log plays out
+/// exactly like the live fight did.
+pub fn round_seed(fight_id: i64, round: i32) -> u64 {
+    (fight_id as u64).rotate_left(16) ^ round as u64
+}
+
+/// Plays the stored oper log of a fight again from its first round. Returns whether
+/// its last round finished the fight, or the reason the log can't be played back.
+pub async fn replay_stored_battle(
+    pool: &SqlitePool,
+    user_id: i64,
+    episode_id: i32,
+    fight_id: i64,
+    start: &ReplayStart,
+) -> Result<std::result::Result<bool, String>> {
+    let rounds = load_round_operations(pool, user_id, episode_id, fight_id).await?;
+
+    let mut data = start.data.clone();
+    let mut deck = start.deck.clone();
+    let mut ai_deck = start.ai_deck.clone();
+    let mut finished = false;
+
+    for (expected, (round_number, record)) in (1..).zip(rounds) {
+        if finished {
+            return Ok(Err(format!(
+                "round {} stored after the fight ended",
+                round_number
+            )));
+        }
+        if round_number != expected {
+            return Ok(Err(format!(
+                "round {} stored, round {} expected",
+                round_number, expected
+            )));
+        }
+
+        for oper in &record.cloth_skill_opers {
+            if use_cloth_skill(&mut data, oper)?.is_none() {
+                return Ok(Err(format!(
+                    "round {} rejected key skill {:?}",
+                    round_number, oper.skill_id
+                )));
+            }
+        }
+
+        // A key skill that ends the fight leaves no round to play
+        if data.fight_over() {
+            finished = true;
+            continue;
+        }
+
+        let mut simulator = BattleSimulator::with_seed(data, round_seed(fight_id, round_number));
+        let round = simulator.process_round(record.opers, deck, ai_deck).await?;
+
+        data = simulator.into_data();
+        deck = round.team_a_cards1;
+        ai_deck = round.ai_use_cards;
+        finished = round.is_finish.unwrap_or(false);
+    }
+
+    Ok(Ok(finished))
+}
+
+/// Checks how the client says the active fight ended, `finished` when it reports a
+/// fight played to the end and not when it aborts, against a replay of the stored oper
+/// log. Fails closed: a fight without replay start or whose replay disagrees is flagged
+/// in `battle_flags` and returns false.
+pub async fn verify_active_battle(
+    ctx: &Arc<Mutex<ConnectionContext>>,
+    pool: &SqlitePool,
+    player_id: i64,
+    finished: bool,
+) -> Result<bool> {
+    let (episode_id, fight_id, start) = {
+        let conn = ctx.lock().await;
+        let Some(battle) = conn.active_battle.as_ref() else {
+            return Ok(false);
+        };
+
+        (
+            battle.episode_id,
+            battle.fight_id.unwrap_or_default(),
+            battle.replay_start.clone(),
+        )
+    };
+
+    let verdict = match start {
+        None => Err("no replay start".to_string()),
+        Some(start) => {
+            match replay_stored_battle(pool, player_id, episode_id, fight_id, &start).await? {
+                Err(reason) => Err(reason),
+                Ok(replay_finished) if replay_finished != finished => Err(format!(
+                    "client reports finished={}, replay finished={}",
+                    finished, replay_finished
+                )),
+                Ok(_) => Ok(()),
+            }
+        }
+    };
+
+    let Err(reason) = verdict else {
+        return Ok(true);
+    };
+
+    tracing::warn!(
+        "Replay verification failed: user={}, episode={}, fight={}: {}",
+        player_id,
+        episode_id,
+        fight_id,
+        reason
+    );
+    flag_battle(pool, player_id, episode_id, fight_id, &reason).await?;
+
+    Ok(false)
+}
@@ -1,16 +1,49 @@
 use config::configs;
 
-#[derive(Debug, Clone)]
+use crate::state::battle::stars::STAR_ADVANCED;
+
+#[derive(Debug, Clone, Default)]
 pub struct DungeonRewards {
     pub normal_bonus: Vec<(u32, u32, i32)>, // (type, id, amount)
     pub first_bonus: Vec<(u32, u32, i32)>,
+    pub advanced_bonus: Vec<(u32, u32, i32)>,
     pub free_bonus: Vec<(u32, u32, i32)>,
 }
 
-/// Generate dungeon rewards from episode data
+impl DungeonRewards {
+    pub fn all(&self) -> Vec<(u32, u32, i32)> {
+        self.normal_bonus
+            .iter()
+            .chain(&self.first_bonus)
+            .chain(&self.advanced_bonus)
+            .chain(&self.free_bonus)
+            .copied()
+            .collect()
+    }
+}
+
+/// Outcome of a won episode against the player's previous best
+#[derive(Debug, Clone, Copy)]
+pub struct DungeonClear {
+    pub star: i32,
+    pub previous_star: i32,
+}
+
+impl DungeonClear {
+    pub fn is_first_clear(&self) -> bool {
+        self.previous_star <= 0 && self.star > 0
+    }
+
+    pub fn is_first_advanced(&self) -> bool {
+        self.previous_star < STAR_ADVANCED && self.star >= STAR_ADVANCED
+    }
+}
+
+/// Generate dungeon rewards from episode data. First clear and advanced bonuses are
+/// only handed out the first time the grade is reached and never multiplied.
 pub fn generate_dungeon_rewards(
     episode_id: i32,
-    is_first_clear: bool,
+    clear: DungeonClear,
     multiplication: i32, // From StartDungeonRequest
 ) -> DungeonRewards {
     let game_data = configs::get();
@@ -18,11 +51,7 @@ pub fn generate_dungeon_rewards(
     let episode = game_data.episode.iter().find(|e| e.id == episode_id);
 
     let Some(episode) = episode else {
-        return DungeonRewards {
-            normal_bonus: vec![],
-            first_bonus: vec![],
-            free_bonus: vec![],
-        };
+        return DungeonRewards::default();
     };
 
     let normal_bonus = if episode.bonus != 0 {
@@ -32,8 +61,15 @@ pub fn generate_dungeon_rewards(
     };
 
     // Parse first clear bonus (only on first completion)
-    let first_bonus = if is_first_clear && episode.first_bonus != 0 {
-        parse_bonus_rewards(episode.first_bonus, multiplication)
+    let first_bonus = if clear.is_first_clear() && episode.first_bonus != 0 {
+        parse_bonus_rewards(episode.first_bonus, 1)
+    } else {
+        vec![]
+    };
+
+    // Parse advanced bonus (only the first time the advanced conditions are met)
+    let advanced_bonus = if clear.is_first_advanced() && episode.advanced_bonus != 0 {
+        parse_bonus_rewards(episode.advanced_bonus, 1)
     } else {
         vec![]
     };
@@ -48,6 +84,7 @@ pub fn generate_dungeon_rewards(
     DungeonRewards {
         normal_bonus,
         first_bonus,
+        advanced_bonus,
         free_bonus,
     }
 }
@@ -70,7 +107,7 @@ fn parse_bonus_rewards(bonus_id: i32, multiplication: i32) -> Vec<(u32, u32, i32
 
 /// Parse reward string format: "2#21#2|9#1003#1|9#1002#3"
 /// Format: type#id#base_amount separated by |
-fn parse_reward_string(reward_str: &str, multiplication: i32) -> Vec<(u32, u32, i32)> {
+pub fn parse_reward_string(reward_str: &str, multiplication: i32) -> Vec<(u32, u32, i32)> {
     let mut rewards = Vec::new();
 
     for part in reward_str.split('|') {
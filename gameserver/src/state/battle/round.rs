@@ -44,7 +44,7 @@ impl RoundState {
             entities,
             buff_mgr: BuffMgr::new(),
             act_point: 4,
-            power: 15,
+            power: fight.attacker.as_ref().and_then(|a| a.power).unwrap_or(15),
             player_deck: vec![],
             ai_cards: vec![],
             used_cards: vec![],
@@ -77,20 +77,12 @@ impl RoundState {
 
     pub fn build_ex_point_info(&self) -> Vec<sonettobuf::FightExPointInfo> {
         self.iter_entities()
-            .map(|entity| {
-                let ex_point_type = if entity.model_id == Some(3120) {
-                    Some(1)
-                } else {
-                    entity.ex_point_type.or(Some(0))
-                };
-
-                sonettobuf::FightExPointInfo {
-                    uid: entity.uid,
-                    ex_point: entity.ex_point,
-                    power_infos: entity.power_infos.clone(),
-                    current_hp: entity.current_hp,
-                    ex_point_type,
-                }
+            .map(|entity| sonettobuf::FightExPointInfo {
+                uid: entity.uid,
+                ex_point: entity.ex_point,
+                power_infos: entity.power_infos.clone(),
+                current_hp: entity.current_hp,
+                ex_point_type: entity.ex_point_type.or(Some(0)),
             })
             .collect()
     }
@@ -1,14 +1,19 @@
-use crate::state::battle::manager::fight_data_mgr::FightDataMgr;
+use crate::state::battle::manager::{buff_mgr::EntityExAttr, fight_data_mgr::FightDataMgr};
 
 use anyhow::Result;
 use sonettobuf::{CardInfo, Fight, FightRound};
+use std::collections::HashMap;
 
 pub async fn build_initial_round(
     fight: Fight,
+    ex_attrs: HashMap<i64, EntityExAttr>,
     player_deck: Vec<CardInfo>,
     ai_deck: Vec<CardInfo>,
 ) -> Result<(FightRound, Fight, FightDataMgr)> {
     let mut fight_mgr = FightDataMgr::new(fight);
+    for (uid, attr) in ex_attrs {
+        fight_mgr.buff_mgr.set_ex_attr(uid, attr);
+    }
 
     let round = fight_mgr.build_initial_round(player_deck, ai_deck)?;
 
@@ -2,6 +2,7 @@ use anyhow::Result;
 use rand::SeedableRng;
 use rand::rngs::StdRng;
 use sonettobuf::{BeginRoundOper, CardInfo, FightRound};
+use std::collections::BTreeMap;
 
 use crate::state::battle::manager::fight_data_mgr::FightDataMgr;
 
@@ -11,9 +12,8 @@ pub struct BattleSimulator {
 }
 
 impl BattleSimulator {
-    pub fn new(data: FightDataMgr) -> Self {
-        let seed = chrono::Utc::now().timestamp_millis() as u64;
-
+    /// Fixed seed so a fight can be replayed round for round
+    pub fn with_seed(data: FightDataMgr, seed: u64) -> Self {
         let fight = data.get_fight_snapshot();
         tracing::info!(
             "Initialized battle with {} player entities, {} enemy entities",
@@ -63,4 +63,17 @@ impl BattleSimulator {
         self.data.update_managers();
         Ok(round)
     }
+
+    /// Behaviors and conditions the executor had no handler for since the last call
+    pub fn take_unhandled(&self) -> BTreeMap<String, u32> {
+        self.data.buff_mgr.unhandled().take()
+    }
+
+    pub fn data(&self) -> &FightDataMgr {
+        &self.data
+    }
+
+    pub fn into_data(self) -> FightDataMgr {
+        self.data
+    }
 }
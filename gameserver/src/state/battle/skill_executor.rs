@@ -4,22 +4,52 @@ use sonettobuf::effect_type_enum::EffectType;
 use sonettobuf::{
     ActEffect, BuffInfo, FightEntityInfo, FightHurtInfo, FightStep, fight_hurt_info, fight_step,
 };
-use std::collections::HashMap;
-use std::sync::atomic::{AtomicI64, Ordering};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
 
-use crate::state::battle::manager::buff_mgr::BuffMgr;
+use crate::state::battle::manager::buff_mgr::{BuffMgr, BuffPlan, BuffSelector};
 
+use super::BUFF_UID_COUNTER;
+use super::ai::{self, AiTarget};
+use super::passives::PassiveTrigger;
 use super::utils::VfxConfig;
 
-static BUFF_UID_COUNTER: AtomicI64 = AtomicI64::new(1000);
+/// Behaviors and conditions the executor had no handler for, with hit counts. Kept on
+/// the fight's [`BuffMgr`], clones share one log so the round copies report into it too.
+#[derive(Default, Debug, Clone)]
+pub struct UnhandledLog(Arc<Mutex<BTreeMap<String, u32>>>);
+
+impl UnhandledLog {
+    fn note(&self, key: String) {
+        if let Ok(mut unhandled) = self.0.lock() {
+            *unhandled.entry(key).or_default() += 1;
+        }
+    }
+
+    /// Drains everything noted since the last call
+    pub fn take(&self) -> BTreeMap<String, u32> {
+        self.0
+            .lock()
+            .map(|mut unhandled| std::mem::take(&mut *unhandled))
+            .unwrap_or_default()
+    }
+}
 
 pub struct SkillExecutor {
     entities: HashMap<i64, FightEntityInfo>,
+    /// Buffs as the effects produced so far leave them. Buff adds are planned against
+    /// this so a second add in the same step sees the first.
+    step_buffs: RefCell<Option<BuffMgr>>,
 }
 
 impl SkillExecutor {
     pub fn new(entities: HashMap<i64, FightEntityInfo>) -> Self {
-        Self { entities }
+        Self {
+            entities,
+            step_buffs: RefCell::new(None),
+        }
     }
 
     pub fn execute_skill(
@@ -29,13 +59,81 @@ impl SkillExecutor {
         skill_id: i32,
         buff_mgr: &BuffMgr,
     ) -> Result<FightStep> {
-        let mut effects = Vec::new();
         let game_data = configs::get();
 
         let skill_data = game_data.skill_effect.iter().find(|s| s.id == skill_id);
 
         tracing::warn!("=== EXECUTING SKILL {} ===", skill_id);
 
+        let (mut effects, any_behavior_defined) =
+            self.execute_slots(caster_uid, target_uid, skill_id, None, buff_mgr)?;
+
+        // Fallback to damageRate if no behaviors produced effects
+        if !any_behavior_defined
+            && let Some(skill) = skill_data
+            && skill.damage_rate > 0
+        {
+            tracing::info!(
+                "Using damageRate fallback for skill {}: {}%",
+                skill_id,
+                skill.damage_rate
+            );
+            let targets = if ai::target_rule(skill_id) == AiTarget::All {
+                self.resolve_targets(caster_uid, target_uid, 201)
+            } else {
+                vec![target_uid]
+            };
+
+            for target in targets {
+                if let Some(damage_effect) = self.calculate_damage_effect(
+                    caster_uid,
+                    target,
+                    skill.damage_rate,
+                    false,
+                    buff_mgr,
+                ) {
+                    effects.push(damage_effect);
+                }
+            }
+        }
+
+        Ok(FightStep {
+            act_type: Some(fight_step::ActType::Skill.into()),
+            from_id: Some(caster_uid),
+            to_id: Some(target_uid),
+            act_id: Some(skill_id),
+            act_effect: effects,
+            card_index: Some(0),
+            support_hero_id: Some(0),
+            fake_timeline: Some(false),
+        })
+    }
+
+    /// Runs the slots of a passive skill effect that fire on `trigger`. Slots without a
+    /// condition fire once at battle start, slots with an unknown one never fire.
+    pub fn execute_passive(
+        &self,
+        caster_uid: i64,
+        target_uid: i64,
+        skill_id: i32,
+        trigger: PassiveTrigger,
+        buff_mgr: &BuffMgr,
+    ) -> Result<Vec<ActEffect>> {
+        let (effects, _) =
+            self.execute_slots(caster_uid, target_uid, skill_id, Some(trigger), buff_mgr)?;
+
+        Ok(effects)
+    }
+
+    fn execute_slots(
+        &self,
+        caster_uid: i64,
+        target_uid: i64,
+        skill_id: i32,
+        trigger: Option<PassiveTrigger>,
+        buff_mgr: &BuffMgr,
+    ) -> Result<(Vec<ActEffect>, bool)> {
+        let mut effects = Vec::new();
         let mut any_behavior_defined = false;
 
         // Process all 20 behavior slots
@@ -53,10 +151,11 @@ impl SkillExecutor {
             let behavior_target = self.get_behavior_target(skill_id, i);
             let condition_target = self.get_condition_target(skill_id, i);
             // 999 = inherit condition target (passive semantics)
-            let effective_target = if behavior_target == 999 {
-                condition_target
-            } else {
-                behavior_target
+            let effective_target = match behavior_target {
+                999 => condition_target,
+                // the selected target of a whole-team skill stands for every enemy
+                1 | 2 if ai::target_rule(skill_id) == AiTarget::All => 201,
+                _ => behavior_target,
             };
 
             tracing::warn!(
@@ -67,47 +166,46 @@ impl SkillExecutor {
                 effective_target
             );
 
+            // Passive slots only run on their own timing, the timing condition
+            // itself is not a state check. Slots with an unknown condition never fire.
+            let condition = match trigger {
+                Some(PassiveTrigger::BattleStart) if condition.is_empty() => condition,
+                Some(_) if condition.is_empty() => continue,
+                Some(trigger) => match PassiveTrigger::from_condition(&condition) {
+                    Some(slot_trigger) if slot_trigger == trigger => String::new(),
+                    Some(_) => continue,
+                    None => {
+                        if trigger == PassiveTrigger::BattleStart {
+                            let cond_id = condition.split('#').next().unwrap_or_default();
+                            buff_mgr
+                                .unhandled()
+                                .note(format!("passive condition {}", cond_id));
+                        }
+                        continue;
+                    }
+                },
+                None => condition,
+            };
+
             // Check condition if present
-            if !condition.is_empty() {
-                let condition_target = self.get_condition_target(skill_id, i);
-                if !self.check_condition(caster_uid, &condition, condition_target, buff_mgr) {
-                    continue;
-                }
+            if !condition.is_empty()
+                && !self.check_condition(caster_uid, &condition, condition_target, buff_mgr)
+            {
+                continue;
             }
 
             // Execute behavior
-            let behavior_effects =
-                self.execute_behavior(caster_uid, target_uid, &behavior, effective_target)?;
+            let behavior_effects = self.execute_behavior(
+                caster_uid,
+                target_uid,
+                &behavior,
+                effective_target,
+                buff_mgr,
+            )?;
             effects.extend(behavior_effects);
         }
 
-        // Fallback to damageRate if no behaviors produced effects
-        if !any_behavior_defined
-            && let Some(skill) = skill_data
-            && skill.damage_rate > 0
-        {
-            tracing::info!(
-                "Using damageRate fallback for skill {}: {}%",
-                skill_id,
-                skill.damage_rate
-            );
-            if let Some(damage_effect) =
-                self.calculate_damage_effect(caster_uid, target_uid, skill.damage_rate, false)
-            {
-                effects.push(damage_effect);
-            }
-        }
-
-        Ok(FightStep {
-            act_type: Some(fight_step::ActType::Skill.into()),
-            from_id: Some(caster_uid),
-            to_id: Some(target_uid),
-            act_id: Some(skill_id),
-            act_effect: effects,
-            card_index: Some(0),
-            support_hero_id: Some(0),
-            fake_timeline: Some(false),
-        })
+        Ok((effects, any_behavior_defined))
     }
 
     fn check_condition(
@@ -152,6 +250,7 @@ impl SkillExecutor {
 
             _ => {
                 tracing::warn!("Unknown condition: {}", condition);
+                buff_mgr.unhandled().note(format!("condition {}", cond_id));
                 false
             }
         }
@@ -163,6 +262,7 @@ impl SkillExecutor {
         target_uid: i64,
         behavior: &str,
         behavior_target: i32,
+        buff_mgr: &BuffMgr,
     ) -> Result<Vec<ActEffect>> {
         let game_data = configs::get();
 
@@ -211,7 +311,7 @@ impl SkillExecutor {
             match behavior_type {
                 "Damage" | "Damage2" | "Detonate" | "Detonate2" => {
                     if let Some(effect) =
-                        self.calculate_damage_effect(caster_uid, target, param1, false)
+                        self.calculate_damage_effect(caster_uid, target, param1, false, buff_mgr)
                     {
                         effects.push(effect);
                     }
@@ -234,7 +334,7 @@ impl SkillExecutor {
                 | "AddBuffRound2"
                 | "ConsumeBloodAddBuff"
                 | "CreateAdditionalDamageAddBuff" => {
-                    effects.push(self.create_buff_effect(caster_uid, target, param1));
+                    effects.extend(self.create_buff_effects(caster_uid, target, param1, buff_mgr));
                 }
 
                 "AddExPoint" => {
@@ -292,15 +392,44 @@ impl SkillExecutor {
                     tracing::warn!("Passive modifier behavior: {}", behavior_type);
                 }
 
-                // Not implemented yet
-                "DecrDurationAndCountBuffType"
-                | "DecrDurationAndCountBuffTypeId"
-                | "DecrDurationAndCountBuffTypeGroup" => {
-                    tracing::warn!("Buff manipulation not implemented: {}", behavior_type);
+                "DecrDurationAndCountBuffType" => {
+                    effects.extend(self.execute_decr_duration_and_count(
+                        target,
+                        BuffSelector::Polarity(param1),
+                        &parts,
+                        buff_mgr,
+                    ));
+                }
+
+                "DecrDurationAndCountBuffTypeId" => {
+                    effects.extend(self.execute_decr_duration_and_count(
+                        target,
+                        BuffSelector::TypeId(param1),
+                        &parts,
+                        buff_mgr,
+                    ));
+                }
+
+                "DecrDurationAndCountBuffTypeGroup" => {
+                    // type ids are listed in the first param: "id#type1,type2#duration#count"
+                    let types: Vec<i32> = parts
+                        .get(1)
+                        .map(|p| p.split(',').filter_map(|t| t.parse().ok()).collect())
+                        .unwrap_or_default();
+
+                    effects.extend(self.execute_decr_duration_and_count(
+                        target,
+                        BuffSelector::TypeGroup(&types),
+                        &parts,
+                        buff_mgr,
+                    ));
                 }
 
                 _ => {
                     tracing::warn!("Unknown behavior type: {}", behavior_type);
+                    buff_mgr
+                        .unhandled()
+                        .note(format!("behavior {} ({})", behavior_id, behavior_type));
                 }
             }
         }
@@ -361,6 +490,68 @@ impl SkillExecutor {
         }
     }
 
+    fn execute_decr_duration_and_count(
+        &self,
+        target_uid: i64,
+        selector: BuffSelector,
+        parts: &[&str],
+        buff_mgr: &BuffMgr,
+    ) -> Vec<ActEffect> {
+        // params: #selector#duration#count
+        let decr_duration = parts
+            .get(2)
+            .and_then(|p| p.parse::<i32>().ok())
+            .unwrap_or(0);
+        let decr_count = parts
+            .get(3)
+            .and_then(|p| p.parse::<i32>().ok())
+            .unwrap_or(0);
+
+        let mut effects = Vec::new();
+
+        for buff in buff_mgr.select(target_uid, selector) {
+            let duration = if buff.duration > 0 {
+                buff.duration - decr_duration
+            } else {
+                buff.duration
+            };
+            let count = if buff.count > 0 {
+                buff.count - decr_count
+            } else {
+                buff.count
+            };
+
+            let expired = (buff.duration > 0 && duration <= 0) || (buff.count > 0 && count <= 0);
+
+            let info = BuffInfo {
+                buff_id: Some(buff.buff_id),
+                duration: Some(duration.max(0)),
+                uid: Some(buff.uid),
+                ex_info: Some(0),
+                from_uid: Some(buff.from_uid),
+                count: Some(count.max(0)),
+                layer: Some(buff.stacks),
+                r#type: Some(0),
+                act_common_params: Some(String::new()),
+                act_info: vec![],
+            };
+
+            effects.push(ActEffect {
+                effect_type: Some(if expired {
+                    EffectType::Buffdel as i32
+                } else {
+                    EffectType::Buffupdate as i32
+                }),
+                target_id: Some(target_uid),
+                effect_num: Some(buff.buff_id),
+                buff: Some(info),
+                ..Default::default()
+            });
+        }
+
+        effects
+    }
+
     fn execute_lost_life(
         &self,
         _caster_uid: i64,
@@ -397,6 +588,7 @@ impl SkillExecutor {
         target_uid: i64,
         base_param: i32,
         is_crit: bool,
+        buff_mgr: &BuffMgr,
     ) -> Option<ActEffect> {
         let caster = self.entities.get(&caster_uid)?;
         let target = self.entities.get(&target_uid)?;
@@ -404,19 +596,28 @@ impl SkillExecutor {
         // Get stats
         let caster_attack = caster.attr.as_ref()?.attack.unwrap_or(100);
         let target_defense = target.attr.as_ref()?.defense.unwrap_or(50);
+        let caster_ex = buff_mgr.ex_attr(caster_uid);
+        let target_ex = buff_mgr.ex_attr(target_uid);
 
         // Simplified damage calculation
-        // TODO: Implement full formula with penetration, def reduction, dmg bonus, etc.
-        let penetration_rate = 0.0;
+        // TODO: Implement full formula with def reduction
+        let penetration_rate = caster_ex.defense_ignore as f32 / 1000.0;
         let def_reduction = 0.0;
         let effective_defense =
             (target_defense as f32 * (1.0 - penetration_rate) * (1.0 - def_reduction)) as i32;
         let attack_contribution = (caster_attack - effective_defense).max(0);
 
         let skill_multiplier = base_param as f32 / 1000.0;
-        let crit_multiplier = if is_crit { 1.5 } else { 1.0 };
+        let dmg_multiplier = (1000 + caster_ex.add_dmg - target_ex.drop_dmg).max(0) as f32 / 1000.0;
+        let crit_multiplier = if is_crit {
+            (1000 + caster_ex.cri_dmg - target_ex.cri_def).max(1000) as f32 / 1000.0
+        } else {
+            1.0
+        };
 
-        let final_damage = (attack_contribution as f32 * skill_multiplier * crit_multiplier) as i32;
+        let final_damage =
+            (attack_contribution as f32 * skill_multiplier * dmg_multiplier * crit_multiplier)
+                as i32;
         let final_damage = final_damage.max(1);
 
         tracing::debug!(
@@ -544,32 +745,97 @@ impl SkillExecutor {
         }
     }
 
-    fn create_buff_effect(&self, caster_uid: i64, target_id: i64, buff_id: i32) -> ActEffect {
-        let duration = config::configs::get()
-            .skill_buff
-            .iter()
-            .find(|b| b.id == buff_id)
-            .map(|b| b.during_time)
-            .unwrap_or(0);
+    fn create_buff_effects(
+        &self,
+        caster_uid: i64,
+        target_id: i64,
+        buff_id: i32,
+        buff_mgr: &BuffMgr,
+    ) -> Vec<ActEffect> {
+        let buff_cfg = config::configs::get().skill_buff.get(buff_id);
+        let duration = buff_cfg.map(|b| b.during_time).unwrap_or(0);
+        let count = buff_cfg.map(|b| b.effect_count).unwrap_or(0);
+
+        let buff_info = |uid: i64, duration: i32, count: i32, layer: i32| BuffInfo {
+            buff_id: Some(buff_id),
+            duration: Some(duration),
+            uid: Some(uid),
+            ex_info: Some(0),
+            from_uid: Some(caster_uid),
+            count: Some(count),
+            layer: Some(layer),
+            r#type: Some(0),
+            act_common_params: Some(String::new()),
+            act_info: vec![],
+        };
 
-        ActEffect {
-            effect_type: Some(EffectType::Buffadd as i32),
-            target_id: Some(target_id),
-            effect_num: Some(buff_id),
-            buff: Some(BuffInfo {
-                buff_id: Some(buff_id),
-                duration: Some(duration),
-                uid: Some(BUFF_UID_COUNTER.fetch_add(1, Ordering::SeqCst)),
-                ex_info: Some(0),
-                from_uid: Some(caster_uid),
-                count: Some(0),
-                layer: Some(1),
-                r#type: Some(0),
-                act_common_params: Some(String::new()),
-                act_info: vec![],
-            }),
-            ..Default::default()
-        }
+        let mut step_buffs = self.step_buffs.borrow_mut();
+        let step_buffs = step_buffs.get_or_insert_with(|| buff_mgr.clone());
+
+        let plan = step_buffs.plan_add(target_id, buff_id);
+        let buff_uid = match plan {
+            BuffPlan::Add { .. } => BUFF_UID_COUNTER.fetch_add(1, Ordering::SeqCst),
+            _ => 0,
+        };
+
+        let effects = match plan.clone() {
+            BuffPlan::Add { removed } => {
+                let mut effects: Vec<ActEffect> = removed
+                    .into_iter()
+                    .filter_map(|uid| {
+                        let old = step_buffs
+                            .get_buffs(target_id)
+                            .iter()
+                            .find(|b| b.uid == uid)?;
+
+                        Some(ActEffect {
+                            effect_type: Some(EffectType::Buffdel as i32),
+                            target_id: Some(target_id),
+                            effect_num: Some(old.buff_id),
+                            buff: Some(BuffInfo {
+                                buff_id: Some(old.buff_id),
+                                ..buff_info(uid, 0, 0, old.stacks)
+                            }),
+                            ..Default::default()
+                        })
+                    })
+                    .collect();
+
+                effects.push(ActEffect {
+                    effect_type: Some(EffectType::Buffadd as i32),
+                    target_id: Some(target_id),
+                    effect_num: Some(buff_id),
+                    buff: Some(buff_info(buff_uid, duration, count, 1)),
+                    ..Default::default()
+                });
+
+                effects
+            }
+
+            BuffPlan::Update {
+                uid,
+                duration,
+                count,
+                stacks,
+            } => vec![ActEffect {
+                effect_type: Some(EffectType::Buffupdate as i32),
+                target_id: Some(target_id),
+                effect_num: Some(buff_id),
+                buff: Some(buff_info(uid, duration, count, stacks)),
+                ..Default::default()
+            }],
+
+            BuffPlan::Reject => vec![ActEffect {
+                effect_type: Some(EffectType::Buffreject as i32),
+                target_id: Some(target_id),
+                effect_num: Some(buff_id),
+                ..Default::default()
+            }],
+        };
+
+        step_buffs.apply_plan(target_id, buff_id, caster_uid, buff_uid, &plan);
+
+        effects
     }
 
     fn get_condition(&self, skill_id: i32, index: i32) -> String {
@@ -0,0 +1,96 @@
+use config::configs;
+use sonettobuf::Fight;
+
+/// Episode cleared
+pub const STAR_NORMAL: i32 = 1;
+/// Episode cleared with the battle's advanced conditions met
+pub const STAR_ADVANCED: i32 = 2;
+
+/// A single clear condition from the battle table's `advancedCondition`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClearCondition {
+    /// Win within the given number of rounds
+    WithinRounds(i32),
+    /// Win without losing a hero
+    NoDeaths,
+    /// Win with the given hero in the team
+    UseHero(i32),
+}
+
+impl ClearCondition {
+    /// Conditions read `type#param` separated by `|`: `1#N` within N rounds,
+    /// `2` no deaths, `3#heroId` hero in the team. The condition table that defines
+    /// the codes is not exported, anything else is left unparsed.
+    fn parse(s: &str) -> Option<Self> {
+        let mut parts = s.split('#');
+        let kind = parts.next()?.trim().parse::<i32>().ok()?;
+        let param = parts.next().and_then(|p| p.trim().parse::<i32>().ok());
+
+        match (kind, param) {
+            (1, Some(rounds)) => Some(Self::WithinRounds(rounds)),
+            (2, _) => Some(Self::NoDeaths),
+            (3, Some(hero_id)) => Some(Self::UseHero(hero_id)),
+            _ => None,
+        }
+    }
+
+    fn is_met(&self, fight: &Fight) -> bool {
+        let attacker = fight.attacker.as_ref();
+        let heroes = || {
+            attacker
+                .into_iter()
+                .flat_map(|a| a.entitys.iter().chain(a.sub_entitys.iter()))
+        };
+
+        match *self {
+            Self::WithinRounds(rounds) => fight.cur_round.unwrap_or(1) <= rounds,
+            Self::NoDeaths => {
+                attacker.is_some_and(|a| a.entitys.iter().all(|e| e.current_hp.unwrap_or(0) > 0))
+            }
+            Self::UseHero(hero_id) => heroes().any(|e| e.model_id == Some(hero_id)),
+        }
+    }
+}
+
+/// Parses the advanced conditions of a battle, `None` for entries it doesn't understand
+pub fn advanced_conditions(battle_id: i32) -> Vec<Option<ClearCondition>> {
+    let Some(battle) = configs::get().battle.get(battle_id) else {
+        return vec![];
+    };
+
+    battle
+        .advanced_condition
+        .split('|')
+        .filter(|s| !s.is_empty())
+        .map(|s| {
+            let condition = ClearCondition::parse(s);
+            if condition.is_none() {
+                tracing::warn!("Battle {}: unknown clear condition '{}'", battle_id, s);
+            }
+            condition
+        })
+        .collect()
+}
+
+/// Stars earned by a won fight. Battles without advanced conditions are a full clear,
+/// a condition that could not be parsed counts as unmet.
+pub fn evaluate_stars(fight: &Fight) -> i32 {
+    let battle_id = fight.battle_id.unwrap_or(0);
+    let conditions = advanced_conditions(battle_id);
+
+    let unmet: Vec<_> = conditions
+        .iter()
+        .filter(|c| !c.is_some_and(|c| c.is_met(fight)))
+        .collect();
+
+    if unmet.is_empty() {
+        STAR_ADVANCED
+    } else {
+        tracing::info!(
+            "Battle {}: advanced conditions not met {:?}",
+            battle_id,
+            unmet
+        );
+        STAR_NORMAL
+    }
+}
@@ -0,0 +1,363 @@
+use chrono::NaiveDateTime;
+use common::time::ServerTime;
+use config::bp::Bp;
+use config::configs;
+use database::db::game::battle_pass;
+use database::models::game::battle_pass::UserBattlePass;
+use sonettobuf::{BpPayPush, BpScoreUpdatePush, CmdId};
+use sqlx::{Sqlite, SqlitePool};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::error::AppError;
+use crate::state::{ConnectionContext, parse_reward_string};
+use crate::util::transaction::{MATERIAL_CURRENCY, Transaction};
+use database::models::game::ledger::LedgerSource;
+
+/// `pay_status` of a pass with the paid track unlocked, 2 is the deluxe edition
+pub const PAY_STATUS_PAID: i32 = 1;
+pub const PAY_STATUS_DELUXE: i32 = 2;
+
+/// One run of the configured pass
+#[derive(Debug, Clone, Copy)]
+pub struct BpSeason {
+    pub bp_id: i32,
+    /// Unix seconds
+    pub start_time: i64,
+    pub end_time: i64,
+}
+
+/// Row of the `bp` table with the given id, the newest pass for 0
+pub fn bp_def(bp_id: i32) -> Option<&'static Bp> {
+    let mut passes = configs::get().bp.iter();
+
+    if bp_id == 0 {
+        passes.max_by_key(|b| b.bp_id)
+    } else {
+        passes.find(|b| b.bp_id == bp_id)
+    }
+}
+
+/// The season running at `now_ms` from `[gameplay.battle_pass]`, `None` before the first
+/// season starts or when the pass isn't in the `bp` table
+pub fn current_season(now_ms: i64) -> Option<BpSeason> {
+    let cfg = common::battle_pass();
+    let bp = bp_def(cfg.id)?;
+
+    let first = match NaiveDateTime::parse_from_str(&cfg.start_time, "%Y-%m-%d %H:%M:%S") {
+        Ok(dt) => dt.and_utc().timestamp(),
+        Err(e) => {
+            tracing::warn!("Invalid battle pass start_time {:?}: {}", cfg.start_time, e);
+            return None;
+        }
+    };
+
+    let now = now_ms / 1000;
+    if now < first {
+        return None;
+    }
+
+    let length = cfg.season_days.max(1) * 86_400;
+    let start_time = first + (now - first) / length * length;
+
+    Some(BpSeason {
+        bp_id: bp.bp_id,
+        start_time,
+        end_time: start_time + length,
+    })
+}
+
+/// Highest level of a pass in `bp_lv_bonus`
+pub fn max_level(bp_id: i32) -> i32 {
+    configs::get()
+        .bp_lv_bonus
+        .iter()
+        .filter(|l| l.bp_id == bp_id)
+        .map(|l| l.level)
+        .max()
+        .unwrap_or(0)
+}
+
+/// Level reached with `score`, every `expLevelUp` points is one level
+pub fn bp_level(bp: &Bp, score: i32) -> i32 {
+    if bp.exp_level_up <= 0 {
+        return 0;
+    }
+    (score / bp.exp_level_up).min(max_level(bp.bp_id))
+}
+
+/// The user's pass for the season. A pass from an earlier season starts over, the weekly
+/// score starts over every server week.
+pub async fn load_battle_pass(
+    pool: &SqlitePool,
+    user_id: i64,
+    season: &BpSeason,
+) -> Result<UserBattlePass, AppError> {
+    let week = ServerTime::server_week(ServerTime::now_ms());
+
+    let mut pass = match battle_pass::get_battle_pass(pool, user_id).await? {
+        Some(pass) if pass.bp_id == season.bp_id && pass.season_start == season.start_time => pass,
+        _ => {
+            tracing::info!(
+                "User {} starts battle pass {} season from {}",
+                user_id,
+                season.bp_id,
+                season.start_time
+            );
+            return Ok(battle_pass::start_season(
+                pool,
+                user_id,
+                season.bp_id,
+                season.start_time,
+                week,
+            )
+            .await?);
+        }
+    };
+
+    if pass.score_week != week {
+        battle_pass::reset_weekly_score(pool, user_id, week).await?;
+        pass.weekly_score = 0;
+        pass.score_week = week;
+    }
+
+    Ok(pass)
+}
+
+/// The running season, its pass and the user's progress in it. Fails when no season is
+/// running or `bp_id` names another pass.
+pub async fn active_battle_pass(
+    pool: &SqlitePool,
+    user_id: i64,
+    bp_id: Option<i32>,
+) -> Result<(BpSeason, &'static Bp, UserBattlePass), AppError> {
+    let season = current_season(ServerTime::now_ms()).ok_or(AppError::InvalidRequest)?;
+
+    if bp_id.is_some_and(|id| id != season.bp_id) {
+        tracing::warn!(
+            "User {} sent battle pass {:?}, running pass is {}",
+            user_id,
+            bp_id,
+            season.bp_id
+        );
+        return Err(AppError::InvalidRequest);
+    }
+
+    let bp = bp_def(season.bp_id).ok_or(AppError::InvalidRequest)?;
+    let pass = load_battle_pass(pool, user_id, &season).await?;
+
+    Ok((season, bp, pass))
+}
+
+/// Part of `amount` that fits below the last level. Task score (`weekly_capped`) also
+/// stops at the pass's `weekLimitTimes` for the week.
+fn capped_score(bp: &Bp, pass: &UserBattlePass, amount: i32, weekly_capped: bool) -> i32 {
+    let mut amount = amount.min(max_level(bp.bp_id) * bp.exp_level_up - pass.score);
+    if weekly_capped && bp.week_limit_times > 0 {
+        amount = amount.min(bp.week_limit_times - pass.weekly_score);
+    }
+    amount
+}
+
+/// Sends the pass score after a committed change
+pub async fn push_bp_score(
+    ctx: Arc<Mutex<ConnectionContext>>,
+    pass: &UserBattlePass,
+) -> Result<(), AppError> {
+    ctx.lock()
+        .await
+        .notify(
+            CmdId::BpScoreUpdatePushCmd,
+            BpScoreUpdatePush {
+                id: Some(pass.bp_id),
+                score: Some(pass.score),
+                weekly_score: Some(pass.weekly_score),
+            },
+        )
+        .await
+}
+
+/// Adds pass score through `db`, capped at the last level. Bought and granted levels
+/// don't count towards the weekly cap of task score (`weekly_capped`). Returns the
+/// updated pass to push once `db` commits, `None` if nothing was added.
+pub async fn add_bp_score(
+    pool: &SqlitePool,
+    db: &mut sqlx::Transaction<'_, Sqlite>,
+    player_id: i64,
+    amount: i32,
+    weekly_capped: bool,
+) -> Result<Option<UserBattlePass>, AppError> {
+    let Some(season) = current_season(ServerTime::now_ms()) else {
+        return Ok(None);
+    };
+    let Some(bp) = bp_def(season.bp_id) else {
+        return Ok(None);
+    };
+    let pass = load_battle_pass(pool, player_id, &season).await?;
+
+    let amount = capped_score(bp, &pass, amount, weekly_capped);
+    if amount <= 0 {
+        return Ok(None);
+    }
+
+    let weekly = if weekly_capped { amount } else { 0 };
+    let Some(pass) = battle_pass::add_score(db, player_id, amount, weekly).await? else {
+        return Ok(None);
+    };
+
+    tracing::info!(
+        "User {} gained {} battle pass score ({} total, {} this week)",
+        player_id,
+        amount,
+        pass.score,
+        pass.weekly_score
+    );
+
+    Ok(Some(pass))
+}
+
+/// Buys `num` levels of the pass with the `[gameplay.battle_pass]` level price, charge and
+/// score in one commit. Levels past the last one are refused. Returns the new score.
+pub async fn buy_bp_levels(
+    ctx: Arc<Mutex<ConnectionContext>>,
+    player_id: i64,
+    bp: &Bp,
+    pass: &UserBattlePass,
+    num: i32,
+) -> Result<i32, AppError> {
+    let cfg = common::battle_pass();
+
+    let levels_left = max_level(bp.bp_id) - bp_level(bp, pass.score);
+    if num <= 0 || num > levels_left {
+        tracing::warn!(
+            "User {} tried to buy {} battle pass levels with {} left",
+            player_id,
+            num,
+            levels_left
+        );
+        return Err(AppError::InvalidRequest);
+    }
+
+    let cost = num
+        .checked_mul(cfg.buy_level_cost)
+        .ok_or(AppError::InvalidRequest)?;
+    let score = num
+        .checked_mul(bp.exp_level_up)
+        .map(|s| capped_score(bp, pass, s, false))
+        .ok_or(AppError::InvalidRequest)?;
+
+    let mut applied = Transaction::new(player_id, LedgerSource::BattlePass)
+        .reference(bp.bp_id as i64)
+        .cost(MATERIAL_CURRENCY, cfg.buy_level_currency as u32, cost)
+        .silent()
+        .apply(ctx.clone())
+        .await?;
+    let updated = battle_pass::add_score(&mut applied.db, player_id, score, 0).await?;
+    applied.commit(ctx.clone()).await?;
+
+    tracing::info!(
+        "User {} bought {} battle pass levels for {} of currency {}",
+        player_id,
+        num,
+        cost,
+        cfg.buy_level_currency
+    );
+
+    let Some(updated) = updated else {
+        return Ok(pass.score);
+    };
+    push_bp_score(ctx, &updated).await?;
+
+    Ok(updated.score)
+}
+
+/// Unlocks the paid track when `goods_id` is one of the running pass's charges. The
+/// `payStatus1Bonus` comes with the paid track, the deluxe edition adds
+/// `payStatus2Bonus`, `specialBonus` and its `payStatus2AddLevel` levels.
+pub async fn apply_bp_charge(
+    ctx: Arc<Mutex<ConnectionContext>>,
+    player_id: i64,
+    goods_id: i32,
+) -> Result<(), AppError> {
+    let Some(season) = current_season(ServerTime::now_ms()) else {
+        return Ok(());
+    };
+    let Some(bp) = bp_def(season.bp_id) else {
+        return Ok(());
+    };
+
+    let pay_status = if goods_id == bp.charge_id1 {
+        PAY_STATUS_PAID
+    } else if goods_id == bp.charge_id2 || goods_id == bp.charge_id1to2 {
+        PAY_STATUS_DELUXE
+    } else {
+        return Ok(());
+    };
+
+    let pool = ctx.lock().await.state.db.clone();
+    let pass = load_battle_pass(&pool, player_id, &season).await?;
+
+    if pass.pay_status >= pay_status {
+        tracing::warn!(
+            "User {} bought battle pass charge {} with pay status {} already",
+            player_id,
+            goods_id,
+            pass.pay_status
+        );
+        return Ok(());
+    }
+
+    let mut rewards = Vec::new();
+    let mut added_score = 0;
+    if pass.pay_status < PAY_STATUS_PAID {
+        rewards.extend(parse_reward_string(&bp.pay_status1_bonus, 1));
+    }
+    if pay_status == PAY_STATUS_DELUXE {
+        rewards.extend(parse_reward_string(&bp.pay_status2_bonus, 1));
+        rewards.extend(parse_reward_string(&bp.special_bonus, 1));
+        added_score = capped_score(
+            bp,
+            &pass,
+            bp.pay_status2_add_level.saturating_mul(bp.exp_level_up),
+            false,
+        );
+    }
+
+    let mut applied = Transaction::new(player_id, LedgerSource::BattlePass)
+        .reference(bp.bp_id as i64)
+        .grants(&rewards)
+        .apply(ctx.clone())
+        .await?;
+    battle_pass::set_pay_status(&mut applied.db, player_id, pay_status).await?;
+    let updated = if added_score > 0 {
+        battle_pass::add_score(&mut applied.db, player_id, added_score, 0).await?
+    } else {
+        None
+    };
+    applied.commit(ctx.clone()).await?;
+
+    tracing::info!(
+        "User {} unlocked pay status {} of battle pass {} with {} rewards",
+        player_id,
+        pay_status,
+        bp.bp_id,
+        rewards.len()
+    );
+
+    ctx.lock()
+        .await
+        .notify(
+            CmdId::BpPayPushCmd,
+            BpPayPush {
+                id: Some(bp.bp_id),
+                pay_status: Some(pay_status),
+            },
+        )
+        .await?;
+
+    if let Some(updated) = updated {
+        push_bp_score(ctx, &updated).await?;
+    }
+
+    Ok(())
+}
@@ -7,11 +7,11 @@ use tokio::sync::Mutex;
 
 use crate::error::AppError;
 
-use crate::state::battle::manager::fight_data_mgr::FightDataMgr;
+use crate::state::battle::{manager::fight_data_mgr::FightDataMgr, replay::ReplayStart};
 use crate::util::common::{encode_message, send_raw_server_message};
 use sonettobuf::CmdId;
 
-use super::{AppState, CommandPacket, PlayerState};
+use super::{AppState, CommandPacket, GameEvent, PlayerState};
 
 pub struct ConnectionContext {
     pub socket: Arc<Mutex<TcpStream>>,
@@ -28,6 +28,9 @@ pub struct ConnectionContext {
     pub active_battle: Option<ActiveBattle>,
     pub bot_welcome_sent: bool,
     pub bot_msg_counter: u64,
+
+    /// Emitted by the current command, dispatched once it's handled
+    pending_events: Vec<GameEvent>,
 }
 
 #[allow(dead_code)]
@@ -52,6 +55,68 @@ pub struct ActiveBattle {
     pub multiplication: Option<i32>,
     pub ai_deck: Vec<sonettobuf::CardInfo>,
     pub fight_data_mgr: Option<FightDataMgr>,
+    /// Fight as it was before round one, for replay verification
+    pub replay_start: Option<ReplayStart>,
+    /// Key skills used since the last round was played, saved with that round
+    pub cloth_skill_opers: Vec<sonettobuf::UseClothSkillOperRecord>,
+    /// State the current round started from, restored by `ResetRoundCmd`
+    pub round_start: Option<RoundStart>,
+    pub stamina_cost: i32, // Stamina paid to enter, partly refunded on abort
+}
+
+/// Snapshot of an [`ActiveBattle`] taken when a round is handed to the client
+#[derive(Debug, Clone, Default)]
+pub struct RoundStart {
+    pub data: Option<FightDataMgr>,
+    pub act_point: i32,
+    pub power: i32,
+    pub deck: Vec<sonettobuf::CardInfo>,
+    pub ai_deck: Vec<sonettobuf::CardInfo>,
+    /// Round data the client was sent for this round
+    pub round: sonettobuf::FightRound,
+}
+
+impl ActiveBattle {
+    /// Carries a played, unfinished round over into the next one
+    pub fn apply_round(&mut self, round: &sonettobuf::FightRound, data: FightDataMgr) {
+        self.current_round = round.cur_round.unwrap_or(self.current_round + 1);
+        self.act_point = round.act_point.unwrap_or(self.act_point);
+        self.power = round.power.unwrap_or(self.power);
+        self.current_deck = round.team_a_cards1.clone();
+        self.ai_deck = round.ai_use_cards.clone();
+        self.fight = Some(data.get_fight_owned());
+        self.fight_data_mgr = Some(data);
+        self.cloth_skill_opers.clear();
+        self.mark_round_start(round);
+    }
+
+    /// Remembers the current state as the start of the round `round` describes
+    pub fn mark_round_start(&mut self, round: &sonettobuf::FightRound) {
+        self.round_start = Some(RoundStart {
+            data: self.fight_data_mgr.clone(),
+            act_point: self.act_point,
+            power: self.power,
+            deck: self.current_deck.clone(),
+            ai_deck: self.ai_deck.clone(),
+            round: round.clone(),
+        });
+    }
+
+    /// Undoes everything done since the round started. Returns the round data to
+    /// send back, `None` when no round has started yet.
+    pub fn reset_round(&mut self) -> Option<sonettobuf::FightRound> {
+        let start = self.round_start.clone()?;
+
+        self.act_point = start.act_point;
+        self.power = start.power;
+        self.current_deck = start.deck;
+        self.ai_deck = start.ai_deck;
+        self.fight = start.data.as_ref().map(|data| data.get_fight_owned());
+        self.fight_data_mgr = start.data;
+        self.cloth_skill_opers.clear();
+
+        Some(start.round)
+    }
 }
 
 #[allow(dead_code)]
@@ -68,9 +133,19 @@ impl ConnectionContext {
             active_battle: None,
             bot_welcome_sent: false,
             bot_msg_counter: 0,
+            pending_events: Vec::new(),
         }
     }
 
+    /// Queues an event for the subscribers on [`AppState::events`]
+    pub fn emit(&mut self, event: GameEvent) {
+        self.pending_events.push(event);
+    }
+
+    pub fn take_events(&mut self) -> Vec<GameEvent> {
+        std::mem::take(&mut self.pending_events)
+    }
+
     pub async fn load_player_state(&mut self, player_id: i64) -> Result<(), AppError> {
         self.player_id = Some(player_id);
         self.logged_in = true;
@@ -0,0 +1,109 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::error::AppError;
+
+use super::ConnectionContext;
+
+/// Rounds of follow-up events handled after one command before giving up
+const MAX_DISPATCH_ROUNDS: usize = 8;
+
+/// Something a player did that progress-tracking systems may care about
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GameEvent {
+    Login,
+    HeroLevelUp {
+        hero_id: i32,
+        old_level: i32,
+        new_level: i32,
+    },
+    /// Insight raised
+    HeroRankUp {
+        hero_id: i32,
+        new_rank: i32,
+    },
+    DungeonCleared {
+        chapter_id: i32,
+        episode_id: i32,
+        star: i32,
+        multiplication: i32,
+    },
+    Summon {
+        pool_id: i32,
+        count: i32,
+    },
+    /// Heroes joined the roster, duplicates not counted
+    HeroesGained {
+        count: i32,
+    },
+    ItemUsed {
+        item_id: i32,
+        count: i32,
+    },
+    StorePurchase {
+        store_id: i32,
+        goods_id: i32,
+        count: i32,
+    },
+    PowerSpent {
+        amount: i32,
+    },
+    /// Psychube fed with exp, its level may not have changed
+    EquipStrengthened {
+        equip_uid: i64,
+    },
+}
+
+pub type EventFuture = Pin<Box<dyn Future<Output = Result<(), AppError>> + Send>>;
+
+/// Called once per emitted event with the emitting player's connection
+pub type Subscriber = fn(Arc<Mutex<ConnectionContext>>, Arc<GameEvent>) -> EventFuture;
+
+/// Subsystems interested in gameplay events, shared by every connection
+#[derive(Default)]
+pub struct EventBus {
+    subscribers: Vec<(&'static str, Subscriber)>,
+}
+
+impl EventBus {
+    pub fn subscribe(&mut self, name: &'static str, subscriber: Subscriber) {
+        self.subscribers.push((name, subscriber));
+    }
+}
+
+/// Hands the events emitted while handling a command to every subscriber.
+/// Subscribers run with the connection unlocked and may emit events of their own,
+/// a failing subscriber is logged without affecting the others.
+pub async fn dispatch_events(ctx: Arc<Mutex<ConnectionContext>>) {
+    for _ in 0..MAX_DISPATCH_ROUNDS {
+        let (events, state) = {
+            let mut conn = ctx.lock().await;
+            (conn.take_events(), conn.state.clone())
+        };
+
+        if events.is_empty() {
+            return;
+        }
+
+        for event in events.into_iter().map(Arc::new) {
+            tracing::debug!("Dispatching {:?}", event);
+
+            for (name, subscriber) in &state.events.subscribers {
+                if let Err(e) = subscriber(ctx.clone(), event.clone()).await {
+                    tracing::warn!("Event subscriber {} failed on {:?}: {}", name, event, e);
+                }
+            }
+        }
+    }
+
+    let dropped = ctx.lock().await.take_events();
+    if !dropped.is_empty() {
+        tracing::warn!(
+            "Dropped {} events, subscribers kept emitting after {} rounds",
+            dropped.len(),
+            MAX_DISPATCH_ROUNDS
+        );
+    }
+}
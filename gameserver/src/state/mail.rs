@@ -0,0 +1,140 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use common::time::ServerTime;
+use database::db::game::mails;
+use database::models::game::mails::{NewMail, UserMail};
+use sonettobuf::{CmdId, DeleteMailsPush, NewMailPush};
+
+use crate::error::AppError;
+use crate::state::AppState;
+
+/// How often expired mails are swept for all players
+const EXPIRY_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Queues `NewMailPush` for the recipient if they are online.
+/// Must not be called while holding the recipient's connection lock.
+async fn push_new_mail(state: &AppState, mail: UserMail) -> Result<(), AppError> {
+    let Some(ctx) = state.get_connection_context(mail.user_id) else {
+        return Ok(());
+    };
+
+    let push = NewMailPush {
+        mail: Some(mail.into()),
+    };
+
+    ctx.lock().await.notify(CmdId::NewMailPushCmd, push).await
+}
+
+pub async fn send_mail(state: &AppState, user_id: i64, mail: &NewMail) -> Result<i64, AppError> {
+    let sent = mails::send_mail(&state.db, user_id, mail).await?;
+    let incr_id = sent.incr_id;
+
+    tracing::info!(
+        "Sent mail {} ({}) to user {}",
+        incr_id,
+        mail.mail_id,
+        user_id
+    );
+    push_new_mail(state, sent).await?;
+
+    Ok(incr_id)
+}
+
+/// Renders a mail template, `None` when no template has that id
+pub async fn render_template(
+    state: &AppState,
+    template_id: i32,
+    params: &[String],
+) -> Result<Option<NewMail>, AppError> {
+    let template = mails::get_template(&state.db, template_id).await?;
+
+    Ok(template.map(|t| t.render(params, ServerTime::now_ms())))
+}
+
+/// Sends a mail to every account, accounts created in the next `window_ms` receive
+/// it on login. Returns the number of accounts it was delivered to right away.
+pub async fn broadcast_mail(
+    state: &AppState,
+    mail: &NewMail,
+    window_ms: i64,
+) -> Result<usize, AppError> {
+    let mut end_time = ServerTime::now_ms().saturating_add(window_ms.max(0));
+    if mail.expire_time > 0 {
+        end_time = end_time.min(mail.expire_time);
+    }
+
+    let (broadcast_id, delivered) = mails::create_broadcast(&state.db, mail, end_time).await?;
+    let count = delivered.len();
+
+    tracing::info!(
+        "Broadcast {} of mail {} delivered to {} users, open until {}",
+        broadcast_id,
+        mail.mail_id,
+        count,
+        end_time
+    );
+
+    for sent in delivered {
+        push_new_mail(state, sent).await?;
+    }
+
+    Ok(count)
+}
+
+/// Delivers the open broadcasts a player has missed, called on login
+/// before the unread mails are pushed.
+pub async fn deliver_broadcasts(state: &AppState, user_id: i64) -> Result<(), AppError> {
+    let delivered = mails::deliver_broadcasts(&state.db, user_id).await?;
+
+    if !delivered.is_empty() {
+        tracing::info!(
+            "Delivered {} broadcast mails to user {}",
+            delivered.len(),
+            user_id
+        );
+    }
+
+    Ok(())
+}
+
+/// Deletes the expired mails of every player and pushes
+/// `DeleteMailsPush` to the ones online. Returns the number of mails deleted.
+async fn expire_mails(state: &AppState) -> Result<usize, AppError> {
+    let expired = mails::expire_mails(&state.db, None, ServerTime::now_ms()).await?;
+    let count = expired.len();
+
+    let mut by_user = std::collections::HashMap::<i64, Vec<u64>>::new();
+    for mail in expired {
+        by_user
+            .entry(mail.user_id)
+            .or_default()
+            .push(mail.incr_id as u64);
+    }
+
+    for (user_id, incr_ids) in by_user {
+        tracing::info!("Expired {} mails of user {}", incr_ids.len(), user_id);
+
+        if let Some(ctx) = state.get_connection_context(user_id) {
+            ctx.lock()
+                .await
+                .notify(CmdId::DeleteMailsPushCmd, DeleteMailsPush { incr_ids })
+                .await?;
+        }
+    }
+
+    Ok(count)
+}
+
+/// Sweeps expired mails forever, spawned once at startup
+pub async fn run_mail_expiry(state: Arc<AppState>) {
+    let mut interval = tokio::time::interval(EXPIRY_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        if let Err(e) = expire_mails(&state).await {
+            tracing::error!("Failed to expire mails: {e}");
+        }
+    }
+}
@@ -1,22 +1,53 @@
+mod achievements;
 mod app;
 
 mod battle;
+mod battle_pass;
 mod connection;
+mod events;
 mod gacha;
+mod mail;
 mod packet;
 mod player;
+mod power;
+mod tasks;
 
+pub use achievements::refresh_achievement_summary;
 pub use app::AppState;
 pub use battle::{
-    BattleContext, create_battle, default_max_ap, end_fight::send_end_fight_push,
-    generate_auto_opers, generate_initial_deck, rewards::generate_dungeon_rewards,
+    BattleContext, create_battle, default_max_ap,
+    end_fight::send_end_fight_push,
+    generate_auto_opers, generate_initial_deck,
+    inspect::{deck_contents, entity_details},
+    manager::fight_data_mgr::FightDataMgr,
+    persist::{persist_active_battle, persist_active_battle_in, restore_active_battle},
+    replay::{ReplayStart, round_seed, verify_active_battle},
+    rewards::{DungeonClear, DungeonRewards, generate_dungeon_rewards, parse_reward_string},
     simulator::BattleSimulator,
+    stars::evaluate_stars,
+    use_cloth_skill,
+};
+// Only the simulator binary and the replay test use these
+#[allow(unused_imports)]
+pub use battle::{replay::replay_stored_battle, reset_uid_counters};
+pub use battle_pass::{
+    active_battle_pass, apply_bp_charge, bp_level, buy_bp_levels, current_season,
 };
 pub use connection::{ActiveBattle, ConnectionContext};
+pub use events::{EventBus, GameEvent, dispatch_events};
 pub use gacha::{
     BannerType, GachaResult, GachaState, build_gacha, get_rewards, grant_dupe_rewards,
     load_gacha_state, parse_item, parse_store_product, save_gacha_state,
 };
 
+pub use mail::{broadcast_mail, deliver_broadcasts, render_template, run_mail_expiry, send_mail};
 pub use packet::CommandPacket;
 pub use player::PlayerState;
+pub use power::{
+    POWER_CURRENCY_ID, buy_power, buy_power_left, episode_power_cost, refresh_power, refund_power,
+    regenerate_power_in, store_power_anchor, use_expired_power_items, use_power_items, write_power,
+};
+pub use tasks::{
+    READ_TASK_LISTENER, TASK_TYPE_BP, TASK_TYPES, TaskClaim, claim_activity_bonuses, claim_task,
+    grant_task_claim, reset_expired_tasks, sync_tasks, task_def, task_defs,
+};
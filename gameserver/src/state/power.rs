@@ -0,0 +1,371 @@
+use common::time::ServerTime;
+use config::configs;
+use database::db::game::{currencies, items, materials};
+use database::models::game::ledger::{LedgerOrigin, LedgerSource};
+use sqlx::{Sqlite, Transaction};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::error::AppError;
+use crate::state::ConnectionContext;
+use crate::util::transaction::{self, MATERIAL_CURRENCY};
+
+/// Currency id of stamina (activity)
+pub const POWER_CURRENCY_ID: i32 = 4;
+
+/// Natural regeneration only fills stamina up to `recoverLimit`; items, purchases and
+/// refunds can overflow it up to `maxLimit`.
+fn regenerate(
+    quantity: i32,
+    anchor: i64,
+    now: i64,
+    cfg: &config::currency::Currency,
+) -> (i32, i64) {
+    let step_ms = cfg.recover_time as i64 * 1000;
+
+    if cfg.recover_limit <= 0 || step_ms <= 0 || cfg.recover_num <= 0 {
+        return (quantity, now);
+    }
+
+    if quantity >= cfg.recover_limit {
+        return (quantity, now);
+    }
+
+    let ticks = (now - anchor).max(0) / step_ms;
+    let regenerated = quantity as i64 + ticks * cfg.recover_num as i64;
+
+    if regenerated >= cfg.recover_limit as i64 {
+        (cfg.recover_limit, now)
+    } else {
+        (regenerated as i32, anchor + ticks * step_ms)
+    }
+}
+
+/// Applies the stamina regenerated since `last_energy_refill_time` and returns the
+/// current amount
+pub async fn refresh_power(conn: &mut ConnectionContext) -> Result<i32, AppError> {
+    let (tx, current, anchor) = begin_power(conn).await?;
+    finish_power(conn, tx, anchor).await?;
+    Ok(current)
+}
+
+/// Gives back up to `amount` stamina, whatever would pass `maxLimit` is dropped.
+/// Returns the stamina actually refunded.
+pub async fn refund_power(
+    conn: &mut ConnectionContext,
+    amount: i32,
+    origin: LedgerOrigin,
+) -> Result<i32, AppError> {
+    let player_id = conn.player_id.ok_or(AppError::NotLoggedIn)?;
+    let (mut tx, current, anchor) = begin_power(conn).await?;
+
+    let refund = amount.min(power_room(current)).max(0);
+    if refund < amount {
+        tracing::warn!(
+            "Player {} is near the stamina cap, refunding {} of {}",
+            player_id,
+            refund,
+            amount
+        );
+    }
+
+    write_power(&mut tx, player_id, current, anchor, refund, origin).await?;
+    finish_power(conn, tx, anchor).await?;
+
+    Ok(refund)
+}
+
+/// Buys one stamina purchase of the day. Returns the purchases left.
+pub async fn buy_power(ctx: Arc<Mutex<ConnectionContext>>) -> Result<i32, AppError> {
+    let (player_id, left) = {
+        let conn = ctx.lock().await;
+        let player_id = conn.player_id.ok_or(AppError::NotLoggedIn)?;
+        (player_id, buy_power_left(&conn).await?)
+    };
+
+    if left <= 0 {
+        tracing::warn!("Player {} has no stamina purchases left today", player_id);
+        return Err(AppError::InvalidRequest);
+    }
+
+    let cfg = common::power();
+    let bought = cfg.buy_costs.len() - left as usize;
+    let cost = cfg.buy_costs[bought];
+
+    let mut applied = transaction::Transaction::new(player_id, LedgerSource::Power)
+        .cost(MATERIAL_CURRENCY, cfg.buy_currency as u32, cost)
+        .grant(MATERIAL_CURRENCY, POWER_CURRENCY_ID as u32, cfg.buy_amount)
+        .silent()
+        .apply(ctx.clone())
+        .await?;
+    // The count read above may be stale, the purchase only stands if it is the one priced
+    let counted =
+        currencies::add_power_buy_count(&mut applied.db, player_id, cfg.buy_costs.len() as i32)
+            .await?;
+    if counted != Some(bought as i32 + 1) {
+        tracing::warn!(
+            "Player {} stamina purchase {} raced another one, dropping it",
+            player_id,
+            bought + 1
+        );
+        return Err(AppError::InvalidRequest);
+    }
+    applied.commit(ctx).await?;
+
+    tracing::info!(
+        "Player {} bought {} stamina for {} (purchase {} of today)",
+        player_id,
+        cfg.buy_amount,
+        cost,
+        bought + 1
+    );
+
+    Ok(left - 1)
+}
+
+/// Uses power items, `(uid, num)` per stack, and converts them into stamina in one
+/// commit, unless that would pass `maxLimit`. Returns the item id and the stamina gained
+/// of each use.
+pub async fn use_power_items(
+    ctx: Arc<Mutex<ConnectionContext>>,
+    uses: &[(i64, i32)],
+) -> Result<Vec<(i64, i32)>, AppError> {
+    let (player_id, pool) = {
+        let conn = ctx.lock().await;
+        let player_id = conn.player_id.ok_or(AppError::NotLoggedIn)?;
+        (player_id, conn.state.db.clone())
+    };
+
+    let owned = items::get_all_power_items(&pool, player_id).await?;
+
+    let mut used = Vec::new();
+    let mut total = 0i32;
+    for &(uid, num) in uses {
+        if num <= 0 {
+            return Err(AppError::InvalidRequest);
+        }
+
+        let item_id = owned
+            .iter()
+            .find(|i| i.uid == uid)
+            .map(|i| i.item_id)
+            .ok_or(AppError::InsufficientItems)?;
+
+        let effect = configs::get()
+            .power_item
+            .get(item_id as i32)
+            .map(|p| p.effect)
+            .unwrap_or(0);
+        let gained = effect.checked_mul(num).ok_or(AppError::InvalidRequest)?;
+        total = total.checked_add(gained).ok_or(AppError::InvalidRequest)?;
+
+        used.push((item_id, gained));
+    }
+
+    let origin = LedgerOrigin::new(LedgerSource::ItemUse);
+    let mut applied = transaction::Transaction::new(player_id, LedgerSource::ItemUse)
+        .grant(MATERIAL_CURRENCY, POWER_CURRENCY_ID as u32, total)
+        .silent()
+        .apply(ctx.clone())
+        .await?;
+
+    for (&(uid, num), &(item_id, _)) in uses.iter().zip(&used) {
+        let taken =
+            materials::take_power_items(&mut applied.db, player_id, uid, num, origin).await?;
+        if taken != Some(item_id) {
+            return Err(AppError::InsufficientItems);
+        }
+        applied.touch_power_item(item_id as u32);
+    }
+
+    applied.commit(ctx).await?;
+
+    Ok(used)
+}
+
+/// Converts every expired power item into stamina, up to `maxLimit`; the rest is
+/// lost with the items. Returns the number of stacks used and the stamina gained.
+pub async fn use_expired_power_items(
+    conn: &mut ConnectionContext,
+) -> Result<(usize, i32), AppError> {
+    let player_id = conn.player_id.ok_or(AppError::NotLoggedIn)?;
+    let now = ServerTime::now_ms() / 1000;
+
+    let expired: Vec<i64> = items::get_all_power_items(&conn.state.db, player_id)
+        .await?
+        .into_iter()
+        .filter(|i| i.expire_time > 0 && (i.expire_time as i64) < now)
+        .map(|i| i.uid)
+        .collect();
+
+    if expired.is_empty() {
+        return Ok((0, 0));
+    }
+
+    let origin = LedgerOrigin::new(LedgerSource::ItemUse);
+    let (mut tx, current, anchor) = begin_power(conn).await?;
+
+    let mut used = 0;
+    let mut total = 0i32;
+    for uid in expired {
+        let Some((item_id, quantity)) =
+            materials::take_expired_power_item(&mut tx, player_id, uid, origin).await?
+        else {
+            continue;
+        };
+
+        let effect = configs::get()
+            .power_item
+            .get(item_id as i32)
+            .map(|p| p.effect)
+            .unwrap_or(0);
+
+        tracing::info!(
+            "Auto-using expired power item {} (uid: {}, qty: {}, effect: {})",
+            item_id,
+            uid,
+            quantity,
+            effect
+        );
+
+        used += 1;
+        total = total.saturating_add(effect.saturating_mul(quantity));
+    }
+
+    let gained = total.min(power_room(current)).max(0);
+    if gained < total {
+        tracing::warn!(
+            "Player {} is near the stamina cap, expired items gave {} of {}",
+            player_id,
+            gained,
+            total
+        );
+    }
+
+    write_power(&mut tx, player_id, current, anchor, gained, origin).await?;
+    finish_power(conn, tx, anchor).await?;
+
+    Ok((used, gained))
+}
+
+/// Stamina that still fits below `maxLimit`
+fn power_room(current: i32) -> i32 {
+    match configs::get().currency.get(POWER_CURRENCY_ID) {
+        Some(cfg) if cfg.max_limit > 0 => (cfg.max_limit - current).max(0),
+        _ => i32::MAX,
+    }
+}
+
+/// Writes `delta` on top of `current` into `tx`. `None` when it would drop below 0 or
+/// a grant would pass `maxLimit`.
+pub async fn write_power(
+    tx: &mut Transaction<'static, Sqlite>,
+    player_id: i64,
+    current: i32,
+    anchor: i64,
+    delta: i32,
+    origin: LedgerOrigin,
+) -> Result<Option<i32>, AppError> {
+    if current + delta < 0 || (delta > 0 && delta > power_room(current)) {
+        return Ok(None);
+    }
+
+    let quantity =
+        materials::change_regen_currency(tx, player_id, POWER_CURRENCY_ID, delta, anchor, origin)
+            .await?;
+
+    Ok(quantity)
+}
+
+/// Writes the stamina regenerated since `last_energy_refill_time` into `tx`. Returns
+/// the regenerated amount and the time regeneration now counts from, to store with
+/// [`store_power_anchor`] once `tx` commits.
+pub async fn regenerate_power_in(
+    conn: &ConnectionContext,
+    tx: &mut Transaction<'static, Sqlite>,
+) -> Result<(i32, i64), AppError> {
+    let player_id = conn.player_id.ok_or(AppError::NotLoggedIn)?;
+    let now = ServerTime::now_ms();
+
+    let stored = currencies::get_currency_quantity(tx, player_id, POWER_CURRENCY_ID).await?;
+
+    let (current, anchor) = match configs::get().currency.get(POWER_CURRENCY_ID) {
+        Some(cfg) => {
+            let last = conn.player_state().and_then(|s| s.last_energy_refill_time);
+            regenerate(stored, last.unwrap_or(now), now, cfg)
+        }
+        None => (stored, now),
+    };
+
+    if current != stored {
+        tracing::debug!(
+            "Player {} regenerated {} stamina",
+            player_id,
+            current - stored
+        );
+        let origin = LedgerOrigin::new(LedgerSource::Power);
+        let delta = current - stored;
+        materials::change_regen_currency(tx, player_id, POWER_CURRENCY_ID, delta, anchor, origin)
+            .await?;
+    }
+
+    Ok((current, anchor))
+}
+
+/// Stores the time regeneration counts from after a committed stamina change
+pub async fn store_power_anchor(conn: &mut ConnectionContext, anchor: i64) -> Result<(), AppError> {
+    // A full bar keeps moving the anchor, it only needs storing once regen resumes
+    let stored = conn.player_state().and_then(|s| s.last_energy_refill_time);
+    if stored != Some(anchor) {
+        conn.update_and_save_player_state(|state| state.last_energy_refill_time = Some(anchor))
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Opens the transaction of a stamina change with the regeneration written into it
+async fn begin_power(
+    conn: &ConnectionContext,
+) -> Result<(Transaction<'static, Sqlite>, i32, i64), AppError> {
+    let mut tx = conn.state.db.begin().await?;
+    let (current, anchor) = regenerate_power_in(conn, &mut tx).await?;
+    Ok((tx, current, anchor))
+}
+
+/// Commits a stamina change and stores the time regeneration counts from
+async fn finish_power(
+    conn: &mut ConnectionContext,
+    tx: Transaction<'static, Sqlite>,
+    anchor: i64,
+) -> Result<(), AppError> {
+    tx.commit().await?;
+    store_power_anchor(conn, anchor).await
+}
+
+/// Daily purchases left
+pub async fn buy_power_left(conn: &ConnectionContext) -> Result<i32, AppError> {
+    let player_id = conn.player_id.ok_or(AppError::NotLoggedIn)?;
+    let bought = currencies::get_power_buy_count(&conn.state.db, player_id).await?;
+
+    Ok((common::power().buy_costs.len() as i32 - bought).max(0))
+}
+
+/// Stamina cost of an episode, `currencyType#currencyId#amount` entries of its
+/// `cost` (or `failCost`) column
+pub fn episode_power_cost(cost: &str, multiplication: i32) -> i32 {
+    cost.split('|')
+        .filter_map(|part| {
+            let parts: Vec<i32> = part.split('#').filter_map(|x| x.parse().ok()).collect();
+            match parts[..] {
+                [2, POWER_CURRENCY_ID, amount] => Some(amount),
+                [..] if !part.is_empty() => {
+                    tracing::warn!("Unsupported episode cost entry '{}'", part);
+                    None
+                }
+                _ => None,
+            }
+        })
+        .sum::<i32>()
+        * multiplication.max(1)
+}
@@ -0,0 +1,445 @@
+use common::time::ServerTime;
+use config::configs;
+use database::db::game::tasks;
+use database::models::game::tasks::{UserTask, UserTaskActivity};
+use sqlx::{Sqlite, SqlitePool};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use super::battle_pass::{self, add_bp_score, push_bp_score};
+use super::events::EventFuture;
+use crate::error::AppError;
+use crate::state::{ConnectionContext, GameEvent, parse_reward_string};
+use crate::util::push::{send_delete_task_push, send_update_task_push};
+use crate::util::transaction::Transaction;
+use database::models::game::ledger::LedgerSource;
+
+pub const TASK_TYPE_DAILY: i32 = 1;
+pub const TASK_TYPE_WEEKLY: i32 = 2;
+pub const TASK_TYPE_NOVICE: i32 = 4;
+pub const TASK_TYPE_BP: i32 = 10;
+
+/// Task types backed by the task tables, the others are served from the captured task info
+pub const TASK_TYPES: [i32; 4] = [
+    TASK_TYPE_DAILY,
+    TASK_TYPE_WEEKLY,
+    TASK_TYPE_NOVICE,
+    TASK_TYPE_BP,
+];
+
+/// Task types with activity points and bonus stages
+const ACTIVITY_TYPES: [i32; 3] = [TASK_TYPE_DAILY, TASK_TYPE_WEEKLY, TASK_TYPE_NOVICE];
+
+/// Listener of tasks completed by opening them (FinishReadTask) rather than by playing
+pub const READ_TASK_LISTENER: &str = "ReadTask";
+
+/// When a task's progress starts over
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskCycle {
+    Daily,
+    Weekly,
+    /// Ends with the battle pass season
+    Season,
+    Never,
+}
+
+impl TaskCycle {
+    /// Unix seconds the current cycle ends at, 0 for tasks that never reset
+    fn expiry(self, now_ms: i64) -> i32 {
+        match self {
+            Self::Daily => ServerTime::next_day_reset_sec(now_ms) as i32,
+            Self::Weekly => ServerTime::next_week_reset_sec(now_ms) as i32,
+            Self::Season => battle_pass::current_season(now_ms).map_or(0, |s| s.end_time as i32),
+            Self::Never => 0,
+        }
+    }
+}
+
+/// A row of `task_daily`, `task_weekly`, `task_novice` or the current pass's `bp_task`
+#[derive(Debug, Clone, Copy)]
+pub struct TaskDef {
+    pub id: i32,
+    pub task_type: i32,
+    pub cycle: TaskCycle,
+    pub min_type: &'static str,
+    pub listener_type: &'static str,
+    pub listener_param: &'static str,
+    pub max_progress: i32,
+    /// Activity points granted when claimed
+    pub activity: i32,
+    pub bonus: &'static str,
+    /// Battle pass score granted when claimed
+    pub bp_score: i32,
+}
+
+macro_rules! task_def {
+    ($task_type:expr, $cycle:expr, $row:expr) => {
+        TaskDef {
+            id: $row.id,
+            task_type: $task_type,
+            cycle: $cycle,
+            min_type: &$row.min_type,
+            listener_type: &$row.listener_type,
+            listener_param: &$row.listener_param,
+            max_progress: $row.max_progress,
+            activity: $row.activity,
+            bonus: &$row.bonus,
+            bp_score: 0,
+        }
+    };
+}
+
+/// Every online task of the task tables
+pub fn task_defs() -> impl Iterator<Item = TaskDef> {
+    let tables = configs::get();
+    let season = battle_pass::current_season(ServerTime::now_ms());
+
+    let daily = tables
+        .task_daily
+        .iter()
+        .filter(|t| t.is_online == 1)
+        .map(|t| task_def!(TASK_TYPE_DAILY, TaskCycle::Daily, t));
+    let weekly = tables
+        .task_weekly
+        .iter()
+        .filter(|t| t.is_online == 1)
+        .map(|t| task_def!(TASK_TYPE_WEEKLY, TaskCycle::Weekly, t));
+    let novice = tables
+        .task_novice
+        .iter()
+        .filter(|t| t.is_online == 1)
+        .map(|t| task_def!(TASK_TYPE_NOVICE, TaskCycle::Never, t));
+    // Returning-player tasks are left out, there's no return detection
+    let bp = tables
+        .bp_task
+        .iter()
+        .filter(move |t| {
+            season.is_some_and(|s| s.bp_id == t.bp_id) && t.is_online == 1 && !t.turnback_task
+        })
+        .map(|t| TaskDef {
+            id: t.id,
+            task_type: TASK_TYPE_BP,
+            cycle: bp_task_cycle(t.loop_type),
+            min_type: &t.min_type,
+            listener_type: &t.listener_type,
+            listener_param: &t.listener_param,
+            max_progress: t.max_progress,
+            activity: 0,
+            bonus: "",
+            bp_score: t.bonus_score,
+        });
+
+    daily.chain(weekly).chain(novice).chain(bp)
+}
+
+/// `loopType` of a `bp_task` row: 1 daily, 2 weekly, anything else once per season
+fn bp_task_cycle(loop_type: i32) -> TaskCycle {
+    match loop_type {
+        1 => TaskCycle::Daily,
+        2 => TaskCycle::Weekly,
+        _ => TaskCycle::Season,
+    }
+}
+
+pub fn task_def(task_id: i32) -> Option<TaskDef> {
+    task_defs().find(|t| t.id == task_id)
+}
+
+/// When the activity of a task type resets, 0 for types that never reset
+fn activity_expiry(task_type: i32, now_ms: i64) -> i32 {
+    match task_type {
+        TASK_TYPE_DAILY => TaskCycle::Daily.expiry(now_ms),
+        TASK_TYPE_WEEKLY => TaskCycle::Weekly.expiry(now_ms),
+        _ => 0,
+    }
+}
+
+/// Listener params are `#` or `,` separated ids, empty matches anything
+fn param_matches(param: &str, id: i32) -> bool {
+    let param = param.trim();
+    param.is_empty()
+        || param
+            .split(['#', ','])
+            .any(|p| p.trim().parse::<i32>() == Ok(id))
+}
+
+/// Progress an event adds to a task
+fn event_progress(def: &TaskDef, event: &GameEvent) -> i32 {
+    let param = def.listener_param;
+
+    match (def.listener_type, event) {
+        ("Login", GameEvent::Login) => 1,
+        (
+            "HeroLevelUp",
+            GameEvent::HeroLevelUp {
+                hero_id,
+                old_level,
+                new_level,
+            },
+        ) if param_matches(param, *hero_id) => new_level - old_level,
+        ("HeroRankUp", GameEvent::HeroRankUp { hero_id, .. }) if param_matches(param, *hero_id) => {
+            1
+        }
+        (
+            "PassEpisode",
+            GameEvent::DungeonCleared {
+                episode_id,
+                multiplication,
+                ..
+            },
+        ) if param_matches(param, *episode_id) => (*multiplication).max(1),
+        ("Summon", GameEvent::Summon { pool_id, count }) if param_matches(param, *pool_id) => {
+            *count
+        }
+        ("UseItem", GameEvent::ItemUsed { item_id, count }) if param_matches(param, *item_id) => {
+            *count
+        }
+        (
+            "BuyGoods",
+            GameEvent::StorePurchase {
+                store_id, count, ..
+            },
+        ) if param_matches(param, *store_id) => *count,
+        ("CostPower", GameEvent::PowerSpent { amount }) => *amount,
+        _ => 0,
+    }
+}
+
+/// Restarts tasks and activity whose cycle has ended
+pub async fn reset_expired_tasks(pool: &SqlitePool, user_id: i64) -> Result<(), AppError> {
+    let now = ServerTime::now_ms();
+    let now_sec = (now / 1000) as i32;
+
+    let expired = tasks::get_expired_tasks(pool, user_id, now_sec).await?;
+    if !expired.is_empty() {
+        let cycles: HashMap<i32, TaskCycle> = task_defs().map(|d| (d.id, d.cycle)).collect();
+
+        // Tasks gone from the tables are left for sync_tasks to drop
+        let reset: Vec<(i32, i32)> = expired
+            .iter()
+            .filter_map(|t| cycles.get(&t.task_id).map(|c| (t.task_id, c.expiry(now))))
+            .collect();
+        tasks::reset_tasks(pool, user_id, &reset).await?;
+
+        tracing::info!("Reset {} tasks of user {}", reset.len(), user_id);
+    }
+
+    for task_type in [TASK_TYPE_DAILY, TASK_TYPE_WEEKLY] {
+        tasks::reset_expired_activity(
+            pool,
+            user_id,
+            task_type,
+            now_sec,
+            activity_expiry(task_type, now),
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Brings the user's tasks in line with the task tables: resets ended cycles, starts
+/// tracking new tasks and drops tasks no longer in the tables. Returns the dropped ids.
+pub async fn sync_tasks(pool: &SqlitePool, user_id: i64) -> Result<Vec<i32>, AppError> {
+    reset_expired_tasks(pool, user_id).await?;
+
+    let now = ServerTime::now_ms();
+    let defs: Vec<TaskDef> = task_defs().collect();
+
+    let rows: Vec<(i32, i32, i32)> = defs
+        .iter()
+        .map(|d| (d.id, d.task_type, d.cycle.expiry(now)))
+        .collect();
+    let inserted = tasks::insert_missing_tasks(pool, user_id, &rows).await?;
+
+    for task_type in ACTIVITY_TYPES {
+        tasks::insert_missing_activity(pool, user_id, task_type, activity_expiry(task_type, now))
+            .await?;
+    }
+
+    let known: HashSet<i32> = defs.iter().map(|d| d.id).collect();
+    let stale: Vec<i32> = tasks::get_tasks(pool, user_id, &TASK_TYPES)
+        .await?
+        .into_iter()
+        .map(|t| t.task_id)
+        .filter(|id| !known.contains(id))
+        .collect();
+    tasks::delete_tasks(pool, user_id, &stale).await?;
+
+    if inserted > 0 || !stale.is_empty() {
+        tracing::info!(
+            "Synced tasks of user {}: {} added, {} removed",
+            user_id,
+            inserted,
+            stale.len()
+        );
+    }
+
+    Ok(stale)
+}
+
+/// Event subscriber advancing the tasks listening for the event
+pub fn on_task_event(ctx: Arc<Mutex<ConnectionContext>>, event: Arc<GameEvent>) -> EventFuture {
+    Box::pin(track_task_progress(ctx, event))
+}
+
+async fn track_task_progress(
+    ctx: Arc<Mutex<ConnectionContext>>,
+    event: Arc<GameEvent>,
+) -> Result<(), AppError> {
+    let (player_id, pool) = {
+        let conn = ctx.lock().await;
+        let player_id = conn.player_id.ok_or(AppError::NotLoggedIn)?;
+        (player_id, conn.state.db.clone())
+    };
+
+    let deleted = if *event == GameEvent::Login {
+        sync_tasks(&pool, player_id).await?
+    } else {
+        reset_expired_tasks(&pool, player_id).await?;
+        Vec::new()
+    };
+
+    let mut changed = Vec::new();
+    for def in task_defs() {
+        let amount = event_progress(&def, &event);
+        if amount <= 0 {
+            continue;
+        }
+
+        if let Some(task) =
+            tasks::add_task_progress(&pool, player_id, def.id, amount, def.max_progress).await?
+        {
+            changed.push(task);
+        }
+    }
+
+    send_delete_task_push(ctx.clone(), deleted).await?;
+    send_update_task_push(ctx, changed, Vec::new()).await?;
+
+    Ok(())
+}
+
+/// Tasks and activity bonuses claimed by one command
+#[derive(Debug, Default)]
+pub struct TaskClaim {
+    pub tasks: Vec<UserTask>,
+    pub activities: Vec<UserTaskActivity>,
+    pub rewards: Vec<(u32, u32, i32)>,
+    pub bp_score: i32,
+}
+
+impl TaskClaim {
+    fn set_activity(&mut self, activity: UserTaskActivity) {
+        self.activities.retain(|a| a.type_id != activity.type_id);
+        self.activities.push(activity);
+    }
+}
+
+/// Claims a completed task: its rewards and activity points. Returns false if the task
+/// isn't complete or was claimed already this cycle.
+pub async fn claim_task(
+    db: &mut sqlx::Transaction<'static, Sqlite>,
+    user_id: i64,
+    def: &TaskDef,
+    claim: &mut TaskClaim,
+) -> Result<bool, AppError> {
+    let Some(task) = tasks::finish_task(db, user_id, def.id, def.max_progress).await? else {
+        return Ok(false);
+    };
+
+    claim.tasks.push(task);
+    claim.rewards.extend(parse_reward_string(def.bonus, 1));
+    claim.bp_score += def.bp_score;
+
+    if def.activity > 0
+        && let Some(activity) =
+            tasks::add_activity(db, user_id, def.task_type, def.activity).await?
+    {
+        claim.set_activity(activity);
+    }
+
+    Ok(true)
+}
+
+/// Claims every activity bonus stage of a task type the user has enough points for,
+/// up to `up_to` if given. Returns false if there was nothing to claim.
+pub async fn claim_activity_bonuses(
+    db: &mut sqlx::Transaction<'static, Sqlite>,
+    user_id: i64,
+    type_id: i32,
+    up_to: Option<i32>,
+    claim: &mut TaskClaim,
+) -> Result<bool, AppError> {
+    let Some(activity) = tasks::get_activity(db, user_id, type_id).await? else {
+        return Ok(false);
+    };
+
+    let mut stages: Vec<_> = configs::get()
+        .task_activity_bonus
+        .iter()
+        .filter(|b| {
+            b.r#type == type_id
+                && b.id > activity.define_id
+                && b.need_activity <= activity.value
+                && up_to.is_none_or(|last| b.id <= last)
+        })
+        .collect();
+    stages.sort_by_key(|b| b.id);
+
+    let Some(last) = stages.last() else {
+        return Ok(false);
+    };
+
+    let Some(updated) =
+        tasks::claim_activity_bonus(db, user_id, type_id, last.id, last.need_activity).await?
+    else {
+        return Ok(false);
+    };
+
+    for stage in &stages {
+        claim.rewards.extend(parse_reward_string(&stage.bonus, 1));
+    }
+    claim.set_activity(updated);
+
+    Ok(true)
+}
+
+/// Grants the rewards of a claim on top of the claim flags written to `db`, commits and
+/// pushes the resulting changes
+pub async fn grant_task_claim(
+    ctx: Arc<Mutex<ConnectionContext>>,
+    player_id: i64,
+    db: sqlx::Transaction<'static, Sqlite>,
+    claim: TaskClaim,
+) -> Result<(), AppError> {
+    let pool = ctx.lock().await.state.db.clone();
+
+    let mut applied = Transaction::new(player_id, LedgerSource::Task)
+        .grants(&claim.rewards)
+        .apply_on(ctx.clone(), db)
+        .await?;
+    let pass = if claim.bp_score > 0 {
+        add_bp_score(&pool, &mut applied.db, player_id, claim.bp_score, true).await?
+    } else {
+        None
+    };
+    applied.commit(ctx.clone()).await?;
+
+    tracing::info!(
+        "User {} claimed {} tasks and {} activity bonuses for {} rewards",
+        player_id,
+        claim.tasks.len(),
+        claim.activities.len(),
+        claim.rewards.len()
+    );
+
+    send_update_task_push(ctx.clone(), claim.tasks, claim.activities).await?;
+
+    if let Some(pass) = pass {
+        push_bp_score(ctx, &pass).await?;
+    }
+
+    Ok(())
+}
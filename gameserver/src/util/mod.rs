@@ -1,4 +1,4 @@
 pub mod common;
 pub mod data_loader;
-pub mod inventory;
 pub mod push;
+pub mod transaction;
@@ -1,9 +1,13 @@
 use crate::error::AppError;
-use crate::state::ConnectionContext;
-use database::db::game::{currencies, items, red_dots, stories::finish_story};
+use crate::state::{ConnectionContext, DungeonRewards};
+use database::db::game::{
+    currencies, dungeons::UserDungeonInfo, items, red_dots, stories::finish_story,
+};
+use database::models::game::dungeons::UserChapterTypeNum;
+use database::models::game::tasks::{UserTask, UserTaskActivity};
 use sonettobuf::{
-    CmdId, CurrencyChangePush, EndDungeonPush, ItemChangePush, MaterialChangePush, MaterialData,
-    StoryFinishPush, UpdateRedDotPush,
+    CmdId, CurrencyChangePush, DeleteTaskPush, EndDungeonPush, ItemChangePush, MaterialChangePush,
+    MaterialData, StoryFinishPush, UpdateRedDotPush, UpdateTaskPush,
 };
 use std::sync::Arc;
 use tokio::sync::Mutex;
@@ -194,27 +198,30 @@ pub async fn send_end_dungeon_push(
     ctx: Arc<Mutex<ConnectionContext>>,
     chapter_id: i32,
     episode_id: i32,
-    normal_bonus: Vec<(u32, u32, i32)>,
+    star: i32,
+    rewards: &DungeonRewards,
 ) -> Result<(), AppError> {
-    let normal_bonus = normal_bonus
-        .into_iter()
-        .map(|(t, id, q)| MaterialData {
-            materil_type: Some(t),
-            materil_id: Some(id),
-            quantity: Some(q),
-        })
-        .collect();
+    let materials = |bonus: &[(u32, u32, i32)]| {
+        bonus
+            .iter()
+            .map(|&(t, id, q)| MaterialData {
+                materil_type: Some(t),
+                materil_id: Some(id),
+                quantity: Some(q),
+            })
+            .collect()
+    };
 
     let push = EndDungeonPush {
         chapter_id: Some(chapter_id),
         episode_id: Some(episode_id),
 
         player_exp: Some(0),
-        star: Some(2),
+        star: Some(star),
 
-        first_bonus: vec![],
-        normal_bonus,
-        advenced_bonus: vec![],
+        first_bonus: materials(&rewards.first_bonus),
+        normal_bonus: materials(&[&rewards.normal_bonus[..], &rewards.free_bonus[..]].concat()),
+        advenced_bonus: materials(&rewards.advanced_bonus),
         addition_bonus: vec![],
         time_first_bonus: vec![],
         drop_bonus: vec![],
@@ -223,7 +230,7 @@ pub async fn send_end_dungeon_push(
         can_update_dungeon_record: Some(false),
         old_record_round: Some(0),
         new_record_round: Some(0),
-        first_pass: Some(false),
+        first_pass: Some(!rewards.first_bonus.is_empty()),
 
         extra_str: Some(String::new()),
         assist_user_id: Some(0),
@@ -241,33 +248,23 @@ pub async fn send_dungeon_update_push(
     ctx: Arc<Mutex<ConnectionContext>>,
     chapter_id: i32,
     episode_id: i32,
-    star: i32,
-    challenge_count: i32,
-    has_record: bool,
-    chapter_type: i32,        // e.g., 6 for episode chapter
-    chapter_today_pass: i32,  // Today's completions for this chapter type
-    chapter_today_total: i32, // Today's total attempts for this chapter type
+    dungeon: &UserDungeonInfo,
+    chapter_type_num: Option<UserChapterTypeNum>,
 ) -> Result<(), AppError> {
     let dungeon_info = sonettobuf::UserDungeon {
         chapter_id: Some(chapter_id),
         episode_id: Some(episode_id),
-        star: Some(star),
-        challenge_count: Some(challenge_count),
-        has_record: Some(has_record),
+        star: Some(dungeon.star),
+        challenge_count: Some(dungeon.challenge_count),
+        has_record: Some(dungeon.has_record),
         left_return_all_num: Some(0),
-        today_pass_num: Some(2),  // Episode-specific today count
-        today_total_num: Some(2), // Episode-specific today total
+        today_pass_num: Some(dungeon.today_pass_num),
+        today_total_num: Some(dungeon.today_total_num),
     };
 
-    let chapter_type_nums = vec![sonettobuf::UserChapterTypeNum {
-        chapter_type: Some(chapter_type),
-        today_pass_num: Some(chapter_today_pass),
-        today_total_num: Some(chapter_today_total),
-    }];
-
     let push = sonettobuf::DungeonUpdatePush {
         dungeon_info: Some(dungeon_info),
-        chapter_type_nums,
+        chapter_type_nums: chapter_type_num.into_iter().map(Into::into).collect(),
     };
 
     let mut conn = ctx.lock().await;
@@ -416,3 +413,38 @@ pub async fn send_equip_update_push_by_uid(
 
     Ok(())
 }
+
+pub async fn send_update_task_push(
+    ctx: Arc<Mutex<ConnectionContext>>,
+    tasks: Vec<UserTask>,
+    activities: Vec<UserTaskActivity>,
+) -> Result<(), AppError> {
+    if tasks.is_empty() && activities.is_empty() {
+        return Ok(());
+    }
+
+    let push = UpdateTaskPush {
+        task_info: tasks.into_iter().map(Into::into).collect(),
+        activity_info: activities.into_iter().map(Into::into).collect(),
+    };
+
+    let mut conn = ctx.lock().await;
+    conn.notify(CmdId::UpdateTaskPushCmd, push).await?;
+
+    Ok(())
+}
+
+pub async fn send_delete_task_push(
+    ctx: Arc<Mutex<ConnectionContext>>,
+    task_ids: Vec<i32>,
+) -> Result<(), AppError> {
+    if task_ids.is_empty() {
+        return Ok(());
+    }
+
+    let mut conn = ctx.lock().await;
+    conn.notify(CmdId::DeleteTaskPushCmd, DeleteTaskPush { task_ids })
+        .await?;
+
+    Ok(())
+}
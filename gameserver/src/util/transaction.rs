@@ -0,0 +1,616 @@
+use crate::error::AppError;
+use crate::state::{
+    ConnectionContext, GameEvent, POWER_CURRENCY_ID, grant_dupe_rewards, regenerate_power_in,
+    store_power_anchor, write_power,
+};
+use crate::util::push;
+use database::db::game::materials;
+use database::models::game::heros::UserHeroModel;
+use database::models::game::ledger::{LedgerOrigin, LedgerSource};
+use sonettobuf::{CmdId, EquipDeletePush, HeroSkinGainPush, HeroUpdatePush};
+use sqlx::Sqlite;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// `materilType` values of the `type#id#quantity` material strings
+pub const MATERIAL_ITEM: u32 = 1;
+pub const MATERIAL_CURRENCY: u32 = 2;
+pub const MATERIAL_HERO: u32 = 4;
+pub const MATERIAL_SKIN: u32 = 5;
+pub const MATERIAL_EQUIP: u32 = 9;
+pub const MATERIAL_POWER_ITEM: u32 = 10;
+pub const MATERIAL_INSIGHT_ITEM: u32 = 24;
+
+/// Costs and rewards of one player action, applied in a single database transaction.
+/// Costs are checked as they are taken, so a player short on anything is neither charged
+/// nor rewarded. After the commit the changes go out as one set of pushes.
+#[derive(Debug, Clone)]
+pub struct Transaction {
+    player_id: i64,
+    origin: LedgerOrigin,
+    costs: Vec<(u32, u32, i32)>,
+    equip_costs: Vec<(i64, i32)>,
+    grants: Vec<(u32, u32, i32)>,
+    /// Net stamina delta, applied on top of regeneration
+    power: i32,
+    approach: Option<u32>,
+    show_rewards: bool,
+}
+
+/// A transaction whose deltas are written but not committed. Extra writes belonging to
+/// the same action go through `db` before [`Applied::commit`].
+pub struct Applied {
+    pub db: sqlx::Transaction<'static, Sqlite>,
+    player_id: i64,
+    /// Regeneration anchor and stamina delta to store once committed
+    power: Option<(i64, i32)>,
+    approach: Option<u32>,
+    show_rewards: bool,
+    changes: Changes,
+}
+
+/// What a committed transaction changed
+#[derive(Debug, Clone, Default)]
+pub struct Changes {
+    pub items: Vec<u32>,
+    pub power_items: Vec<u32>,
+    pub insight_items: Vec<u32>,
+    /// `(currency_id, delta)`
+    pub currencies: Vec<(i32, i32)>,
+    pub equip_uids: Vec<i64>,
+    pub deleted_equip_uids: Vec<i64>,
+    /// Granted heroes in grant order, one entry per copy
+    pub heroes: Vec<HeroGain>,
+    /// Heroes to resend, granted ones and those touched through [`Applied::db`]
+    pub updated_heroes: Vec<i32>,
+    pub skins: Vec<i32>,
+    /// `(material_type, material_id, quantity)` shown in the reward popup, duplicate
+    /// heroes appear as their converted materials
+    pub rewards: Vec<(u32, u32, i32)>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct HeroGain {
+    pub is_new: bool,
+    pub duplicate_count: i32,
+}
+
+impl Changes {
+    fn add_item(&mut self, item_id: u32) {
+        if !self.items.contains(&item_id) {
+            self.items.push(item_id);
+        }
+    }
+
+    fn add_currency(&mut self, currency_id: i32, delta: i32) {
+        self.currencies.push((currency_id, delta));
+    }
+
+    fn add_equip(&mut self, uid: i64) {
+        if !self.equip_uids.contains(&uid) {
+            self.equip_uids.push(uid);
+        }
+    }
+
+    fn add_hero(&mut self, hero_id: i32) {
+        if !self.updated_heroes.contains(&hero_id) {
+            self.updated_heroes.push(hero_id);
+        }
+    }
+}
+
+impl Transaction {
+    /// Every delta lands in the economy ledger under `source`
+    pub fn new(player_id: i64, source: LedgerSource) -> Self {
+        Self {
+            player_id,
+            origin: LedgerOrigin::new(source),
+            costs: Vec::new(),
+            equip_costs: Vec::new(),
+            grants: Vec::new(),
+            power: 0,
+            approach: None,
+            show_rewards: true,
+        }
+    }
+
+    /// Takes an item or currency, the only material types that can be spent. Stamina
+    /// counts what regenerated so far.
+    pub fn cost(mut self, material_type: u32, material_id: u32, quantity: i32) -> Self {
+        if quantity > 0 {
+            if is_power(material_type, material_id) {
+                self.power -= quantity;
+            } else {
+                self.costs.push((material_type, material_id, quantity));
+            }
+        }
+        self
+    }
+
+    pub fn costs(mut self, costs: &[(u32, u32, i32)]) -> Self {
+        for &(material_type, material_id, quantity) in costs {
+            self = self.cost(material_type, material_id, quantity);
+        }
+        self
+    }
+
+    /// Takes `count` copies of the equipment row `uid`
+    pub fn cost_equip(mut self, uid: i64, count: i32) -> Self {
+        if count > 0 {
+            self.equip_costs.push((uid, count));
+        }
+        self
+    }
+
+    /// Gives a material. Stamina can't pass its `maxLimit`, a grant that would fails the
+    /// whole transaction.
+    pub fn grant(mut self, material_type: u32, material_id: u32, quantity: i32) -> Self {
+        if quantity > 0 {
+            if is_power(material_type, material_id) {
+                self.power += quantity;
+            } else {
+                self.grants.push((material_type, material_id, quantity));
+            }
+        }
+        self
+    }
+
+    pub fn grants(mut self, grants: &[(u32, u32, i32)]) -> Self {
+        for &(material_type, material_id, quantity) in grants {
+            self = self.grant(material_type, material_id, quantity);
+        }
+        self
+    }
+
+    /// Id of what caused the transaction within its source, written to the ledger
+    pub fn reference(mut self, reference_id: i64) -> Self {
+        self.origin.reference_id = Some(reference_id);
+        self
+    }
+
+    /// `getApproach` of the reward popup and skin pushes
+    pub fn approach(mut self, approach: u32) -> Self {
+        self.approach = Some(approach);
+        self
+    }
+
+    /// Skips the reward popup, for replies that show the rewards themselves
+    pub fn silent(mut self) -> Self {
+        self.show_rewards = false;
+        self
+    }
+
+    /// Writes every delta into a new database transaction. A cost the player can't pay
+    /// rolls everything back, resyncs the short material and fails with
+    /// `InsufficientItems` or `InsufficientCurrency`.
+    pub async fn apply(self, ctx: Arc<Mutex<ConnectionContext>>) -> Result<Applied, AppError> {
+        let pool = ctx.lock().await.state.db.clone();
+        let db = pool.begin().await?;
+        self.apply_on(ctx, db).await
+    }
+
+    /// Like [`Transaction::apply`], on top of writes already made through `db`, so that
+    /// whatever decided the rewards commits or rolls back with them
+    pub async fn apply_on(
+        self,
+        ctx: Arc<Mutex<ConnectionContext>>,
+        mut db: sqlx::Transaction<'static, Sqlite>,
+    ) -> Result<Applied, AppError> {
+        let mut changes = Changes::default();
+        let player_id = self.player_id;
+
+        if let Err(e) = self.take_costs(&mut db, &mut changes).await {
+            drop(db);
+            if matches!(
+                e,
+                AppError::InsufficientItems | AppError::InsufficientCurrency
+            ) {
+                self.resync_costs(ctx).await?;
+            }
+            return Err(e);
+        }
+
+        let power = match self.power {
+            0 => None,
+            delta => match apply_power(&ctx, &mut db, player_id, self.origin, delta).await? {
+                Some(anchor) => {
+                    changes.add_currency(POWER_CURRENCY_ID, delta);
+                    if delta > 0 {
+                        let id = POWER_CURRENCY_ID as u32;
+                        changes.rewards.push((MATERIAL_CURRENCY, id, delta));
+                    }
+                    Some((anchor, delta))
+                }
+                None => {
+                    drop(db);
+                    push::send_currency_change_push(ctx, player_id, vec![(POWER_CURRENCY_ID, 0)])
+                        .await?;
+                    if delta < 0 {
+                        return Err(AppError::InsufficientCurrency);
+                    }
+                    tracing::warn!(
+                        "User {} can't get {} stamina, it would pass the cap",
+                        player_id,
+                        delta
+                    );
+                    return Err(AppError::InvalidRequest);
+                }
+            },
+        };
+
+        for &(material_type, material_id, quantity) in &self.grants {
+            give(
+                &mut db,
+                player_id,
+                self.origin,
+                material_type,
+                material_id,
+                quantity,
+                &mut changes,
+            )
+            .await?;
+        }
+
+        Ok(Applied {
+            db,
+            player_id,
+            power,
+            approach: self.approach,
+            show_rewards: self.show_rewards,
+            changes,
+        })
+    }
+
+    /// Applies and commits in one go
+    pub async fn commit(self, ctx: Arc<Mutex<ConnectionContext>>) -> Result<Changes, AppError> {
+        self.apply(ctx.clone()).await?.commit(ctx).await
+    }
+
+    async fn take_costs(
+        &self,
+        db: &mut sqlx::Transaction<'static, Sqlite>,
+        changes: &mut Changes,
+    ) -> Result<(), AppError> {
+        let player_id = self.player_id;
+        let origin = self.origin;
+
+        for &(material_type, material_id, quantity) in &self.costs {
+            match material_type {
+                MATERIAL_ITEM => {
+                    if !materials::take_item(db, player_id, material_id, quantity, origin).await? {
+                        tracing::info!(
+                            "User {} can't pay {} of item {}",
+                            player_id,
+                            quantity,
+                            material_id
+                        );
+                        return Err(AppError::InsufficientItems);
+                    }
+                    changes.add_item(material_id);
+                }
+                MATERIAL_CURRENCY => {
+                    let currency_id = material_id as i32;
+                    if !materials::take_currency(db, player_id, currency_id, quantity, origin)
+                        .await?
+                    {
+                        tracing::info!(
+                            "User {} can't pay {} of currency {}",
+                            player_id,
+                            quantity,
+                            currency_id
+                        );
+                        return Err(AppError::InsufficientCurrency);
+                    }
+                    changes.add_currency(currency_id, -quantity);
+                }
+                _ => {
+                    tracing::warn!(
+                        "Unsupported cost material type {} (id={})",
+                        material_type,
+                        material_id
+                    );
+                    return Err(AppError::InvalidRequest);
+                }
+            }
+        }
+
+        for &(uid, count) in &self.equip_costs {
+            let Some((_, left)) =
+                materials::take_equipment(db, player_id, uid, count, origin).await?
+            else {
+                tracing::info!(
+                    "User {} can't use {} of equipment uid={}",
+                    player_id,
+                    count,
+                    uid
+                );
+                return Err(AppError::InsufficientItems);
+            };
+
+            if left > 0 {
+                changes.add_equip(uid);
+            } else {
+                changes.deleted_equip_uids.push(uid);
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn resync_costs(&self, ctx: Arc<Mutex<ConnectionContext>>) -> Result<(), AppError> {
+        let items: Vec<u32> = self
+            .costs
+            .iter()
+            .filter(|(t, _, _)| *t == MATERIAL_ITEM)
+            .map(|(_, id, _)| *id)
+            .collect();
+        let currencies: Vec<(i32, i32)> = self
+            .costs
+            .iter()
+            .filter(|(t, _, _)| *t == MATERIAL_CURRENCY)
+            .map(|(_, id, _)| (*id as i32, 0))
+            .collect();
+
+        push::send_item_change_push(ctx.clone(), self.player_id, items, vec![], vec![]).await?;
+        push::send_currency_change_push(ctx, self.player_id, currencies).await?;
+
+        Ok(())
+    }
+}
+
+impl Applied {
+    pub fn changes(&self) -> &Changes {
+        &self.changes
+    }
+
+    /// Resends an equipment changed through `db` with the other pushes
+    pub fn touch_equip(&mut self, uid: i64) {
+        self.changes.add_equip(uid);
+    }
+
+    /// Resends the stacks of a power item changed through `db` with the other pushes
+    pub fn touch_power_item(&mut self, item_id: u32) {
+        if !self.changes.power_items.contains(&item_id) {
+            self.changes.power_items.push(item_id);
+        }
+    }
+
+    /// Resends a hero changed through `db` with the other pushes
+    pub fn touch_hero(&mut self, hero_id: i32) {
+        self.changes.add_hero(hero_id);
+    }
+
+    /// Commits, then pushes items, currencies, equipment, heroes and skins, and the reward
+    /// popup unless the transaction is silent
+    pub async fn commit(self, ctx: Arc<Mutex<ConnectionContext>>) -> Result<Changes, AppError> {
+        let Applied {
+            db,
+            player_id,
+            power,
+            approach,
+            show_rewards,
+            changes,
+        } = self;
+
+        db.commit().await?;
+
+        if let Some((anchor, delta)) = power {
+            let mut conn = ctx.lock().await;
+            store_power_anchor(&mut conn, anchor).await?;
+            if delta < 0 {
+                conn.emit(GameEvent::PowerSpent { amount: -delta });
+            }
+        }
+
+        let new_heroes = changes.heroes.iter().filter(|h| h.is_new).count() as i32;
+        if new_heroes > 0 {
+            ctx.lock()
+                .await
+                .emit(GameEvent::HeroesGained { count: new_heroes });
+        }
+
+        push::send_item_change_push(
+            ctx.clone(),
+            player_id,
+            changes.items.clone(),
+            changes.power_items.clone(),
+            changes.insight_items.clone(),
+        )
+        .await?;
+        push::send_currency_change_push(ctx.clone(), player_id, changes.currencies.clone()).await?;
+        push::send_equip_update_push_by_uid(ctx.clone(), player_id, &changes.equip_uids).await?;
+
+        if !changes.deleted_equip_uids.is_empty() {
+            ctx.lock()
+                .await
+                .notify(
+                    CmdId::EquipDeletePushCmd,
+                    EquipDeletePush {
+                        uids: changes.deleted_equip_uids.clone(),
+                    },
+                )
+                .await?;
+        }
+
+        if !changes.updated_heroes.is_empty() {
+            let pool = ctx.lock().await.state.db.clone();
+            let hero = UserHeroModel::new(player_id, pool);
+
+            let mut hero_updates = Vec::new();
+            for hero_id in &changes.updated_heroes {
+                hero_updates.push(hero.get_hero(*hero_id).await?.into());
+            }
+
+            ctx.lock()
+                .await
+                .notify(
+                    CmdId::HeroHeroUpdatePushCmd,
+                    HeroUpdatePush { hero_updates },
+                )
+                .await?;
+        }
+
+        for skin_id in &changes.skins {
+            ctx.lock()
+                .await
+                .notify(
+                    CmdId::HeroSkinGainPushCmd,
+                    HeroSkinGainPush {
+                        skin_id: Some(*skin_id),
+                        first_gain: Some(true),
+                        get_approach: approach.map(|a| a as i32),
+                    },
+                )
+                .await?;
+        }
+
+        if show_rewards {
+            push::send_material_change_push(ctx, changes.rewards.clone(), approach).await?;
+        }
+
+        Ok(changes)
+    }
+}
+
+fn is_power(material_type: u32, material_id: u32) -> bool {
+    material_type == MATERIAL_CURRENCY && material_id == POWER_CURRENCY_ID as u32
+}
+
+/// Writes a stamina delta on top of the regenerated amount. Returns the regeneration
+/// anchor to store after the commit, `None` when short or past `maxLimit`.
+async fn apply_power(
+    ctx: &Arc<Mutex<ConnectionContext>>,
+    db: &mut sqlx::Transaction<'static, Sqlite>,
+    player_id: i64,
+    origin: LedgerOrigin,
+    delta: i32,
+) -> Result<Option<i64>, AppError> {
+    let (current, anchor) = {
+        let conn = ctx.lock().await;
+        regenerate_power_in(&conn, db).await?
+    };
+
+    let written = write_power(db, player_id, current, anchor, delta, origin).await?;
+    Ok(written.map(|_| anchor))
+}
+
+async fn give(
+    db: &mut sqlx::Transaction<'static, Sqlite>,
+    player_id: i64,
+    origin: LedgerOrigin,
+    material_type: u32,
+    material_id: u32,
+    quantity: i32,
+    changes: &mut Changes,
+) -> Result<(), AppError> {
+    match material_type {
+        MATERIAL_ITEM => {
+            materials::give_item(db, player_id, material_id, quantity, origin).await?;
+            changes.add_item(material_id);
+        }
+        MATERIAL_CURRENCY => {
+            materials::give_currency(db, player_id, material_id as i32, quantity, origin).await?;
+            changes.add_currency(material_id as i32, quantity);
+        }
+        MATERIAL_HERO => {
+            let hero_id = material_id as i32;
+            for _ in 0..quantity {
+                give_hero(db, player_id, origin, hero_id, changes).await?;
+            }
+            return Ok(());
+        }
+        MATERIAL_SKIN => {
+            let skin_id = material_id as i32;
+            if !materials::give_skin(db, player_id, skin_id).await? {
+                tracing::info!("User {} already has skin {}", player_id, skin_id);
+                return Ok(());
+            }
+            changes.skins.push(skin_id);
+        }
+        MATERIAL_EQUIP => {
+            let uids =
+                materials::give_equipment(db, player_id, material_id as i32, quantity, origin)
+                    .await?;
+            for uid in uids {
+                changes.add_equip(uid);
+            }
+        }
+        MATERIAL_POWER_ITEM => {
+            materials::give_power_item(db, player_id, material_id as i32, quantity, origin).await?;
+            if !changes.power_items.contains(&material_id) {
+                changes.power_items.push(material_id);
+            }
+        }
+        MATERIAL_INSIGHT_ITEM => {
+            materials::give_insight_item(db, player_id, material_id as i32, quantity, origin)
+                .await?;
+            if !changes.insight_items.contains(&material_id) {
+                changes.insight_items.push(material_id);
+            }
+        }
+        _ => {
+            tracing::warn!(
+                "Unsupported reward material type {} (id={})",
+                material_type,
+                material_id
+            );
+            return Ok(());
+        }
+    }
+
+    changes.rewards.push((material_type, material_id, quantity));
+    Ok(())
+}
+
+/// One copy of a hero, duplicates turn into their `duplicateItem` materials
+async fn give_hero(
+    db: &mut sqlx::Transaction<'static, Sqlite>,
+    player_id: i64,
+    origin: LedgerOrigin,
+    hero_id: i32,
+    changes: &mut Changes,
+) -> Result<(), AppError> {
+    changes.add_hero(hero_id);
+
+    if materials::find_hero(db, player_id, hero_id)
+        .await?
+        .is_none()
+    {
+        materials::insert_hero(db, player_id, hero_id).await?;
+        changes.heroes.push(HeroGain {
+            is_new: true,
+            duplicate_count: 0,
+        });
+        changes.rewards.push((MATERIAL_HERO, hero_id as u32, 1));
+        return Ok(());
+    }
+
+    let duplicate_count = materials::add_hero_duplicate(db, player_id, hero_id).await?;
+    changes.heroes.push(HeroGain {
+        is_new: false,
+        duplicate_count,
+    });
+
+    let (items, currencies) = grant_dupe_rewards(hero_id, duplicate_count).await?;
+    for (item_id, amount) in items {
+        materials::give_item(db, player_id, item_id, amount, origin).await?;
+        changes.add_item(item_id);
+        changes.rewards.push((MATERIAL_ITEM, item_id, amount));
+    }
+    for (currency_id, amount) in currencies {
+        materials::give_currency(db, player_id, currency_id, amount, origin).await?;
+        changes.add_currency(currency_id, amount);
+        changes
+            .rewards
+            .push((MATERIAL_CURRENCY, currency_id as u32, amount));
+    }
+
+    tracing::info!(
+        "User {} already has hero {}, granted dupe rewards (duplicate #{})",
+        player_id,
+        hero_id,
+        duplicate_count
+    );
+
+    Ok(())
+}
@@ -0,0 +1,163 @@
+//! Replay regression: a fight played round by round has to come out the same when its
+//! stored oper log is played again, which is what `verify_active_battle` relies on.
+//!
+//! Needs the excel tables, set `SONETTO_EXCEL_DATA` to their directory. Skipped otherwise.
+
+use anyhow::{Context, Result};
+use config::configs;
+use database::{
+    db::{
+        game::battle::save_round_operations,
+        user::account::{TokenInfo, create_user},
+    },
+    models::game::heros::UserHeroModel,
+    run_migrations,
+};
+use gameserver::state::{
+    BattleContext, BattleSimulator, ReplayStart, create_battle, default_max_ap,
+    generate_auto_opers, generate_initial_deck, replay_stored_battle, reset_uid_counters,
+    round_seed,
+};
+use rand::{SeedableRng, rngs::StdRng};
+use sonettobuf::FightGroup;
+use sqlx::{SqlitePool, sqlite::SqlitePoolOptions};
+
+const USER_ID: i64 = 1;
+const FIGHT_ID: i64 = 1_700_000_000_000;
+const MAX_ROUNDS: i32 = 30;
+
+async fn setup(data_dir: &str) -> Result<(SqlitePool, FightGroup, i32)> {
+    configs::init(data_dir)?;
+
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await?;
+    run_migrations(&pool).await?;
+
+    let token = TokenInfo {
+        token: String::new(),
+        refresh_token: String::new(),
+        expires_at: 0,
+    };
+    create_user(&pool, USER_ID, "replay@localhost", "replay", &token, 0).await?;
+
+    let tables = configs::get();
+    let hero_id = tables
+        .character_level
+        .iter()
+        .map(|l| l.hero_id)
+        .next()
+        .context("no hero in character_level")?;
+    let episode_id = tables
+        .episode
+        .iter()
+        .find(|e| e.battle_id != 0)
+        .map(|e| e.id)
+        .context("no episode with a battle")?;
+
+    let model = UserHeroModel::new(USER_ID, pool.clone());
+    let uid = model.create_hero(hero_id).await?;
+
+    let fight_group = FightGroup {
+        hero_list: vec![uid],
+        cloth_id: Some(1),
+        ..Default::default()
+    };
+
+    Ok((pool, fight_group, episode_id))
+}
+
+#[tokio::test]
+async fn stored_oper_log_replays_to_the_live_result() -> Result<()> {
+    let Ok(data_dir) = std::env::var("SONETTO_EXCEL_DATA") else {
+        eprintln!("SONETTO_EXCEL_DATA not set, skipping the replay regression");
+        return Ok(());
+    };
+
+    let (pool, fight_group, episode_id) = setup(&data_dir).await?;
+    let episode = configs::get()
+        .episode
+        .iter()
+        .find(|e| e.id == episode_id)
+        .context("episode vanished")?;
+
+    reset_uid_counters();
+
+    let battle_ctx = BattleContext {
+        player_id: USER_ID,
+        chapter_id: episode.chapter_id,
+        episode_id,
+        battle_id: episode.battle_id,
+        max_ap: default_max_ap(episode_id, fight_group.hero_list.len()),
+    };
+
+    let mut deck = generate_initial_deck(
+        &pool,
+        USER_ID,
+        &fight_group,
+        battle_ctx.max_ap,
+        &mut StdRng::seed_from_u64(7),
+    )
+    .await?
+    .card_group;
+
+    let (_, _, mut data, mut ai_deck) =
+        create_battle(&pool, battle_ctx, &fight_group, deck.clone()).await?;
+
+    let start = ReplayStart {
+        data: data.clone(),
+        deck: deck.clone(),
+        ai_deck: ai_deck.clone(),
+    };
+
+    // Live fight, storing every round like BeginRound does
+    let mut live_finished = false;
+    let mut rounds = 0;
+
+    while !live_finished && rounds < MAX_ROUNDS {
+        rounds += 1;
+
+        let opers = generate_auto_opers(&deck);
+        save_round_operations(
+            &pool,
+            USER_ID,
+            episode_id,
+            FIGHT_ID,
+            rounds,
+            vec![],
+            opers.clone(),
+        )
+        .await?;
+
+        let mut simulator = BattleSimulator::with_seed(data, round_seed(FIGHT_ID, rounds));
+        let round = simulator.process_round(opers, deck, ai_deck).await?;
+
+        data = simulator.into_data();
+        deck = round.team_a_cards1;
+        ai_deck = round.ai_use_cards;
+        live_finished = round.is_finish.unwrap_or(false);
+    }
+
+    let replayed = replay_stored_battle(&pool, USER_ID, episode_id, FIGHT_ID, &start).await?;
+    assert_eq!(replayed, Ok(live_finished), "replay diverged from the live fight");
+
+    if live_finished {
+        // A round stored after the end can't be played back
+        save_round_operations(
+            &pool,
+            USER_ID,
+            episode_id,
+            FIGHT_ID,
+            rounds + 1,
+            vec![],
+            vec![],
+        )
+        .await?;
+
+        let tampered = replay_stored_battle(&pool, USER_ID, episode_id, FIGHT_ID, &start).await?;
+        assert!(tampered.is_err(), "extra round after the end was accepted");
+    }
+
+    Ok(())
+}
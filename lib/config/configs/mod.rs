@@ -1,5 +1,7 @@
 // Auto-generated module declarations
 
+pub mod achievement;
+pub mod achievement_task;
 pub mod activity101;
 pub mod activity174_role;
 pub mod activity191_role;
@@ -51,12 +53,19 @@ pub mod store_charge_optional;
 pub mod store_goods;
 pub mod summon;
 pub mod summon_pool;
+pub mod talent_cube_attr;
 pub mod talent_scheme;
 pub mod talent_style_cost;
+pub mod task_activity_bonus;
+pub mod task_daily;
+pub mod task_novice;
+pub mod task_weekly;
 
 use std::sync::OnceLock;
 
 pub struct GameDB {
+    pub achievement: achievement::AchievementTable,
+    pub achievement_task: achievement_task::AchievementTaskTable,
     pub activity101: activity101::Activity101Table,
     pub activity174_role: activity174_role::Activity174RoleTable,
     pub activity191_role: activity191_role::Activity191RoleTable,
@@ -108,12 +117,23 @@ pub struct GameDB {
     pub store_goods: store_goods::StoreGoodsTable,
     pub summon: summon::SummonTable,
     pub summon_pool: summon_pool::SummonPoolTable,
+    pub talent_cube_attr: talent_cube_attr::TalentCubeAttrTable,
     pub talent_scheme: talent_scheme::TalentSchemeTable,
     pub talent_style_cost: talent_style_cost::TalentStyleCostTable,
+    pub task_activity_bonus: task_activity_bonus::TaskActivityBonusTable,
+    pub task_daily: task_daily::TaskDailyTable,
+    pub task_novice: task_novice::TaskNoviceTable,
+    pub task_weekly: task_weekly::TaskWeeklyTable,
 }
 
 impl GameDB {
     pub fn load(data_dir: &str) -> anyhow::Result<Self> {
+        let achievement = achievement::AchievementTable::load(
+            &format!("{}/achievement.json", data_dir)
+        ).map_err(|e| anyhow::anyhow!("Failed to load achievement.json: {}", e))?;
+        let achievement_task = achievement_task::AchievementTaskTable::load(
+            &format!("{}/achievement_task.json", data_dir)
+        ).map_err(|e| anyhow::anyhow!("Failed to load achievement_task.json: {}", e))?;
         let activity101 = activity101::Activity101Table::load(
             &format!("{}/activity101.json", data_dir)
         ).map_err(|e| anyhow::anyhow!("Failed to load activity101.json: {}", e))?;
@@ -267,14 +287,31 @@ impl GameDB {
         let summon_pool = summon_pool::SummonPoolTable::load(
             &format!("{}/summon_pool.json", data_dir)
         ).map_err(|e| anyhow::anyhow!("Failed to load summon_pool.json: {}", e))?;
+        let talent_cube_attr = talent_cube_attr::TalentCubeAttrTable::load(
+            &format!("{}/talent_cube_attr.json", data_dir)
+        ).map_err(|e| anyhow::anyhow!("Failed to load talent_cube_attr.json: {}", e))?;
         let talent_scheme = talent_scheme::TalentSchemeTable::load(
             &format!("{}/talent_scheme.json", data_dir)
         ).map_err(|e| anyhow::anyhow!("Failed to load talent_scheme.json: {}", e))?;
         let talent_style_cost = talent_style_cost::TalentStyleCostTable::load(
             &format!("{}/talent_style_cost.json", data_dir)
         ).map_err(|e| anyhow::anyhow!("Failed to load talent_style_cost.json: {}", e))?;
+        let task_activity_bonus = task_activity_bonus::TaskActivityBonusTable::load(
+            &format!("{}/task_activity_bonus.json", data_dir)
+        ).map_err(|e| anyhow::anyhow!("Failed to load task_activity_bonus.json: {}", e))?;
+        let task_daily = task_daily::TaskDailyTable::load(
+            &format!("{}/task_daily.json", data_dir)
+        ).map_err(|e| anyhow::anyhow!("Failed to load task_daily.json: {}", e))?;
+        let task_novice = task_novice::TaskNoviceTable::load(
+            &format!("{}/task_novice.json", data_dir)
+        ).map_err(|e| anyhow::anyhow!("Failed to load task_novice.json: {}", e))?;
+        let task_weekly = task_weekly::TaskWeeklyTable::load(
+            &format!("{}/task_weekly.json", data_dir)
+        ).map_err(|e| anyhow::anyhow!("Failed to load task_weekly.json: {}", e))?;
 
         Ok(Self {
+            achievement,
+            achievement_task,
             activity101,
             activity174_role,
             activity191_role,
@@ -326,8 +363,13 @@ impl GameDB {
             store_goods,
             summon,
             summon_pool,
+            talent_cube_attr,
             talent_scheme,
             talent_style_cost,
+            task_activity_bonus,
+            task_daily,
+            task_novice,
+            task_weekly,
         })
     }
 
@@ -0,0 +1,82 @@
+// Auto-generated from JSON data
+// Do not edit manually
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskNovice {
+    pub activity: i32,
+    pub bonus: String,
+    pub desc: String,
+    pub id: i32,
+    #[serde(rename = "isOnline")]
+    pub is_online: i32,
+    #[serde(rename = "jumpId")]
+    pub jump_id: i32,
+    #[serde(rename = "listenerParam")]
+    pub listener_param: String,
+    #[serde(rename = "listenerType")]
+    pub listener_type: String,
+    #[serde(rename = "maxProgress")]
+    pub max_progress: i32,
+    #[serde(rename = "minType")]
+    pub min_type: String,
+    pub name: String,
+    #[serde(rename = "openLimit")]
+    pub open_limit: String,
+    pub prepose: String,
+    #[serde(rename = "sortId")]
+    pub sort_id: i32,
+}
+use std::collections::HashMap;
+
+pub struct TaskNoviceTable {
+    records: Vec<TaskNovice>,
+    by_id: HashMap<i32, usize>,
+}
+
+impl TaskNoviceTable {
+    pub fn load(path: &str) -> anyhow::Result<Self> {
+        let json = std::fs::read_to_string(path)?;
+        let value: serde_json::Value = serde_json::from_str(&json)?;
+
+        let records: Vec<TaskNovice> = if let Some(array) = value.as_array() {
+            if array.len() >= 2 && array[1].is_array() {
+                serde_json::from_value(array[1].clone())?
+            } else {
+                serde_json::from_value(value)?
+            }
+        } else {
+            serde_json::from_value(value)?
+        };
+
+        let mut by_id = HashMap::with_capacity(records.len());
+
+        for (idx, record) in records.iter().enumerate() {
+            by_id.insert(record.id, idx);
+        }
+
+        Ok(Self {
+            records,
+            by_id,
+        })
+    }
+
+    #[inline]
+    pub fn get(&self, id: i32) -> Option<&TaskNovice> {
+        self.by_id.get(&id).map(|&i| &self.records[i])
+    }
+
+    #[inline]
+    pub fn all(&self) -> &[TaskNovice] {
+        &self.records
+    }
+
+    #[inline]
+    pub fn iter(&self) -> std::slice::Iter<'_, TaskNovice> {
+        self.records.iter()
+    }
+
+    pub fn len(&self) -> usize { self.records.len() }
+    pub fn is_empty(&self) -> bool { self.records.is_empty() }
+}
\ No newline at end of file
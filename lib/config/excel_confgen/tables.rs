@@ -13,6 +13,7 @@ pub const FILTER_TABLES: &[&str] = &[
     "skill",
     "skill_ex_level",
     "talent_scheme",
+    "talent_cube_attr",
     "item",
     "power_item",
     "insight_item",
@@ -51,5 +52,11 @@ pub const FILTER_TABLES: &[&str] = &[
     "bp_des",
     "bp_lv_bonus",
     "bp_task",
+    "task_daily",
+    "task_weekly",
+    "task_novice",
+    "task_activity_bonus",
+    "achievement",
+    "achievement_task",
     "activity101",
 ];
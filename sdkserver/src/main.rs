@@ -1,5 +1,8 @@
 use ::config::configs;
-use common::{config, excel_data_directory, host, http_port, init_config, init_tracing};
+use common::{
+    config, excel_data_directory, host, http_port, init_config, init_tracing, starter_profile,
+};
+use database::db::starter_profile as starter;
 use database::{DatabaseSettings, connect_to, run_migrations};
 use gameserver::state::AppState as GameState;
 use reqwest::Client;
@@ -62,6 +65,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     configs::init(excel_data_directory().to_str().unwrap())?;
     info!("Game data loaded");
 
+    starter::init(starter_profile())?;
+
     let state = AppState {
         sdk: SdkState {
             http_client: Client::new(),